@@ -0,0 +1,414 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Error index - describes the stable codes of all Twig errors.
+//!
+//! Codes are of the form `T<group><number>`, where the group identifies the error type:
+//!
+//! * `T01..` - `TwigError`
+//! * `T02..` - `LexerError` and `SyntaxError`
+//! * `T03..` - `ParserError`
+//! * `T04..` - `LoaderError`
+//! * `T05..` - `ExtensionRegistryError`
+//! * `T06..` - `RuntimeError`
+//! * `T07..` - `NodeError`
+//! * `T08..` - `TokenError`
+//!
+//! Codes never change their meaning. Codes of removed errors are not reused.
+//!
+//! # Examples
+//!
+//! ```
+//! use twig::api::error::index;
+//!
+//! let entry = index::lookup("T0611").unwrap();
+//!
+//! assert_eq!(entry.name, "RuntimeError::UndefinedVariable");
+//! ```
+
+/// Description of an error code.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    /// E.g. `T0611`
+    pub code: &'static str,
+    /// Error type and variant, e.g. `RuntimeError::UndefinedVariable`.
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// Get the description of an error code.
+pub fn lookup(code: &str) -> Option<&'static Entry> {
+    INDEX.iter().find(|entry| entry.code == code)
+}
+
+/// All error codes, ordered by code.
+pub static INDEX: &[Entry] = &[
+    Entry {
+        code: "T0101",
+        name: "TwigError::LoaderNotInitialized",
+        description: "A template was loaded before a loader was set via `Engine::set_loader()`.",
+    },
+    Entry {
+        code: "T0102",
+        name: "TwigError::LexerNotInitialized",
+        description: "The lexer has not been initialized prior usage.",
+    },
+    Entry {
+        code: "T0103",
+        name: "TwigError::EmbeddedTemplateNotFound",
+        description: "A template refers to an embedded template it does not define. This indicates a bug in a custom token parser or node visitor.",
+    },
+    Entry {
+        code: "T0201",
+        name: "SyntaxError::UnexpectedCharacter",
+        description: "The template contains a character which is not valid within a tag or expression, e.g. `{{ a $ b }}`.",
+    },
+    Entry {
+        code: "T0202",
+        name: "SyntaxError::UnexpectedBracket",
+        description: "A closing bracket does not have a matching opening bracket, e.g. `{{ a) }}`.",
+    },
+    Entry {
+        code: "T0203",
+        name: "SyntaxError::UnexpectedEof",
+        description: "The template ends within a tag or expression, e.g. within a string.",
+    },
+    Entry {
+        code: "T0204",
+        name: "SyntaxError::UnclosedBracket",
+        description: "A bracket is closed by a different kind of bracket, e.g. `{{ (a] }}`.",
+    },
+    Entry {
+        code: "T0205",
+        name: "SyntaxError::UnclosedComment",
+        description: "A comment is opened with `{#` but never closed with `#}`.",
+    },
+    Entry {
+        code: "T0206",
+        name: "SyntaxError::UnclosedBlock",
+        description: "A tag is opened with `{%` but never closed with `%}`.",
+    },
+    Entry {
+        code: "T0207",
+        name: "SyntaxError::UnclosedVariable",
+        description: "An expression is opened with `{{` but never closed with `}}`.",
+    },
+    Entry {
+        code: "T0210",
+        name: "LexerError::PatternRegexError",
+        description: "The lexer could not be created, because the patterns built from the options and the operators of the extensions are not valid regular expressions.",
+    },
+    Entry {
+        code: "T0211",
+        name: "LexerError::_InvalidPatternMatch",
+        description: "A pattern of the lexer matched unexpectedly. This indicates a bug in the lexer.",
+    },
+    Entry {
+        code: "T0212",
+        name: "LexerError::InvalidValue",
+        description: "An operator registered by an extension is empty, or starts or ends with whitespace.",
+    },
+    Entry {
+        code: "T0301",
+        name: "ParserError::TokenParserError",
+        description: "A token parser rejected the content of its tag, e.g. `{% for %}` without a loop variable.",
+    },
+    Entry {
+        code: "T0302",
+        name: "ParserError::NoTagHandler",
+        description: "A tag is not known to the engine, e.g. a misspelled `{% endfro %}` or a tag of an extension which is not registered.",
+    },
+    Entry {
+        code: "T0303",
+        name: "ParserError::UnexpectedBinaryOperator",
+        description: "An unknown binary operator follows an expression, e.g. the misspelled `1 startswith 2` - or a binary operator is registered with a factory of unary operators, which indicates a bug in an extension.",
+    },
+    Entry {
+        code: "T0304",
+        name: "ParserError::UnknownFilter",
+        description: "A filter is not known to the engine, e.g. a misspelled `|lenght` or a filter of an extension which is not registered.",
+    },
+    Entry {
+        code: "T0305",
+        name: "ParserError::UnknownFunction",
+        description: "A function is not known to the engine.",
+    },
+    Entry {
+        code: "T0306",
+        name: "ParserError::UnknownTest",
+        description: "A test is not known to the engine, e.g. `is defind`.",
+    },
+    Entry {
+        code: "T0307",
+        name: "ParserError::UnexpectedToken",
+        description: "A token does not fit the syntax at its position, e.g. two expressions without an operator in between.",
+    },
+    Entry {
+        code: "T0308",
+        name: "ParserError::UnexpectedEof",
+        description: "The template ends before all tags are closed, e.g. `{% if %}` without `{% endif %}`.",
+    },
+    Entry {
+        code: "T0309",
+        name: "ParserError::NodeVisitorFailure",
+        description: "A node visitor failed to modify the syntax tree.",
+    },
+    Entry {
+        code: "T0401",
+        name: "LoaderError::ArrayTemplateNotFound",
+        description: "The array loader does not contain a template of the name.",
+    },
+    Entry {
+        code: "T0402",
+        name: "LoaderError::FileSystemTemplateNotFound",
+        description: "None of the directories of the namespace contains a template of the name.",
+    },
+    Entry {
+        code: "T0403",
+        name: "LoaderError::FileSystemNamespaceNotInitialized",
+        description: "No directories are registered for the namespace of a template name.",
+    },
+    Entry {
+        code: "T0404",
+        name: "LoaderError::FileSystemMalformedNamespacedPath",
+        description: "A template name starting with `@` has no path after the namespace.",
+    },
+    Entry {
+        code: "T0405",
+        name: "LoaderError::FileSystemInvalidPath",
+        description: "A template name refers to a file outside the registered directories, e.g. via `..`.",
+    },
+    Entry {
+        code: "T0406",
+        name: "LoaderError::FileSystemTemplateNotReadable",
+        description: "A template file exists but can not be read.",
+    },
+    Entry {
+        code: "T0501",
+        name: "ExtensionRegistryError::ExtensionInitFailure",
+        description: "An extension failed to initialize.",
+    },
+    Entry {
+        code: "T0502",
+        name: "ExtensionRegistryError::DuplicateExtension",
+        description: "Two extensions of the same name are registered.",
+    },
+    Entry {
+        code: "T0503",
+        name: "ExtensionRegistryError::MissingDependency",
+        description: "An extension depends on another extension which is not registered.",
+    },
+    Entry {
+        code: "T0504",
+        name: "ExtensionRegistryError::DependencyCycle",
+        description: "Extensions depend on each other.",
+    },
+    Entry {
+        code: "T0505",
+        name: "ExtensionRegistryError::DuplicateFilter",
+        description: "Two extensions register a filter of the same name.",
+    },
+    Entry {
+        code: "T0506",
+        name: "ExtensionRegistryError::DuplicateFunction",
+        description: "Two extensions register a function of the same name.",
+    },
+    Entry {
+        code: "T0507",
+        name: "ExtensionRegistryError::DuplicateOperatorUnary",
+        description: "Two extensions register a unary operator of the same name.",
+    },
+    Entry {
+        code: "T0508",
+        name: "ExtensionRegistryError::DuplicateOperatorBinary",
+        description: "Two extensions register a binary operator of the same name.",
+    },
+    Entry {
+        code: "T0509",
+        name: "ExtensionRegistryError::DuplicateTest",
+        description: "Two extensions register a test of the same name.",
+    },
+    Entry {
+        code: "T0510",
+        name: "ExtensionRegistryError::DuplicateGlobal",
+        description: "Two extensions or `Setup::add_global()` register a global of the same name.",
+    },
+    Entry {
+        code: "T0511",
+        name: "ExtensionRegistryError::DuplicateTagHandler",
+        description: "Two extensions register a tag handler of the same name.",
+    },
+    Entry {
+        code: "T0512",
+        name: "ExtensionRegistryError::DuplicateTokenParser",
+        description: "Two extensions register a token parser for the same tag.",
+    },
+    Entry {
+        code: "T0601",
+        name: "RuntimeError::NotAnExpression",
+        description: "A statement node is evaluated like an expression. This indicates a bug in a custom token parser or node visitor.",
+    },
+    Entry {
+        code: "T0602",
+        name: "RuntimeError::UnknownOperator",
+        description: "An operator of the syntax tree is not known to the engine, or can not be evaluated.",
+    },
+    Entry {
+        code: "T0603",
+        name: "RuntimeError::UnknownFilter",
+        description: "A filter of the syntax tree is not known to the engine, e.g. because a node visitor added it.",
+    },
+    Entry {
+        code: "T0604",
+        name: "RuntimeError::UnknownFunction",
+        description: "A function of the syntax tree is not known to the engine.",
+    },
+    Entry {
+        code: "T0605",
+        name: "RuntimeError::UnknownTest",
+        description: "A test of the syntax tree is not known to the engine.",
+    },
+    Entry {
+        code: "T0606",
+        name: "RuntimeError::UnsupportedOperandTypes",
+        description: "An operator is applied to values it does not support, e.g. `[1] - 'a'`.",
+    },
+    Entry {
+        code: "T0607",
+        name: "RuntimeError::DivisionByZero",
+        description: "The right operand of `/`, `//` or `%` is zero.",
+    },
+    Entry {
+        code: "T0608",
+        name: "RuntimeError::InvalidRegex",
+        description: "The pattern of a `matches` operator is not a valid regular expression.",
+    },
+    Entry {
+        code: "T0609",
+        name: "RuntimeError::InvalidArgument",
+        description: "A filter, function, test or operator rejected an argument, e.g. a negative step of `range()`.",
+    },
+    Entry {
+        code: "T0610",
+        name: "RuntimeError::InvalidArgumentType",
+        description: "A filter, function or test is called with an argument of the wrong type, e.g. `[1]|upper`.",
+    },
+    Entry {
+        code: "T0611",
+        name: "RuntimeError::UndefinedVariable",
+        description: "With strict variables, a template refers to a variable which is neither passed to the template nor assigned. Check it with `is defined` or provide a fallback with `|default`.",
+    },
+    Entry {
+        code: "T0612",
+        name: "RuntimeError::UndefinedAttribute",
+        description: "With strict variables, a template refers to an attribute or key a value does not have.",
+    },
+    Entry {
+        code: "T0613",
+        name: "RuntimeError::UnknownConstant",
+        description: "A template refers to a constant which is not defined via `Setup::add_constant()`.",
+    },
+    Entry {
+        code: "T0614",
+        name: "RuntimeError::LoadTemplate",
+        description: "A template referred to by another template, e.g. via `include`, can not be loaded or compiled. The cause describes the error of the referred template.",
+    },
+    Entry {
+        code: "T0615",
+        name: "RuntimeError::NoTemplateFound",
+        description: "None of the candidates of a dynamic template name exists, e.g. `{% extends ['a', 'b'] %}`.",
+    },
+    Entry {
+        code: "T0616",
+        name: "RuntimeError::CircularInheritance",
+        description: "Templates extend or use each other in a cycle.",
+    },
+    Entry {
+        code: "T0617",
+        name: "RuntimeError::UnknownMacro",
+        description: "A macro is called which is not defined by the imported template, or which has not been imported.",
+    },
+    Entry {
+        code: "T0618",
+        name: "RuntimeError::NotTraitable",
+        description: "A template imported via `use` extends another template or has content outside of blocks.",
+    },
+    Entry {
+        code: "T0619",
+        name: "RuntimeError::TraitConflict",
+        description: "Templates imported via `use` define blocks of the same name. Rename one of them with `use .. with name as other_name`.",
+    },
+    Entry {
+        code: "T0620",
+        name: "RuntimeError::DepthLimitExceeded",
+        description: "Templates or macros are nested too deeply, usually because of an infinite recursion.",
+    },
+    Entry {
+        code: "T0621",
+        name: "RuntimeError::BlockNotFound",
+        description: "A block is rendered which is not defined by the template or its parents.",
+    },
+    Entry {
+        code: "T0622",
+        name: "RuntimeError::NoParentBlock",
+        description: "`parent()` is called in a block which does not override a block of a parent template.",
+    },
+    Entry {
+        code: "T0701",
+        name: "NodeError::AttributeNotFound",
+        description: "An attribute of a node of the syntax tree is requested which the node does not have.",
+    },
+    Entry {
+        code: "T0801",
+        name: "TokenError::_NoValue",
+        description: "A token without a value is asked for its value.",
+    },
+    Entry {
+        code: "T0802",
+        name: "TokenError::UnexpectedTokenAtItem",
+        description: "A token of the token stream does not match the expected pattern.",
+    },
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use setup::Setup;
+    use api::value::Hash;
+    use extension::core::test::engine_with;
+    use api::parser::error::NodeError;
+    use api::token::error::TokenError;
+
+    #[test]
+    fn codes() {
+        for (entry, next) in INDEX.iter().zip(INDEX.iter().skip(1)) {
+            assert!(entry.code < next.code, "{} must be ordered before {}", entry.code, next.code);
+        }
+
+        for entry in INDEX {
+            assert!(entry.code.len() == 5 && entry.code.starts_with('T'), "{}", entry.code);
+            assert!(entry.description.ends_with('.'), "{}", entry.code);
+        }
+
+        assert_eq!(lookup("T0103").map(|e| e.name), Some("TwigError::EmbeddedTemplateNotFound"));
+        assert_eq!(lookup("T9999"), None);
+
+        let node_error = NodeError::AttributeNotFound { key: "x".to_string(), node_tag: "print".to_string() };
+        assert_eq!(lookup(node_error.code()).map(|e| e.name), Some("NodeError::AttributeNotFound"));
+        assert_eq!(lookup(TokenError::_NoValue.code()).map(|e| e.name), Some("TokenError::_NoValue"));
+    }
+
+    #[test]
+    fn errors_are_indexed() {
+        let sources = ["{{ 1 // 0 }}", "{{ [1]|upper }}", "{% include 'missing' %}", "{{ a", "{% if true %}", "{{ 1 2 }}", "{{ constant('X') }}"];
+
+        for source in &sources {
+            let mut twig = engine_with(Setup::default(), &[("index", source)]);
+            let error = twig.render("index", Hash::new()).unwrap_err();
+
+            assert!(lookup(error.code()).is_some(), "{} of {:?} is not indexed", error.code(), source);
+        }
+    }
+}
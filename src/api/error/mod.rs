@@ -49,6 +49,8 @@
 //! ```
 
 #[macro_use] mod macros;
+pub mod index;
+pub mod suggest;
 use std::fmt::{self, Debug, Display};
 use std::error::Error; // use std Error-trait to improve cross-crate compatibility
 use std::ops::{Deref, DerefMut};
@@ -89,10 +91,11 @@ pub trait Dump {
 ///
 /// Wrapper around some error type `T` implementing `std::error::Error`.
 /// * Adds support for a backtrace.
+/// * The error is boxed to keep `Result<_, Traced<T>>` small.
 pub struct Traced<T>
     where T: Error
 {
-    error: T,
+    error: Box<T>,
     trace: Trace,
 }
 
@@ -114,7 +117,7 @@ impl<T> Traced<T> where
     /// ```
     pub fn new(error: T, location: Location) -> Traced<T> {
         Traced {
-            error: error,
+            error: Box::new(error),
             trace: Trace(vec![location]),
         }
     }
@@ -137,19 +140,42 @@ impl<T> Traced<T> where
         trace.push(loc);
 
         Traced {
-            error: error.into(),
+            error: Box::new((*error).into()),
             trace: trace
         }
     }
 
+    /// Converts the error with `f`, keeping the backtrace.
+    pub fn map<R, F>(self, f: F) -> Traced<R>
+        where F: FnOnce(T) -> R,
+              R: Error
+    {
+        let Traced { error, trace } = self;
+
+        Traced {
+            error: Box::new(f(*error)),
+            trace,
+        }
+    }
+
     /// Creates an iterator to iterate along the error cause-chain.
     pub fn iter(&self) -> ErrorIter {
         ErrorIter {
-            next: Some(&self.error),
+            next: Some(&*self.error),
         }
     }
 }
 
+impl<T> Deref for Traced<T> where
+    T: Error
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.error
+    }
+}
+
 impl<T> Error for Traced<T> where
     T: Error
 {
@@ -246,7 +272,7 @@ impl Display for Trace {
 /// Location in rust source code
 ///
 /// Debug::fmt() output is formatted in a compact way: `"{filename}:{line}:{column}"`.
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone)]
 pub struct Location {
     pub filename : &'static str,    // e.g. /src/lexer/job/state/shared_traits.rs
     pub line : u32,
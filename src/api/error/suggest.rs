@@ -0,0 +1,157 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Suggestions of similar names for error messages, e.g. for misspelled variables.
+
+/// Get the candidates which are similar to `name`, the most similar first.
+///
+/// Candidates are similar if their edit distance is at most a third of the length of `name`
+/// (but at least one), or if one contains the other.
+///
+/// # Examples
+///
+/// ```
+/// use twig::api::error::suggest::similar;
+///
+/// assert_eq!(similar("usr", vec!["user", "users", "items"]), vec!["user"]);
+/// assert_eq!(similar("item", vec!["user", "items", "item_list"]), vec!["items", "item_list"]);
+/// ```
+pub fn similar<I, S>(name: &str, candidates: I) -> Vec<String> where
+    I: IntoIterator<Item=S>,
+    S: Into<String>
+{
+    rank(name, candidates.into_iter().map(Into::into).map(|candidate| (candidate.clone(), candidate)))
+}
+
+/// Like `similar()`, but also compares names made of several words, e.g. the test `divisible by`
+/// or the operator `starts with`, by their first word and without spaces.
+///
+/// # Examples
+///
+/// ```
+/// use twig::api::error::suggest::similar_words;
+///
+/// assert_eq!(similar_words("divisble", vec!["divisible by", "defined"]), vec!["divisible by"]);
+/// assert_eq!(similar_words("startswith", vec!["starts with", "ends with"]), vec!["starts with"]);
+/// ```
+pub fn similar_words<I, S>(name: &str, candidates: I) -> Vec<String> where
+    I: IntoIterator<Item=S>,
+    S: Into<String>
+{
+    rank(name, candidates.into_iter().map(Into::into).flat_map(|candidate: String| {
+        let first = candidate.split(' ').next().unwrap_or_default().to_string();
+        let joined = candidate.replace(' ', "");
+
+        vec![(candidate.clone(), candidate.clone()), (first, candidate.clone()), (joined, candidate)]
+    }))
+}
+
+/// Formats suggestions for an error message, e.g. ` Did you mean "user" or "users"?`.
+///
+/// Returns an empty string if there are no suggestions.
+pub fn did_you_mean(similar: &[String]) -> String {
+    if similar.is_empty() {
+        return String::new();
+    }
+
+    format!(" Did you mean {}?", one_of(similar))
+}
+
+/// Formats a list of names as alternatives, e.g. `"user", "users" or "items"`.
+pub fn one_of(names: &[String]) -> String {
+    let quoted: Vec<String> = names.iter().map(|s| format!("{:?}", s)).collect();
+
+    match quoted.split_last() {
+        None => String::new(),
+        Some((last, [])) => last.clone(),
+        Some((last, init)) => format!("{} or {}", init.join(", "), last),
+    }
+}
+
+/// Get the candidates whose key is similar to `name`, the most similar first.
+///
+/// Candidates are `(key, candidate)` pairs - a candidate may have several keys.
+fn rank<I>(name: &str, candidates: I) -> Vec<String> where
+    I: IntoIterator<Item=(String, String)>
+{
+    let max_distance = (name.chars().count() / 3).max(1);
+    let lowercase = name.to_lowercase();
+
+    let mut similar: Vec<(usize, String)> = candidates.into_iter()
+        .filter(|(_, candidate)| candidate != name)
+        .filter_map(|(key, candidate)| {
+            let distance = distance(&lowercase, &key.to_lowercase());
+            let contains = lowercase.len() >= 3 && key.to_lowercase().contains(&lowercase);
+
+            if distance <= max_distance || contains {
+                Some((distance, candidate))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    similar.sort();
+
+    let mut ranked: Vec<String> = vec![];
+    for (_, candidate) in similar {
+        if !ranked.contains(&candidate) {
+            ranked.push(candidate);
+        }
+    }
+
+    ranked
+}
+
+/// Get the edit distance of two strings, where swapping adjacent characters counts as one edit
+/// (optimal string alignment distance).
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    d[0] = (0..=b.len()).collect();
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            d[i][j] = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "abc"), 3);
+        assert_eq!(distance("name", "name"), 0);
+        assert_eq!(distance("nmae", "name"), 1);
+    }
+
+    #[test]
+    fn suggestions() {
+        assert_eq!(similar("nmae", vec!["name", "names", "age"]), vec!["name"]);
+        assert_eq!(similar("item", vec!["items", "item_list", "other"]), vec!["items", "item_list"]);
+        assert!(similar("x", vec!["abc"]).is_empty());
+        assert_eq!(did_you_mean(&["a".to_string(), "b".to_string(), "c".to_string()]), " Did you mean \"a\", \"b\" or \"c\"?");
+        assert_eq!(did_you_mean(&[]), "");
+        assert_eq!(one_of(&["a".to_string()]), "\"a\"");
+        assert_eq!(similar_words("sameas", vec!["same as", "empty"]), vec!["same as"]);
+        assert!(similar_words("by", vec!["divisible by"]).is_empty());
+    }
+}
@@ -14,9 +14,12 @@
 
 use std::fmt;
 use engine;
+use engine::ExtensionRegistry;
 use api::parser::{Job, ParserError};
+use api::runtime::{self, RuntimeError};
 use api::token::stream::Item;
-use api::Node;
+use api::{Node, Value};
+use api::node::Arrow;
 use api::error::Traced;
 
 
@@ -25,6 +28,14 @@ pub trait Extension : fmt::Debug {
     /// Get the name of the extension.
     fn name(&self) -> &'static str;
 
+    /// Get the names of all extensions which must be initialized before this one.
+    ///
+    /// The extension registry initializes extensions in topological order of their
+    /// dependencies. Unknown dependencies and dependency cycles are reported as errors.
+    fn dependencies(&self) -> &'static [&'static str] {
+        &[]
+    }
+
     /// Initialize and register the extension.
     ///
     /// This method is supposed to push all filters, functions etc. of the extension to the
@@ -40,28 +51,178 @@ pub trait Extension : fmt::Debug {
 /// E.g. the `default` filter returns the result of the variable expression if it is defined,
 /// otherwise it returns the default value. The `escaper` filter escapes the result according
 /// to the output channel (html, html attribute, css, js, url, ..)
-pub trait Filter : fmt::Debug {}
+pub trait Filter : fmt::Debug {
+    /// Applies the filter to a value, e.g. `value|filter(arguments)`.
+    fn apply(&self, job: &mut runtime::Job, value: Value, arguments: Vec<Value>)
+        -> Result<Value, Traced<RuntimeError>>;
+
+    /// Get the escaping strategies the output of the filter is safe for, e.g. `["html"]`.
+    ///
+    /// The output of safe filters is not auto-escaped again. `"all"` denotes all strategies.
+    fn is_safe(&self, _arguments: &[Box<dyn Node>]) -> Vec<String> {
+        vec![]
+    }
+
+    /// Returns true if undefined variables and attributes may be passed to the filter, e.g.
+    /// `missing|default('x')`. They are passed as null, even with strict variables.
+    fn accepts_undefined(&self) -> bool {
+        false
+    }
+
+    /// Applies the filter with an arrow function as first argument, e.g. `items|map(x => x * 2)`.
+    ///
+    /// Returns `None` if the filter does not accept arrow functions (default).
+    fn apply_arrow(&self, _job: &mut runtime::Job, _value: Value, _arrow: &Arrow, _arguments: Vec<Value>)
+        -> Option<Result<Value, Traced<RuntimeError>>> {
+        None
+    }
+}
 
 /// Can be used to perform complex computations.
 ///
 /// E.g. the `round` function rounds a floating number with a given precision.
-pub trait Function : fmt::Debug {}
+pub trait Function : fmt::Debug {
+    /// Calls the function, e.g. `function(arguments)`.
+    fn call(&self, job: &mut runtime::Job, arguments: Vec<Value>)
+        -> Result<Value, Traced<RuntimeError>>;
+
+    /// Get the escaping strategies the output of the function is safe for, e.g. `["html"]`.
+    ///
+    /// The output of safe functions is not auto-escaped again. `"all"` denotes all strategies.
+    fn is_safe(&self, _arguments: &[Box<dyn Node>]) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// Can be used to define global variables.
+///
+/// Templates can test for these global variables to trigger conditional behavior, or use
+/// them as argument for functions, etc. Variables of the render context shadow globals with
+/// the same name.
+///
+/// See `twig::api::ext::global` for constant and lazily computed globals.
+pub trait Global : fmt::Debug {
+    /// Get the value of the global.
+    ///
+    /// Called at most once per render - i.e. when a template first refers to the global.
+    fn value(&self, job: &mut runtime::Job) -> Result<Value, Traced<RuntimeError>>;
+}
 
-/// Can be used to define global constants.
+/// Definition of globals.
 ///
-/// Templates can test for these global constants to trigger conditional behavior, or use
-/// them as argument for functions, etc
-pub trait Global : fmt::Debug {}
+/// # Examples
+///
+/// ```
+/// use twig::Setup;
+/// use twig::api::Value;
+/// use twig::api::ext::global;
+///
+/// let twig = Setup::default()
+///     .add_global("site_name", global::Constant::new("Twig"))
+///     .add_global("answer", global::Lazy::new(|| Value::from(6 * 7)))
+///     .init_engine()
+///     .unwrap();
+///
+/// assert!(twig.extensions().globals().contains_key("answer"));
+/// ```
+pub mod global {
+    use std::fmt;
+    use api::Value;
+    use api::runtime::{self, RuntimeError};
+    use api::error::Traced;
+    use super::Global;
+
+    /// A global with a constant value.
+    #[derive(Debug, PartialEq)]
+    pub struct Constant(pub Value);
+
+    impl Constant {
+        pub fn new<V>(value: V) -> Box<Constant> where
+            V: Into<Value>
+        {
+            Box::new(Constant(value.into()))
+        }
+    }
+
+    impl Global for Constant {
+        fn value(&self, _job: &mut runtime::Job) -> Result<Value, Traced<RuntimeError>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    /// A global which is computed when a template first refers to it during a render.
+    pub struct Lazy {
+        compute: Box<dyn Fn() -> Value>,
+    }
+
+    impl Lazy {
+        pub fn new<F>(compute: F) -> Box<Lazy> where
+            F: Fn() -> Value + 'static
+        {
+            Box::new(Lazy {
+                compute: Box::new(compute),
+            })
+        }
+    }
+
+    impl fmt::Debug for Lazy {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "Lazy")
+        }
+    }
+
+    impl Global for Lazy {
+        fn value(&self, _job: &mut runtime::Job) -> Result<Value, Traced<RuntimeError>> {
+            Ok((self.compute)())
+        }
+    }
+}
 
 /// Modifies the abstract syntax tree immediately after parsing.
 ///
 /// E.g. `twig::extension::optimizer` defines the `optimizeRawFilter` node visitor which strips all "raw" filters from the syntax tree.
-pub trait NodeVisitor : fmt::Debug {}
+///
+/// Each node visitor traverses the whole tree on its own (depth-first). Visitors are applied
+/// one after another in ascending order of their priority (see `twig::api::node::Traverser`).
+pub trait NodeVisitor : fmt::Debug {
+    /// Called before child nodes are visited.
+    ///
+    /// Returns the node to proceed with, i.e. either the given node or a replacement.
+    fn enter_node(&self, node: Box<dyn Node>, ext: &ExtensionRegistry)
+        -> Result<Box<dyn Node>, Traced<ParserError>>;
+
+    /// Called after child nodes are visited.
+    ///
+    /// Returns the given node, a replacement, or `None` to remove the node from its parent, if
+    /// the parent is a `Body` node.
+    fn leave_node(&self, node: Box<dyn Node>, ext: &ExtensionRegistry)
+        -> Result<Option<Box<dyn Node>>, Traced<ParserError>>;
+
+    /// Get the priority of this visitor.
+    ///
+    /// Visitors with lower priority are applied first. The priority should be between
+    /// -10 and 10 (default is 0).
+    fn priority(&self) -> i32 {
+        0
+    }
+}
 
 /// Can be used in conditional Twig statements.
 ///
 /// E.g. the `defined` test checks if a variable is defined in the current context.
-pub trait Test : fmt::Debug {}
+pub trait Test : fmt::Debug {
+    /// Tests a value, e.g. `value is test(arguments)`.
+    fn test(&self, job: &mut runtime::Job, value: Value, arguments: Vec<Value>)
+        -> Result<bool, Traced<RuntimeError>>;
+
+    /// Tests the tested expression itself instead of its value, e.g. `foo.bar is defined`.
+    ///
+    /// Returns `None` if the test only depends on the value (default).
+    fn test_node(&self, _job: &mut runtime::Job, _node: &dyn Node, _arguments: &[Value])
+        -> Option<Result<bool, Traced<RuntimeError>>> {
+        None
+    }
+}
 
 /// Transforms a sub-sequence from the token stream (=lexed template) to nodes in the abstract syntax tree.
 ///
@@ -110,9 +271,47 @@ pub struct BinaryOperator {
     pub assoc: op::Assoc,
 }
 
+/// Definition of operators.
+///
+/// Operators are either implemented by a rust function, which the evaluator calls with the
+/// evaluated operands (`Operation::Callable`), or by a custom node type, which is created by the
+/// parser and evaluates itself (`Operation::Class`).
+///
+/// # Examples
+///
+/// ```
+/// use twig::api::Value;
+/// use twig::api::error::Traced;
+/// use twig::api::runtime::RuntimeError;
+/// use twig::api::ext::{BinaryOperator, op};
+///
+/// fn spaceship(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+///     Ok(Value::from(match left.compare(right) {
+///         Some(ordering) => ordering as i64,
+///         None => 0,
+///     }))
+/// }
+///
+/// let operator = BinaryOperator {
+///     repr: "<=>".to_string(),
+///     ext: op::Extension("my_extension".to_string()),
+///     prec: op::Precedence(20),
+///     op: op::Operation::Callable(op::Function::binary("spaceship", spaceship)),
+///     assoc: op::Assoc::Left,
+/// };
+///
+/// assert_eq!(operator.op, op::Operation::Callable(op::Function::binary("spaceship", spaceship)));
+/// ```
 pub mod op {
+    use std::fmt;
+    use api::{Node, Value};
+    use api::parser::{Job, ParserError};
+    use api::runtime::RuntimeError;
+    use api::token::stream::Position;
+    use api::error::Traced;
+
     #[derive(Debug, PartialEq)]
-    pub struct Extension(String); // might switch to ID for faster lookups
+    pub struct Extension(pub String); // might switch to ID for faster lookups
 
     #[derive(Debug, PartialEq, PartialOrd)]
     pub struct Precedence(pub usize);
@@ -130,13 +329,113 @@ pub mod op {
         Right,
     }
 
-    #[derive(Debug, PartialEq)]
+    pub type UnaryCallable = Box<dyn Fn(&Value) -> Result<Value, Traced<RuntimeError>>>;
+    pub type BinaryCallable = Box<dyn Fn(&Value, &Value) -> Result<Value, Traced<RuntimeError>>>;
+
+    /// Rust implementation of an operator.
+    pub enum Callable {
+        Unary(UnaryCallable),
+        Binary(BinaryCallable),
+    }
+
+    /// Operator implemented by a rust function or closure.
     pub struct Function {
-        name: String
+        name: String,
+        callable: Callable,
     }
 
-    #[derive(Debug, PartialEq)]
+    impl Function {
+        pub fn unary<N, F>(name: N, callable: F) -> Function where
+            N: Into<String>,
+            F: Fn(&Value) -> Result<Value, Traced<RuntimeError>> + 'static
+        {
+            Function {
+                name: name.into(),
+                callable: Callable::Unary(Box::new(callable)),
+            }
+        }
+
+        pub fn binary<N, F>(name: N, callable: F) -> Function where
+            N: Into<String>,
+            F: Fn(&Value, &Value) -> Result<Value, Traced<RuntimeError>> + 'static
+        {
+            Function {
+                name: name.into(),
+                callable: Callable::Binary(Box::new(callable)),
+            }
+        }
+
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+
+        pub fn callable(&self) -> &Callable {
+            &self.callable
+        }
+    }
+
+    // NOTE: closures can not be compared - functions are compared by name.
+    impl PartialEq for Function {
+        fn eq(&self, other: &Function) -> bool {
+            self.name == other.name
+        }
+    }
+
+    impl fmt::Debug for Function {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "Function({:?})", self.name)
+        }
+    }
+
+    pub type UnaryFactory = fn(Box<dyn Node>, Position) -> Box<dyn Node>;
+    pub type BinaryFactory = fn(Box<dyn Node>, Box<dyn Node>, Position) -> Box<dyn Node>;
+    pub type ParserFactory = fn(&mut Job, Box<dyn Node>, Position) -> Result<Box<dyn Node>, Traced<ParserError>>;
+
+    /// Creates the node of an operator expression.
+    #[derive(Clone, Copy)]
+    pub enum Factory {
+        /// Creates a node from the operand of a unary operator.
+        Unary(UnaryFactory),
+        /// Creates a node from both operands of a binary operator.
+        Binary(BinaryFactory),
+        /// Parses the right-hand side of a binary operator on its own, e.g. the test of `is`.
+        Parser(ParserFactory),
+    }
+
+    /// Operator implemented by a custom node type.
     pub struct Class {
-        name: String
+        name: String,
+        factory: Factory,
+    }
+
+    impl Class {
+        pub fn new<N>(name: N, factory: Factory) -> Class where
+            N: Into<String>
+        {
+            Class {
+                name: name.into(),
+                factory,
+            }
+        }
+
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+
+        pub fn factory(&self) -> Factory {
+            self.factory
+        }
+    }
+
+    impl PartialEq for Class {
+        fn eq(&self, other: &Class) -> bool {
+            self.name == other.name
+        }
+    }
+
+    impl fmt::Debug for Class {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "Class({:?})", self.name)
+        }
     }
 }
@@ -56,6 +56,34 @@ impl Error for SyntaxError {
     }
 }
 
+impl SyntaxError {
+    /// Get the stable code of the error, see `twig::api::error::index`.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            SyntaxError::UnexpectedCharacter{..} => "T0201",
+            SyntaxError::UnexpectedBracket{..} => "T0202",
+            SyntaxError::UnexpectedEof{..} => "T0203",
+            SyntaxError::UnclosedBracket{..} => "T0204",
+            SyntaxError::UnclosedComment{..} => "T0205",
+            SyntaxError::UnclosedBlock{..} => "T0206",
+            SyntaxError::UnclosedVariable{..} => "T0207",
+        }
+    }
+
+    /// Get the state of the lexer when the error occurred.
+    pub fn cursor(&self) -> &cursor::CursorDump {
+        match *self {
+            SyntaxError::UnexpectedCharacter { ref cursor, .. }
+            | SyntaxError::UnexpectedBracket { ref cursor, .. }
+            | SyntaxError::UnexpectedEof { ref cursor, .. }
+            | SyntaxError::UnclosedBracket { ref cursor, .. }
+            | SyntaxError::UnclosedComment { ref cursor }
+            | SyntaxError::UnclosedBlock { ref cursor }
+            | SyntaxError::UnclosedVariable { ref cursor } => cursor,
+        }
+    }
+}
+
 impl Display for SyntaxError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         try!(write!(f, "{}", self.description()));
@@ -113,13 +141,32 @@ impl Display for SyntaxError {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub enum LexerError {
     PatternRegexError,
     _InvalidPatternMatch,
     InvalidValue {
         value: String
     },
+    Syntax(SyntaxError),
+}
+
+impl From<SyntaxError> for LexerError {
+    fn from(error: SyntaxError) -> LexerError {
+        LexerError::Syntax(error)
+    }
+}
+
+impl LexerError {
+    /// Get the stable code of the error, see `twig::api::error::index`.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            LexerError::PatternRegexError => "T0210",
+            LexerError::_InvalidPatternMatch => "T0211",
+            LexerError::InvalidValue{..} => "T0212",
+            LexerError::Syntax(ref error) => error.code(),
+        }
+    }
 }
 
 impl Error for LexerError {
@@ -128,12 +175,24 @@ impl Error for LexerError {
             LexerError::PatternRegexError => "Could not initialize lexer due to invalid regular expression.",
             LexerError::_InvalidPatternMatch => "Invalid pattern match.",
             LexerError::InvalidValue{..} => "Invalid value.",
+            LexerError::Syntax(ref error) => error.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        match *self {
+            LexerError::Syntax(ref error) => Some(error),
+            _ => None,
         }
     }
 }
 
 impl Display for LexerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let LexerError::Syntax(ref error) = *self {
+            return write!(f, "{}", error);
+        }
+
         try!(write!(f, "{}", self.description()));
 
         match *self {
@@ -144,6 +203,7 @@ impl Display for LexerError {
             } => {
                 write!(f, " Found value {}", value)
             },
+            LexerError::Syntax(_) => Ok(()),
         }
     }
 }
@@ -7,6 +7,7 @@
 
 use std::fmt;
 use template;
+use api::token::stream;
 use api::error::Dump;
 
 pub type Position = usize;
@@ -17,12 +18,80 @@ pub struct Cursor<'a> {
     pos: Position,   // 0,..
     end: Position,   // 0,..
     line: Line,    // 1,..
+    line_start: Position, // 0,..
     template: &'a template::Raw,
 }
 
+impl<'a> Cursor<'a> {
+    pub fn new(template: &'a template::Raw) -> Cursor<'a> {
+        Cursor {
+            pos: 0,
+            end: template.code.len(),
+            line: 1,
+            line_start: 0,
+            template,
+        }
+    }
+
+    /// Byte offset within the template code.
+    pub fn pos(&self) -> Position {
+        self.pos
+    }
+
+    pub fn line(&self) -> Line {
+        self.line
+    }
+
+    pub fn is_eof(&self) -> bool {
+        self.pos >= self.end
+    }
+
+    pub fn template(&self) -> &'a template::Raw {
+        self.template
+    }
+
+    /// Remaining template code.
+    pub fn rest(&self) -> &'a str {
+        &self.template.code[self.pos..]
+    }
+
+    /// Position (line and column) of the cursor for tokens.
+    pub fn position(&self) -> stream::Position {
+        stream::Position {
+            line: self.line,
+            column: self.template.code[self.line_start..self.pos].chars().count() + 1,
+        }
+    }
+
+    /// Moves the cursor forward by `len` bytes.
+    pub fn advance(&mut self, len: usize) {
+        let end = ::std::cmp::min(self.pos + len, self.end);
+
+        for (i, c) in self.template.code[self.pos..end].char_indices() {
+            if c == '\n' {
+                self.line += 1;
+                self.line_start = self.pos + i + 1;
+            }
+        }
+
+        self.pos = end;
+    }
+
+    /// Moves the cursor forward while `predicate` holds.
+    pub fn advance_while<P>(&mut self, predicate: P) where
+        P: Fn(char) -> bool
+    {
+        let len = self.rest().find(|c: char| !predicate(c)).unwrap_or_else(|| self.rest().len());
+
+        self.advance(len);
+    }
+}
+
 impl<'a> fmt::Display for Cursor<'a> {
-    fn fmt(&self, _f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        unimplemented!()
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "line {line} in template {name:?}",
+            line = self.line,
+            name = self.template.name())
     }
 }
 
@@ -47,8 +116,25 @@ pub struct CursorDump {
     template: template::Raw,
 }
 
+impl CursorDump {
+    /// Byte offset within the template code.
+    pub fn pos(&self) -> Position {
+        self.pos
+    }
+
+    pub fn line(&self) -> Line {
+        self.line
+    }
+
+    pub fn template(&self) -> &template::Raw {
+        &self.template
+    }
+}
+
 impl fmt::Display for CursorDump {
-    fn fmt(&self, _f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        unimplemented!()
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "line {line} in template {name:?}",
+            line = self.line,
+            name = self.template.name())
     }
 }
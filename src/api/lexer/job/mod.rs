@@ -8,7 +8,859 @@
 pub mod cursor;
 pub use self::cursor::Cursor;
 
+use template;
+use api::Lexer;
+use api::token::{Token, Punctuation, BracketType};
+use api::token::stream::{Item, Stream};
+use api::error::{Traced, Dump};
+use api::lexer::error::SyntaxError;
+
 // Finite State Machine loosely inspired by
 // * http://www.huffingtonpost.com/damien-radtke/rustic-state-machines-for_b_4466566.html
 
-pub struct Job;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Data,
+    Block,
+    Var,
+    String,
+    Interpolation,
+}
+
+#[derive(Debug)]
+pub struct Job<'a, 't> {
+    lexer: &'a Lexer,
+    cursor: Cursor<'t>,
+    stream: Stream<'t>,
+    states: Vec<State>,
+    brackets: Vec<(BracketType, usize)>, // bracket + line
+    tag_start: usize, // index of the start token of the current tag in the stream
+}
+
+impl<'a, 't> Job<'a, 't> {
+    pub fn new(lexer: &'a Lexer, template: &'t template::Raw) -> Job<'a, 't> {
+        Job {
+            lexer,
+            cursor: Cursor::new(template),
+            stream: Stream::new(template),
+            states: vec![State::Data],
+            brackets: vec![],
+            tag_start: 0,
+        }
+    }
+
+    /// Tokenizes the whole template.
+    pub fn tokenize(mut self) -> Result<Stream<'t>, Traced<SyntaxError>> {
+        while !self.cursor.is_eof() {
+            try_traced!(self.lex());
+        }
+
+        try_traced!(self.lex_eof());
+        self.push(Token::Eof);
+
+        Ok(self.stream)
+    }
+
+    /// Tokenizes the whole template and collects all syntax errors instead of stopping at the
+    /// first one.
+    ///
+    /// Tags with syntax errors are dropped from the stream. Lexing resumes after the next
+    /// `%}` or `}}`.
+    pub fn tokenize_recovering(mut self) -> (Stream<'t>, Vec<Traced<SyntaxError>>) {
+        let mut errors = vec![];
+
+        while !self.cursor.is_eof() {
+            if let Err(error) = self.lex() {
+                errors.push(error);
+                self.resync();
+            }
+        }
+
+        if let Err(error) = self.lex_eof() {
+            errors.push(error);
+            self.resync();
+        }
+
+        self.push(Token::Eof);
+
+        (self.stream, errors)
+    }
+
+    fn lex(&mut self) -> Result<(), Traced<SyntaxError>> {
+        match self.state() {
+            State::Data => self.lex_data(),
+            State::Block => self.lex_block(),
+            State::Var => self.lex_var(),
+            State::String => self.lex_string(),
+            State::Interpolation => self.lex_interpolation(),
+        }
+    }
+
+    /// Checks that all tags, strings and brackets are closed at the end of the template.
+    fn lex_eof(&self) -> Result<(), Traced<SyntaxError>> {
+        match self.state() {
+            State::Block => return traced_err!(SyntaxError::UnclosedBlock {
+                cursor: self.cursor.dump(),
+            }),
+            State::Var => return traced_err!(SyntaxError::UnclosedVariable {
+                cursor: self.cursor.dump(),
+            }),
+            _ => {},
+        }
+
+        if let Some((bracket, _)) = self.brackets.last() {
+            return traced_err!(SyntaxError::UnexpectedEof {
+                reason: match bracket {
+                    BracketType::DoubleQuote => "Unclosed string",
+                    BracketType::Interpolation => "Unclosed string interpolation",
+                    _ => "Unclosed bracket",
+                },
+                cursor: self.cursor.dump(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Drops the tokens of the current tag and moves the cursor after the next `%}` or `}}`.
+    ///
+    /// Outside of tags (e.g. in unclosed comments) the rest of the template is skipped.
+    fn resync(&mut self) {
+        let rest = self.cursor.rest();
+
+        let len = if self.states.len() > 1 {
+            let opt = self.lexer.options();
+            self.stream.truncate(self.tag_start);
+
+            [opt.tag_block_end.raw(), opt.tag_variable_end.raw()].iter()
+                .filter_map(|end| rest.find(end).map(|pos| pos + end.len()))
+                .min()
+                .unwrap_or(rest.len())
+        } else {
+            rest.len()
+        };
+
+        self.cursor.advance(len);
+        self.states.truncate(1);
+        self.brackets.clear();
+    }
+
+    fn state(&self) -> State {
+        *self.states.last().unwrap_or(&State::Data)
+    }
+
+    fn push(&mut self, token: Token) {
+        let position = self.cursor.position();
+        self.stream.push(Item::new(token, position));
+    }
+
+    fn push_at(&mut self, token: Token, position: ::api::token::stream::Position) {
+        self.stream.push(Item::new(token, position));
+    }
+
+    /// Lexes raw template text up to the next tag.
+    fn lex_data(&mut self) -> Result<(), Traced<SyntaxError>> {
+        let opt = self.lexer.options();
+        let rest = self.cursor.rest();
+        let trim = opt.whitespace_trim.raw();
+
+        // find the next tag
+        let next = [opt.tag_comment_start.raw(), opt.tag_block_start.raw(), opt.tag_expression_start.raw()]
+            .iter()
+            .filter_map(|delimiter| rest.find(delimiter).map(|pos| (pos, *delimiter)))
+            .min_by_key(|&(pos, delimiter)| (pos, -(delimiter.len() as isize)));
+
+        let (pos, delimiter) = match next {
+            Some(next) => next,
+            None => {
+                let position = self.cursor.position();
+                self.cursor.advance(rest.len());
+                self.push_at(Token::Text(rest.to_string()), position);
+
+                return Ok(());
+            }
+        };
+
+        let trimmed = rest[pos + delimiter.len()..].starts_with(trim);
+        let mut text = &rest[..pos];
+        if trimmed {
+            text = text.trim_end();
+        }
+
+        if !text.is_empty() {
+            let position = self.cursor.position();
+            self.push_at(Token::Text(text.to_string()), position);
+        }
+
+        self.cursor.advance(pos);
+        let position = self.cursor.position();
+        self.cursor.advance(delimiter.len());
+        if trimmed {
+            self.cursor.advance(trim.len());
+        }
+
+        if delimiter == opt.tag_comment_start.raw() {
+            self.lex_comment()
+        } else if delimiter == opt.tag_block_start.raw() {
+            if let Some(tag) = self.match_raw_block_start() {
+                return self.lex_raw_data(tag);
+            }
+
+            self.tag_start = self.stream.len();
+            self.push_at(Token::BlockStart, position);
+            self.states.push(State::Block);
+
+            Ok(())
+        } else {
+            self.tag_start = self.stream.len();
+            self.push_at(Token::ExpressionStart, position);
+            self.states.push(State::Var);
+
+            Ok(())
+        }
+    }
+
+    /// Skips a comment.
+    fn lex_comment(&mut self) -> Result<(), Traced<SyntaxError>> {
+        let opt = self.lexer.options();
+        let rest = self.cursor.rest();
+        let end = opt.tag_comment_end.raw();
+
+        let pos = match rest.find(end) {
+            Some(pos) => pos,
+            None => return traced_err!(SyntaxError::UnclosedComment {
+                cursor: self.cursor.dump(),
+            }),
+        };
+
+        let trimmed = rest[..pos].ends_with(opt.whitespace_trim.raw());
+        self.cursor.advance(pos + end.len());
+        self.skip_after_tag(trimmed);
+
+        Ok(())
+    }
+
+    /// Detects `{% raw %}` or `{% verbatim %}` and moves the cursor after the tag.
+    fn match_raw_block_start(&mut self) -> Option<&'static str> {
+        let opt = self.lexer.options();
+        let rest = self.cursor.rest();
+        let inner = rest.trim_start();
+
+        for tag in &["verbatim", "raw"] {
+            if !inner.starts_with(tag) {
+                continue;
+            }
+
+            let after = inner[tag.len()..].trim_start();
+            let after = if after.starts_with(opt.whitespace_trim.raw()) {
+                &after[opt.whitespace_trim.raw().len()..]
+            } else {
+                after
+            };
+
+            if after.starts_with(opt.tag_block_end.raw()) {
+                let trimmed = inner[tag.len()..].trim_start().starts_with(opt.whitespace_trim.raw());
+                let len = rest.len() - after.len() + opt.tag_block_end.raw().len();
+                self.cursor.advance(len);
+                self.skip_after_tag(trimmed);
+
+                return Some(tag);
+            }
+        }
+
+        None
+    }
+
+    /// Lexes the content of `{% raw %}..{% endraw %}` as text.
+    fn lex_raw_data(&mut self, tag: &str) -> Result<(), Traced<SyntaxError>> {
+        let opt = self.lexer.options();
+        let rest = self.cursor.rest();
+        let start = opt.tag_block_start.raw();
+        let trim = opt.whitespace_trim.raw();
+        let end_tag = format!("end{}", tag);
+        let mut offset = 0;
+
+        while let Some(pos) = rest[offset..].find(start).map(|pos| pos + offset) {
+            let after_start = &rest[pos + start.len()..];
+            let trim_before = after_start.starts_with(trim);
+            let inner = if trim_before { &after_start[trim.len()..] } else { after_start };
+            let inner = inner.trim_start();
+
+            if inner.starts_with(&end_tag[..]) {
+                let after = inner[end_tag.len()..].trim_start();
+                let trim_after = after.starts_with(trim);
+                let after = if trim_after { &after[trim.len()..] } else { after };
+
+                if after.starts_with(opt.tag_block_end.raw()) {
+                    let mut text = &rest[..pos];
+                    if trim_before {
+                        text = text.trim_end();
+                    }
+
+                    let position = self.cursor.position();
+                    self.push_at(Token::Text(text.to_string()), position);
+
+                    let len = rest.len() - after.len() + opt.tag_block_end.raw().len();
+                    self.cursor.advance(len);
+                    self.skip_after_tag(trim_after);
+
+                    return Ok(());
+                }
+            }
+
+            offset = pos + start.len();
+        }
+
+        traced_err!(SyntaxError::UnexpectedEof {
+            reason: if tag == "raw" { "Unclosed \"raw\" block" } else { "Unclosed \"verbatim\" block" },
+            cursor: self.cursor.dump(),
+        })
+    }
+
+    /// Skips whitespace after a tag with whitespace control - or a single newline after blocks and comments.
+    fn skip_after_tag(&mut self, trimmed: bool) {
+        if trimmed {
+            self.cursor.advance_while(char::is_whitespace);
+        } else if self.cursor.rest().starts_with('\n') {
+            self.cursor.advance(1);
+        }
+    }
+
+    /// Returns the length of the tag end at the cursor (including leading whitespace), if any.
+    fn match_tag_end(&self, end: &str) -> Option<(usize, bool)> {
+        let rest = self.cursor.rest();
+        let inner = rest.trim_start();
+        let trim = self.lexer.options().whitespace_trim.raw();
+
+        if inner.starts_with(end) {
+            return Some((rest.len() - inner.len() + end.len(), false));
+        }
+
+        if inner.starts_with(trim) && inner[trim.len()..].starts_with(end) {
+            return Some((rest.len() - inner.len() + trim.len() + end.len(), true));
+        }
+
+        None
+    }
+
+    fn lex_block(&mut self) -> Result<(), Traced<SyntaxError>> {
+        if self.brackets.is_empty() {
+            let end = self.lexer.options().tag_block_end.raw();
+
+            if let Some((len, trimmed)) = self.match_tag_end(end) {
+                self.push(Token::BlockEnd);
+                self.cursor.advance(len);
+                self.skip_after_tag(trimmed);
+                self.states.pop();
+
+                return Ok(());
+            }
+        }
+
+        self.lex_expression()
+    }
+
+    fn lex_var(&mut self) -> Result<(), Traced<SyntaxError>> {
+        if self.brackets.is_empty() {
+            let end = self.lexer.options().tag_variable_end.raw();
+
+            if let Some((len, trimmed)) = self.match_tag_end(end) {
+                self.push(Token::ExpressionEnd);
+                self.cursor.advance(len);
+                if trimmed {
+                    self.cursor.advance_while(char::is_whitespace);
+                }
+                self.states.pop();
+
+                return Ok(());
+            }
+        }
+
+        self.lex_expression()
+    }
+
+    fn lex_expression(&mut self) -> Result<(), Traced<SyntaxError>> {
+        self.cursor.advance_while(char::is_whitespace);
+
+        if self.cursor.is_eof() {
+            return match self.state() {
+                State::Block => traced_err!(SyntaxError::UnclosedBlock {
+                    cursor: self.cursor.dump(),
+                }),
+                State::Var => traced_err!(SyntaxError::UnclosedVariable {
+                    cursor: self.cursor.dump(),
+                }),
+                _ => traced_err!(SyntaxError::UnexpectedEof {
+                    reason: "Unclosed string interpolation",
+                    cursor: self.cursor.dump(),
+                }),
+            };
+        }
+
+        let rest = self.cursor.rest();
+
+        // operators
+        if let Some((len, operator)) = self.match_operator(rest) {
+            self.push(Token::Operator(operator));
+            self.cursor.advance(len);
+
+            return Ok(());
+        }
+
+        let c = rest.chars().next().unwrap_or(' ');
+
+        // names
+        if is_name_start(c) {
+            let len = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+            self.push(Token::Name(rest[..len].to_string()));
+            self.cursor.advance(len);
+
+            return Ok(());
+        }
+
+        // numbers
+        if c.is_ascii_digit() {
+            let (len, token) = lex_number(rest);
+            self.push(token);
+            self.cursor.advance(len);
+
+            return Ok(());
+        }
+
+        // punctuation
+        if let Some(punctuation) = Punctuation::from_char(c) {
+            match punctuation {
+                Punctuation::OpeningBracket(ref bracket) => {
+                    self.brackets.push((bracket.clone(), self.cursor.line()));
+                },
+                Punctuation::ClosingBracket(ref bracket) => {
+                    match self.brackets.pop() {
+                        None => return traced_err!(SyntaxError::UnexpectedBracket {
+                            bracket: bracket.clone(),
+                            cursor: self.cursor.dump(),
+                        }),
+                        Some((ref before, line_before)) if before != bracket => return traced_err!(SyntaxError::UnclosedBracket {
+                            bracket: bracket.clone(),
+                            bracket_before: before.clone(),
+                            line_before,
+                            cursor: self.cursor.dump(),
+                        }),
+                        _ => {},
+                    }
+                },
+                _ => {},
+            }
+
+            self.push(Token::Punctuation(punctuation));
+            self.cursor.advance(1);
+
+            return Ok(());
+        }
+
+        // strings
+        if c == '\'' {
+            return match scan_quoted(rest, '\'') {
+                Some(len) => {
+                    self.push(Token::Value(unescape(&rest[1..len - 1])));
+                    self.cursor.advance(len);
+
+                    Ok(())
+                },
+                None => traced_err!(SyntaxError::UnexpectedEof {
+                    reason: "Unclosed string",
+                    cursor: self.cursor.dump(),
+                }),
+            };
+        }
+
+        if c == '"' {
+            // plain double quoted string without interpolation
+            if let Some(len) = scan_quoted(rest, '"') {
+                if !contains_interpolation(&rest[1..len - 1]) {
+                    self.push(Token::Value(unescape(&rest[1..len - 1])));
+                    self.cursor.advance(len);
+
+                    return Ok(());
+                }
+            }
+
+            self.brackets.push((BracketType::DoubleQuote, self.cursor.line()));
+            self.states.push(State::String);
+            self.cursor.advance(1);
+
+            return Ok(());
+        }
+
+        traced_err!(SyntaxError::UnexpectedCharacter {
+            character: c,
+            cursor: self.cursor.dump(),
+        })
+    }
+
+    /// Matches the longest operator at the beginning of `code`.
+    ///
+    /// Operators consisting of several words (e.g. `not in`) may be separated by arbitrary
+    /// whitespace. They are normalized to single spaces.
+    fn match_operator(&self, code: &str) -> Option<(usize, String)> {
+        for operator in self.lexer.operators() {
+            if let Some(len) = match_words(code, operator) {
+                return Some((len, operator.clone()));
+            }
+        }
+
+        None
+    }
+
+    /// Lexes the content of a double quoted string with interpolation.
+    fn lex_string(&mut self) -> Result<(), Traced<SyntaxError>> {
+        let rest = self.cursor.rest();
+
+        if rest.starts_with("#{") {
+            self.brackets.push((BracketType::Interpolation, self.cursor.line()));
+            self.push(Token::InterpolationStart);
+            self.cursor.advance(2);
+            self.states.push(State::Interpolation);
+
+            return Ok(());
+        }
+
+        if rest.starts_with('"') {
+            self.brackets.pop();
+            self.states.pop();
+            self.cursor.advance(1);
+
+            return Ok(());
+        }
+
+        // scan up to the end of string or next interpolation
+        let mut len = 0;
+        let mut chars = rest.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            len = i;
+            match c {
+                '\\' => { chars.next(); },
+                '"' => break,
+                '#' if rest[i..].starts_with("#{") => break,
+                _ => {},
+            }
+            len = rest.len();
+        }
+
+        if len == rest.len() {
+            return traced_err!(SyntaxError::UnexpectedEof {
+                reason: "Unclosed string",
+                cursor: self.cursor.dump(),
+            });
+        }
+
+        self.push(Token::Value(unescape(&rest[..len])));
+        self.cursor.advance(len);
+
+        Ok(())
+    }
+
+    fn lex_interpolation(&mut self) -> Result<(), Traced<SyntaxError>> {
+        if let Some(&(BracketType::Interpolation, _)) = self.brackets.last() {
+            let rest = self.cursor.rest();
+            let inner = rest.trim_start();
+
+            if inner.starts_with('}') {
+                self.cursor.advance(rest.len() - inner.len());
+                self.brackets.pop();
+                self.push(Token::InterpolationEnd);
+                self.cursor.advance(1);
+                self.states.pop();
+
+                return Ok(());
+            }
+        }
+
+        self.lex_expression()
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c == '_' || c.is_alphabetic() || (c as u32) >= 0x7f
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_start(c) || c.is_ascii_digit()
+}
+
+/// Returns the length of `words` at the beginning of `code`.
+///
+/// Whitespace in `words` matches any non-empty sequence of whitespace. Words must not be
+/// followed by name characters, e.g. the operator `in` does not match `index`.
+fn match_words(code: &str, words: &str) -> Option<usize> {
+    let mut len = 0;
+
+    for (i, word) in words.split(' ').enumerate() {
+        if i > 0 {
+            let ws = code[len..].len() - code[len..].trim_start().len();
+            if ws == 0 {
+                return None;
+            }
+            len += ws;
+        }
+
+        if !code[len..].starts_with(word) {
+            return None;
+        }
+        len += word.len();
+    }
+
+    let ends_with_name_char = words.chars().last().is_some_and(is_name_char);
+    let followed_by_name_char = code[len..].chars().next().is_some_and(is_name_char);
+
+    if ends_with_name_char && followed_by_name_char {
+        return None;
+    }
+
+    Some(len)
+}
+
+/// Lexes a number like `42`, `3.14` or `1.5e3`.
+fn lex_number(code: &str) -> (usize, Token) {
+    let bytes = code.as_bytes();
+    let digits = |from: usize| bytes[from..].iter().take_while(|b| b.is_ascii_digit()).count();
+
+    let mut len = digits(0);
+    let mut float = false;
+
+    if len + 1 < bytes.len() && bytes[len] == b'.' && bytes[len + 1].is_ascii_digit() {
+        len += 1 + digits(len + 1);
+        float = true;
+    }
+
+    if len + 1 < bytes.len() && (bytes[len] == b'e' || bytes[len] == b'E') {
+        let sign = if bytes[len + 1] == b'+' || bytes[len + 1] == b'-' { 1 } else { 0 };
+        let exponent = if len + 1 + sign < bytes.len() { digits(len + 1 + sign) } else { 0 };
+
+        if exponent > 0 {
+            len += 1 + sign + exponent;
+            float = true;
+        }
+    }
+
+    let number = &code[..len];
+
+    if !float {
+        if let Ok(i) = number.parse::<i64>() {
+            return (len, Token::IntegerNumber(i));
+        }
+    }
+
+    (len, Token::FloatingNumber(number.parse::<f64>().unwrap_or(0.0)))
+}
+
+/// Returns the length of the quoted string at the beginning of `code` (including quotes).
+fn scan_quoted(code: &str, quote: char) -> Option<usize> {
+    let mut chars = code.char_indices().skip(1);
+
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c == quote {
+            return Some(i + c.len_utf8());
+        }
+    }
+
+    None
+}
+
+fn contains_interpolation(string: &str) -> bool {
+    let mut chars = string.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => { chars.next(); },
+            '#' if chars.peek() == Some(&'{') => return true,
+            _ => {},
+        }
+    }
+
+    false
+}
+
+/// Replaces escape sequences, like PHP `stripcslashes()`.
+pub fn unescape(string: &str) -> String {
+    let mut result = String::with_capacity(string.len());
+    let mut chars = string.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('v') => result.push('\u{0B}'),
+            Some('f') => result.push('\u{0C}'),
+            Some('e') => result.push('\u{1B}'),
+            Some('0') => result.push('\0'),
+            Some(c) => result.push(c),
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use api::lexer::Options;
+
+    fn lexer(operators: &[&str]) -> Lexer {
+        let mut operators: Vec<String> = operators.iter().map(|o| o.to_string()).collect();
+        operators.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+
+        Lexer {
+            opt: Options::default(),
+            operators,
+        }
+    }
+
+    fn tokens(code: &str) -> Vec<Token> {
+        let lexer = lexer(&["=", "not", "in", "not in", "starts with", "..", "-", "+", "~", "is", "is not"]);
+        let template = template::Raw::new(code, "test");
+        let stream = Job::new(&lexer, &template).tokenize().unwrap();
+
+        stream.into_iter().map(|item| item.token().clone()).collect()
+    }
+
+    #[test]
+    fn text_and_expressions() {
+        assert_eq!(tokens("Hello {{ name }}!"), vec![
+            Token::Text("Hello ".to_string()),
+            Token::ExpressionStart,
+            Token::Name("name".to_string()),
+            Token::ExpressionEnd,
+            Token::Text("!".to_string()),
+            Token::Eof,
+        ]);
+    }
+
+    #[test]
+    fn block_eats_newline() {
+        assert_eq!(tokens("{% if a %}\nb"), vec![
+            Token::BlockStart,
+            Token::Name("if".to_string()),
+            Token::Name("a".to_string()),
+            Token::BlockEnd,
+            Token::Text("b".to_string()),
+            Token::Eof,
+        ]);
+    }
+
+    #[test]
+    fn whitespace_control() {
+        assert_eq!(tokens("a  {{- 1 -}}  b {#- x -#}  c"), vec![
+            Token::Text("a".to_string()),
+            Token::ExpressionStart,
+            Token::IntegerNumber(1),
+            Token::ExpressionEnd,
+            Token::Text("b".to_string()),
+            Token::Text("c".to_string()),
+            Token::Eof,
+        ]);
+    }
+
+    #[test]
+    fn multi_word_operators() {
+        assert_eq!(tokens("{{ a not   in b starts\nwith c is not d innocent }}"), vec![
+            Token::ExpressionStart,
+            Token::Name("a".to_string()),
+            Token::Operator("not in".to_string()),
+            Token::Name("b".to_string()),
+            Token::Operator("starts with".to_string()),
+            Token::Name("c".to_string()),
+            Token::Operator("is not".to_string()),
+            Token::Name("d".to_string()),
+            Token::Name("innocent".to_string()),
+            Token::ExpressionEnd,
+            Token::Eof,
+        ]);
+    }
+
+    #[test]
+    fn numbers_and_ranges() {
+        assert_eq!(tokens("{{ 1..2.5 }}"), vec![
+            Token::ExpressionStart,
+            Token::IntegerNumber(1),
+            Token::Operator("..".to_string()),
+            Token::FloatingNumber(2.5),
+            Token::ExpressionEnd,
+            Token::Eof,
+        ]);
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(tokens(r#"{{ 'it\'s' ~ "a #{b ~ "c"} d" }}"#), vec![
+            Token::ExpressionStart,
+            Token::Value("it's".to_string()),
+            Token::Operator("~".to_string()),
+            Token::Value("a ".to_string()),
+            Token::InterpolationStart,
+            Token::Name("b".to_string()),
+            Token::Operator("~".to_string()),
+            Token::Value("c".to_string()),
+            Token::InterpolationEnd,
+            Token::Value(" d".to_string()),
+            Token::ExpressionEnd,
+            Token::Eof,
+        ]);
+    }
+
+    #[test]
+    fn hash_in_expression() {
+        let tokens = tokens("{{ {'a': {'b': 1}} }}");
+
+        assert_eq!(tokens.len(), 12);
+        assert_eq!(tokens[tokens.len() - 2], Token::ExpressionEnd);
+    }
+
+    #[test]
+    fn verbatim() {
+        assert_eq!(tokens("{% raw %}{{ a }}{% endraw %}"), vec![
+            Token::Text("{{ a }}".to_string()),
+            Token::Eof,
+        ]);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let lexer = lexer(&[]);
+
+        for code in &["{{ a", "{% a", "{# a", "{{ (a }}", "{{ a) }}", "{{ 'a }}", "{{ $ }}"] {
+            let template = template::Raw::new(*code, "test");
+            assert!(Job::new(&lexer, &template).tokenize().is_err(), "{:?} should fail", code);
+        }
+    }
+
+    #[test]
+    fn recovery() {
+        let lexer = lexer(&[]);
+        let template = template::Raw::new("a{{ $ }}b{% if (x %}c{{ 'd }}e{{ f }}{{ g", "test");
+        let (stream, errors) = Job::new(&lexer, &template).tokenize_recovering();
+        let tokens: Vec<Token> = stream.into_iter().map(|item| item.token().clone()).collect();
+
+        assert_eq!(tokens, vec![
+            Token::Text("a".to_string()),
+            Token::Text("b".to_string()),
+            Token::Text("c".to_string()),
+            Token::Text("e".to_string()),
+            Token::ExpressionStart,
+            Token::Name("f".to_string()),
+            Token::ExpressionEnd,
+            Token::Eof,
+        ]);
+
+        let codes: Vec<&str> = errors.iter().map(|error| error.code()).collect();
+        assert_eq!(codes, vec!["T0201", "T0201", "T0203", "T0207"]);
+    }
+}
@@ -8,26 +8,71 @@
 use template;
 use api::token;
 use api::error::Traced;
+use engine::ExtensionRegistry;
 
 pub mod job;
 pub mod error;
 pub mod options;
 pub use self::error::{LexerError, SyntaxError};
 pub use self::options::Options;
+pub use self::job::Job;
 
 
 #[derive(PartialEq, Debug, Default)]
-pub struct Lexer;
+pub struct Lexer {
+    opt: Options,
+    operators: Vec<String>,
+}
 
 impl Lexer {
-    pub fn new(_opt: Options) -> Result<Lexer, Traced<LexerError>> {
-        unimplemented!()
+    /// Create a new lexer for the operators registered by engine extensions.
+    pub fn new(ext: &ExtensionRegistry, opt: Options) -> Result<Lexer, Traced<LexerError>> {
+        let mut operators: Vec<String> = vec!["=".to_string(), "=>".to_string()];
+        operators.extend(ext.operators_unary().keys().cloned());
+        operators.extend(ext.operators_binary().keys().cloned());
+
+        for op in &operators {
+            if op.is_empty() || op.starts_with(char::is_whitespace) || op.ends_with(char::is_whitespace) {
+                return traced_err!(LexerError::InvalidValue {
+                    value: format!("operator {:?}", op)
+                })
+            }
+        }
+
+        // longest operators first - e.g. `not in` before `not`
+        operators.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        operators.dedup();
+
+        Ok(Lexer {
+            opt,
+            operators,
+        })
     }
 
-    #[allow(dead_code)] // TODO: testcase
-    pub fn tokenize<'a, 't> (&'a self, _template: &'t template::Raw) -> Result<token::Stream<'t>, Traced<LexerError>>
+    pub fn options(&self) -> &Options {
+        &self.opt
+    }
+
+    /// Get all operators known to the lexer, longest operators first.
+    pub fn operators(&self) -> &[String] {
+        &self.operators
+    }
+
+    pub fn tokenize<'a, 't> (&'a self, template: &'t template::Raw) -> Result<token::Stream<'t>, Traced<LexerError>>
+        where 't: 'a // the template must outlive the Lexer
+    {
+        Ok(try_traced!(Job::new(self, template).tokenize()))
+    }
+
+    /// Tokenizes a template and collects all syntax errors, see `Job::tokenize_recovering`.
+    ///
+    /// The returned token stream lacks the tags with syntax errors, but always ends with
+    /// `Token::Eof`.
+    pub fn tokenize_recovering<'a, 't> (&'a self, template: &'t template::Raw) -> (token::Stream<'t>, Vec<Traced<LexerError>>)
         where 't: 'a // the template must outlive the Lexer
     {
-        unimplemented!()
+        let (stream, errors) = Job::new(self, template).tokenize_recovering();
+
+        (stream, errors.into_iter().map(|error| error.map(LexerError::from)).collect())
     }
 }
@@ -10,9 +10,12 @@ pub mod ext;
 pub mod lexer;
 pub mod node;
 pub mod parser;
+pub mod runtime;
 pub mod token;
+pub mod value;
 pub use self::ext::Extension;
 pub use self::lexer::Lexer;
 pub use self::parser::Parser;
 pub use self::token::Token;
 pub use self::node::Node;
+pub use self::value::Value;
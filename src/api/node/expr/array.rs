@@ -0,0 +1,98 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Array and hash literals.
+
+use api::{Node, Value};
+use api::value;
+use api::node;
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Array literal, e.g. `[1, 2, 3]`.
+///
+/// Children: `[value_0, value_1, ..]`
+#[derive(Debug)]
+pub struct Array {
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Array {
+    pub fn new(values: Vec<Box<dyn Node>>, position: Position) -> Box<Array> {
+        Box::new(Array {
+            position,
+            children: values,
+        })
+    }
+
+    pub fn values(&self) -> &[Box<dyn Node>] {
+        &self.children
+    }
+}
+
+impl Node for Array {
+    fn tag(&self) -> &str { "array" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn evaluate(&self, job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        Ok(Value::Array(try_traced!(node::evaluate_all(self.values(), job))))
+    }
+}
+
+/// Hash literal, e.g. `{ 'a': 1, (b): 2 }`.
+///
+/// Children: `[key_0, value_0, key_1, value_1, ..]`
+#[derive(Debug)]
+pub struct Hash {
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Hash {
+    pub fn new(pairs: Vec<(Box<dyn Node>, Box<dyn Node>)>, position: Position) -> Box<Hash> {
+        let mut children = Vec::with_capacity(pairs.len() * 2);
+
+        for (key, value) in pairs {
+            children.push(key);
+            children.push(value);
+        }
+
+        Box::new(Hash {
+            position,
+            children,
+        })
+    }
+
+    /// Get all pairs of key and value expressions.
+    pub fn pairs(&self) -> Vec<(&dyn Node, &dyn Node)> {
+        self.children.chunks(2)
+            .filter(|pair| pair.len() == 2)
+            .map(|pair| (&*pair[0], &*pair[1]))
+            .collect()
+    }
+}
+
+impl Node for Hash {
+    fn tag(&self) -> &str { "hash" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn evaluate(&self, job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        let mut hash = value::Hash::new();
+
+        for (key, value) in self.pairs() {
+            let key = try_traced!(key.evaluate(job));
+            let value = try_traced!(value.evaluate(job));
+            hash.insert(key.to_string(), value);
+        }
+
+        Ok(Value::Hash(hash))
+    }
+}
@@ -0,0 +1,77 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Arrow functions.
+
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::value::Hash;
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Arrow function, e.g. `(value, key) => value * 2`.
+///
+/// Arrow functions are no values - they can only be passed as the first argument of filters
+/// which accept them, like `map`, `filter`, `reduce` and `sort`.
+///
+/// Children: `[body]`
+#[derive(Debug)]
+pub struct Arrow {
+    params: Vec<String>,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Arrow {
+    pub fn new(params: Vec<String>, body: Box<dyn Node>, position: Position) -> Box<Arrow> {
+        Box::new(Arrow {
+            params,
+            position,
+            children: vec![body],
+        })
+    }
+
+    /// Names of the parameters.
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+
+    pub fn body(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    /// Evaluates the body in a new scope, where the parameters are bound to the arguments.
+    ///
+    /// Missing arguments are null, additional arguments are ignored.
+    pub fn call(&self, job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let mut arguments = arguments.into_iter();
+        let scope: Hash = self.params.iter()
+            .map(|param| (&param[..], arguments.next().unwrap_or_default()))
+            .collect();
+
+        job.scoped(scope, |job| self.body().evaluate(job))
+    }
+}
+
+impl Node for Arrow {
+    fn tag(&self) -> &str { "arrow" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "params" => Ok(Value::from(self.params.clone())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn evaluate(&self, _job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        traced_err!(RuntimeError::InvalidArgument {
+            reason: format!("Arrow functions can only be passed to filters like \"map\", at {}.", self.position),
+        })
+    }
+}
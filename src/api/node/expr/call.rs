@@ -0,0 +1,305 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Calls of filters, functions, tests and macros.
+
+use api::{Node, Value};
+use api::node::{self, NodeError, Arrow};
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Filter application, e.g. `name|upper` or `items|join(', ')`.
+///
+/// Children: `[node, argument_0, argument_1, ..]`, where `argument_0` may be an arrow function,
+/// e.g. `items|map(x => x * 2)`.
+#[derive(Debug)]
+pub struct Filter {
+    name: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Filter {
+    pub fn new<N>(name: N, node: Box<dyn Node>, arguments: Vec<Box<dyn Node>>, position: Position) -> Box<Filter> where
+        N: Into<String>
+    {
+        let mut children = vec![node];
+        children.extend(arguments);
+
+        Box::new(Filter {
+            name: name.into(),
+            position,
+            children,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The filtered expression.
+    pub fn node(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    pub fn arguments(&self) -> &[Box<dyn Node>] {
+        &self.children[1..]
+    }
+}
+
+impl Node for Filter {
+    fn tag(&self) -> &str { "filter" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "name" => Ok(Value::from(self.name())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn evaluate(&self, job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        let ext = job.ext();
+        let filter = match ext.filters().get(&self.name) {
+            Some(filter) => filter,
+            None => return traced_err!(RuntimeError::UnknownFilter {
+                name: self.name.clone(),
+            }),
+        };
+
+        let value = if filter.accepts_undefined() {
+            try_traced!(job.lenient(|job| self.node().evaluate(job)))
+        } else {
+            try_traced!(self.node().evaluate(job))
+        };
+        let arrow = self.arguments().first().and_then(|a| a.downcast_ref::<Arrow>());
+        let arguments = match arrow {
+            Some(_) => try_traced!(node::evaluate_all(&self.arguments()[1..], job)),
+            None => try_traced!(node::evaluate_all(self.arguments(), job)),
+        };
+        job.set_position(&self.position);
+
+        match arrow {
+            Some(arrow) => match filter.apply_arrow(job, value, arrow, arguments) {
+                Some(result) => result,
+                None => traced_err!(RuntimeError::InvalidArgumentType {
+                    callable: format!("filter {:?}", self.name),
+                    argument: "argument 1".to_string(),
+                    expected: "a value".to_string(),
+                    found: "arrow function",
+                    template: job.template_name().to_string(),
+                    position: self.position.clone(),
+                }),
+            },
+            None => filter.apply(job, value, arguments),
+        }
+    }
+}
+
+/// Function call, e.g. `range(1, 10)`.
+///
+/// Children: `[argument_0, argument_1, ..]`
+#[derive(Debug)]
+pub struct Function {
+    name: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Function {
+    pub fn new<N>(name: N, arguments: Vec<Box<dyn Node>>, position: Position) -> Box<Function> where
+        N: Into<String>
+    {
+        Box::new(Function {
+            name: name.into(),
+            position,
+            children: arguments,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arguments(&self) -> &[Box<dyn Node>] {
+        &self.children
+    }
+}
+
+impl Node for Function {
+    fn tag(&self) -> &str { "function" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "name" => Ok(Value::from(self.name())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn evaluate(&self, job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        let arguments = try_traced!(node::evaluate_all(self.arguments(), job));
+        let ext = job.ext();
+        job.set_position(&self.position);
+
+        match ext.functions().get(&self.name) {
+            Some(function) => function.call(job, arguments),
+            None => traced_err!(RuntimeError::UnknownFunction {
+                name: self.name.clone(),
+            }),
+        }
+    }
+}
+
+/// Test, e.g. `foo is defined` or `n is divisible by(3)`.
+///
+/// Children: `[node, argument_0, argument_1, ..]`
+#[derive(Debug)]
+pub struct Test {
+    name: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Test {
+    pub fn new<N>(name: N, node: Box<dyn Node>, arguments: Vec<Box<dyn Node>>, position: Position) -> Box<Test> where
+        N: Into<String>
+    {
+        let mut children = vec![node];
+        children.extend(arguments);
+
+        Box::new(Test {
+            name: name.into(),
+            position,
+            children,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The tested expression.
+    pub fn node(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    pub fn arguments(&self) -> &[Box<dyn Node>] {
+        &self.children[1..]
+    }
+}
+
+impl Node for Test {
+    fn tag(&self) -> &str { "test" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "name" => Ok(Value::from(self.name())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn evaluate(&self, job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        let ext = job.ext();
+        let test = match ext.tests().get(&self.name) {
+            Some(test) => test,
+            None => return traced_err!(RuntimeError::UnknownTest {
+                name: self.name.clone(),
+            }),
+        };
+
+        let arguments = try_traced!(node::evaluate_all(self.arguments(), job));
+        job.set_position(&self.position);
+
+        if let Some(result) = test.test_node(job, self.node(), &arguments) {
+            return Ok(Value::from(try_traced!(result)));
+        }
+
+        let value = try_traced!(self.node().evaluate(job));
+        job.set_position(&self.position);
+
+        Ok(Value::from(try_traced!(test.test(job, value, arguments))))
+    }
+}
+
+/// Macro call, e.g. `forms.input('name')` after `{% import 'forms.twig' as forms %}`.
+///
+/// Children: `[template, argument_0, argument_1, ..]`, where `template` evaluates to the name
+/// of the template defining the macro.
+#[derive(Debug)]
+pub struct MacroCall {
+    name: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl MacroCall {
+    pub fn new<N>(template: Box<dyn Node>, name: N, arguments: Vec<Box<dyn Node>>, position: Position) -> Box<MacroCall> where
+        N: Into<String>
+    {
+        let mut children = vec![template];
+        children.extend(arguments);
+
+        Box::new(MacroCall {
+            name: name.into(),
+            position,
+            children,
+        })
+    }
+
+    /// Name of the macro.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Expression for the name of the template defining the macro.
+    pub fn template(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    pub fn arguments(&self) -> &[Box<dyn Node>] {
+        &self.children[1..]
+    }
+}
+
+impl Node for MacroCall {
+    fn tag(&self) -> &str { "macro_call" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "name" => Ok(Value::from(self.name())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn evaluate(&self, job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        let template = try_traced!(self.template().evaluate(job));
+        let arguments = try_traced!(node::evaluate_all(self.arguments(), job));
+
+        if template.is_null() {
+            return traced_err!(RuntimeError::UnknownMacro {
+                name: self.name.clone(),
+                template: None,
+                caller: job.template_name().to_string(),
+                position: self.position.clone(),
+            });
+        }
+
+        let template = try_traced!(job.load_template(&template.to_string(), &self.position));
+
+        job.call_macro(template, &self.name, arguments, &self.position)
+    }
+}
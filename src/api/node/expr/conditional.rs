@@ -0,0 +1,56 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Ternary operator.
+
+use api::{Node, Value};
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Conditional expression, e.g. `a ? b : c`.
+///
+/// Children: `[test, then, else]`
+#[derive(Debug)]
+pub struct Conditional {
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Conditional {
+    pub fn new(test: Box<dyn Node>, then: Box<dyn Node>, otherwise: Box<dyn Node>, position: Position) -> Box<Conditional> {
+        Box::new(Conditional {
+            position,
+            children: vec![test, then, otherwise],
+        })
+    }
+
+    pub fn test(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    pub fn then(&self) -> &dyn Node {
+        &*self.children[1]
+    }
+
+    pub fn otherwise(&self) -> &dyn Node {
+        &*self.children[2]
+    }
+}
+
+impl Node for Conditional {
+    fn tag(&self) -> &str { "conditional" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn evaluate(&self, job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        if try_traced!(self.test().evaluate(job)).to_bool() {
+            self.then().evaluate(job)
+        } else {
+            self.otherwise().evaluate(job)
+        }
+    }
+}
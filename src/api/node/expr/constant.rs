@@ -0,0 +1,52 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Constant expressions.
+
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Literal value, e.g. `"foo"`, `42` or `true`.
+#[derive(Debug)]
+pub struct Constant {
+    value: Value,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Constant {
+    pub fn new(value: Value, position: Position) -> Box<Constant> {
+        Box::new(Constant {
+            value,
+            position,
+            children: vec![],
+        })
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+}
+
+impl Node for Constant {
+    fn tag(&self) -> &str { "constant" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "value" => Ok(self.value.clone()),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn evaluate(&self, _job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        Ok(self.value.clone())
+    }
+}
@@ -0,0 +1,124 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Attribute access.
+
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::runtime::{Job, RuntimeError};
+use api::error::{suggest, Traced};
+use api::token::stream::Position;
+
+/// Kind of attribute access.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttributeType {
+    /// `foo.bar` - array key, object property or method
+    Any,
+    /// `foo['bar']` - array key only
+    Array,
+    /// `foo.bar()` - method call
+    Method,
+}
+
+impl AttributeType {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            AttributeType::Any => "any",
+            AttributeType::Array => "array",
+            AttributeType::Method => "method",
+        }
+    }
+}
+
+/// Access to an attribute of a value, e.g. `user.name`, `items[0]` or `forms.input('x')`.
+///
+/// Children: `[node, attribute, arguments..]`
+#[derive(Debug)]
+pub struct GetAttr {
+    call_type: AttributeType,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl GetAttr {
+    pub fn new(node: Box<dyn Node>, attribute: Box<dyn Node>, arguments: Vec<Box<dyn Node>>, call_type: AttributeType, position: Position) -> Box<GetAttr> {
+        let mut children = vec![node, attribute];
+        children.extend(arguments);
+
+        Box::new(GetAttr {
+            call_type,
+            position,
+            children,
+        })
+    }
+
+    pub fn call_type(&self) -> AttributeType {
+        self.call_type
+    }
+
+    /// The expression whose attribute is accessed.
+    pub fn node(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    /// The attribute name or key expression.
+    pub fn attribute_node(&self) -> &dyn Node {
+        &*self.children[1]
+    }
+
+    /// Method arguments.
+    pub fn arguments(&self) -> &[Box<dyn Node>] {
+        &self.children[2..]
+    }
+}
+
+impl Node for GetAttr {
+    fn tag(&self) -> &str { "get_attr" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "type" => Ok(Value::from(self.call_type.name())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn evaluate(&self, job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        let value = try_traced!(self.node().evaluate(job));
+        let attribute = try_traced!(self.attribute_node().evaluate(job));
+
+        match value.attribute(&attribute) {
+            Some(value) => Ok(value.clone()),
+            None if job.strict_variables() => {
+                let similar = match value {
+                    Value::Hash(ref hash) => suggest::similar(&attribute.to_string(), hash.keys()),
+                    _ => vec![],
+                };
+
+                traced_err!(RuntimeError::UndefinedAttribute {
+                    attribute: attribute.to_string(),
+                    found: value.type_name(),
+                    template: job.template_name().to_string(),
+                    position: self.position.clone(),
+                    similar,
+                })
+            },
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn is_defined(&self, job: &mut Job) -> Result<bool, Traced<RuntimeError>> {
+        if !try_traced!(self.node().is_defined(job)) {
+            return Ok(false);
+        }
+
+        let value = try_traced!(self.node().evaluate(job));
+        let attribute = try_traced!(self.attribute_node().evaluate(job));
+
+        Ok(value.attribute(&attribute).is_some())
+    }
+}
@@ -0,0 +1,23 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Expression nodes.
+
+pub mod array;
+pub mod arrow;
+pub mod call;
+pub mod conditional;
+pub mod constant;
+pub mod get_attr;
+pub mod name;
+pub mod operator;
+pub use self::array::{Array, Hash};
+pub use self::arrow::Arrow;
+pub use self::call::{Filter, Function, Test, MacroCall};
+pub use self::conditional::Conditional;
+pub use self::constant::Constant;
+pub use self::get_attr::{GetAttr, AttributeType};
+pub use self::name::Name;
+pub use self::operator::{Binary, Unary};
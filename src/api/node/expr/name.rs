@@ -0,0 +1,72 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Variable names.
+
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::runtime::{Job, RuntimeError};
+use api::error::{suggest, Traced};
+use api::token::stream::Position;
+
+/// Variable lookup, e.g. `user`.
+#[derive(Debug)]
+pub struct Name {
+    name: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Name {
+    pub fn new<N>(name: N, position: Position) -> Box<Name> where
+        N: Into<String>
+    {
+        Box::new(Name {
+            name: name.into(),
+            position,
+            children: vec![],
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Node for Name {
+    fn tag(&self) -> &str { "name" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "name" => Ok(Value::from(self.name())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    /// Evaluates to the value of the variable - or `_self` to the name of the current template.
+    fn evaluate(&self, job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        if self.name == "_self" {
+            return Ok(Value::from(job.template_name()));
+        }
+
+        match try_traced!(job.lookup(&self.name)) {
+            Some(value) => Ok(value),
+            None if job.strict_variables() => traced_err!(RuntimeError::UndefinedVariable {
+                name: self.name.clone(),
+                template: job.template_name().to_string(),
+                position: self.position.clone(),
+                similar: suggest::similar(&self.name, job.variable_names()),
+            }),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn is_defined(&self, job: &mut Job) -> Result<bool, Traced<RuntimeError>> {
+        Ok(self.name == "_self" || try_traced!(job.lookup(&self.name)).is_some())
+    }
+}
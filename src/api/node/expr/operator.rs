@@ -0,0 +1,139 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Operator expressions.
+
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::ext::{UnaryOperator, BinaryOperator};
+use api::ext::op::{Operation, Callable};
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Binary operation, e.g. `a + b`.
+///
+/// Children: `[left, right]`
+#[derive(Debug)]
+pub struct Binary {
+    operator: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Binary {
+    pub fn new<O>(operator: O, left: Box<dyn Node>, right: Box<dyn Node>, position: Position) -> Box<Binary> where
+        O: Into<String>
+    {
+        Box::new(Binary {
+            operator: operator.into(),
+            position,
+            children: vec![left, right],
+        })
+    }
+
+    /// Token representation of the operator, e.g. `"+"`.
+    pub fn operator(&self) -> &str {
+        &self.operator
+    }
+
+    pub fn left(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    pub fn right(&self) -> &dyn Node {
+        &*self.children[1]
+    }
+}
+
+impl Node for Binary {
+    fn tag(&self) -> &str { "binary" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "operator" => Ok(Value::from(self.operator())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn evaluate(&self, job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        let left = try_traced!(self.left().evaluate(job));
+        let right = try_traced!(self.right().evaluate(job));
+        job.set_position(&self.position);
+
+        if let Some(&BinaryOperator { op: Operation::Callable(ref function), .. }) = job.ext().operators_binary().get(&self.operator) {
+            if let Callable::Binary(ref callable) = *function.callable() {
+                return callable(&left, &right);
+            }
+        }
+
+        traced_err!(RuntimeError::UnknownOperator {
+            repr: self.operator.clone(),
+        })
+    }
+}
+
+/// Unary operation, e.g. `not a` or `-b`.
+///
+/// Children: `[node]`
+#[derive(Debug)]
+pub struct Unary {
+    operator: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Unary {
+    pub fn new<O>(operator: O, node: Box<dyn Node>, position: Position) -> Box<Unary> where
+        O: Into<String>
+    {
+        Box::new(Unary {
+            operator: operator.into(),
+            position,
+            children: vec![node],
+        })
+    }
+
+    /// Token representation of the operator, e.g. `"not"`.
+    pub fn operator(&self) -> &str {
+        &self.operator
+    }
+
+    pub fn node(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+}
+
+impl Node for Unary {
+    fn tag(&self) -> &str { "unary" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "operator" => Ok(Value::from(self.operator())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn evaluate(&self, job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        let operand = try_traced!(self.node().evaluate(job));
+        job.set_position(&self.position);
+
+        if let Some(&UnaryOperator { op: Operation::Callable(ref function), .. }) = job.ext().operators_unary().get(&self.operator) {
+            if let Callable::Unary(ref callable) = *function.callable() {
+                return callable(&operand);
+            }
+        }
+
+        traced_err!(RuntimeError::UnknownOperator {
+            repr: self.operator.clone(),
+        })
+    }
+}
@@ -4,13 +4,194 @@
 // file that was distributed with this source code.
 
 //! Node.
+//!
+//! Nodes of the abstract syntax tree, created by the parser and token parsers.
+//!
+//! Each node stores its sub-nodes as a list of children (like `Twig_Node` in TwigPHP), which
+//! allows node visitors to traverse and modify the tree generically. Concrete node types
+//! provide typed accessors for their children and attributes. Node visitors may replace
+//! nodes, but only remove children of `Body` nodes - removing required child nodes (e.g. the
+//! condition of an `If` node) fails with `ParserError::NodeVisitorFailure`.
+//!
+//! A `Box<Node>` can be downcast to concrete node types:
+//!
+//! ```
+//! use twig::api::Node;
+//! use twig::api::node::{Text, Body};
+//! use twig::api::token::stream::Position;
+//!
+//! let body: Box<dyn Node> = Body::new(vec![Text::new("Hello", Position::default())], Position::default());
+//! let text = body.children()[0].downcast_ref::<Text>().unwrap();
+//!
+//! assert_eq!(text.data(), "Hello");
+//! ```
 
+use std::any::Any;
 use std::fmt::Debug;
+use api::Value;
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
 use api::token::stream::Position;
 
-pub trait Node : Debug {
+pub mod expr;
+pub mod stmt;
+pub mod traverser;
+pub use self::expr::{Constant, Name, GetAttr, AttributeType, Filter, Function, Test, MacroCall, Binary, Unary,
+    Conditional, Array, Hash, Arrow};
+pub use self::stmt::{Body, Module, Text, Print, Do, If, For, Set, With, Apply, Block, BlockReference, Use,
+    Include, Embed, Macro, Import, AutoEscape};
+pub use self::traverser::Traverser;
+pub use api::parser::error::NodeError;
+
+pub trait Node : Debug + Any {
     fn tag(&self) -> &str;
     fn position(&self) -> &Position;
-    fn children(&self) -> &Vec<Box<Node>>;
-    fn children_mut(&mut self) -> &mut Vec<Box<Node>>;
+    fn children(&self) -> &Vec<Box<dyn Node>>;
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>>;
+
+    /// Get a named attribute of this node, e.g. the `name` of a block.
+    ///
+    /// # Failures
+    /// * When the node has no attribute with the given key
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        attribute_not_found(self.tag(), key)
+    }
+
+    /// Renders the node to the output of the runtime job.
+    ///
+    /// By default all child nodes are rendered in order.
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        for child in self.children() {
+            try_traced!(child.render(job));
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates an expression node.
+    ///
+    /// # Failures
+    /// * When the node is not an expression (default)
+    fn evaluate(&self, _job: &mut Job) -> Result<Value, Traced<RuntimeError>> {
+        traced_err!(RuntimeError::NotAnExpression {
+            tag: self.tag().to_string(),
+        })
+    }
+
+    /// Returns true if an expression node refers to something defined, e.g. an existing variable.
+    ///
+    /// Used by the `defined` test. Unlike `evaluate()`, this must not fail for undefined
+    /// variables or attributes. By default, the node is evaluated and considered defined.
+    fn is_defined(&self, job: &mut Job) -> Result<bool, Traced<RuntimeError>> {
+        try_traced!(self.evaluate(job));
+
+        Ok(true)
+    }
+}
+
+impl dyn Node {
+    /// Returns true if the node is of type `T`.
+    pub fn is<T: Node>(&self) -> bool {
+        (self as &dyn Any).is::<T>()
+    }
+
+    /// Returns a reference to the concrete node type, if it is of type `T`.
+    pub fn downcast_ref<T: Node>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref::<T>()
+    }
+
+    /// Returns a mutable reference to the concrete node type, if it is of type `T`.
+    pub fn downcast_mut<T: Node>(&mut self) -> Option<&mut T> {
+        (self as &mut dyn Any).downcast_mut::<T>()
+    }
+
+    /// Converts the boxed node to the concrete node type, if it is of type `T`.
+    ///
+    /// Returns the original node otherwise.
+    pub fn downcast<T: Node>(self: Box<Self>) -> Result<Box<T>, Box<dyn Node>> {
+        if self.is::<T>() {
+            let any: Box<dyn Any> = self;
+
+            Ok(any.downcast::<T>().unwrap_or_else(|_| unreachable!()))
+        } else {
+            Err(self)
+        }
+    }
+}
+
+/// Evaluates a list of expression nodes, e.g. arguments of a function call.
+pub fn evaluate_all(nodes: &[Box<dyn Node>], job: &mut Job) -> Result<Vec<Value>, Traced<RuntimeError>> {
+    let mut values = Vec::with_capacity(nodes.len());
+
+    for node in nodes {
+        values.push(try_traced!(node.evaluate(job)));
+    }
+
+    Ok(values)
+}
+
+/// Error for attributes which are not defined by a node.
+pub fn attribute_not_found<T>(node_tag: &str, key: &str) -> Result<T, Traced<NodeError>> {
+    traced_err!(NodeError::AttributeNotFound {
+        key: key.to_string(),
+        node_tag: node_tag.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pos() -> Position {
+        Position { line: 1, column: 1 }
+    }
+
+    #[test]
+    fn downcast() {
+        let node: Box<dyn Node> = Print::new(Name::new("foo", pos()), pos());
+
+        assert!(node.is::<Print>());
+        assert!(node.downcast_ref::<Text>().is_none());
+        assert_eq!(node.children()[0].downcast_ref::<Name>().unwrap().name(), "foo");
+
+        let node = node.downcast::<Text>().unwrap_err();
+        let print = node.downcast::<Print>().unwrap();
+        assert_eq!(print.expr().tag(), "name");
+    }
+
+    #[test]
+    fn attributes() {
+        let node: Box<dyn Node> = Block::new("content", Body::new(vec![], pos()), pos());
+
+        assert_eq!(node.attribute("name").unwrap(), Value::from("content"));
+
+        match *node.attribute("missing").unwrap_err() {
+            NodeError::AttributeNotFound { ref key, ref node_tag } => {
+                assert_eq!(key, "missing");
+                assert_eq!(node_tag, "block");
+            },
+        }
+    }
+
+    #[test]
+    fn if_branches() {
+        let node = If::new(vec![
+            (Name::new("a", pos()) as Box<dyn Node>, Text::new("A", pos()) as Box<dyn Node>),
+            (Name::new("b", pos()), Text::new("B", pos())),
+        ], Some(Text::new("C", pos())), pos());
+
+        let tests = node.tests();
+        assert_eq!(tests.len(), 2);
+        assert_eq!(tests[1].0.attribute("name").unwrap(), Value::from("b"));
+        assert_eq!(node.else_body().unwrap().attribute("data").unwrap(), Value::from("C"));
+    }
+
+    #[test]
+    fn binary() {
+        let node = Binary::new("+", Constant::new(Value::from(1), pos()), Constant::new(Value::from(2), pos()), pos());
+
+        assert_eq!(node.operator(), "+");
+        assert_eq!(node.left().attribute("value").unwrap(), Value::from(1));
+        assert_eq!(node.right().attribute("value").unwrap(), Value::from(2));
+    }
 }
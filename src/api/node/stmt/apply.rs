@@ -0,0 +1,76 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Filtered output.
+
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::value::Hash;
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Applies filters to the rendered body, e.g. `{% apply upper|trim %}..{% endapply %}`.
+///
+/// The filter expression refers to the rendered body by a temporary variable, which is only
+/// defined while the filters are evaluated.
+///
+/// Children: `[body, filter]`
+#[derive(Debug)]
+pub struct Apply {
+    variable: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Apply {
+    pub fn new<V>(variable: V, body: Box<dyn Node>, filter: Box<dyn Node>, position: Position) -> Box<Apply> where
+        V: Into<String>
+    {
+        Box::new(Apply {
+            variable: variable.into(),
+            position,
+            children: vec![body, filter],
+        })
+    }
+
+    /// Name of the temporary variable holding the rendered body.
+    pub fn variable(&self) -> &str {
+        &self.variable
+    }
+
+    pub fn body(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    pub fn filter(&self) -> &dyn Node {
+        &*self.children[1]
+    }
+}
+
+impl Node for Apply {
+    fn tag(&self) -> &str { "apply" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "variable" => Ok(Value::from(self.variable())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        let body = try_traced!(job.capture(|job| self.body().render(job)));
+
+        let mut scope = Hash::new();
+        scope.insert(&self.variable[..], Value::from(body));
+        let value = try_traced!(job.scoped(scope, |job| self.filter().evaluate(job)));
+        job.write(&value.to_string());
+
+        Ok(())
+    }
+}
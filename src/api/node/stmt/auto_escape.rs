@@ -0,0 +1,57 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Auto-escaping of a body.
+
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Changes the auto-escaping strategy of a body, e.g. `{% autoescape 'js' %}..{% endautoescape %}`.
+///
+/// The strategy is applied to the print statements of the body by the escaper extension at
+/// compile time - the node itself just renders its body.
+///
+/// Children: `[body]`
+#[derive(Debug)]
+pub struct AutoEscape {
+    strategy: Option<String>,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl AutoEscape {
+    pub fn new(strategy: Option<String>, body: Box<dyn Node>, position: Position) -> Box<AutoEscape> {
+        Box::new(AutoEscape {
+            strategy,
+            position,
+            children: vec![body],
+        })
+    }
+
+    /// Get the name of the strategy, or `None` if auto-escaping is disabled.
+    pub fn strategy(&self) -> Option<&str> {
+        self.strategy.as_deref()
+    }
+
+    pub fn body(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+}
+
+impl Node for AutoEscape {
+    fn tag(&self) -> &str { "autoescape" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "strategy" => Ok(Value::from(self.strategy())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Block definitions, references and imports.
+
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Definition of a named block, e.g. `{% block content %}..{% endblock %}`.
+///
+/// Children: `[body]`
+#[derive(Debug)]
+pub struct Block {
+    name: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Block {
+    pub fn new<N>(name: N, body: Box<dyn Node>, position: Position) -> Box<Block> where
+        N: Into<String>
+    {
+        Box::new(Block {
+            name: name.into(),
+            position,
+            children: vec![body],
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn body(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+}
+
+impl Node for Block {
+    fn tag(&self) -> &str { "block" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "name" => Ok(Value::from(self.name())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+}
+
+/// Placeholder in the template body, where a block is rendered.
+///
+/// The block definition itself is stored in the module (see `Module::blocks()`).
+#[derive(Debug)]
+pub struct BlockReference {
+    name: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl BlockReference {
+    pub fn new<N>(name: N, position: Position) -> Box<BlockReference> where
+        N: Into<String>
+    {
+        Box::new(BlockReference {
+            name: name.into(),
+            position,
+            children: vec![],
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Node for BlockReference {
+    fn tag(&self) -> &str { "block_reference" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "name" => Ok(Value::from(self.name())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    /// Renders the most derived definition of the block.
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        job.render_block(&self.name)
+    }
+}
+
+/// Imports the blocks of another template, e.g. `{% use 'blocks.twig' with sidebar as base_sidebar %}`.
+///
+/// Children: `[template]`, where `template` is a constant template name.
+#[derive(Debug)]
+pub struct Use {
+    renames: Vec<(String, String)>,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Use {
+    /// Create a new import with a list of renamed blocks, i.e. `(name, alias)` pairs.
+    pub fn new(template: Box<dyn Node>, renames: Vec<(String, String)>, position: Position) -> Box<Use> {
+        Box::new(Use {
+            renames,
+            position,
+            children: vec![template],
+        })
+    }
+
+    /// Expression for the template name.
+    pub fn template(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    /// Renamed blocks as `(name, alias)` pairs.
+    pub fn renames(&self) -> &[(String, String)] {
+        &self.renames
+    }
+
+    /// Get the name a block of the used template is imported as.
+    pub fn alias<'a>(&'a self, name: &'a str) -> &'a str {
+        self.renames.iter().find(|r| r.0 == name).map_or(name, |r| &r.1[..])
+    }
+}
+
+impl Node for Use {
+    fn tag(&self) -> &str { "use" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "renames" => Ok(Value::Hash(self.renames.iter().map(|r| (r.0.clone(), Value::from(&r.1[..]))).collect())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+}
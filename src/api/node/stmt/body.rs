@@ -0,0 +1,32 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Generic list of nodes.
+
+use api::Node;
+use api::token::stream::Position;
+
+/// Sequence of nodes, e.g. the body of a block or a for-loop.
+#[derive(Debug)]
+pub struct Body {
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Body {
+    pub fn new(children: Vec<Box<dyn Node>>, position: Position) -> Box<Body> {
+        Box::new(Body {
+            position,
+            children,
+        })
+    }
+}
+
+impl Node for Body {
+    fn tag(&self) -> &str { "body" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+}
@@ -0,0 +1,220 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Control structures.
+
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::value::Hash;
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Conditional branches, e.g. `{% if a %}..{% elseif b %}..{% else %}..{% endif %}`.
+///
+/// Children: `[test_0, body_0, test_1, body_1, .., else?]`
+#[derive(Debug)]
+pub struct If {
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl If {
+    pub fn new(tests: Vec<(Box<dyn Node>, Box<dyn Node>)>, else_body: Option<Box<dyn Node>>, position: Position) -> Box<If> {
+        let mut children = Vec::with_capacity(tests.len() * 2 + 1);
+
+        for (test, body) in tests {
+            children.push(test);
+            children.push(body);
+        }
+
+        if let Some(else_body) = else_body {
+            children.push(else_body);
+        }
+
+        Box::new(If {
+            position,
+            children,
+        })
+    }
+
+    /// Get all pairs of conditions and bodies.
+    pub fn tests(&self) -> Vec<(&dyn Node, &dyn Node)> {
+        self.children.chunks(2)
+            .filter(|pair| pair.len() == 2)
+            .map(|pair| (&*pair[0], &*pair[1]))
+            .collect()
+    }
+
+    pub fn else_body(&self) -> Option<&dyn Node> {
+        if self.children.len() % 2 == 1 {
+            self.children.last().map(|b| &**b)
+        } else {
+            None
+        }
+    }
+}
+
+impl Node for If {
+    fn tag(&self) -> &str { "if" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        for (test, body) in self.tests() {
+            if try_traced!(test.evaluate(job)).to_bool() {
+                return body.render(job);
+            }
+        }
+
+        match self.else_body() {
+            Some(else_body) => else_body.render(job),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Loop over a sequence, e.g. `{% for key, value in items %}..{% else %}..{% endfor %}`.
+///
+/// Children: `[seq, body, else?]`
+#[derive(Debug)]
+pub struct For {
+    key_target: Option<String>,
+    value_target: String,
+    with_loop: bool,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl For {
+    pub fn new<V>(key_target: Option<String>, value_target: V, seq: Box<dyn Node>, body: Box<dyn Node>, else_body: Option<Box<dyn Node>>, position: Position) -> Box<For> where
+        V: Into<String>
+    {
+        let mut children = vec![seq, body];
+
+        if let Some(else_body) = else_body {
+            children.push(else_body);
+        }
+
+        Box::new(For {
+            key_target,
+            value_target: value_target.into(),
+            with_loop: true,
+            position,
+            children,
+        })
+    }
+
+    /// Name of the key variable, if any.
+    pub fn key_target(&self) -> Option<&str> {
+        self.key_target.as_ref().map(|k| &k[..])
+    }
+
+    /// Name of the value variable.
+    pub fn value_target(&self) -> &str {
+        &self.value_target
+    }
+
+    /// Whether the special `loop` variable is maintained (default: true).
+    pub fn with_loop(&self) -> bool {
+        self.with_loop
+    }
+
+    pub fn set_with_loop(&mut self, with_loop: bool) {
+        self.with_loop = with_loop;
+    }
+
+    pub fn seq(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    pub fn body(&self) -> &dyn Node {
+        &*self.children[1]
+    }
+
+    pub fn else_body(&self) -> Option<&dyn Node> {
+        self.children.get(2).map(|b| &**b)
+    }
+}
+
+impl Node for For {
+    fn tag(&self) -> &str { "for" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "key_target" => Ok(Value::from(self.key_target())),
+            "value_target" => Ok(Value::from(self.value_target())),
+            "with_loop" => Ok(Value::from(self.with_loop())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    /// Renders the body for each element of the sequence, or the else-body for empty sequences.
+    ///
+    /// Like in TwigPHP, variables assigned in the loop are discarded afterwards - unless they
+    /// were already defined before the loop.
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        let pairs = try_traced!(self.seq().evaluate(job)).to_pairs();
+
+        if pairs.is_empty() {
+            return match self.else_body() {
+                Some(else_body) => else_body.render(job),
+                None => Ok(()),
+            };
+        }
+
+        let parent = job.context().flatten();
+        let length = pairs.len();
+
+        job.context_mut().push_scope(Hash::new());
+        let mut result = Ok(());
+
+        for (index, (key, value)) in pairs.into_iter().enumerate() {
+            if let Some(key_target) = self.key_target() {
+                job.set(key_target, key);
+            }
+            job.set(self.value_target(), value);
+
+            if self.with_loop() {
+                job.set("loop", Value::from(loop_variable(&parent, index, length)));
+            }
+
+            result = self.body().render(job);
+            if result.is_err() {
+                break;
+            }
+        }
+
+        let scope = job.context_mut().pop_scope().unwrap_or_default();
+        for (name, value) in scope {
+            if parent.contains_key(&name) && name != "loop"
+                && name != self.value_target() && Some(&name[..]) != self.key_target()
+            {
+                job.set(name, value);
+            }
+        }
+
+        result
+    }
+}
+
+/// Builds the special `loop` variable of an iteration.
+fn loop_variable(parent: &Hash, index: usize, length: usize) -> Hash {
+    let mut variable = Hash::new();
+    variable.insert("parent", Value::Hash(parent.clone()));
+    variable.insert("index0", Value::from(index));
+    variable.insert("index", Value::from(index + 1));
+    variable.insert("revindex0", Value::from(length - index - 1));
+    variable.insert("revindex", Value::from(length - index));
+    variable.insert("first", Value::from(index == 0));
+    variable.insert("last", Value::from(index + 1 == length));
+    variable.insert("length", Value::from(length));
+
+    variable
+}
@@ -0,0 +1,199 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Inclusion of other templates.
+
+use std::rc::Rc;
+use template;
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::value::Hash;
+use api::runtime::{Job, RuntimeError, FrameKind};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Renders another template, e.g. `{% include 'x' with {..} only ignore missing %}`.
+///
+/// Children: `[expr, variables?]`
+#[derive(Debug)]
+pub struct Include {
+    only: bool,
+    ignore_missing: bool,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Include {
+    pub fn new(expr: Box<dyn Node>, variables: Option<Box<dyn Node>>, only: bool, ignore_missing: bool, position: Position) -> Box<Include> {
+        let mut children = vec![expr];
+
+        if let Some(variables) = variables {
+            children.push(variables);
+        }
+
+        Box::new(Include {
+            only,
+            ignore_missing,
+            position,
+            children,
+        })
+    }
+
+    /// Expression for the template name.
+    pub fn expr(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    /// Expression for additional variables.
+    pub fn variables(&self) -> Option<&dyn Node> {
+        self.children.get(1).map(|v| &**v)
+    }
+
+    /// Whether the included template has access to the current context.
+    pub fn only(&self) -> bool {
+        self.only
+    }
+
+    pub fn ignore_missing(&self) -> bool {
+        self.ignore_missing
+    }
+}
+
+impl Node for Include {
+    fn tag(&self) -> &str { "include" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "only" => Ok(Value::from(self.only())),
+            "ignore_missing" => Ok(Value::from(self.ignore_missing())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        let names = try_traced!(self.expr().evaluate(job));
+
+        let template = match job.load_first_template(&names, &self.position) {
+            Ok(template) => template,
+            Err(ref error) if self.ignore_missing() && error.is_template_not_found() => return Ok(()),
+            Err(error) => return Err(error),
+        };
+
+        render_with(job, template, self.variables(), self.only(), FrameKind::Include, &self.position)
+    }
+}
+
+/// Includes an embedded template, e.g. `{% embed 'x' %}{% block a %}..{% endblock %}{% endembed %}`.
+///
+/// The embedded template (with its block overrides) is compiled separately. It is referenced
+/// by its `index` in the enclosing template.
+///
+/// Children: `[expr, variables?]`, where `expr` is the name of the template to embed.
+#[derive(Debug)]
+pub struct Embed {
+    index: usize,
+    only: bool,
+    ignore_missing: bool,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Embed {
+    pub fn new(index: usize, expr: Box<dyn Node>, variables: Option<Box<dyn Node>>, only: bool, ignore_missing: bool, position: Position) -> Box<Embed> {
+        let mut children = vec![expr];
+
+        if let Some(variables) = variables {
+            children.push(variables);
+        }
+
+        Box::new(Embed {
+            index,
+            only,
+            ignore_missing,
+            position,
+            children,
+        })
+    }
+
+    /// Index of the embedded template within the enclosing template.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Expression for the name of the embedded template.
+    pub fn expr(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    /// Expression for additional variables.
+    pub fn variables(&self) -> Option<&dyn Node> {
+        self.children.get(1).map(|v| &**v)
+    }
+
+    /// Whether the embedded template has access to the current context.
+    pub fn only(&self) -> bool {
+        self.only
+    }
+
+    pub fn ignore_missing(&self) -> bool {
+        self.ignore_missing
+    }
+}
+
+impl Node for Embed {
+    fn tag(&self) -> &str { "embed" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "index" => Ok(Value::from(self.index())),
+            "only" => Ok(Value::from(self.only())),
+            "ignore_missing" => Ok(Value::from(self.ignore_missing())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        if self.ignore_missing() {
+            let names = try_traced!(self.expr().evaluate(job));
+
+            match job.load_first_template(&names, &self.position) {
+                Ok(_) => {},
+                Err(ref error) if error.is_template_not_found() => return Ok(()),
+                Err(error) => return Err(error),
+            }
+        }
+
+        let template = try_traced!(job.load_embedded_template(self.index(), &self.position));
+
+        render_with(job, template, self.variables(), self.only(), FrameKind::Embed, &self.position)
+    }
+}
+
+/// Renders an included template with additional variables, and optionally without access to
+/// the current context.
+fn render_with(job: &mut Job, template: Rc<template::Compiled>, variables: Option<&dyn Node>, only: bool, kind: FrameKind, position: &Position) -> Result<(), Traced<RuntimeError>> {
+    let tag = match kind {
+        FrameKind::Embed => "embed",
+        _ => "include",
+    };
+
+    let variables = match variables {
+        Some(variables) => match try_traced!(variables.evaluate(job)) {
+            value @ Value::Hash(_) => value.to_hash(),
+            value => return traced_err!(RuntimeError::InvalidArgument {
+                reason: format!("Variables passed to an {:?} tag must be a hash, got {}.", tag, value.type_name()),
+            }),
+        },
+        None => Hash::new(),
+    };
+
+    job.include(template, variables, !only, kind, position)
+}
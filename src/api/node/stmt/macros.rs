@@ -0,0 +1,134 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Macro definitions and imports.
+
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Definition of a macro, e.g. `{% macro input(name, value = '') %}..{% endmacro %}`.
+///
+/// Children: `[body, default_0, default_1, ..]`, with one default value per argument.
+#[derive(Debug)]
+pub struct Macro {
+    name: String,
+    arguments: Vec<String>,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Macro {
+    /// Create a new macro with named arguments and their default values.
+    pub fn new<N>(name: N, arguments: Vec<(String, Box<dyn Node>)>, body: Box<dyn Node>, position: Position) -> Box<Macro> where
+        N: Into<String>
+    {
+        let mut names = Vec::with_capacity(arguments.len());
+        let mut children = vec![body];
+
+        for (name, default) in arguments {
+            names.push(name);
+            children.push(default);
+        }
+
+        Box::new(Macro {
+            name: name.into(),
+            arguments: names,
+            position,
+            children,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the argument names.
+    pub fn arguments(&self) -> &[String] {
+        &self.arguments
+    }
+
+    /// Get the default value of the i-th argument.
+    pub fn default_value(&self, i: usize) -> Option<&dyn Node> {
+        self.children.get(i + 1).map(|d| &**d)
+    }
+
+    pub fn body(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+}
+
+impl Node for Macro {
+    fn tag(&self) -> &str { "macro" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "name" => Ok(Value::from(self.name())),
+            "arguments" => Ok(Value::from(self.arguments.clone())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+}
+
+/// Imports the macros of a template as a variable, e.g. `{% import 'forms.twig' as forms %}`.
+///
+/// Children: `[expr]`
+#[derive(Debug)]
+pub struct Import {
+    alias: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Import {
+    pub fn new<A>(expr: Box<dyn Node>, alias: A, position: Position) -> Box<Import> where
+        A: Into<String>
+    {
+        Box::new(Import {
+            alias: alias.into(),
+            position,
+            children: vec![expr],
+        })
+    }
+
+    /// Expression for the template name.
+    pub fn expr(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    /// Name of the variable the macros are assigned to.
+    pub fn alias(&self) -> &str {
+        &self.alias
+    }
+}
+
+impl Node for Import {
+    fn tag(&self) -> &str { "import" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "alias" => Ok(Value::from(self.alias())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    /// Assigns the name of the imported template to the alias, after making sure it exists.
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        let name = try_traced!(self.expr().evaluate(job)).to_string();
+        let template = try_traced!(job.load_template(&name, &self.position));
+
+        job.set(self.alias(), Value::from(template.name()));
+
+        Ok(())
+    }
+}
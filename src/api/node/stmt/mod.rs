@@ -0,0 +1,31 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Statement nodes.
+
+pub mod apply;
+pub mod auto_escape;
+pub mod block;
+pub mod body;
+pub mod control;
+pub mod include;
+pub mod macros;
+pub mod module;
+pub mod print;
+pub mod set;
+pub mod text;
+pub mod with;
+pub use self::apply::Apply;
+pub use self::auto_escape::AutoEscape;
+pub use self::block::{Block, BlockReference, Use};
+pub use self::body::Body;
+pub use self::control::{If, For};
+pub use self::include::{Include, Embed};
+pub use self::macros::{Macro, Import};
+pub use self::module::Module;
+pub use self::print::{Print, Do};
+pub use self::set::Set;
+pub use self::text::Text;
+pub use self::with::With;
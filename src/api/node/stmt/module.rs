@@ -0,0 +1,147 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Root node of a template.
+
+use std::mem;
+use api::{Node, Value};
+use api::node::{self, NodeError, Block, BlockReference, Body, Macro, Text};
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Root node of a template.
+///
+/// Children: `[body, blocks, macros, traits, embedded_templates, parent?]`, where `blocks` and
+/// `macros` are lists of all `Block` and `Macro` definitions of this template, `traits` is the
+/// list of `Use` tags, `embedded_templates` is the list of modules of all `embed` tags and
+/// `parent` is the expression of the `extends` tag (if any).
+#[derive(Debug)]
+pub struct Module {
+    name: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Module {
+    pub fn new<N>(name: N, body: Box<dyn Node>, blocks: Box<dyn Node>, macros: Box<dyn Node>) -> Box<Module> where
+        N: ToString
+    {
+        let position = body.position().clone();
+
+        Box::new(Module {
+            name: name.to_string(),
+            position: position.clone(),
+            children: vec![body, blocks, macros, Body::new(vec![], position.clone()), Body::new(vec![], position)],
+        })
+    }
+
+    /// Twig template name, e.g. "@namespace/path/to/template".
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn body(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    pub fn blocks(&self) -> &dyn Node {
+        &*self.children[1]
+    }
+
+    pub fn macros(&self) -> &dyn Node {
+        &*self.children[2]
+    }
+
+    pub fn set_macros(&mut self, macros: Box<dyn Node>) {
+        self.children[2] = macros;
+    }
+
+    /// Templates whose blocks are imported via `{% use %}`.
+    pub fn traits(&self) -> &dyn Node {
+        &*self.children[3]
+    }
+
+    pub fn set_traits(&mut self, traits: Box<dyn Node>) {
+        self.children[3] = traits;
+    }
+
+    /// Modules of the templates embedded via `{% embed %}`, referenced by their index.
+    pub fn embedded_templates(&self) -> &dyn Node {
+        &*self.children[4]
+    }
+
+    pub fn set_embedded_templates(&mut self, embedded_templates: Box<dyn Node>) {
+        self.children[4] = embedded_templates;
+    }
+
+    /// Removes the embedded templates from this module, e.g. to compile them separately.
+    pub fn take_embedded_templates(&mut self) -> Vec<Box<dyn Node>> {
+        mem::take(self.children[4].children_mut())
+    }
+
+    /// Expression which evaluates to the name of the parent template (or a list of candidates).
+    pub fn parent(&self) -> Option<&dyn Node> {
+        self.children.get(5).map(|p| &**p)
+    }
+
+    pub fn set_parent(&mut self, parent: Box<dyn Node>) {
+        self.children.truncate(5);
+        self.children.push(parent);
+    }
+
+    /// Get the definition of a block in this template.
+    pub fn block(&self, name: &str) -> Option<&Block> {
+        self.blocks().children().iter()
+            .filter_map(|b| b.downcast_ref::<Block>())
+            .find(|b| b.name() == name)
+    }
+
+    /// Returns true if the template can be imported via `{% use %}`, i.e. if it does not extend
+    /// another template and has no content outside of blocks.
+    pub fn is_traitable(&self) -> bool {
+        self.parent().is_none() && self.body().children().iter().all(|node| match node.downcast_ref::<Text>() {
+            Some(text) => text.data().trim().is_empty(),
+            None => node.is::<BlockReference>() || (node.is::<Body>() && node.children().is_empty()),
+        })
+    }
+
+    /// Get the definition of a macro in this template.
+    pub fn macro_(&self, name: &str) -> Option<&Macro> {
+        self.macros().children().iter()
+            .filter_map(|m| m.downcast_ref::<Macro>())
+            .find(|m| m.name() == name)
+    }
+}
+
+impl Node for Module {
+    fn tag(&self) -> &str { "module" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "name" => Ok(Value::from(self.name())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    /// Renders the body - or for child templates the parent template, after executing the
+    /// body (e.g. `set` tags outside of blocks).
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        try_traced!(self.body().render(job));
+
+        match self.parent() {
+            Some(parent) => {
+                let position = parent.position();
+                let parent = try_traced!(job.load_parent(parent));
+
+                job.extend(parent, position)
+            },
+            None => Ok(()),
+        }
+    }
+}
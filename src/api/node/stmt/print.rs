@@ -0,0 +1,82 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Output and evaluation of expressions.
+
+use api::Node;
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Outputs the result of an expression, e.g. `{{ user.name }}`.
+///
+/// Children: `[expr]`
+#[derive(Debug)]
+pub struct Print {
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Print {
+    pub fn new(expr: Box<dyn Node>, position: Position) -> Box<Print> {
+        Box::new(Print {
+            position,
+            children: vec![expr],
+        })
+    }
+
+    pub fn expr(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+}
+
+impl Node for Print {
+    fn tag(&self) -> &str { "print" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        let value = try_traced!(self.expr().evaluate(job));
+        job.write(&value.to_string());
+
+        Ok(())
+    }
+}
+
+/// Evaluates an expression without output, e.g. `{% do 1 + 2 %}`.
+///
+/// Children: `[expr]`
+#[derive(Debug)]
+pub struct Do {
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Do {
+    pub fn new(expr: Box<dyn Node>, position: Position) -> Box<Do> {
+        Box::new(Do {
+            position,
+            children: vec![expr],
+        })
+    }
+
+    pub fn expr(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+}
+
+impl Node for Do {
+    fn tag(&self) -> &str { "do" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        try_traced!(self.expr().evaluate(job));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Variable assignment.
+
+use api::{Node, Value};
+use api::value::Markup;
+use api::node::{self, NodeError};
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Assigns values to variables.
+///
+/// * `{% set a, b = 1, 2 %}` - children: `[value_0, value_1, ..]`, one value per name
+/// * `{% set a %}..{% endset %}` - children: `[body]`, the rendered body is captured as markup,
+///   which is not escaped again
+#[derive(Debug)]
+pub struct Set {
+    names: Vec<String>,
+    capture: bool,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Set {
+    pub fn new(names: Vec<String>, values: Vec<Box<dyn Node>>, position: Position) -> Box<Set> {
+        Box::new(Set {
+            names,
+            capture: false,
+            position,
+            children: values,
+        })
+    }
+
+    pub fn new_capture<N>(name: N, body: Box<dyn Node>, position: Position) -> Box<Set> where
+        N: Into<String>
+    {
+        Box::new(Set {
+            names: vec![name.into()],
+            capture: true,
+            position,
+            children: vec![body],
+        })
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Whether the rendered body is assigned, instead of values.
+    pub fn capture(&self) -> bool {
+        self.capture
+    }
+
+    pub fn values(&self) -> &[Box<dyn Node>] {
+        &self.children
+    }
+}
+
+impl Node for Set {
+    fn tag(&self) -> &str { "set" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "names" => Ok(Value::from(self.names.clone())),
+            "capture" => Ok(Value::from(self.capture())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        if self.capture() {
+            let captured = try_traced!(job.capture(|job| self.children[0].render(job)));
+            job.set(&self.names[0][..], Value::from(Markup::new(captured, "all")));
+
+            return Ok(());
+        }
+
+        // evaluate all values first, so `{% set a, b = b, a %}` swaps values
+        let values = try_traced!(node::evaluate_all(self.values(), job));
+        for (name, value) in self.names.iter().zip(values) {
+            job.set(&name[..], value);
+        }
+
+        Ok(())
+    }
+}
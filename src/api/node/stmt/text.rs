@@ -0,0 +1,60 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Raw template text.
+
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Template text outside of any tags, which is output as is.
+#[derive(Debug)]
+pub struct Text {
+    data: String,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Text {
+    pub fn new<D>(data: D, position: Position) -> Box<Text> where
+        D: Into<String>
+    {
+        Box::new(Text {
+            data: data.into(),
+            position,
+            children: vec![],
+        })
+    }
+
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut String {
+        &mut self.data
+    }
+}
+
+impl Node for Text {
+    fn tag(&self) -> &str { "text" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "data" => Ok(Value::from(self.data())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        job.write(&self.data);
+
+        Ok(())
+    }
+}
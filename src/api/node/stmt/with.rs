@@ -0,0 +1,84 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Scoped variables.
+
+use api::{Node, Value};
+use api::node::{self, NodeError};
+use api::value::Hash;
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use api::token::stream::Position;
+
+/// Renders a body within a new scope, e.g. `{% with { a: 1 } only %}..{% endwith %}`.
+///
+/// Variables assigned within the body are discarded afterwards. With `only`, the outer
+/// variables are not visible.
+///
+/// Children: `[body, vars?]`
+#[derive(Debug)]
+pub struct With {
+    only: bool,
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl With {
+    pub fn new(body: Box<dyn Node>, vars: Option<Box<dyn Node>>, only: bool, position: Position) -> Box<With> {
+        let mut children = vec![body];
+        children.extend(vars);
+
+        Box::new(With {
+            only,
+            position,
+            children,
+        })
+    }
+
+    pub fn body(&self) -> &dyn Node {
+        &*self.children[0]
+    }
+
+    pub fn vars(&self) -> Option<&dyn Node> {
+        self.children.get(1).map(|v| &**v)
+    }
+
+    /// Whether outer variables are hidden.
+    pub fn only(&self) -> bool {
+        self.only
+    }
+}
+
+impl Node for With {
+    fn tag(&self) -> &str { "with" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "only" => Ok(Value::from(self.only())),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        let vars = match self.vars() {
+            Some(vars) => match try_traced!(vars.evaluate(job)) {
+                value @ Value::Hash(_) => value.to_hash(),
+                value => return traced_err!(RuntimeError::InvalidArgument {
+                    reason: format!("Variables passed to a \"with\" tag must be a hash, got {}.", value.type_name()),
+                }),
+            },
+            None => Hash::new(),
+        };
+
+        if self.only() {
+            job.isolated(vars, |job| self.body().render(job))
+        } else {
+            job.scoped(vars, |job| self.body().render(job))
+        }
+    }
+}
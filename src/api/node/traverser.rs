@@ -0,0 +1,201 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Applies node visitors to an abstract syntax tree.
+
+use std::mem;
+use api::Node;
+use api::node::Body;
+use api::ext::NodeVisitor;
+use api::error::Traced;
+use api::parser::ParserError;
+use engine::ExtensionRegistry;
+
+#[derive(Debug)]
+pub struct Traverser<'a> {
+    visitors: Vec<&'a dyn NodeVisitor>,
+}
+
+impl<'a> Traverser<'a> {
+    /// Create a traverser for the given visitors.
+    ///
+    /// Visitors are ordered by priority. Visitors of equal priority keep their relative order.
+    pub fn new(visitors: &'a [Box<dyn NodeVisitor>]) -> Traverser<'a> {
+        let mut visitors: Vec<&'a dyn NodeVisitor> = visitors.iter().map(|v| &**v).collect();
+        visitors.sort_by_key(|v| v.priority());
+
+        Traverser {
+            visitors,
+        }
+    }
+
+    /// Apply all visitors one after another to the tree below `root`.
+    ///
+    /// Visitors may only remove children of `Body` nodes, i.e. from lists of nodes.
+    ///
+    /// # Failures
+    /// * When a visitor fails
+    /// * When a visitor removes the root node
+    /// * When a visitor removes a child of another node, e.g. an operand of a `Binary` node
+    pub fn traverse(&self, root: Box<dyn Node>, ext: &ExtensionRegistry) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let mut root = root;
+
+        for visitor in &self.visitors {
+            root = match try_traced!(traverse_for_visitor(*visitor, root, ext)) {
+                Some(node) => node,
+                None => return traced_err!(ParserError::NodeVisitorFailure {
+                    reason: format!("The root node has been removed by {:?}.", visitor)
+                }),
+            }
+        }
+
+        Ok(root)
+    }
+}
+
+fn traverse_for_visitor(visitor: &dyn NodeVisitor, node: Box<dyn Node>, ext: &ExtensionRegistry)
+    -> Result<Option<Box<dyn Node>>, Traced<ParserError>>
+{
+    let mut node = try_traced!(visitor.enter_node(node, ext));
+
+    let removable = node.downcast_ref::<Body>().is_some();
+    let children = mem::take(node.children_mut());
+    let mut visited = Vec::with_capacity(children.len());
+
+    for (index, child) in children.into_iter().enumerate() {
+        match try_traced!(traverse_for_visitor(visitor, child, ext)) {
+            Some(child) => visited.push(child),
+            None if removable => {},
+            None => return traced_err!(ParserError::NodeVisitorFailure {
+                reason: format!("The child {} of the {:?} node at {} has been removed by {:?}, but only children of \"body\" nodes can be removed.",
+                    index, node.tag(), node.position(), visitor)
+            }),
+        }
+    }
+
+    *node.children_mut() = visited;
+
+    visitor.leave_node(node, ext)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use api::token::stream::Position;
+
+    #[derive(Debug)]
+    struct Dummy {
+        tag: String,
+        position: Position,
+        children: Vec<Box<dyn Node>>,
+    }
+
+    impl Node for Dummy {
+        fn tag(&self) -> &str { &self.tag }
+        fn position(&self) -> &Position { &self.position }
+        fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+        fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+    }
+
+    fn dummy(tag: &str, children: Vec<Box<dyn Node>>) -> Box<dyn Node> {
+        Box::new(Dummy { tag: tag.to_string(), position: Position::default(), children })
+    }
+
+    fn tags(node: &dyn Node) -> String {
+        let children: Vec<String> = node.children().iter().map(|c| tags(&**c)).collect();
+
+        if children.is_empty() {
+            node.tag().to_string()
+        } else {
+            format!("{}({})", node.tag(), children.join(","))
+        }
+    }
+
+    /// Renames nodes on enter, removes nodes on leave.
+    #[derive(Debug)]
+    struct Rename {
+        from: &'static str,
+        to: &'static str,
+        remove: &'static str,
+        priority: i32,
+    }
+
+    impl NodeVisitor for Rename {
+        fn enter_node(&self, node: Box<dyn Node>, _ext: &ExtensionRegistry) -> Result<Box<dyn Node>, Traced<ParserError>> {
+            if node.tag() == self.from {
+                let mut node = node;
+                let children = mem::take(node.children_mut());
+
+                return Ok(dummy(self.to, children));
+            }
+
+            Ok(node)
+        }
+
+        fn leave_node(&self, node: Box<dyn Node>, _ext: &ExtensionRegistry) -> Result<Option<Box<dyn Node>>, Traced<ParserError>> {
+            if node.tag() == self.remove {
+                return Ok(None);
+            }
+
+            Ok(Some(node))
+        }
+
+        fn priority(&self) -> i32 { self.priority }
+    }
+
+    fn tree() -> Box<dyn Node> {
+        Body::new(vec![
+            dummy("a", vec![dummy("b", vec![]), Body::new(vec![dummy("c", vec![])], Position::default())]),
+            dummy("c", vec![]),
+        ], Position::default())
+    }
+
+    #[test]
+    fn replace_and_remove() {
+        let visitors: Vec<Box<dyn NodeVisitor>> = vec![
+            Box::new(Rename { from: "a", to: "x", remove: "c", priority: 0 }),
+        ];
+        let root = Traverser::new(&visitors).traverse(tree(), &ExtensionRegistry::default()).unwrap();
+
+        assert_eq!(tags(&*root), "body(x(b,body))");
+    }
+
+    #[test]
+    fn priority() {
+        let visitors: Vec<Box<dyn NodeVisitor>> = vec![
+            Box::new(Rename { from: "b", to: "d", remove: "", priority: 5 }),
+            Box::new(Rename { from: "a", to: "b", remove: "", priority: -5 }),
+        ];
+        let root = Traverser::new(&visitors).traverse(tree(), &ExtensionRegistry::default()).unwrap();
+
+        assert_eq!(tags(&*root), "body(d(d,body(c)),c)");
+    }
+
+    #[test]
+    fn remove_root() {
+        let visitors: Vec<Box<dyn NodeVisitor>> = vec![
+            Box::new(Rename { from: "", to: "", remove: "body", priority: 0 }),
+        ];
+        let result = Traverser::new(&visitors).traverse(tree(), &ExtensionRegistry::default());
+
+        match *result.unwrap_err() {
+            ParserError::NodeVisitorFailure { .. } => {},
+            ref err => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn remove_required_child() {
+        let visitors: Vec<Box<dyn NodeVisitor>> = vec![
+            Box::new(Rename { from: "", to: "", remove: "b", priority: 0 }),
+        ];
+        let result = Traverser::new(&visitors).traverse(tree(), &ExtensionRegistry::default());
+
+        match *result.unwrap_err() {
+            ParserError::NodeVisitorFailure { ref reason } => assert!(reason.starts_with("The child 0 of the \"a\" node at 0:0 has been removed by Rename"), "{}", reason),
+            ref err => panic!("unexpected error {:?}", err),
+        }
+    }
+}
@@ -8,8 +8,9 @@
 use std::fmt::{self, Display};
 use std::error::Error;
 
-use api::parser::job::{self, cursor};
+use api::parser::job;
 use api::token;
+use api::error::suggest;
 
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -17,16 +18,39 @@ pub enum ParserError {
     TokenParserError {
         tag: &'static str, // known at compile-time
         error: String,
-        job: job::JobDump,
+        position: token::stream::Position,
     },
     NoTagHandler {
         tag: String, // only known at runtime
         position: token::stream::Position,
         job: job::JobDump,
+        /// Names of similar tags.
+        similar: Vec<String>,
     },
+    /// Raised if a unary operator or a name follows an expression, e.g. `1 not 2` or `1 startswith 2`.
     UnexpectedBinaryOperator {
         name: String,
-        job: job::JobDump,
+        position: token::stream::Position,
+        /// Similar binary operators and tests, e.g. `is defined`.
+        similar: Vec<String>,
+    },
+    UnknownFilter {
+        name: String,
+        position: token::stream::Position,
+        /// Names of similar filters.
+        similar: Vec<String>,
+    },
+    UnknownFunction {
+        name: String,
+        position: token::stream::Position,
+        /// Names of similar functions.
+        similar: Vec<String>,
+    },
+    UnknownTest {
+        name: String,
+        position: token::stream::Position,
+        /// Names of similar tests.
+        similar: Vec<String>,
     },
     UnexpectedToken {
         reason: Option<&'static str>,
@@ -36,7 +60,13 @@ pub enum ParserError {
     UnexpectedEof {
         reason: Option<&'static str>,
         expected: Option<token::PatternDump>,
-        cursor: cursor::CursorDump,
+        /// Position of the end of the template.
+        position: token::stream::Position,
+    },
+    /// To be used by custom implementations of `twig::api::ext::NodeVisitor`
+    /// (also raised if the root node of a template has been removed)
+    NodeVisitorFailure {
+        reason: String,
     },
 }
 
@@ -46,8 +76,56 @@ impl Error for ParserError {
             ParserError::TokenParserError{..} => "Token parser error.",
             ParserError::NoTagHandler{..} => "There is no registered tag handler for named block.",
             ParserError::UnexpectedBinaryOperator{..} => "Unexpected Binary Operator.",
+            ParserError::UnknownFilter{..} => "Unknown filter.",
+            ParserError::UnknownFunction{..} => "Unknown function.",
+            ParserError::UnknownTest{..} => "Unknown test.",
             ParserError::UnexpectedToken{..} => "Unexpected Token.",
             ParserError::UnexpectedEof{..} => "Unexpected end of token stream.",
+            ParserError::NodeVisitorFailure{..} => "Node visitor failed to modify the syntax tree.",
+        }
+    }
+}
+
+impl ParserError {
+    /// Get the stable code of the error, see `twig::api::error::index`.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            ParserError::TokenParserError{..} => "T0301",
+            ParserError::NoTagHandler{..} => "T0302",
+            ParserError::UnexpectedBinaryOperator{..} => "T0303",
+            ParserError::UnknownFilter{..} => "T0304",
+            ParserError::UnknownFunction{..} => "T0305",
+            ParserError::UnknownTest{..} => "T0306",
+            ParserError::UnexpectedToken{..} => "T0307",
+            ParserError::UnexpectedEof{..} => "T0308",
+            ParserError::NodeVisitorFailure{..} => "T0309",
+        }
+    }
+
+    /// Get the position within the template, if the error records one.
+    pub fn position(&self) -> Option<&token::stream::Position> {
+        match *self {
+            ParserError::TokenParserError { ref position, .. }
+            | ParserError::NoTagHandler { ref position, .. }
+            | ParserError::UnexpectedBinaryOperator { ref position, .. }
+            | ParserError::UnexpectedEof { ref position, .. }
+            | ParserError::UnknownFilter { ref position, .. }
+            | ParserError::UnknownFunction { ref position, .. }
+            | ParserError::UnknownTest { ref position, .. } => Some(position),
+            ParserError::UnexpectedToken { ref found, .. } => Some(found.position()),
+            _ => None,
+        }
+    }
+
+    /// Get the names suggested instead of a misspelled name, e.g. of a tag or filter.
+    pub fn similar(&self) -> &[String] {
+        match *self {
+            ParserError::NoTagHandler { ref similar, .. }
+            | ParserError::UnexpectedBinaryOperator { ref similar, .. }
+            | ParserError::UnknownFilter { ref similar, .. }
+            | ParserError::UnknownFunction { ref similar, .. }
+            | ParserError::UnknownTest { ref similar, .. } => similar,
+            _ => &[],
         }
     }
 }
@@ -58,29 +136,52 @@ impl Display for ParserError {
 
         match *self {
             ParserError::TokenParserError {
-                tag, ref error, ref job
+                tag, ref error, ref position
             } => {
-                write!(f, " {tag:?}-block: {error} for job {job}.",
-                    tag = tag, error = error, job = job)
+                write!(f, " {tag:?}-block at {pos}: {error}.",
+                    tag = tag, error = error, pos = position)
             },
             ParserError::NoTagHandler {
-                tag: ref t, position: ref p, job: ref j
+                tag: ref t, position: ref p, job: ref j, ref similar
             } => {
-                write!(f, " Found block {tag} at {pos} for job {job}.",
-                    tag = t, pos = p, job = j)
+                write!(f, " Found block {tag} at {pos} for job {job}.{suggestions}",
+                    tag = t, pos = p, job = j,
+                    suggestions = suggest::did_you_mean(similar))
             },
             ParserError::UnexpectedBinaryOperator {
-                name: ref n, job: ref j
+                name: ref n, ref position, ref similar
             } => {
-                write!(f, " The binary operator {name:?} is unknown to the engine for job {job}",
+                write!(f, " The binary operator {name:?} at {pos} is unknown to the engine.{suggestions}",
                     name = n,
-                    job = j)
+                    pos = position,
+                    suggestions = suggest::did_you_mean(similar))
+            },
+            ParserError::UnknownFilter {
+                ref name, ref position, ref similar
+            } => {
+                write!(f, " The filter {name:?} at {pos} is unknown to the engine.{suggestions}",
+                    name = name, pos = position,
+                    suggestions = suggest::did_you_mean(similar))
+            },
+            ParserError::UnknownFunction {
+                ref name, ref position, ref similar
+            } => {
+                write!(f, " The function {name:?} at {pos} is unknown to the engine.{suggestions}",
+                    name = name, pos = position,
+                    suggestions = suggest::did_you_mean(similar))
+            },
+            ParserError::UnknownTest {
+                ref name, ref position, ref similar
+            } => {
+                write!(f, " The test {name:?} at {pos} is unknown to the engine.{suggestions}",
+                    name = name, pos = position,
+                    suggestions = suggest::did_you_mean(similar))
             },
             ParserError::UnexpectedToken {
                 reason: r, expected: ref x, found: ref i
             } => {
-                try!(write!(f, " Expected token {x:?} but found {t:?} at {p:?}.",
-                    x = x, t = i.token(), p = i.position()));
+                write!(f, " Expected token {x:?} but found {t:?} at {p}.",
+                    x = x, t = i.token(), p = i.position())?;
 
                 if let Some(reason) = r {
                     try!(write!(f, " {}", reason));
@@ -89,7 +190,7 @@ impl Display for ParserError {
                 Ok(())
             },
             ParserError::UnexpectedEof {
-                reason, ref expected, ref cursor
+                reason, ref expected, ref position
             } => {
                 if let Some(ref expected) = *expected {
                     try!(write!(f, " Expected token to match {:?}.", expected));
@@ -99,8 +200,13 @@ impl Display for ParserError {
                     try!(write!(f, " {}", reason))
                 }
 
-                write!(f, " For {}.", cursor)
-            }
+                write!(f, " The template ends at {}.", position)
+            },
+            ParserError::NodeVisitorFailure {
+                ref reason
+            } => {
+                write!(f, " {}", reason)
+            },
         }
     }
 }
@@ -114,6 +220,15 @@ pub enum NodeError {
     }
 }
 
+impl NodeError {
+    /// Get the stable code of the error, see `twig::api::error::index`.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            NodeError::AttributeNotFound{..} => "T0701",
+        }
+    }
+}
+
 impl Error for NodeError {
     fn description(&self) -> &str {
         match *self {
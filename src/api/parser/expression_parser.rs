@@ -4,15 +4,550 @@
 // file that was distributed with this source code.
 
 //! Expression parser.
+//!
+//! Parses expressions by precedence climbing, with the operators registered by engine
+//! extensions.
 
 use engine::ExtensionRegistry;
 use std::rc::Rc;
+use api::{Node, Value};
+use api::node::{self, Arrow, Constant, Name, GetAttr, AttributeType, Filter, Function, MacroCall, Binary, Unary,
+    Conditional, Array};
+use api::token::{Token, Type, Punctuation, BracketType};
+use api::token::stream::Position;
+use api::ext::op::{Assoc, Operation, Factory};
+use api::parser::{Job, ParserError};
+use api::error::{suggest, Traced, Dump};
 
-#[derive(Debug)]
-pub struct Expression; // dummy
+const OPENING_ROUND: Token = Token::Punctuation(Punctuation::OpeningBracket(BracketType::Round));
+const CLOSING_ROUND: Token = Token::Punctuation(Punctuation::ClosingBracket(BracketType::Round));
+const OPENING_SQUARE: Token = Token::Punctuation(Punctuation::OpeningBracket(BracketType::Square));
+const CLOSING_SQUARE: Token = Token::Punctuation(Punctuation::ClosingBracket(BracketType::Square));
+const OPENING_CURLY: Token = Token::Punctuation(Punctuation::OpeningBracket(BracketType::Curly));
+const CLOSING_CURLY: Token = Token::Punctuation(Punctuation::ClosingBracket(BracketType::Curly));
+const COMMA: Token = Token::Punctuation(Punctuation::Comma);
+const COLON: Token = Token::Punctuation(Punctuation::Colon);
+const DOT: Token = Token::Punctuation(Punctuation::Dot);
+const VERTICAL_BAR: Token = Token::Punctuation(Punctuation::VerticalBar);
+const QUESTION_MARK: Token = Token::Punctuation(Punctuation::QuestionMark);
 
 #[derive(Debug)]
-#[allow(dead_code)]
 pub struct ExpressionParser {
     ext: Rc<ExtensionRegistry>,
 }
+
+impl ExpressionParser {
+    pub fn new(ext: Rc<ExtensionRegistry>) -> ExpressionParser {
+        ExpressionParser {
+            ext,
+        }
+    }
+
+    /// Parses an expression with binary operators of at least the given precedence.
+    pub fn parse_expression(&self, job: &mut Job, precedence: usize) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let mut expr = try_traced!(self.parse_primary(job));
+
+        loop {
+            let item = job.cursor().current();
+            let op = match *item.token() {
+                Token::Operator(ref repr) => match self.ext.operators_binary().get(repr) {
+                    Some(op) if op.prec.0 >= precedence => op,
+                    _ => break,
+                },
+                _ => break,
+            };
+            job.cursor().next();
+
+            let position = item.position().clone();
+            let right_precedence = match op.assoc {
+                Assoc::Left => op.prec.0 + 1,
+                Assoc::Right => op.prec.0,
+            };
+
+            expr = match op.op {
+                Operation::Callable(_) => {
+                    let right = try_traced!(self.parse_expression(job, right_precedence));
+                    Binary::new(op.repr.clone(), expr, right, position)
+                },
+                Operation::Class(ref class) => match class.factory() {
+                    Factory::Binary(factory) => {
+                        let right = try_traced!(self.parse_expression(job, right_precedence));
+                        factory(expr, right, position)
+                    },
+                    Factory::Parser(factory) => try_traced!(factory(job, expr, position)),
+                    Factory::Unary(_) => return traced_err!(ParserError::UnexpectedBinaryOperator {
+                        name: op.repr.clone(),
+                        position,
+                        similar: suggest::similar(&op.repr, self.ext.operators_binary().keys().map(|k| &k[..])),
+                    }),
+                },
+            };
+        }
+
+        if precedence == 0 {
+            return self.parse_conditional_expression(job, expr);
+        }
+
+        Ok(expr)
+    }
+
+    /// Fails if a name follows a complete expression and is similar to a binary operator or a
+    /// test, e.g. the misspelled operator in `1 startswith 2`.
+    pub fn check_misspelled_operator(&self, job: &mut Job) -> Result<(), Traced<ParserError>> {
+        let item = job.cursor().current();
+        let name = match *item.token() {
+            Token::Name(ref name) => name,
+            _ => return Ok(()),
+        };
+
+        let operators = self.ext.operators_binary().keys().filter(|repr| repr.starts_with(char::is_alphabetic));
+        let mut similar = suggest::similar_words(name, operators.map(|k| &k[..]));
+        similar.extend(suggest::similar_words(name, self.ext.tests().keys().map(|k| &k[..]))
+            .into_iter()
+            .map(|test| format!("is {}", test)));
+
+        if similar.is_empty() {
+            return Ok(());
+        }
+
+        traced_err!(ParserError::UnexpectedBinaryOperator {
+            name: name.clone(),
+            position: item.position().clone(),
+            similar,
+        })
+    }
+
+    /// Parses unary operators, parenthesized expressions and primary expressions.
+    fn parse_primary(&self, job: &mut Job) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let item = job.cursor().current();
+
+        if let Token::Operator(ref repr) = *item.token() {
+            if let Some(op) = self.ext.operators_unary().get(repr) {
+                job.cursor().next();
+                let position = item.position().clone();
+                let operand = try_traced!(self.parse_expression(job, op.prec.0));
+
+                let expr = match op.op {
+                    Operation::Callable(_) => Unary::new(op.repr.clone(), operand, position),
+                    Operation::Class(ref class) => match class.factory() {
+                        Factory::Unary(factory) => factory(operand, position),
+                        _ => return traced_err!(ParserError::UnexpectedToken {
+                            reason: Some("Unary operators must be created by unary factories."),
+                            expected: format!("{:?}", Type::Operator),
+                            found: item.dump(),
+                        }),
+                    },
+                };
+
+                return self.parse_postfix_expression(job, expr);
+            }
+        }
+
+        if let Some(params) = arrow_params(job) {
+            return self.parse_arrow(job, params);
+        }
+
+        if job.cursor().next_if(OPENING_ROUND).is_some() {
+            let expr = try_traced!(self.parse_expression(job, 0));
+            try_traced!(job.cursor().expect(CLOSING_ROUND, Some("An opened parenthesis is not properly closed.")));
+
+            return self.parse_postfix_expression(job, expr);
+        }
+
+        self.parse_primary_expression(job)
+    }
+
+    /// Parses an arrow function, starting at the parameters - e.g. `(a, b) => a + b`.
+    fn parse_arrow(&self, job: &mut Job, params: Vec<String>) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let position = job.cursor().current().position().clone();
+
+        while !is_arrow(job.cursor().next().token()) {}
+
+        let body = try_traced!(self.parse_expression(job, 0));
+
+        Ok(Arrow::new(params, body, position))
+    }
+
+    /// Parses `test ? then : otherwise`.
+    fn parse_conditional_expression(&self, job: &mut Job, mut expr: Box<dyn Node>) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        while let Some(item) = job.cursor().next_if(QUESTION_MARK) {
+            let then = try_traced!(self.parse_expression(job, 0));
+
+            let otherwise = if job.cursor().next_if(COLON).is_some() {
+                try_traced!(self.parse_expression(job, 0))
+            } else {
+                Constant::new(Value::from(""), item.position().clone())
+            };
+
+            expr = Conditional::new(expr, then, otherwise, item.position().clone());
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_primary_expression(&self, job: &mut Job) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let item = job.cursor().current();
+        let position = item.position().clone();
+
+        let expr: Box<dyn Node> = match *item.token() {
+            Token::Name(ref name) => {
+                job.cursor().next();
+
+                match &name[..] {
+                    "true" | "TRUE" => Constant::new(Value::from(true), position),
+                    "false" | "FALSE" => Constant::new(Value::from(false), position),
+                    "null" | "NULL" | "none" | "NONE" => Constant::new(Value::Null, position),
+                    _ if job.cursor().test(OPENING_ROUND) => try_traced!(self.parse_function(job, name, position)),
+                    _ => Name::new(name.clone(), position),
+                }
+            },
+            Token::IntegerNumber(i) => {
+                job.cursor().next();
+                Constant::new(Value::from(i), position)
+            },
+            Token::FloatingNumber(x) => {
+                job.cursor().next();
+                Constant::new(Value::from(x), position)
+            },
+            Token::Value(_) | Token::InterpolationStart => try_traced!(self.parse_string_expression(job)),
+            Token::Operator(ref name) if is_name(name) => {
+                // e.g. a variable named like an operator
+                job.cursor().next();
+                Name::new(name.clone(), position)
+            },
+            Token::Punctuation(Punctuation::OpeningBracket(BracketType::Square)) => try_traced!(self.parse_array_expression(job)),
+            Token::Punctuation(Punctuation::OpeningBracket(BracketType::Curly)) => try_traced!(self.parse_hash_expression(job)),
+            _ => return traced_err!(ParserError::UnexpectedToken {
+                reason: Some("Unexpected token at the beginning of an expression."),
+                expected: "expression".to_string(),
+                found: item.dump(),
+            }),
+        };
+
+        self.parse_postfix_expression(job, expr)
+    }
+
+    /// Parses string literals with interpolation, e.g. `"Hello #{name}!"`.
+    fn parse_string_expression(&self, job: &mut Job) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let mut parts: Vec<Box<dyn Node>> = vec![];
+        let mut next_can_be_string = true;
+
+        loop {
+            let item = job.cursor().current();
+
+            match *item.token() {
+                Token::Value(ref string) if next_can_be_string => {
+                    job.cursor().next();
+                    parts.push(Constant::new(Value::from(&string[..]), item.position().clone()));
+                    next_can_be_string = false;
+                },
+                Token::InterpolationStart => {
+                    job.cursor().next();
+                    parts.push(try_traced!(self.parse_expression(job, 0)));
+                    try_traced!(job.cursor().expect(Type::InterpolationEnd, Some("An interpolation is not properly closed.")));
+                    next_can_be_string = true;
+                },
+                _ => break,
+            }
+        }
+
+        let mut parts = parts.into_iter();
+        let mut expr = parts.next().expect("string expression must not be empty");
+
+        for part in parts {
+            let position = expr.position().clone();
+            expr = Binary::new("~", expr, part, position);
+        }
+
+        Ok(expr)
+    }
+
+    /// Parses `[1, 2, 3]`.
+    fn parse_array_expression(&self, job: &mut Job) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let item = try_traced!(job.cursor().expect(OPENING_SQUARE, Some("An array element was expected.")));
+        let mut values = vec![];
+
+        while !job.cursor().test(CLOSING_SQUARE) {
+            if !values.is_empty() {
+                try_traced!(job.cursor().expect(COMMA, Some("An array element must be followed by a comma.")));
+
+                // trailing comma
+                if job.cursor().test(CLOSING_SQUARE) {
+                    break;
+                }
+            }
+
+            values.push(try_traced!(self.parse_expression(job, 0)));
+        }
+
+        try_traced!(job.cursor().expect(CLOSING_SQUARE, Some("An opened array is not properly closed.")));
+
+        Ok(Array::new(values, item.position().clone()))
+    }
+
+    /// Parses `{ 'a': 1, b: 2, (c): 3 }`.
+    fn parse_hash_expression(&self, job: &mut Job) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let item = try_traced!(job.cursor().expect(OPENING_CURLY, Some("A hash element was expected.")));
+        let mut pairs = vec![];
+
+        while !job.cursor().test(CLOSING_CURLY) {
+            if !pairs.is_empty() {
+                try_traced!(job.cursor().expect(COMMA, Some("A hash value must be followed by a comma.")));
+
+                // trailing comma
+                if job.cursor().test(CLOSING_CURLY) {
+                    break;
+                }
+            }
+
+            let key_item = job.cursor().current();
+            let position = key_item.position().clone();
+
+            // a hash key can be:
+            //  * a number -- 12
+            //  * a string -- 'a'
+            //  * a name, which is equivalent to a string -- a
+            //  * an expression, which must be enclosed in parentheses -- (1 + 2)
+            let key: Box<dyn Node> = match *key_item.token() {
+                Token::Name(ref name) | Token::Value(ref name) => {
+                    job.cursor().next();
+                    Constant::new(Value::from(&name[..]), position)
+                },
+                Token::IntegerNumber(i) => {
+                    job.cursor().next();
+                    Constant::new(Value::from(i), position)
+                },
+                Token::Punctuation(Punctuation::OpeningBracket(BracketType::Round)) => {
+                    try_traced!(self.parse_expression(job, 0))
+                },
+                _ => return traced_err!(ParserError::UnexpectedToken {
+                    reason: Some("A hash key must be a quoted string, a number, a name, or an expression enclosed in parentheses."),
+                    expected: "hash key".to_string(),
+                    found: key_item.dump(),
+                }),
+            };
+
+            try_traced!(job.cursor().expect(COLON, Some("A hash key must be followed by a colon (:).")));
+            let value = try_traced!(self.parse_expression(job, 0));
+
+            pairs.push((key, value));
+        }
+
+        try_traced!(job.cursor().expect(CLOSING_CURLY, Some("An opened hash is not properly closed.")));
+
+        Ok(node::Hash::new(pairs, item.position().clone()))
+    }
+
+    /// Parses attribute access, subscripts and filters following an expression.
+    fn parse_postfix_expression(&self, job: &mut Job, mut expr: Box<dyn Node>) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        loop {
+            let token = job.cursor().current().token();
+
+            expr = if *token == DOT || *token == OPENING_SQUARE {
+                try_traced!(self.parse_subscript_expression(job, expr))
+            } else if *token == VERTICAL_BAR {
+                try_traced!(self.parse_filter_expression(job, expr))
+            } else {
+                break;
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// Parses `expr.attribute`, `expr.method(arguments)`, `expr[key]` and `expr[from:to]`.
+    fn parse_subscript_expression(&self, job: &mut Job, expr: Box<dyn Node>) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let item = job.cursor().next();
+        let position = item.position().clone();
+
+        if *item.token() == DOT {
+            let attribute_item = job.cursor().next();
+            let attribute_position = attribute_item.position().clone();
+
+            let attribute = match *attribute_item.token() {
+                Token::Name(ref name) => Constant::new(Value::from(&name[..]), attribute_position),
+                Token::Operator(ref name) if is_name(name) => Constant::new(Value::from(&name[..]), attribute_position),
+                Token::IntegerNumber(i) => Constant::new(Value::from(i), attribute_position),
+                _ => return traced_err!(ParserError::UnexpectedToken {
+                    reason: Some("Expected name or number."),
+                    expected: format!("{:?}", Type::Name),
+                    found: attribute_item.dump(),
+                }),
+            };
+
+            if job.cursor().test(OPENING_ROUND) {
+                let arguments = try_traced!(self.parse_arguments(job));
+
+                // e.g. `forms.input()` after `{% import 'forms.twig' as forms %}`
+                if expr.downcast_ref::<Name>().is_some_and(|n| job.is_imported_template(n.name())) {
+                    if let Token::Name(ref name) = *attribute_item.token() {
+                        return Ok(MacroCall::new(expr, name.clone(), arguments, position));
+                    }
+                }
+
+                return Ok(GetAttr::new(expr, attribute, arguments, AttributeType::Method, position));
+            }
+
+            return Ok(GetAttr::new(expr, attribute, vec![], AttributeType::Any, position));
+        }
+
+        // slice `[from:length]` with optional bounds
+        let from = if job.cursor().test(COLON) {
+            None
+        } else {
+            Some(try_traced!(self.parse_expression(job, 0)))
+        };
+
+        if let Some(colon) = job.cursor().next_if(COLON) {
+            let from = from.unwrap_or_else(|| Constant::new(Value::from(0), colon.position().clone()));
+            let length = if job.cursor().test(CLOSING_SQUARE) {
+                Constant::new(Value::Null, colon.position().clone())
+            } else {
+                try_traced!(self.parse_expression(job, 0))
+            };
+
+            try_traced!(job.cursor().expect(CLOSING_SQUARE, None));
+
+            return Ok(Filter::new("slice", expr, vec![from, length], position));
+        }
+
+        try_traced!(job.cursor().expect(CLOSING_SQUARE, None));
+
+        match from {
+            Some(key) => Ok(GetAttr::new(expr, key, vec![], AttributeType::Array, position)),
+            None => unreachable!(), // handled as slice
+        }
+    }
+
+    /// Parses `expr|filter` and `expr|filter(arguments)`.
+    fn parse_filter_expression(&self, job: &mut Job, expr: Box<dyn Node>) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        job.cursor().next();
+
+        self.parse_filter_expression_raw(job, expr)
+    }
+
+    /// Parses a chain of filters without leading vertical bar, e.g. `upper|trim` in `{% apply upper|trim %}`.
+    pub fn parse_filter_expression_raw(&self, job: &mut Job, mut expr: Box<dyn Node>) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        loop {
+            let item = try_traced!(job.cursor().expect(Type::Name, Some("A filter name was expected.")));
+            let name = item.token().value_as_str().unwrap_or_default();
+            let position = item.position().clone();
+
+            if !self.ext.filters().contains_key(name) {
+                return traced_err!(ParserError::UnknownFilter {
+                    name: name.to_string(),
+                    position,
+                    similar: suggest::similar(name, self.ext.filters().keys().map(|k| &k[..])),
+                });
+            }
+
+            let arguments = if job.cursor().test(OPENING_ROUND) {
+                try_traced!(self.parse_arguments(job))
+            } else {
+                vec![]
+            };
+
+            expr = Filter::new(name, expr, arguments, position);
+
+            if job.cursor().next_if(VERTICAL_BAR).is_none() {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// Parses `function(arguments)`.
+    fn parse_function(&self, job: &mut Job, name: &str, position: Position) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        if let Some((template, name)) = job.imported_macro(name).cloned() {
+            let arguments = try_traced!(self.parse_arguments(job));
+
+            return Ok(MacroCall::new(Name::new(template, position.clone()), name, arguments, position));
+        }
+
+        if !self.ext.functions().contains_key(name) {
+            return traced_err!(ParserError::UnknownFunction {
+                name: name.to_string(),
+                position,
+                similar: suggest::similar(name, self.ext.functions().keys().map(|k| &k[..])),
+            });
+        }
+
+        let arguments = try_traced!(self.parse_arguments(job));
+
+        Ok(Function::new(name, arguments, position))
+    }
+
+    /// Parses a list of comma separated expressions in parentheses, e.g. `(1, a + b)`.
+    pub fn parse_arguments(&self, job: &mut Job) -> Result<Vec<Box<dyn Node>>, Traced<ParserError>> {
+        try_traced!(job.cursor().expect(OPENING_ROUND, Some("A list of arguments must begin with an opening parenthesis.")));
+        let mut arguments = vec![];
+
+        while !job.cursor().test(CLOSING_ROUND) {
+            if !arguments.is_empty() {
+                try_traced!(job.cursor().expect(COMMA, Some("Arguments must be separated by a comma.")));
+            }
+
+            arguments.push(try_traced!(self.parse_expression(job, 0)));
+        }
+
+        try_traced!(job.cursor().expect(CLOSING_ROUND, Some("A list of arguments must be closed by a parenthesis.")));
+
+        Ok(arguments)
+    }
+}
+
+/// Get the parameters if the cursor is at the beginning of an arrow function, i.e. `x =>` or
+/// `(x, y) =>`.
+fn arrow_params(job: &mut Job) -> Option<Vec<String>> {
+    let cursor = job.cursor();
+
+    if let Token::Name(ref name) = *cursor.current().token() {
+        return match is_arrow(cursor.look(1).token()) {
+            true => Some(vec![name.clone()]),
+            false => None,
+        };
+    }
+
+    if !cursor.test(OPENING_ROUND) {
+        return None;
+    }
+
+    let mut params = vec![];
+    let mut n = 1;
+
+    if *cursor.look(n).token() != CLOSING_ROUND {
+        loop {
+            match *cursor.look(n).token() {
+                Token::Name(ref name) => params.push(name.clone()),
+                _ => return None,
+            }
+
+            match cursor.look(n + 1).token() {
+                token if *token == COMMA => n += 2,
+                token if *token == CLOSING_ROUND => {
+                    n += 1;
+                    break;
+                },
+                _ => return None,
+            }
+        }
+    }
+
+    match is_arrow(cursor.look(n + 1).token()) {
+        true => Some(params),
+        false => None,
+    }
+}
+
+/// Returns true if the token separates the parameters and the body of an arrow function.
+fn is_arrow(token: &Token) -> bool {
+    match *token {
+        Token::Operator(ref repr) => repr == "=>",
+        _ => false,
+    }
+}
+
+/// Returns true if an operator could also be a name, e.g. `in` or `matches`.
+fn is_name(repr: &str) -> bool {
+    repr.chars().next().is_some_and(|c| c == '_' || c.is_alphabetic())
+        && repr.chars().all(|c| c == '_' || c.is_alphanumeric())
+}
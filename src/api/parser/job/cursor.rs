@@ -5,9 +5,11 @@
 
 //! Position within a token stream.
 
-use api::token::stream::{self, Stream};
+use api::token::stream::{self, Item, Stream};
+use api::token::{self, Token};
+use api::parser::ParserError;
 use std::fmt;
-use api::error::Dump;
+use api::error::{Traced, Dump};
 
 pub type Position = usize;
 
@@ -18,6 +20,118 @@ pub struct Cursor<'stream> {
     stream: &'stream Stream<'stream>, // inner lifetime: 'template
 }
 
+impl<'stream> Cursor<'stream> {
+    /// Create a new cursor at the beginning of a token stream.
+    ///
+    /// The stream must not be empty - streams created by the lexer end with `Token::Eof`.
+    pub fn new(stream: &'stream Stream<'stream>) -> Cursor<'stream> {
+        Cursor {
+            next: 0,
+            end: stream.len(),
+            stream,
+        }
+    }
+
+    pub fn stream(&self) -> &'stream Stream<'stream> {
+        self.stream
+    }
+
+    /// Get the position of the current item.
+    pub fn position(&self) -> Position {
+        self.next
+    }
+
+    /// Moves the cursor to a position, e.g. to parse some tokens again.
+    pub fn seek(&mut self, position: Position) {
+        self.next = ::std::cmp::min(position, self.end);
+    }
+
+    /// Get the current item (without moving the cursor).
+    ///
+    /// Beyond the end of the stream, the last item is returned - i.e. `Token::Eof`.
+    pub fn current(&self) -> &'stream Item {
+        self.look(0)
+    }
+
+    /// Get the n-th item after the current one (without moving the cursor).
+    pub fn look(&self, n: usize) -> &'stream Item {
+        let index = ::std::cmp::min(self.next + n, self.end.saturating_sub(1));
+
+        self.stream.get(index).expect("token stream must not be empty")
+    }
+
+    /// Get the current item and move the cursor to the next item.
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> &'stream Item {
+        let item = self.current();
+
+        if self.next < self.end {
+            self.next += 1;
+        }
+
+        item
+    }
+
+    /// Returns true if the current item matches the pattern.
+    pub fn test<T>(&self, pattern: T) -> bool where
+        T: token::Pattern
+    {
+        pattern.matches(self.current().token())
+    }
+
+    /// Moves the cursor to the next item if the current item matches the pattern.
+    pub fn next_if<T>(&mut self, pattern: T) -> Option<&'stream Item> where
+        T: token::Pattern
+    {
+        if self.test(pattern) {
+            Some(self.next())
+        } else {
+            None
+        }
+    }
+
+    /// Moves the cursor to the next item if the current item matches the pattern.
+    ///
+    /// # Failures
+    /// * When the current item does not match the pattern.
+    pub fn expect<T>(&mut self, pattern: T, reason: Option<&'static str>) -> Result<&'stream Item, Traced<ParserError>> where
+        T: token::Pattern + 'static
+    {
+        if self.test(&pattern) {
+            return Ok(self.next());
+        }
+
+        if self.is_eof() {
+            return traced_err!(ParserError::UnexpectedEof {
+                reason,
+                expected: Some(<dyn token::Pattern as Dump>::dump(&pattern)),
+                position: self.current().position().clone(),
+            });
+        }
+
+        traced_err!(ParserError::UnexpectedToken {
+            reason,
+            expected: <dyn token::Pattern as Dump>::dump(&pattern),
+            found: self.current().dump(),
+        })
+    }
+
+    /// Moves the cursor after the end of the current tag, i.e. after the next `%}` or `}}`.
+    pub fn skip_tag(&mut self) {
+        while !self.is_eof() {
+            match *self.next().token() {
+                Token::BlockEnd | Token::ExpressionEnd => break,
+                _ => {},
+            }
+        }
+    }
+
+    /// Returns true if the end of the template is reached.
+    pub fn is_eof(&self) -> bool {
+        *self.current().token() == Token::Eof
+    }
+}
+
 impl<'stream> fmt::Display for Cursor<'stream> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         write!(f, "cursor (next: {next}/{end}) for {tokens:?}",
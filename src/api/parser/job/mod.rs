@@ -8,5 +8,465 @@
 pub mod cursor;
 pub use self::cursor::Cursor;
 
-pub struct Job;
+use std::rc::Rc;
+use std::mem;
+use std::collections::HashMap;
+use engine::ExtensionRegistry;
+use api::Node;
+use api::node::{Body, Block, BlockReference, Macro, Module, Print, Text, Use};
+use api::token::{Token, Type};
+use api::token::stream::{Item, Stream};
+use api::ext::token_parser::{self, TestResult};
+use api::parser::{Parser, ParserError};
+use api::parser::expression_parser::ExpressionParser;
+use api::error::{suggest, Traced, Dump};
+
 pub type JobDump = String;
+
+/// A parser job - parses one token stream into the abstract syntax tree of a template.
+///
+/// Token parsers get access to the job to parse their own tokens, sub-expressions and
+/// nested bodies.
+#[derive(Debug)]
+pub struct Job<'a> {
+    parser: &'a Parser,
+    ext: Rc<ExtensionRegistry>,
+    cursor: Cursor<'a>,
+    parent: Option<Box<dyn Node>>,
+    blocks: Vec<Box<dyn Node>>,
+    block_stack: Vec<String>,
+    embedded_templates: Vec<Box<dyn Node>>,
+    macros: Vec<Box<dyn Node>>,
+    traits: Vec<Box<dyn Node>>,
+    /// Aliases of imported templates, e.g. `forms` in `{% import 'forms.twig' as forms %}`.
+    imported_templates: Vec<String>,
+    /// Imported macros by alias, with the alias of their template and their name.
+    imported_macros: HashMap<String, (String, String)>,
+    /// Errors the job recovered from - `None` unless parsing with error recovery.
+    errors: Option<Vec<Traced<ParserError>>>,
+}
+
+impl<'a> Job<'a> {
+    pub fn new(parser: &'a Parser, stream: &'a Stream<'a>) -> Job<'a> {
+        Job {
+            parser,
+            ext: parser.ext().clone(),
+            cursor: Cursor::new(stream),
+            parent: None,
+            blocks: vec![],
+            block_stack: vec![],
+            embedded_templates: vec![],
+            macros: vec![],
+            traits: vec![],
+            imported_templates: vec![],
+            imported_macros: HashMap::new(),
+            errors: None,
+        }
+    }
+
+    /// Parses the whole token stream into a module node.
+    pub fn parse(mut self) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        self.parse_module()
+    }
+
+    /// Parses the whole token stream into a module node and collects all errors instead of
+    /// stopping at the first one.
+    ///
+    /// Tags and expressions with errors are skipped up to the next `%}` or `}}` and missing
+    /// from the module, which is only `None` if the job could not recover. The end tags of
+    /// skipped tags, e.g. `{% endfor %}`, are skipped without errors.
+    pub fn parse_recovering(mut self) -> (Option<Box<dyn Node>>, Vec<Traced<ParserError>>) {
+        self.errors = Some(vec![]);
+        let module = self.parse_module();
+        let mut errors = self.errors.take().unwrap_or_default();
+
+        match module {
+            Ok(module) => (Some(module), errors),
+            Err(error) => {
+                errors.push(error);
+                (None, errors)
+            },
+        }
+    }
+
+    fn parse_module(&mut self) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let body = try_traced!(self.subparse(None));
+        let mut module = try_traced!(self.module(body));
+        let position = module.position().clone();
+        module.set_macros(Body::new(mem::take(&mut self.macros), position.clone()));
+        module.set_embedded_templates(Body::new(mem::take(&mut self.embedded_templates), position));
+
+        Ok(module)
+    }
+
+    /// Records an error if parsing with error recovery, see `Job::parse_recovering`.
+    ///
+    /// # Failures
+    /// * When not parsing with error recovery, i.e. returns the error
+    fn recover(&mut self, error: Traced<ParserError>) -> Result<(), Traced<ParserError>> {
+        match self.errors {
+            Some(ref mut errors) => {
+                errors.push(error);
+                Ok(())
+            },
+            None => Err(error),
+        }
+    }
+
+    /// Parses the body of an embedded template up to the end of `test` and adds its module to
+    /// the embedded templates of this template, e.g. `{% embed 'parent' %}..{% endembed %}`.
+    ///
+    /// Returns the index of the embedded template.
+    pub fn embed_template(&mut self, parent: Box<dyn Node>, test: &token_parser::Test) -> Result<usize, Traced<ParserError>> {
+        let outer_parent = self.parent.replace(parent);
+        let outer_blocks = mem::take(&mut self.blocks);
+        let outer_block_stack = mem::take(&mut self.block_stack);
+        let outer_traits = mem::take(&mut self.traits);
+
+        let module = self.subparse(Some(test)).and_then(|body| self.module(body));
+
+        self.parent = outer_parent;
+        self.blocks = outer_blocks;
+        self.block_stack = outer_block_stack;
+        self.traits = outer_traits;
+
+        self.embedded_templates.push(try_traced!(module));
+
+        Ok(self.embedded_templates.len() - 1)
+    }
+
+    /// Builds the module of the current template (or embedded template) from its body.
+    fn module(&mut self, mut body: Box<dyn Node>) -> Result<Box<Module>, Traced<ParserError>> {
+        let position = body.position().clone();
+
+        if self.parent.is_some() {
+            if let Err(error) = self.filter_child_body(&mut *body) {
+                try_traced!(self.recover(error));
+            }
+        }
+
+        let mut module = Module::new(self.template_name(),
+            body,
+            Body::new(mem::take(&mut self.blocks), position.clone()),
+            Body::new(vec![], position));
+
+        module.set_traits(Body::new(mem::take(&mut self.traits), module.position().clone()));
+
+        if let Some(parent) = self.parent.take() {
+            module.set_parent(parent);
+        }
+
+        Ok(module)
+    }
+
+    /// Removes output from the body of a child template, which is never displayed.
+    ///
+    /// # Failures
+    /// * When the body contains anything but whitespace outside of blocks
+    fn filter_child_body(&self, body: &mut dyn Node) -> Result<(), Traced<ParserError>> {
+        for node in body.children() {
+            let is_output = match node.downcast_ref::<Text>() {
+                Some(text) => !text.data().trim().is_empty(),
+                None => node.is::<Print>(),
+            };
+
+            if is_output {
+                return traced_err!(ParserError::TokenParserError {
+                    tag: "extends",
+                    error: "A template that extends another one cannot include content outside Twig blocks".to_string(),
+                    position: node.position().clone(),
+                });
+            }
+        }
+
+        body.children_mut().retain(|node| !node.is::<Text>() && !node.is::<BlockReference>());
+
+        Ok(())
+    }
+
+    pub fn parser(&self) -> &'a Parser {
+        self.parser
+    }
+
+    /// Get the engine extensions.
+    pub fn ext(&self) -> &Rc<ExtensionRegistry> {
+        &self.ext
+    }
+
+    pub fn cursor(&mut self) -> &mut Cursor<'a> {
+        &mut self.cursor
+    }
+
+    /// Get the name of the template being parsed.
+    pub fn template_name(&self) -> &'a str {
+        self.cursor.stream().template().name()
+    }
+
+    /// Get the parent expression of a child template, if any.
+    pub fn parent(&self) -> Option<&dyn Node> {
+        self.parent.as_deref()
+    }
+
+    /// Sets the parent expression, i.e. `{% extends parent %}`.
+    pub fn set_parent(&mut self, parent: Box<dyn Node>) {
+        self.parent = Some(parent);
+    }
+
+    /// Get the definition of a block in this template.
+    pub fn block(&self, name: &str) -> Option<&Block> {
+        self.blocks.iter()
+            .filter_map(|b| b.downcast_ref::<Block>())
+            .find(|b| b.name() == name)
+    }
+
+    /// Adds the definition of a block to the template.
+    pub fn push_block(&mut self, block: Box<Block>) {
+        self.blocks.push(block);
+    }
+
+    /// Get the definition of a macro in this template.
+    pub fn macro_(&self, name: &str) -> Option<&Macro> {
+        self.macros.iter()
+            .filter_map(|m| m.downcast_ref::<Macro>())
+            .find(|m| m.name() == name)
+    }
+
+    /// Adds the definition of a macro to the template.
+    pub fn push_macro(&mut self, definition: Box<Macro>) {
+        self.macros.push(definition);
+    }
+
+    /// Registers the alias of an imported template, so `alias.name()` calls its macro `name`.
+    pub fn import_template<A>(&mut self, alias: A) where
+        A: Into<String>
+    {
+        self.imported_templates.push(alias.into());
+    }
+
+    /// Returns true if `alias` refers to an imported template.
+    pub fn is_imported_template(&self, alias: &str) -> bool {
+        self.imported_templates.iter().any(|t| t == alias)
+    }
+
+    /// Registers the alias of an imported macro, so `alias()` calls the macro `name` of the
+    /// template imported as `template_alias`.
+    pub fn import_macro<A, T, N>(&mut self, alias: A, template_alias: T, name: N) where
+        A: Into<String>,
+        T: Into<String>,
+        N: Into<String>
+    {
+        self.imported_macros.insert(alias.into(), (template_alias.into(), name.into()));
+    }
+
+    /// Get the alias of the template and the name of an imported macro.
+    pub fn imported_macro(&self, alias: &str) -> Option<&(String, String)> {
+        self.imported_macros.get(alias)
+    }
+
+    /// Get a variable name for an imported template, which can not clash with user variables.
+    pub fn internal_name(&self) -> String {
+        format!("__internal_{}", self.imported_templates.len())
+    }
+
+    /// Adds a template whose blocks are imported, i.e. `{% use %}`.
+    pub fn push_trait(&mut self, definition: Box<Use>) {
+        self.traits.push(definition);
+    }
+
+    /// Get the names of the blocks currently parsed, the innermost last.
+    pub fn block_stack(&self) -> &[String] {
+        &self.block_stack
+    }
+
+    pub fn push_block_stack<N>(&mut self, name: N) where
+        N: Into<String>
+    {
+        self.block_stack.push(name.into());
+    }
+
+    pub fn pop_block_stack(&mut self) -> Option<String> {
+        self.block_stack.pop()
+    }
+
+    /// Parses text, print statements and tags until the end of the template or until `test`
+    /// matches the tag name of a block.
+    pub fn subparse(&mut self, test: Option<&token_parser::Test>) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let position = self.cursor.current().position().clone();
+        let mut nodes: Vec<Box<dyn Node>> = vec![];
+        // tags whose token parser failed, so their end tags are skipped without errors
+        let mut failed_tags: Vec<String> = vec![];
+
+        while !self.cursor.is_eof() {
+            let tag = match (self.cursor.current().token(), self.cursor.look(1).token()) {
+                (Token::BlockStart, Token::Name(tag)) if self.ext.token_parsers().contains_key(tag) => Some(tag.clone()),
+                _ => None,
+            };
+
+            match self.subparse_next(test, &mut nodes) {
+                Ok(true) => return Ok(Body::new(nodes, position)),
+                Ok(false) => {},
+                Err(error) => {
+                    match *error {
+                        ParserError::NoTagHandler { ref tag, .. } if close_failed_tag(tag, &mut failed_tags) => {},
+                        _ => {
+                            failed_tags.extend(tag);
+                            try_traced!(self.recover(error));
+                        },
+                    }
+
+                    self.cursor.skip_tag();
+                },
+            }
+        }
+
+        if test.is_some() {
+            return traced_err!(ParserError::UnexpectedEof {
+                reason: Some("Missing end tag."),
+                expected: None,
+                position: self.cursor.current().position().clone(),
+            });
+        }
+
+        Ok(Body::new(nodes, position))
+    }
+
+    /// Parses the next text, print statement or tag of a body into `nodes`.
+    ///
+    /// Returns true if `test` matches the tag, i.e. at the end of the body.
+    fn subparse_next(&mut self, test: Option<&token_parser::Test>, nodes: &mut Vec<Box<dyn Node>>) -> Result<bool, Traced<ParserError>> {
+        let item = self.cursor.current();
+
+        match *item.token() {
+            Token::Text(ref text) => {
+                self.cursor.next();
+                nodes.push(Text::new(text.clone(), item.position().clone()));
+            },
+            Token::ExpressionStart => {
+                self.cursor.next();
+                let expr = try_traced!(self.parse_expression());
+                try_traced!(self.expect_end(Type::ExpressionEnd));
+                nodes.push(Print::new(expr, item.position().clone()));
+            },
+            Token::BlockStart => {
+                self.cursor.next();
+                let tag_item = self.cursor.current();
+                let tag = match *tag_item.token() {
+                    Token::Name(ref tag) => tag,
+                    _ => return traced_err!(ParserError::UnexpectedToken {
+                        reason: Some("A block must start with a tag name."),
+                        expected: format!("{:?}", Type::Name),
+                        found: tag_item.dump(),
+                    }),
+                };
+
+                if let Some(test) = test {
+                    match test(tag_item) {
+                        TestResult::KeepToken => return Ok(true),
+                        TestResult::DropToken => {
+                            self.cursor.next();
+                            return Ok(true);
+                        },
+                        TestResult::Continue => {},
+                    }
+                }
+
+                let ext = self.ext.clone();
+                let token_parser = match ext.token_parsers().get(tag) {
+                    Some(token_parser) => token_parser,
+                    None => return traced_err!(ParserError::NoTagHandler {
+                        tag: tag.to_string(),
+                        position: tag_item.position().clone(),
+                        job: self.dump(),
+                        similar: suggest::similar(tag, tag_names(&ext, test, tag_item)),
+                    }),
+                };
+
+                self.cursor.next();
+                nodes.push(try_traced!(token_parser.parse(self, tag_item)));
+            },
+            _ => return traced_err!(ParserError::UnexpectedToken {
+                reason: Some("Lexer or parser ended up in unsupported state."),
+                expected: "TEXT, VAR_START or BLOCK_START".to_string(),
+                found: item.dump(),
+            }),
+        }
+
+        Ok(false)
+    }
+
+    /// Parses an expression.
+    pub fn parse_expression(&mut self) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        self.parse_expression_with_precedence(0)
+    }
+
+    /// Parses an expression with binary operators of at least the given precedence.
+    pub fn parse_expression_with_precedence(&mut self, precedence: usize) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        ExpressionParser::new(self.ext.clone()).parse_expression(self, precedence)
+    }
+
+    /// Expects the end of a tag or print statement after an expression, i.e. `%}` or `}}`.
+    ///
+    /// # Failures
+    /// * When a name similar to a binary operator or test follows the expression, e.g. the
+    ///   misspelled operator in `{{ 1 startswith 2 }}`
+    /// * When anything else follows the expression
+    pub fn expect_end(&mut self, end: Type) -> Result<(), Traced<ParserError>> {
+        try_traced!(ExpressionParser::new(self.ext.clone()).check_misspelled_operator(self));
+        try_traced!(self.cursor.expect(end, None));
+
+        Ok(())
+    }
+
+    /// Parses a chain of filters applied to `node`, e.g. `upper|trim`.
+    pub fn parse_filter_expression_raw(&mut self, node: Box<dyn Node>) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        ExpressionParser::new(self.ext.clone()).parse_filter_expression_raw(self, node)
+    }
+
+    /// Parses a list of comma separated expressions in parentheses, e.g. `(1, a + b)`.
+    pub fn parse_arguments(&mut self) -> Result<Vec<Box<dyn Node>>, Traced<ParserError>> {
+        ExpressionParser::new(self.ext.clone()).parse_arguments(self)
+    }
+}
+
+/// Removes the innermost failed tag which `tag` ends (e.g. `endfor`) from `failed_tags`.
+///
+/// Returns true if `tag` ends or continues (`else` and `elseif`) a failed tag, i.e. is no
+/// error of its own.
+fn close_failed_tag(tag: &str, failed_tags: &mut Vec<String>) -> bool {
+    if tag == "else" || tag == "elseif" {
+        return !failed_tags.is_empty();
+    }
+
+    match failed_tags.iter().rposition(|failed| tag.strip_prefix("end") == Some(&failed[..])) {
+        Some(index) => {
+            failed_tags.truncate(index);
+            true
+        },
+        None => false,
+    }
+}
+
+/// Get the names of all tags which are valid at `item`, i.e. the registered tags and the end
+/// tags `test` is looking for (e.g. `endfor` within a for loop).
+fn tag_names(ext: &ExtensionRegistry, test: Option<&token_parser::Test>, item: &Item) -> Vec<String> {
+    let mut names: Vec<String> = ext.token_parsers().keys().cloned().collect();
+
+    if let Some(test) = test {
+        let end_tags = ext.token_parsers().keys()
+            .map(|tag| format!("end{}", tag))
+            .chain(vec!["else".to_string(), "elseif".to_string()])
+            .filter(|tag| !matches!(test(&Item::new(Token::Name(tag.clone()), item.position().clone())), TestResult::Continue));
+
+        names.extend(end_tags);
+    }
+
+    names
+}
+
+impl<'a> Dump for Job<'a> {
+    type Data = JobDump;
+
+    fn dump(&self) -> Self::Data {
+        format!("template {:?}", self.template_name())
+    }
+}
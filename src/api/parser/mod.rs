@@ -5,8 +5,10 @@
 
 //! Parser
 
-use template;
+use std::rc::Rc;
+use engine::ExtensionRegistry;
 use api::token;
+use api::Node;
 use api::error::Traced;
 
 pub mod options;
@@ -17,18 +19,63 @@ pub use self::job::Job;
 pub use self::error::ParserError;
 pub use self::options::Options;
 
-#[derive(Debug, Default)]
-pub struct Parser;
+#[derive(Debug)]
+pub struct Parser {
+    ext: Rc<ExtensionRegistry>,
+    opt: Options,
+}
 
 impl Parser {
-    pub fn new(_opt: Options) -> Result<Parser, Traced<ParserError>> {
-        unimplemented!()
+    /// Create a new parser for the token parsers and operators registered by engine extensions.
+    pub fn new(ext: Rc<ExtensionRegistry>, opt: Options) -> Result<Parser, Traced<ParserError>> {
+        Ok(Parser {
+            ext,
+            opt,
+        })
+    }
+
+    pub fn ext(&self) -> &Rc<ExtensionRegistry> {
+        &self.ext
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.opt
+    }
+
+    /// Parses a token stream into the abstract syntax tree of a template.
+    ///
+    /// # Failures
+    /// * When the token stream does not end with `Token::Eof`
+    /// * When the token stream does not match the template syntax
+    pub fn parse<'a, 't> (&'a self, stream: &'t token::Stream<'t>) -> Result<Box<dyn Node>, Traced<ParserError>>
+        where 't: 'a // the token stream must outlive the Parser
+    {
+        try_traced!(check_eof(stream));
+
+        Job::new(self, stream).parse()
     }
 
-    #[allow(dead_code)] // TODO: testcase
-    pub fn parse<'a, 't> (&'a self, _stream: &'t token::Stream<'t>) -> Result<template::Compiled, Traced<ParserError>>
+    /// Parses a token stream and collects all errors, see `Job::parse_recovering`.
+    ///
+    /// Returns the (partial) abstract syntax tree with the errors, e.g. for linting.
+    pub fn parse_recovering<'a, 't> (&'a self, stream: &'t token::Stream<'t>) -> (Option<Box<dyn Node>>, Vec<Traced<ParserError>>)
         where 't: 'a // the token stream must outlive the Parser
     {
-        unimplemented!()
+        if let Err(error) = check_eof(stream) {
+            return (None, vec![error]);
+        }
+
+        Job::new(self, stream).parse_recovering()
+    }
+}
+
+fn check_eof(stream: &token::Stream) -> Result<(), Traced<ParserError>> {
+    match stream.get(stream.len().wrapping_sub(1)) {
+        Some(item) if *item.token() == token::Token::Eof => Ok(()),
+        _ => traced_err!(ParserError::UnexpectedEof {
+            reason: Some("Token stream must end with EOF."),
+            expected: Some(format!("{:?}", token::Type::Eof)),
+            position: stream.get(stream.len().wrapping_sub(1)).map(|item| item.position().clone()).unwrap_or_default(),
+        }),
     }
 }
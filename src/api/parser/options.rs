@@ -5,7 +5,7 @@
 
 //! Options of the parser.
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
 pub struct Options;
 
 //... should these Options structures have public members, or not?
@@ -0,0 +1,94 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Variables of a template during rendering.
+
+use api::Value;
+use api::value::Hash;
+
+/// Stack of variable scopes.
+///
+/// Variables are looked up from the innermost scope outwards. Assignments always go to the
+/// innermost scope, so they are discarded together with that scope - e.g. at the end of a loop.
+#[derive(Debug, Default)]
+pub struct Context {
+    scopes: Vec<Hash>,
+}
+
+impl Context {
+    pub fn new(data: Hash) -> Context {
+        Context {
+            scopes: vec![data],
+        }
+    }
+
+    /// Get a variable from the innermost scope where it is defined.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.scopes.iter().rev().filter_map(|scope| scope.get(name)).next()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Assign a variable in the innermost scope.
+    pub fn set<K>(&mut self, name: K, value: Value) where
+        K: Into<String>
+    {
+        if self.scopes.is_empty() {
+            self.scopes.push(Hash::new());
+        }
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, value);
+        }
+    }
+
+    pub fn push_scope(&mut self, scope: Hash) {
+        self.scopes.push(scope);
+    }
+
+    pub fn pop_scope(&mut self) -> Option<Hash> {
+        self.scopes.pop()
+    }
+
+    /// Get all variables, with inner scopes shadowing outer scopes.
+    pub fn flatten(&self) -> Hash {
+        let mut all = Hash::new();
+
+        for scope in &self.scopes {
+            for (key, value) in scope {
+                all.insert(key.clone(), value.clone());
+            }
+        }
+
+        all
+    }
+
+    /// Get the names of all variables.
+    pub fn names(&self) -> Vec<String> {
+        self.flatten().keys().iter().map(|k| k.to_string()).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scopes() {
+        let mut context = Context::new(vec![("a", Value::from(1))].into_iter().collect());
+        context.push_scope(Hash::new());
+        context.set("a", Value::from(2));
+        context.set("b", Value::from(3));
+
+        assert_eq!(context.get("a"), Some(&Value::from(2)));
+        assert_eq!(context.flatten().len(), 2);
+
+        context.pop_scope();
+        assert_eq!(context.get("a"), Some(&Value::from(1)));
+        assert!(!context.contains("b"));
+    }
+}
@@ -0,0 +1,393 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Typisation of runtime errors.
+
+use std::fmt::{self, Display};
+use std::error::Error;
+use engine::TwigError;
+use api::token::stream::Position;
+use api::error::{suggest, Traced};
+
+#[derive(Debug)]
+pub enum RuntimeError {
+    /// Raised if a statement node is evaluated like an expression.
+    NotAnExpression {
+        tag: String,
+    },
+    UnknownOperator {
+        repr: String,
+    },
+    UnknownFilter {
+        name: String,
+    },
+    UnknownFunction {
+        name: String,
+    },
+    UnknownTest {
+        name: String,
+    },
+    UnsupportedOperandTypes {
+        operator: String,
+        types: Vec<&'static str>,
+    },
+    DivisionByZero {
+        operator: String,
+    },
+    InvalidRegex {
+        pattern: String,
+        reason: String,
+    },
+    /// To be used by custom implementations of filters, functions, tests and operators.
+    InvalidArgument {
+        reason: String,
+    },
+    /// Raised if a filter, function or test is called with an argument of the wrong type.
+    InvalidArgumentType {
+        /// E.g. `filter "join"`
+        callable: String,
+        /// E.g. `glue`
+        argument: String,
+        /// E.g. `a string`
+        expected: String,
+        found: &'static str,
+        template: String,
+        position: Position,
+    },
+    /// Raised in strict mode if a template refers to an undefined variable.
+    UndefinedVariable {
+        name: String,
+        template: String,
+        position: Position,
+        /// Names of similar variables in scope.
+        similar: Vec<String>,
+    },
+    /// Raised in strict mode if a template refers to an undefined attribute or key of a value.
+    UndefinedAttribute {
+        attribute: String,
+        /// Type of the value, e.g. `hash`.
+        found: &'static str,
+        template: String,
+        position: Position,
+        /// Names of similar attributes of the value.
+        similar: Vec<String>,
+    },
+    /// Raised if a template refers to a constant which is not defined via `Setup::add_constant()`.
+    UnknownConstant {
+        name: String,
+        template: String,
+        position: Position,
+    },
+    /// Raised if a template referred to by another template (e.g. via `extends`) can not be
+    /// loaded or compiled.
+    LoadTemplate {
+        name: String,
+        caller: String,
+        position: Position,
+        cause: Traced<TwigError>,
+    },
+    /// Raised if none of the candidates of a dynamic parent exists, e.g. `{% extends ['a', 'b'] %}`.
+    NoTemplateFound {
+        candidates: Vec<String>,
+        caller: String,
+        position: Position,
+    },
+    /// Raised if a template extends or uses itself, directly or via other templates.
+    CircularInheritance {
+        chain: Vec<String>,
+        caller: String,
+        position: Position,
+    },
+    /// Raised if a macro is not defined by the template it is called from, or if no template
+    /// has been imported under the name of the call.
+    UnknownMacro {
+        name: String,
+        template: Option<String>,
+        caller: String,
+        position: Position,
+    },
+    /// Raised if a template imported via `use` extends another template or has content
+    /// outside of blocks.
+    NotTraitable {
+        name: String,
+        caller: String,
+        position: Position,
+    },
+    /// Raised if blocks of different templates imported via `use` have the same name.
+    TraitConflict {
+        name: String,
+        template: String,
+        traits: Vec<String>,
+        position: Position,
+    },
+    /// Raised if templates include each other too deeply, e.g. by an infinite recursion.
+    DepthLimitExceeded {
+        limit: usize,
+        template: String,
+        caller: String,
+        position: Position,
+    },
+    BlockNotFound {
+        name: String,
+        template: String,
+    },
+    NoParentBlock {
+        name: String,
+        template: String,
+    },
+}
+
+impl Error for RuntimeError {
+    fn description(&self) -> &str {
+        match *self {
+            RuntimeError::NotAnExpression{..} => "Node is not an expression.",
+            RuntimeError::UnknownOperator{..} => "Unknown operator.",
+            RuntimeError::UnknownFilter{..} => "Unknown filter.",
+            RuntimeError::UnknownFunction{..} => "Unknown function.",
+            RuntimeError::UnknownTest{..} => "Unknown test.",
+            RuntimeError::UnsupportedOperandTypes{..} => "Unsupported operand types.",
+            RuntimeError::DivisionByZero{..} => "Division by zero.",
+            RuntimeError::InvalidRegex{..} => "Invalid regular expression.",
+            RuntimeError::InvalidArgument{..} => "Invalid argument.",
+            RuntimeError::InvalidArgumentType{..} => "Invalid argument type.",
+            RuntimeError::UndefinedVariable{..} => "Undefined variable.",
+            RuntimeError::UndefinedAttribute{..} => "Undefined attribute.",
+            RuntimeError::UnknownConstant{..} => "Unknown constant.",
+            RuntimeError::LoadTemplate{..} => "Template could not be loaded.",
+            RuntimeError::NoTemplateFound{..} => "Template not found.",
+            RuntimeError::CircularInheritance{..} => "Circular template inheritance.",
+            RuntimeError::UnknownMacro{..} => "Unknown macro.",
+            RuntimeError::NotTraitable{..} => "Template can not be used.",
+            RuntimeError::TraitConflict{..} => "Conflicting blocks of used templates.",
+            RuntimeError::DepthLimitExceeded{..} => "Template depth limit exceeded.",
+            RuntimeError::BlockNotFound{..} => "Block not found.",
+            RuntimeError::NoParentBlock{..} => "Block has no parent.",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        match *self {
+            RuntimeError::LoadTemplate { ref cause, .. } => Some(&**cause),
+            _ => None,
+        }
+    }
+}
+
+impl RuntimeError {
+    /// Get the stable code of the error, see `twig::api::error::index`.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            RuntimeError::NotAnExpression{..} => "T0601",
+            RuntimeError::UnknownOperator{..} => "T0602",
+            RuntimeError::UnknownFilter{..} => "T0603",
+            RuntimeError::UnknownFunction{..} => "T0604",
+            RuntimeError::UnknownTest{..} => "T0605",
+            RuntimeError::UnsupportedOperandTypes{..} => "T0606",
+            RuntimeError::DivisionByZero{..} => "T0607",
+            RuntimeError::InvalidRegex{..} => "T0608",
+            RuntimeError::InvalidArgument{..} => "T0609",
+            RuntimeError::InvalidArgumentType{..} => "T0610",
+            RuntimeError::UndefinedVariable{..} => "T0611",
+            RuntimeError::UndefinedAttribute{..} => "T0612",
+            RuntimeError::UnknownConstant{..} => "T0613",
+            RuntimeError::LoadTemplate{..} => "T0614",
+            RuntimeError::NoTemplateFound{..} => "T0615",
+            RuntimeError::CircularInheritance{..} => "T0616",
+            RuntimeError::UnknownMacro{..} => "T0617",
+            RuntimeError::NotTraitable{..} => "T0618",
+            RuntimeError::TraitConflict{..} => "T0619",
+            RuntimeError::DepthLimitExceeded{..} => "T0620",
+            RuntimeError::BlockNotFound{..} => "T0621",
+            RuntimeError::NoParentBlock{..} => "T0622",
+        }
+    }
+
+    /// Returns true if the error is raised because a referred template does not exist.
+    pub fn is_template_not_found(&self) -> bool {
+        match *self {
+            RuntimeError::LoadTemplate { ref cause, .. } => cause.is_template_not_found(),
+            RuntimeError::NoTemplateFound { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Get the position within the current template, if the error records one.
+    pub fn position(&self) -> Option<&Position> {
+        match *self {
+            RuntimeError::InvalidArgumentType { ref position, .. }
+            | RuntimeError::UndefinedVariable { ref position, .. }
+            | RuntimeError::UndefinedAttribute { ref position, .. }
+            | RuntimeError::UnknownConstant { ref position, .. }
+            | RuntimeError::LoadTemplate { ref position, .. }
+            | RuntimeError::NoTemplateFound { ref position, .. }
+            | RuntimeError::UnknownMacro { ref position, .. }
+            | RuntimeError::NotTraitable { ref position, .. }
+            | RuntimeError::CircularInheritance { ref position, .. }
+            | RuntimeError::TraitConflict { ref position, .. }
+            | RuntimeError::DepthLimitExceeded { ref position, .. } => Some(position),
+            _ => None,
+        }
+    }
+
+    /// Get the names suggested instead of a misspelled name, e.g. of a variable.
+    pub fn similar(&self) -> &[String] {
+        match *self {
+            RuntimeError::UndefinedVariable { ref similar, .. }
+            | RuntimeError::UndefinedAttribute { ref similar, .. } => similar,
+            _ => &[],
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{}", self.description()));
+
+        match *self {
+            RuntimeError::NotAnExpression {
+                ref tag
+            } => {
+                write!(f, " Node {tag:?} can not be evaluated.",
+                    tag = tag)
+            },
+            RuntimeError::UnknownOperator {
+                ref repr
+            } => {
+                write!(f, " The operator {repr:?} is unknown to the engine.",
+                    repr = repr)
+            },
+            RuntimeError::UnknownFilter {
+                ref name
+            } => {
+                write!(f, " The filter {name:?} is unknown to the engine.",
+                    name = name)
+            },
+            RuntimeError::UnknownFunction {
+                ref name
+            } => {
+                write!(f, " The function {name:?} is unknown to the engine.",
+                    name = name)
+            },
+            RuntimeError::UnknownTest {
+                ref name
+            } => {
+                write!(f, " The test {name:?} is unknown to the engine.",
+                    name = name)
+            },
+            RuntimeError::UnsupportedOperandTypes {
+                ref operator, ref types
+            } => {
+                write!(f, " Operator {operator:?} can not be applied to {types}.",
+                    operator = operator,
+                    types = types.join(" and "))
+            },
+            RuntimeError::DivisionByZero {
+                ref operator
+            } => {
+                write!(f, " Right operand of {operator:?} is zero.",
+                    operator = operator)
+            },
+            RuntimeError::InvalidRegex {
+                ref pattern, ref reason
+            } => {
+                write!(f, " Pattern {pattern:?}: {reason}.",
+                    pattern = pattern,
+                    reason = reason)
+            },
+            RuntimeError::InvalidArgument {
+                ref reason
+            } => {
+                write!(f, " {}", reason)
+            },
+            RuntimeError::InvalidArgumentType {
+                ref callable, ref argument, ref expected, found, ref template, ref position
+            } => {
+                write!(f, " The {callable} expects {argument} to be {expected}, got {found} in template {template:?} at {pos}.",
+                    callable = callable, argument = argument, expected = expected, found = found,
+                    template = template, pos = position)
+            },
+            RuntimeError::UndefinedVariable {
+                ref name, ref template, ref position, ref similar
+            } => {
+                write!(f, " Variable {name:?} does not exist in template {template:?} at {pos}.{suggestions}",
+                    name = name, template = template, pos = position,
+                    suggestions = suggest::did_you_mean(similar))
+            },
+            RuntimeError::UndefinedAttribute {
+                ref attribute, found, ref template, ref position, ref similar
+            } => {
+                write!(f, " Key {attribute:?} for {found} does not exist in template {template:?} at {pos}.{suggestions}",
+                    attribute = attribute, found = found, template = template, pos = position,
+                    suggestions = suggest::did_you_mean(similar))
+            },
+            RuntimeError::UnknownConstant {
+                ref name, ref template, ref position
+            } => {
+                write!(f, " The constant {name:?} is not defined in template {template:?} at {pos}.",
+                    name = name, template = template, pos = position)
+            },
+            RuntimeError::LoadTemplate {
+                ref name, ref caller, ref position, ref cause
+            } => {
+                write!(f, " Template {name:?} referred to by {caller:?} at {pos}: {cause}",
+                    name = name, caller = caller, pos = position, cause = **cause)
+            },
+            RuntimeError::NoTemplateFound {
+                ref candidates, ref caller, ref position
+            } => {
+                write!(f, " Unable to find one of the templates {candidates:?} referred to by {caller:?} at {pos}.",
+                    candidates = candidates, caller = caller, pos = position)
+            },
+            RuntimeError::CircularInheritance {
+                ref chain, ref caller, ref position
+            } => {
+                write!(f, " {chain}, referred to by {caller:?} at {pos}.",
+                    chain = chain.join(" -> "), caller = caller, pos = position)
+            },
+            RuntimeError::UnknownMacro {
+                ref name, template: Some(ref template), ref caller, ref position
+            } => {
+                write!(f, " Macro {name:?} is not defined in template {template:?}, called by {caller:?} at {pos}.",
+                    name = name, template = template, caller = caller, pos = position)
+            },
+            RuntimeError::UnknownMacro {
+                ref name, template: None, ref caller, ref position
+            } => {
+                write!(f, " Macro {name:?} called by {caller:?} at {pos} has not been imported.",
+                    name = name, caller = caller, pos = position)
+            },
+            RuntimeError::NotTraitable {
+                ref name, ref caller, ref position
+            } => {
+                write!(f, " Template {name:?} used by {caller:?} at {pos} must not extend another template or have content outside of blocks.",
+                    name = name, caller = caller, pos = position)
+            },
+            RuntimeError::TraitConflict {
+                ref name, ref template, ref traits, ref position
+            } => {
+                write!(f, " Block {name:?} of template {template:?} is imported from both {traits} at {pos}, rename one of them with `use .. with {name} as ..`.",
+                    name = name, template = template, traits = traits.join(" and "), pos = position)
+            },
+            RuntimeError::DepthLimitExceeded {
+                limit, ref template, ref caller, ref position
+            } => {
+                write!(f, " Template {template:?} referred to by {caller:?} at {pos} can not be rendered within {limit} nested templates - is there an infinite recursion?",
+                    template = template, caller = caller, pos = position, limit = limit)
+            },
+            RuntimeError::BlockNotFound {
+                ref name, ref template
+            } => {
+                write!(f, " Block {name:?} is not defined by template {template:?} or its parents.",
+                    name = name, template = template)
+            },
+            RuntimeError::NoParentBlock {
+                ref name, ref template
+            } => {
+                write!(f, " Block {name:?} of template {template:?} does not override a block of a parent template.",
+                    name = name, template = template)
+            },
+        }
+    }
+}
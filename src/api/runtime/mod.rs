@@ -0,0 +1,851 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Runtime - renders the abstract syntax tree of compiled templates.
+//!
+//! Nodes render themselves (see `twig::api::Node::render()` and `evaluate()`). The runtime
+//! job provides access to the engine, the current variables and the output.
+
+use std::rc::Rc;
+use std::mem;
+use std::collections::HashMap;
+use engine::{self, Engine, ExtensionRegistry};
+use template;
+use api::{Node, Value};
+use api::node::{Block, Use};
+use api::value::{Hash, Markup};
+use api::token::stream::Position;
+use api::error::Traced;
+
+pub mod context;
+pub mod error;
+pub mod trace;
+pub use self::context::Context;
+pub use self::error::RuntimeError;
+pub use self::trace::{Callers, Frame, FrameKind, TemplateTrace};
+
+/// Maximum number of nested templates, e.g. via `include`.
+///
+/// Guards against infinite recursion, which would overflow the stack otherwise.
+pub const MAX_DEPTH: usize = 50;
+
+/// A template of the inheritance chain, with the blocks it imports via `{% use %}`.
+#[derive(Debug)]
+struct Level {
+    template: Rc<template::Compiled>,
+    traits: Vec<Trait>,
+}
+
+/// A block imported via `{% use %}`.
+#[derive(Debug)]
+struct Trait {
+    /// Name of the block in the importing template, i.e. after renaming.
+    name: String,
+    /// Template defining the block.
+    template: Rc<template::Compiled>,
+    /// Name of the block in the defining template.
+    block: String,
+}
+
+/// A runtime job - renders one template (including all templates it refers to).
+#[derive(Debug)]
+pub struct Job<'e> {
+    engine: &'e mut Engine,
+    ext: Rc<ExtensionRegistry>,
+    context: Context,
+    globals: HashMap<String, Value>,
+    output: String,
+    /// Templates currently rendered, the innermost last.
+    templates: Vec<Rc<template::Compiled>>,
+    /// Inheritance chain of the current template, the child first.
+    chain: Vec<Level>,
+    /// Blocks currently rendered, with the slot of their definition in the chain.
+    ///
+    /// Each level of the chain has two slots: `2 * i` for the blocks of the template itself
+    /// and `2 * i + 1` for the blocks imported via `use`.
+    blocks: Vec<(String, usize)>,
+    /// Position of the filter, function or test currently called, if any within the current
+    /// template.
+    position: Option<Position>,
+    /// Whether undefined variables and attributes raise errors.
+    strict_variables: bool,
+    /// Call sites of the templates currently rendered, the outermost first.
+    frames: Vec<Frame>,
+    /// Location of the last error, recorded when it leaves the template it occurred in.
+    trace: Option<TemplateTrace>,
+    /// Templates compiled from strings during this job, e.g. via `template_from_string()`.
+    string_templates: HashMap<String, Rc<template::Compiled>>,
+}
+
+impl<'e> Job<'e> {
+    pub fn new(engine: &'e mut Engine, data: Hash) -> Job<'e> {
+        Job {
+            ext: engine.extension_registry(),
+            strict_variables: engine.options().strict_variables(),
+            engine,
+            context: Context::new(data),
+            globals: HashMap::new(),
+            output: String::new(),
+            templates: vec![],
+            chain: vec![],
+            blocks: vec![],
+            position: None,
+            frames: vec![],
+            trace: None,
+            string_templates: HashMap::new(),
+        }
+    }
+
+    pub fn engine(&mut self) -> &mut Engine {
+        self.engine
+    }
+
+    pub fn options(&self) -> &engine::Options {
+        self.engine.options()
+    }
+
+    /// Get the engine extensions.
+    ///
+    /// Returns a shared handle, so filters etc. can be called with a mutable job.
+    pub fn ext(&self) -> Rc<ExtensionRegistry> {
+        self.ext.clone()
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    pub fn context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    /// Get a variable.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.context.get(name)
+    }
+
+    /// Get a variable or - if it is not defined in the context - a global.
+    ///
+    /// Globals are computed on first access and cached for the rest of the render.
+    pub fn lookup(&mut self, name: &str) -> Result<Option<Value>, Traced<RuntimeError>> {
+        if let Some(value) = self.context.get(name) {
+            return Ok(Some(value.clone()));
+        }
+
+        if let Some(value) = self.globals.get(name) {
+            return Ok(Some(value.clone()));
+        }
+
+        let ext = self.ext.clone();
+        match ext.globals().get(name) {
+            Some(global) => {
+                let value = try_traced!(global.value(self));
+                self.globals.insert(name.to_string(), value.clone());
+
+                Ok(Some(value))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Get the names of all variables and globals, e.g. to suggest similar names.
+    pub fn variable_names(&self) -> Vec<String> {
+        let mut names = self.context.names();
+        names.extend(self.ext.globals().keys().cloned());
+
+        names
+    }
+
+    /// Returns true if undefined variables and attributes raise errors.
+    pub fn strict_variables(&self) -> bool {
+        self.strict_variables
+    }
+
+    /// Executes `f` with strict variables disabled, i.e. undefined variables and attributes
+    /// evaluate to null.
+    pub fn lenient<F, T>(&mut self, f: F) -> T where
+        F: FnOnce(&mut Job<'e>) -> T
+    {
+        let strict_variables = mem::replace(&mut self.strict_variables, false);
+        let result = f(self);
+        self.strict_variables = strict_variables;
+
+        result
+    }
+
+    /// Assign a variable in the current scope.
+    pub fn set<K>(&mut self, name: K, value: Value) where
+        K: Into<String>
+    {
+        self.context.set(name, value)
+    }
+
+    /// Write to the output.
+    pub fn write(&mut self, data: &str) {
+        self.output.push_str(data);
+    }
+
+    /// Renders into a separate buffer and returns its content, e.g. for `{% set %}` captures.
+    pub fn capture<F>(&mut self, f: F) -> Result<String, Traced<RuntimeError>> where
+        F: FnOnce(&mut Job<'e>) -> Result<(), Traced<RuntimeError>>
+    {
+        let outer = mem::take(&mut self.output);
+        let result = f(self);
+        let captured = mem::replace(&mut self.output, outer);
+
+        try_traced!(result);
+
+        Ok(captured)
+    }
+
+    /// Executes `f` with a new variable scope.
+    ///
+    /// Variables assigned within `f` are discarded afterwards.
+    pub fn scoped<F, T>(&mut self, scope: Hash, f: F) -> Result<T, Traced<RuntimeError>> where
+        F: FnOnce(&mut Job<'e>) -> Result<T, Traced<RuntimeError>>
+    {
+        self.context.push_scope(scope);
+        let result = f(self);
+        self.context.pop_scope();
+
+        result
+    }
+
+    /// Executes `f` with a new context, which only contains the variables of `scope`.
+    ///
+    /// Globals remain visible.
+    pub fn isolated<F, T>(&mut self, scope: Hash, f: F) -> Result<T, Traced<RuntimeError>> where
+        F: FnOnce(&mut Job<'e>) -> Result<T, Traced<RuntimeError>>
+    {
+        let outer = mem::replace(&mut self.context, Context::new(scope));
+        let result = f(self);
+        self.context = outer;
+
+        result
+    }
+
+    /// Get the position of the filter, function or test currently called.
+    pub fn position(&self) -> Position {
+        self.position.clone().unwrap_or_default()
+    }
+
+    pub fn set_position(&mut self, position: &Position) {
+        self.position = Some(position.clone());
+    }
+
+    /// Get the name of the template currently rendered.
+    pub fn template_name(&self) -> &str {
+        self.templates.last().map_or("", |t| t.name())
+    }
+
+    /// Get the call sites of the templates currently rendered, the outermost first.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Takes the location of the last error within the templates.
+    ///
+    /// Returns an empty trace if the error did not occur within a template.
+    pub fn take_trace(&mut self) -> TemplateTrace {
+        self.trace.take().unwrap_or_default()
+    }
+
+    /// Loads a template referred to by the current template at `position`.
+    pub fn load_template(&mut self, name: &str, position: &Position) -> Result<Rc<template::Compiled>, Traced<RuntimeError>> {
+        if let Some(template) = self.string_templates.get(name) {
+            return Ok(template.clone());
+        }
+
+        match self.engine.load_template(name, None) {
+            Ok(template) => Ok(template),
+            Err(cause) => traced_err!(RuntimeError::LoadTemplate {
+                name: name.to_string(),
+                caller: self.template_name().to_string(),
+                position: position.clone(),
+                cause,
+            }),
+        }
+    }
+
+    /// Compiles a template from a string, e.g. for `template_from_string()`.
+    ///
+    /// The template is not registered in the engine, but only loadable by its name for the rest
+    /// of this job.
+    pub fn add_template(&mut self, name: &str, source: &str, position: &Position) -> Result<Rc<template::Compiled>, Traced<RuntimeError>> {
+        match self.engine.compile_string(name, source) {
+            Ok(template) => {
+                let template = Rc::new(template);
+                self.string_templates.insert(name.to_string(), template.clone());
+
+                Ok(template)
+            },
+            Err(cause) => traced_err!(RuntimeError::LoadTemplate {
+                name: name.to_string(),
+                caller: self.template_name().to_string(),
+                position: position.clone(),
+                cause,
+            }),
+        }
+    }
+
+    /// Loads an embedded template of the current template, i.e. of an `{% embed %}` tag.
+    pub fn load_embedded_template(&mut self, index: usize, position: &Position) -> Result<Rc<template::Compiled>, Traced<RuntimeError>> {
+        let name = self.template_name().to_string();
+
+        if let Some(template) = self.string_templates.get(&name) {
+            if let Some(embedded) = template.embedded_template(index) {
+                return Ok(embedded);
+            }
+        }
+
+        match self.engine.load_template(&name, Some(index as u32)) {
+            Ok(template) => Ok(template),
+            Err(cause) => traced_err!(RuntimeError::LoadTemplate {
+                caller: name.clone(),
+                name,
+                position: position.clone(),
+                cause,
+            }),
+        }
+    }
+
+    /// Get the source of a template without rendering it, e.g. for `source('name')`.
+    pub fn template_source(&mut self, name: &str, position: &Position) -> Result<String, Traced<RuntimeError>> {
+        let source = match self.engine.loader() {
+            Ok(loader) => loader.source(name).map(|s| s.into_owned()).map_err(|e| e.trace(loc!())),
+            Err(error) => Err(error),
+        };
+
+        match source {
+            Ok(source) => Ok(source),
+            Err(cause) => traced_err!(RuntimeError::LoadTemplate {
+                name: name.to_string(),
+                caller: self.template_name().to_string(),
+                position: position.clone(),
+                cause,
+            }),
+        }
+    }
+
+    /// Loads the first existing template of a list of candidates, or a single template.
+    pub fn load_first_template(&mut self, names: &Value, position: &Position) -> Result<Rc<template::Compiled>, Traced<RuntimeError>> {
+        let candidates: Vec<String> = match *names {
+            Value::Array(ref candidates) => candidates.iter().map(|c| c.to_string()).collect(),
+            ref name => return self.load_template(&name.to_string(), position),
+        };
+
+        for candidate in &candidates {
+            match self.engine.load_template(candidate, None) {
+                Ok(template) => return Ok(template),
+                Err(ref error) if error.is_template_not_found() => continue,
+                Err(cause) => return traced_err!(RuntimeError::LoadTemplate {
+                    name: candidate.clone(),
+                    caller: self.template_name().to_string(),
+                    position: position.clone(),
+                    cause,
+                }),
+            }
+        }
+
+        traced_err!(RuntimeError::NoTemplateFound {
+            candidates,
+            caller: self.template_name().to_string(),
+            position: position.clone(),
+        })
+    }
+
+    /// Evaluates the parent expression of a child template and loads the parent template.
+    pub fn load_parent(&mut self, parent: &dyn Node) -> Result<Rc<template::Compiled>, Traced<RuntimeError>> {
+        let names = try_traced!(parent.evaluate(self));
+
+        self.load_first_template(&names, parent.position())
+    }
+
+    /// Renders a template, with its own inheritance chain.
+    pub fn render_template(&mut self, template: Rc<template::Compiled>) -> Result<(), Traced<RuntimeError>> {
+        let chain = mem::take(&mut self.chain);
+        let blocks = mem::take(&mut self.blocks);
+
+        let result = self.push_level(template.clone())
+            .and_then(|_| self.within(template, |job, template| template.render(job)));
+
+        self.chain = chain;
+        self.blocks = blocks;
+
+        result
+    }
+
+    /// Renders another template with additional variables, e.g. for `{% include %}`.
+    ///
+    /// Without `with_context`, the template only has access to `variables` and the globals.
+    /// `kind` and `position` describe the call site for template backtraces.
+    ///
+    /// # Failures
+    /// * When more than `MAX_DEPTH` templates are nested
+    pub fn include(&mut self, template: Rc<template::Compiled>, variables: Hash, with_context: bool, kind: FrameKind, position: &Position) -> Result<(), Traced<RuntimeError>> {
+        try_traced!(self.check_depth(&template, position));
+
+        self.called(kind, position, |job| if with_context {
+            job.scoped(variables, |job| job.render_template(template))
+        } else {
+            job.isolated(variables, |job| job.render_template(template))
+        })
+    }
+
+    /// Calls a macro of `template` with positional arguments and returns its output as markup.
+    ///
+    /// The macro is rendered in an isolated scope, which only contains its arguments and
+    /// `varargs` - the list of additional arguments.
+    ///
+    /// # Failures
+    /// * When the template does not define the macro
+    /// * When more than `MAX_DEPTH` templates or macros are nested
+    pub fn call_macro(&mut self, template: Rc<template::Compiled>, name: &str, mut arguments: Vec<Value>, position: &Position) -> Result<Value, Traced<RuntimeError>> {
+        let definition = match template.module().and_then(|m| m.macro_(name)) {
+            Some(definition) => definition,
+            None => return traced_err!(RuntimeError::UnknownMacro {
+                name: name.to_string(),
+                template: Some(template.name().to_string()),
+                caller: self.template_name().to_string(),
+                position: position.clone(),
+            }),
+        };
+
+        try_traced!(self.check_depth(&template, position));
+
+        let varargs = if arguments.len() > definition.arguments().len() {
+            arguments.split_off(definition.arguments().len())
+        } else {
+            vec![]
+        };
+        let mut arguments = arguments.into_iter();
+        let mut scope = Hash::new();
+
+        for (i, argument) in definition.arguments().iter().enumerate() {
+            let value = match arguments.next() {
+                Some(value) => value,
+                None => match definition.default_value(i) {
+                    Some(default) => try_traced!(self.isolated(Hash::new(), |job| default.evaluate(job))),
+                    None => Value::Null,
+                },
+            };
+
+            scope.insert(argument.clone(), value);
+        }
+
+        scope.insert("varargs".to_string(), Value::from(varargs));
+
+        let output = try_traced!(self.called(FrameKind::Macro(name.to_string()), position, |job| {
+            job.isolated(scope, |job| {
+                job.within(template.clone(), |job, _| job.capture(|job| definition.body().render(job)))
+            })
+        }));
+
+        Ok(Value::from(Markup::new(output, "all")))
+    }
+
+    /// Renders the parent of the current template, which refers to it at `position`.
+    ///
+    /// # Failures
+    /// * When the parent is already part of the inheritance chain
+    pub fn extend(&mut self, parent: Rc<template::Compiled>, position: &Position) -> Result<(), Traced<RuntimeError>> {
+        try_traced!(self.check_circular(&parent, position));
+        try_traced!(self.push_level(parent.clone()));
+
+        self.called(FrameKind::Extends, position, |job| job.within(parent, |job, parent| parent.render(job)))
+    }
+
+    /// Renders the most derived definition of a block within the current inheritance chain.
+    pub fn render_block(&mut self, name: &str) -> Result<(), Traced<RuntimeError>> {
+        match self.find_block(name, 0) {
+            Some(index) => self.render_block_at(name, index),
+            None => traced_err!(RuntimeError::BlockNotFound {
+                name: name.to_string(),
+                template: self.chain.first().map_or("", |l| l.template.name()).to_string(),
+            }),
+        }
+    }
+
+    /// Renders the definition of the current block in the parent templates, i.e. `parent()`.
+    pub fn render_parent_block(&mut self) -> Result<(), Traced<RuntimeError>> {
+        let (name, slot) = match self.blocks.last() {
+            Some(&(ref name, slot)) => (name.clone(), slot),
+            None => return traced_err!(RuntimeError::InvalidArgument {
+                reason: "Calling \"parent\" outside a block is forbidden.".to_string(),
+            }),
+        };
+
+        match self.find_block(&name, slot + 1) {
+            Some(parent_slot) => self.render_block_at(&name, parent_slot),
+            None => traced_err!(RuntimeError::NoParentBlock {
+                template: self.chain[slot / 2].template.name().to_string(),
+                name,
+            }),
+        }
+    }
+
+    /// Renders a block of another template (and its parents), e.g. `block('name', 'other.twig')`.
+    pub fn render_block_of(&mut self, name: &str, template: Rc<template::Compiled>) -> Result<(), Traced<RuntimeError>> {
+        let chain = mem::take(&mut self.chain);
+        let blocks = mem::take(&mut self.blocks);
+
+        let result = self.resolve_chain(template).and_then(|_| self.render_block(name));
+
+        self.chain = chain;
+        self.blocks = blocks;
+
+        result
+    }
+
+    /// Completes the inheritance chain, starting with `template`, without rendering anything.
+    fn resolve_chain(&mut self, template: Rc<template::Compiled>) -> Result<(), Traced<RuntimeError>> {
+        try_traced!(self.push_level(template.clone()));
+        let mut current = template;
+
+        loop {
+            let parent = match current.module().and_then(|m| m.parent()) {
+                Some(parent) => try_traced!(self.within(current.clone(), |job, _| {
+                    let template = try_traced!(job.load_parent(parent));
+                    try_traced!(job.check_circular(&template, parent.position()));
+                    try_traced!(job.push_level(template.clone()));
+
+                    Ok(template)
+                })),
+                None => return Ok(()),
+            };
+
+            current = parent;
+        }
+    }
+
+    /// Fails if a parent template, referred to by the current template at `position`, is
+    /// already part of the inheritance chain.
+    fn check_circular(&self, parent: &Rc<template::Compiled>, position: &Position) -> Result<(), Traced<RuntimeError>> {
+        if self.chain.iter().any(|l| Rc::ptr_eq(&l.template, parent)) {
+            let mut chain: Vec<String> = self.chain.iter().map(|l| l.template.name().to_string()).collect();
+            chain.push(parent.name().to_string());
+
+            return traced_err!(RuntimeError::CircularInheritance {
+                chain,
+                caller: self.template_name().to_string(),
+                position: position.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Appends a template to the inheritance chain, with the blocks it imports via `use`.
+    ///
+    /// # Failures
+    /// * When an imported template can not be used or blocks of imported templates conflict
+    fn push_level(&mut self, template: Rc<template::Compiled>) -> Result<(), Traced<RuntimeError>> {
+        let traits = try_traced!(self.traits(&template, &mut vec![]));
+        self.chain.push(Level {
+            template,
+            traits,
+        });
+
+        Ok(())
+    }
+
+    /// Get the blocks a template imports via `use` - including blocks the used templates import.
+    ///
+    /// `using` is the list of importing templates, to detect cycles.
+    fn traits(&mut self, template: &Rc<template::Compiled>, using: &mut Vec<String>) -> Result<Vec<Trait>, Traced<RuntimeError>> {
+        let module = match template.module() {
+            Some(module) => module,
+            None => return Ok(vec![]),
+        };
+
+        using.push(template.name().to_string());
+        let traits = try_traced!(self.within(template.clone(), |job, _| {
+            let mut traits: Vec<Trait> = vec![];
+
+            for node in module.traits().children() {
+                let definition = match node.downcast_ref::<Use>() {
+                    Some(definition) => definition,
+                    None => continue,
+                };
+
+                let name = try_traced!(definition.template().evaluate(job)).to_string();
+                let used = try_traced!(job.load_template(&name, definition.position()));
+                let used_module = match used.module() {
+                    Some(used_module) if used_module.is_traitable() => used_module,
+                    _ => return traced_err!(RuntimeError::NotTraitable {
+                        name,
+                        caller: template.name().to_string(),
+                        position: definition.position().clone(),
+                    }),
+                };
+
+                if using.iter().any(|name| name == used.name()) {
+                    let mut chain = using.clone();
+                    chain.push(used.name().to_string());
+
+                    return traced_err!(RuntimeError::CircularInheritance {
+                        chain,
+                        caller: template.name().to_string(),
+                        position: definition.position().clone(),
+                    });
+                }
+
+                // blocks of the used template override blocks it imports itself
+                let mut imported = try_traced!(job.traits(&used, using));
+                imported.retain(|t| used_module.block(&t.name).is_none());
+                imported.extend(used_module.blocks().children().iter()
+                    .filter_map(|b| b.downcast_ref::<Block>())
+                    .map(|b| Trait {
+                        name: b.name().to_string(),
+                        template: used.clone(),
+                        block: b.name().to_string(),
+                    }));
+
+                for (block, alias) in definition.renames() {
+                    match imported.iter_mut().find(|t| t.name == *block) {
+                        Some(imported) => imported.name = alias.clone(),
+                        None => return traced_err!(RuntimeError::BlockNotFound {
+                            name: block.clone(),
+                            template: name,
+                        }),
+                    }
+                }
+
+                for imported in imported {
+                    if let Some(conflict) = traits.iter().find(|t| t.name == imported.name) {
+                        return traced_err!(RuntimeError::TraitConflict {
+                            name: imported.name,
+                            template: template.name().to_string(),
+                            traits: vec![conflict.template.name().to_string(), imported.template.name().to_string()],
+                            position: definition.position().clone(),
+                        });
+                    }
+
+                    traits.push(imported);
+                }
+            }
+
+            Ok(traits)
+        }));
+        using.pop();
+
+        Ok(traits)
+    }
+
+    /// Get the first slot in the chain from `start` on, which defines a block.
+    fn find_block(&self, name: &str, start: usize) -> Option<usize> {
+        (start..self.chain.len() * 2).find(|&slot| {
+            let level = &self.chain[slot / 2];
+
+            if slot.is_multiple_of(2) {
+                level.template.module().is_some_and(|m| m.block(name).is_some())
+            } else {
+                level.traits.iter().any(|t| t.name == name)
+            }
+        })
+    }
+
+    fn render_block_at(&mut self, name: &str, slot: usize) -> Result<(), Traced<RuntimeError>> {
+        let level = &self.chain[slot / 2];
+        let (template, block) = if slot.is_multiple_of(2) {
+            (level.template.clone(), name.to_string())
+        } else {
+            match level.traits.iter().find(|t| t.name == name) {
+                Some(imported) => (imported.template.clone(), imported.block.clone()),
+                None => return Ok(()),
+            }
+        };
+
+        self.blocks.push((name.to_string(), slot));
+        let result = self.within(template, |job, template| match template.module().and_then(|m| m.block(&block)) {
+            Some(block) => block.body().render(job),
+            None => Ok(()),
+        });
+        self.blocks.pop();
+
+        result
+    }
+
+    /// Fails if `template`, referred to by the current template at `position`, would exceed
+    /// the maximum depth of nested templates.
+    fn check_depth(&self, template: &template::Compiled, position: &Position) -> Result<(), Traced<RuntimeError>> {
+        if self.templates.len() >= MAX_DEPTH {
+            return traced_err!(RuntimeError::DepthLimitExceeded {
+                limit: MAX_DEPTH,
+                template: template.name().to_string(),
+                caller: self.template_name().to_string(),
+                position: position.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Executes `f` with a frame for a call of another template at `position` of the current
+    /// template.
+    fn called<F, T>(&mut self, kind: FrameKind, position: &Position, f: F) -> Result<T, Traced<RuntimeError>> where
+        F: FnOnce(&mut Job<'e>) -> Result<T, Traced<RuntimeError>>
+    {
+        self.frames.push(Frame {
+            kind,
+            template: self.template_name().to_string(),
+            position: position.clone(),
+        });
+        let result = f(self);
+        self.frames.pop();
+
+        result
+    }
+
+    /// Executes `f` while `template` is the current template.
+    ///
+    /// Records the location of an error leaving `template`, unless an inner template already
+    /// recorded it.
+    fn within<F, T>(&mut self, template: Rc<template::Compiled>, f: F) -> Result<T, Traced<RuntimeError>> where
+        F: FnOnce(&mut Job<'e>, &template::Compiled) -> Result<T, Traced<RuntimeError>>
+    {
+        self.templates.push(template.clone());
+        let outer = mem::take(&mut self.position);
+        let result = f(self, &template);
+
+        match result {
+            Ok(_) => self.trace = None,
+            Err(ref error) if self.trace.is_none() => {
+                self.trace = Some(TemplateTrace {
+                    template: template.name().to_string(),
+                    position: error.position().cloned().or_else(|| self.position.clone()),
+                    frames: self.frames.iter().rev().cloned().collect(),
+                });
+            },
+            Err(_) => {},
+        }
+
+        self.position = outer;
+        self.templates.pop();
+
+        result
+    }
+
+    /// Get the rendered output.
+    pub fn into_output(self) -> String {
+        self.output
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FrameKind;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use setup::Setup;
+    use loader;
+    use api::Value;
+    use api::ext::global;
+    use api::value::Hash;
+
+    #[test]
+    fn globals() {
+        let calls = Rc::new(Cell::new(0));
+        let counter = calls.clone();
+
+        let mut twig = Setup::default()
+            .add_global("site", global::Constant::new("Twig"))
+            .add_global("now", global::Lazy::new(move || {
+                counter.set(counter.get() + 1);
+                Value::from(counter.get())
+            }))
+            .add_global("unused", global::Lazy::new(|| panic!("must not be computed")))
+            .init_engine()
+            .unwrap();
+        let mut templates = loader::Array::new();
+        templates.set_template("index", "{{ site }} {{ now }} {{ now }}");
+        twig.set_loader(Box::new(templates));
+
+        assert_eq!(twig.render("index", Hash::new()).unwrap(), "Twig 1 1");
+        assert_eq!(twig.render("index", vec![("site", "Shadowed")].into_iter().collect()).unwrap(), "Shadowed 2 2");
+        assert_eq!(calls.get(), 2);
+    }
+    #[test]
+    fn strict_variables() {
+        let templates = vec![
+            ("variable", "{% for item in items %}{{ itme }}{% endfor %}"),
+            ("attribute", "{{ user.nmae }}"),
+            ("key", "\n{{ items[5] }}"),
+            ("lenient", "{{ missing }}|{{ user.age }}|{{ missing.name|default('x') }}|{{ user.age|default('y') }}|{{ missing is defined }}|{{ user.name }}"),
+        ];
+        let data: Hash = vec![
+            ("items", Value::from(vec![1])),
+            ("user", Value::Hash(vec![("name", "Tom"), ("names", "Tom Tim")].into_iter().collect())),
+        ].into_iter().collect();
+
+        let mut lenient = Setup::default().init_engine().unwrap();
+        let mut strict = Setup::default().set_strict_variables(true).init_engine().unwrap();
+        for twig in [&mut lenient, &mut strict] {
+            let mut loader = loader::Array::new();
+            for &(name, source) in &templates {
+                loader.set_template(name, source);
+            }
+            twig.set_loader(Box::new(loader));
+        }
+
+        assert_eq!(lenient.render("variable", data.clone()).unwrap(), "");
+        assert_eq!(lenient.render("attribute", data.clone()).unwrap(), "");
+        assert_eq!(lenient.render("lenient", data.clone()).unwrap(), "||x|y||Tom");
+        assert!(strict.render("lenient", Hash::new()).unwrap_err().to_string().contains("Variable \"missing\" does not exist"));
+
+        let error = strict.render("variable", data.clone()).unwrap_err().to_string();
+        assert!(error.contains("Variable \"itme\" does not exist in template \"variable\" at 1:27. Did you mean \"item\"?"), "{}", error);
+
+        let error = strict.render("attribute", data.clone()).unwrap_err().to_string();
+        assert!(error.contains("Key \"nmae\" for hash does not exist in template \"attribute\" at 1:8. Did you mean \"name\"?"), "{}", error);
+
+        let error = strict.render("key", data.clone()).unwrap_err().to_string();
+        assert!(error.contains("Key \"5\" for array does not exist in template \"key\" at 2:"), "{}", error);
+    }
+
+    #[test]
+    fn template_trace() {
+        let mut twig = Setup::default().init_engine().unwrap();
+        let mut loader = loader::Array::new();
+        loader.set_template("page", "{% extends 'layout' %}{% block content %}\n  {% include 'list' %}{% endblock %}");
+        loader.set_template("layout", "<main>{% block content %}{% endblock %}</main>");
+        loader.set_template("list", "{% import 'forms' as forms %}\n{{ forms.row(1) }}");
+        loader.set_template("forms", "{% macro row(n) %}{% embed 'cell' %}{% endembed %}{% endmacro %}");
+        loader.set_template("cell", "\n\n  {{ [1]|upper }}");
+        loader.set_template("valid", "{% include 'layout' %}{% include 'missing' ignore missing %}{{ 1 // 0 }}");
+        loader.set_template("recursive", "\n{% include 'recursive' %}");
+        loader.set_template("circular", "{% extends 'circular_parent' %}");
+        loader.set_template("circular_parent", "\n{% extends 'circular' %}");
+        twig.set_loader(Box::new(loader));
+
+        let error = twig.render("page", Hash::new()).unwrap_err();
+        let trace = match *error {
+            ::engine::TwigError::Runtime(_, ref trace) => trace.clone(),
+            _ => panic!("unexpected error {}", error),
+        };
+
+        assert_eq!(trace.template, "cell");
+        assert_eq!(trace.frames.iter().map(|f| f.kind.clone()).collect::<Vec<_>>(), vec![
+            FrameKind::Extends, FrameKind::Embed, FrameKind::Macro("row".to_string()), FrameKind::Include, FrameKind::Extends,
+        ]);
+        assert!(error.to_string().contains(concat!(
+            "got array in template \"cell\" at 3:10. Extended from \"forms\" at 1:28, ",
+            "embedded in \"forms\" at 1:22, in macro \"row\" called from \"list\" at 2:9, ",
+            "included from \"page\" at 2:6, extended from \"page\" at 1:12.",
+        )), "{}", error);
+
+        let error = twig.render("valid", Hash::new()).unwrap_err().to_string();
+        assert!(error.contains("zero. In template \"valid\" at 1:"), "{}", error);
+        assert!(!error.contains("included from"), "{}", error);
+
+        let error = twig.render("recursive", Hash::new()).unwrap_err().to_string();
+        assert!(error.contains("referred to by \"recursive\" at 2:4 can not be rendered"), "{}", error);
+        assert!(!error.contains("0:0") && !error.contains("In template"), "{}", error);
+
+        let error = twig.render("circular", Hash::new()).unwrap_err().to_string();
+        assert!(error.contains("circular -> circular_parent -> circular, referred to by \"circular_parent\" at 2:12."), "{}", error);
+        assert!(!error.contains("0:0"), "{}", error);
+    }
+}
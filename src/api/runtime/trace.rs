@@ -0,0 +1,116 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Template backtraces - where in the templates a runtime error occurred.
+
+use std::fmt::{self, Display};
+use api::token::stream::Position;
+
+/// How a template was entered, e.g. via `{% include %}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameKind {
+    Include,
+    Embed,
+    Extends,
+    /// Macro call, with the name of the macro.
+    Macro(String),
+}
+
+/// Call site of a template, e.g. the `{% include %}` tag in the including template.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub kind: FrameKind,
+    /// Name of the calling template.
+    pub template: String,
+    /// Position of the call within the calling template.
+    pub position: Position,
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            FrameKind::Include => write!(f, "included from"),
+            FrameKind::Embed => write!(f, "embedded in"),
+            FrameKind::Extends => write!(f, "extended from"),
+            FrameKind::Macro(ref name) => write!(f, "in macro {name:?} called from", name = name),
+        }?;
+
+        write!(f, " {template:?} at {pos}",
+            template = self.template,
+            pos = self.position)
+    }
+}
+
+/// Location of a runtime error within the templates, with the frames that led there.
+///
+/// Displays like ` In template "row.twig" at 1:8, included from "list.twig" at 2:4.`, or
+/// nothing if the location is unknown.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TemplateTrace {
+    /// Name of the template the error occurred in.
+    pub template: String,
+    /// Position within the template, if known.
+    pub position: Option<Position>,
+    /// Call sites of the template, the innermost first.
+    pub frames: Vec<Frame>,
+}
+
+impl TemplateTrace {
+    /// Returns true if the location of the error is unknown, e.g. because it did not occur
+    /// while rendering a template.
+    pub fn is_empty(&self) -> bool {
+        self.template.is_empty() && self.frames.is_empty()
+    }
+
+    /// Displays the call sites only, like ` Included from "list.twig" at 2:4.`, for errors which
+    /// already name the template and position they occurred at.
+    pub fn callers(&self) -> Callers<'_> {
+        Callers(self)
+    }
+}
+
+impl Display for TemplateTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        write!(f, " In template {template:?}",
+            template = self.template)?;
+
+        if let Some(ref position) = self.position {
+            write!(f, " at {pos}",
+                pos = position)?;
+        }
+
+        for frame in &self.frames {
+            write!(f, ", {}", frame)?;
+        }
+
+        write!(f, ".")
+    }
+}
+
+/// Call sites of a template trace, see `TemplateTrace::callers`.
+#[derive(Debug)]
+pub struct Callers<'a>(&'a TemplateTrace);
+
+impl<'a> Display for Callers<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, frame) in self.0.frames.iter().enumerate() {
+            let frame = frame.to_string();
+
+            if i == 0 {
+                let mut chars = frame.chars();
+                let first: String = chars.next().into_iter().flat_map(char::to_uppercase).collect();
+                write!(f, " {}{}", first, chars.as_str())?;
+            } else {
+                write!(f, ", {}", frame)?;
+            }
+        }
+
+        if self.0.frames.is_empty() { Ok(()) } else { write!(f, ".") }
+    }
+}
@@ -19,6 +19,16 @@ pub enum TokenError {
     }
 }
 
+impl TokenError {
+    /// Get the stable code of the error, see `twig::api::error::index`.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            TokenError::_NoValue => "T0801",
+            TokenError::UnexpectedTokenAtItem{..} => "T0802",
+        }
+    }
+}
+
 impl Error for TokenError {
     fn description(&self) -> &str {
         match *self {
@@ -37,8 +47,8 @@ impl Display for TokenError {
             TokenError::UnexpectedTokenAtItem {
                 reason, ref expected, ref found
             } => {
-                try!(write!(f, " Expected token matching {x:?} but found item {t:?} at {p:?}",
-                    x = expected, t = found.token(), p = found.position()));
+                write!(f, " Expected token matching {x:?} but found item {t:?} at {p}",
+                    x = expected, t = found.token(), p = found.position())?;
 
                 if let Some(reason) = reason {
                     try!(write!(f, " {}", reason))
@@ -15,9 +15,20 @@ pub use self::error::TokenError;
 
 #[derive(PartialEq, Clone)]
 pub enum Token {
-    _Eof,
+    Eof,
     Text(String),
-    // ..
+    BlockStart,
+    ExpressionStart,
+    BlockEnd,
+    ExpressionEnd,
+    Name(String),
+    IntegerNumber(i64),
+    FloatingNumber(f64),
+    Value(String), // string literal
+    Operator(String),
+    Punctuation(Punctuation),
+    InterpolationStart,
+    InterpolationEnd,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -38,6 +49,7 @@ pub enum BracketType {
     Curly,
     DoubleQuote, // Pseudo-Bracket - never being pushed to a real token Stream
                  // but used as a temporary state of the lexer
+    Interpolation, // Pseudo-Bracket `#{` - see DoubleQuote
 }
 
 #[derive(PartialEq)]
@@ -47,42 +59,134 @@ pub enum BracketType {
 pub enum Type {
     Eof,
     Text,
-    // ..
+    BlockStart,
+    ExpressionStart,
+    BlockEnd,
+    ExpressionEnd,
+    Name,
+    Number,
+    String,
+    Operator,
+    Punctuation,
+    InterpolationStart,
+    InterpolationEnd,
+}
+
+impl Punctuation {
+    /// Returns the character representation of the punctuation.
+    pub fn as_char(&self) -> char {
+        match *self {
+            Punctuation::Dot => '.',
+            Punctuation::Comma => ',',
+            Punctuation::Colon => ':',
+            Punctuation::VerticalBar => '|',
+            Punctuation::QuestionMark => '?',
+            Punctuation::OpeningBracket(ref b) => b.opening(),
+            Punctuation::ClosingBracket(ref b) => b.closing(),
+        }
+    }
+
+    /// Creates punctuation from its character representation.
+    pub fn from_char(c: char) -> Option<Punctuation> {
+        Some(match c {
+            '.' => Punctuation::Dot,
+            ',' => Punctuation::Comma,
+            ':' => Punctuation::Colon,
+            '|' => Punctuation::VerticalBar,
+            '?' => Punctuation::QuestionMark,
+            '(' => Punctuation::OpeningBracket(BracketType::Round),
+            '[' => Punctuation::OpeningBracket(BracketType::Square),
+            '{' => Punctuation::OpeningBracket(BracketType::Curly),
+            ')' => Punctuation::ClosingBracket(BracketType::Round),
+            ']' => Punctuation::ClosingBracket(BracketType::Square),
+            '}' => Punctuation::ClosingBracket(BracketType::Curly),
+            _ => return None,
+        })
+    }
+}
+
+impl BracketType {
+    pub fn opening(&self) -> char {
+        match *self {
+            BracketType::Round => '(',
+            BracketType::Square => '[',
+            BracketType::Curly => '{',
+            BracketType::DoubleQuote => '"',
+            BracketType::Interpolation => '#',
+        }
+    }
+
+    pub fn closing(&self) -> char {
+        match *self {
+            BracketType::Round => ')',
+            BracketType::Square => ']',
+            BracketType::Curly | BracketType::Interpolation => '}',
+            BracketType::DoubleQuote => '"',
+        }
+    }
 }
 
-#[allow(unused_variables)]
 impl Token {
-    // TODO store String representation for numbers and Punctuation?
     // NOTE: Because of Number Types we need to return `String` copys instead of `&'a str`
-    pub fn value<'a>(&'a self) -> Option<String> {
+    pub fn value(&self) -> Option<String> {
         match *self {
-            Token::_Eof => None,
-            Token::Text(ref x) => Some(x.to_string()),
-            // ..
+            Token::Eof
+            | Token::BlockStart
+            | Token::ExpressionStart
+            | Token::BlockEnd
+            | Token::ExpressionEnd
+            | Token::InterpolationStart
+            | Token::InterpolationEnd => None,
+            Token::Text(ref x)
+            | Token::Name(ref x)
+            | Token::Value(ref x)
+            | Token::Operator(ref x) => Some(x.to_string()),
+            Token::IntegerNumber(x) => Some(x.to_string()),
+            Token::FloatingNumber(x) => Some(x.to_string()),
+            Token::Punctuation(ref p) => Some(p.as_char().to_string()),
         }
     }
 
     // NOTE: Does *not* yield number types - use value() instead.
-    pub fn value_as_str<'a>(&'a self) -> Option<&str> {
+    pub fn value_as_str(&self) -> Option<&str> {
         match *self {
-            Token::_Eof => None,
-            Token::Text(ref x) => Some(x),
-            // ..
+            Token::Text(ref x)
+            | Token::Name(ref x)
+            | Token::Value(ref x)
+            | Token::Operator(ref x) => Some(x),
+            _ => None,
         }
     }
 
     pub fn get_type(&self) -> Type {
         match *self {
-            Token::_Eof => Type::Eof,
+            Token::Eof => Type::Eof,
             Token::Text(_) => Type::Text,
-            // ..
+            Token::BlockStart => Type::BlockStart,
+            Token::ExpressionStart => Type::ExpressionStart,
+            Token::BlockEnd => Type::BlockEnd,
+            Token::ExpressionEnd => Type::ExpressionEnd,
+            Token::Name(_) => Type::Name,
+            Token::IntegerNumber(_) | Token::FloatingNumber(_) => Type::Number,
+            Token::Value(_) => Type::String,
+            Token::Operator(_) => Type::Operator,
+            Token::Punctuation(_) => Type::Punctuation,
+            Token::InterpolationStart => Type::InterpolationStart,
+            Token::InterpolationEnd => Type::InterpolationEnd,
         }
     }
 
-    #[allow(dead_code)] // TODO: testcase
     pub fn is_type(&self, typ: Type) -> bool {
         self.get_type() == typ
     }
+
+    /// Describes the token in plain english, e.g. `name "foo"` or `end of template`.
+    pub fn describe(&self) -> String {
+        match self.value() {
+            Some(value) => format!("{} {:?}", self.get_type().description(), value),
+            None => self.get_type().description().to_string(),
+        }
+    }
 }
 
 impl fmt::Display for Token {
@@ -111,23 +215,42 @@ impl Dump for Token {
     }
 }
 
-#[allow(unused_variables)]
 impl Type {
     /// Returns the name of the token type (internal representation).
     pub fn name(&self) -> &'static str {
          match *self {
             Type::Eof => "EOF",
             Type::Text => "TEXT",
-            // ..
+            Type::BlockStart => "BLOCK_START",
+            Type::ExpressionStart => "VAR_START",
+            Type::BlockEnd => "BLOCK_END",
+            Type::ExpressionEnd => "VAR_END",
+            Type::Name => "NAME",
+            Type::Number => "NUMBER",
+            Type::String => "STRING",
+            Type::Operator => "OPERATOR",
+            Type::Punctuation => "PUNCTUATION",
+            Type::InterpolationStart => "INTERPOLATION_START",
+            Type::InterpolationEnd => "INTERPOLATION_END",
         }
     }
 
     /// Returns the description of the token type in plain english.
-    pub fn _description(&self) -> &'static str {
+    pub fn description(&self) -> &'static str {
          match *self {
             Type::Eof => "end of template",
             Type::Text => "text",
-            // ..
+            Type::BlockStart => "begin of statement block",
+            Type::ExpressionStart => "begin of print statement",
+            Type::BlockEnd => "end of statement block",
+            Type::ExpressionEnd => "end of print statement",
+            Type::Name => "name",
+            Type::Number => "number",
+            Type::String => "string",
+            Type::Operator => "operator",
+            Type::Punctuation => "punctuation",
+            Type::InterpolationStart => "begin of string interpolation",
+            Type::InterpolationEnd => "end of string interpolation",
         }
     }
 }
@@ -145,7 +268,7 @@ impl fmt::Debug for Type {
 }
 
 pub trait Pattern: fmt::Debug + fmt::Display {
-    fn matches(&self, &Token) -> bool;
+    fn matches(&self, token: &Token) -> bool;
 }
 
 impl Pattern for Token {
@@ -160,9 +283,17 @@ impl Pattern for Type {
     }
 }
 
+impl<T> Pattern for &T where
+    T: Pattern + ?Sized
+{
+    fn matches(&self, token: &Token) -> bool {
+        (**self).matches(token)
+    }
+}
+
 pub type PatternDump = String;
 
-impl Dump for Pattern {
+impl Dump for dyn Pattern {
     type Data = PatternDump;
 
     fn dump(&self) -> Self::Data {
@@ -11,7 +11,7 @@ use template;
 use api::token::TokenError;
 use api::error::{Traced, Dump};
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Position {
     pub line: usize,
     pub column: usize,
@@ -32,6 +32,13 @@ pub struct Item {
 }
 
 impl Item {
+    pub fn new(token: Token, position: Position) -> Item {
+        Item {
+            token,
+            position,
+        }
+    }
+
     pub fn token(&self) -> &Token {
         &self.token
     }
@@ -48,7 +55,7 @@ impl Item {
         } else {
             traced_err!(TokenError::UnexpectedTokenAtItem {
                 reason: reason,
-                expected: <token::Pattern as Dump>::dump(&pattern),
+                expected: <dyn token::Pattern as Dump>::dump(&pattern),
                 found: self.dump(),
             })
         }
@@ -102,11 +109,48 @@ pub struct StreamDump {
     pub items_str: String,
 }
 
+impl<'a> Stream<'a> {
+    pub fn new(template: &'a template::Raw) -> Stream<'a> {
+        Stream {
+            items: vec![],
+            _template: template,
+        }
+    }
+
+    pub fn push(&mut self, item: Item) {
+        self.items.push(item);
+    }
+
+    /// Removes all items after the first `len` items.
+    pub fn truncate(&mut self, len: usize) {
+        self.items.truncate(len);
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Item> {
+        self.items.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn template(&self) -> &'a template::Raw {
+        self._template
+    }
+}
+
 impl<'a> Dump for Stream<'a> {
     type Data = StreamDump;
 
     fn dump(&self) -> Self::Data {
-        unimplemented!()
+        StreamDump {
+            template_str: self._template.to_string(),
+            items_str: self.to_string(),
+        }
     }
 }
 
@@ -0,0 +1,130 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Ordered map of values.
+
+use std::iter::FromIterator;
+use std::slice;
+use std::vec;
+use api::Value;
+
+/// Ordered map of values with string keys.
+///
+/// Keys keep the order of their first insertion, like PHP arrays.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hash {
+    entries: Vec<(String, Value)>,
+}
+
+impl Hash {
+    pub fn new() -> Hash {
+        Hash::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries.iter().find(|e| e.0 == key).map(|e| &e.1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.entries.iter_mut().find(|e| e.0 == key).map(|e| &mut e.1)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Inserts a value, returning the previous value for this key (if any).
+    ///
+    /// Existing keys keep their position.
+    pub fn insert<K>(&mut self, key: K, value: Value) -> Option<Value> where
+        K: Into<String>
+    {
+        let key = key.into();
+
+        if let Some(slot) = self.get_mut(&key) {
+            return Some(::std::mem::replace(slot, value));
+        }
+
+        self.entries.push((key, value));
+        None
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        match self.entries.iter().position(|e| e.0 == key) {
+            Some(i) => Some(self.entries.remove(i).1),
+            None => None,
+        }
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, (String, Value)> {
+        self.entries.iter()
+    }
+
+    pub fn keys(&self) -> Vec<&str> {
+        self.entries.iter().map(|e| &e.0[..]).collect()
+    }
+
+    pub fn values(&self) -> Vec<&Value> {
+        self.entries.iter().map(|e| &e.1).collect()
+    }
+}
+
+impl IntoIterator for Hash {
+    type Item = (String, Value);
+    type IntoIter = vec::IntoIter<(String, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Hash {
+    type Item = &'a (String, Value);
+    type IntoIter = slice::Iter<'a, (String, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for Hash where
+    K: Into<String>,
+    V: Into<Value>
+{
+    fn from_iter<I>(iter: I) -> Hash where
+        I: IntoIterator<Item=(K, V)>
+    {
+        let mut hash = Hash::new();
+
+        for (k, v) in iter {
+            hash.insert(k, v.into());
+        }
+
+        hash
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn insert_keeps_order() {
+        let mut hash: Hash = vec![("b", 1), ("a", 2)].into_iter().collect();
+
+        assert_eq!(hash.insert("b", Value::from(3)), Some(Value::from(1)));
+        assert_eq!(hash.insert("c", Value::from(4)), None);
+        assert_eq!(hash.keys(), vec!["b", "a", "c"]);
+        assert_eq!(hash.get("b"), Some(&Value::from(3)));
+    }
+}
@@ -0,0 +1,45 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Safe markup.
+
+/// String which is safe to output without escaping for one escaping strategy.
+///
+/// E.g. the result of `value|escape('js')` is safe for `js`, but will still be escaped when
+/// printed in a `html` context. The strategy `"all"` marks trusted content, like the output
+/// of macros and `{% set %}..{% endset %}` captures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Markup {
+    content: String,
+    strategy: String,
+}
+
+impl Markup {
+    pub fn new<C, S>(content: C, strategy: S) -> Markup where
+        C: Into<String>,
+        S: Into<String>
+    {
+        Markup {
+            content: content.into(),
+            strategy: strategy.into(),
+        }
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Get the name of the strategy the content is safe for, or `"all"`.
+    pub fn strategy(&self) -> &str {
+        &self.strategy
+    }
+
+    /// Returns true if the content does not need to be escaped with the given strategy.
+    ///
+    /// Content escaped for html attributes is safe within html, too.
+    pub fn is_safe_for(&self, strategy: &str) -> bool {
+        self.strategy == "all" || self.strategy == strategy || (self.strategy == "html_attr" && strategy == "html")
+    }
+}
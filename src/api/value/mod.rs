@@ -0,0 +1,384 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Values of template variables and expressions.
+
+use std::fmt;
+use std::cmp::Ordering;
+
+pub mod hash;
+pub mod markup;
+pub use self::hash::Hash;
+pub use self::markup::Markup;
+
+/// Dynamically typed value, as known from twig templates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Value {
+    #[default]
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    /// List of values, e.g. `[1, 2, 3]`
+    Array(Vec<Value>),
+    /// Ordered map of values, e.g. `{ 'a': 1, 'b': 2 }`
+    Hash(Hash),
+    /// String which is safe to output, e.g. the result of the `escape` filter
+    Markup(Markup),
+}
+
+impl Value {
+    /// Returns the name of the value type (e.g. for error messages).
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Int(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Str(_) => "string",
+            Value::Array(_) => "array",
+            Value::Hash(_) => "hash",
+            Value::Markup(_) => "markup",
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+
+    /// Converts the value to a boolean, like PHP does.
+    ///
+    /// `null`, `false`, `0`, `0.0`, `""`, `"0"` and empty collections are false.
+    pub fn to_bool(&self) -> bool {
+        match *self {
+            Value::Null => false,
+            Value::Bool(b) => b,
+            Value::Int(i) => i != 0,
+            Value::Float(f) => f != 0.0,
+            Value::Str(ref s) => !(s.is_empty() || s == "0"),
+            Value::Array(ref a) => !a.is_empty(),
+            Value::Hash(ref h) => !h.is_empty(),
+            Value::Markup(ref m) => !(m.content().is_empty() || m.content() == "0"),
+        }
+    }
+
+    /// Returns the string slice, if this is a string value (or markup).
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::Str(ref s) => Some(s),
+            Value::Markup(ref m) => Some(m.content()),
+            _ => None,
+        }
+    }
+
+    /// Converts the value to a number (`Int` or `Float`), like PHP arithmetic does.
+    ///
+    /// Returns `None` for non-numeric strings and collections.
+    pub fn to_number(&self) -> Option<Value> {
+        match *self {
+            Value::Null => Some(Value::Int(0)),
+            Value::Bool(b) => Some(Value::Int(b as i64)),
+            Value::Int(_) | Value::Float(_) => Some(self.clone()),
+            Value::Str(ref s) => parse_numeric(s),
+            Value::Markup(ref m) => parse_numeric(m.content()),
+            Value::Array(_) | Value::Hash(_) => None,
+        }
+    }
+
+    /// Converts the value to an integer, like PHP `intval()`.
+    pub fn to_int(&self) -> i64 {
+        match self.to_number() {
+            Some(Value::Int(i)) => i,
+            Some(Value::Float(x)) => x as i64,
+            _ => self.to_bool() as i64,
+        }
+    }
+
+    /// Converts the value to a floating point number, like PHP `floatval()`.
+    pub fn to_float(&self) -> f64 {
+        match self.to_number() {
+            Some(Value::Int(i)) => i as f64,
+            Some(Value::Float(x)) => x,
+            _ => self.to_bool() as i64 as f64,
+        }
+    }
+
+    /// Compares two values, like PHP loose comparison.
+    ///
+    /// Numbers and numeric strings are compared numerically, other strings byte-wise.
+    /// Booleans and `null` are compared as booleans (except `null` with strings). Collections
+    /// are compared by size first and element-wise afterwards. Returns `None` if the values are
+    /// incomparable (e.g. `NAN` or hashes with different keys). Markup is compared like strings.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Markup(m), _) => Value::from(m.content()).compare(other),
+            (_, Value::Markup(m)) => self.compare(&Value::from(m.content())),
+            (Value::Null, Value::Str(s)) => "".partial_cmp(&s[..]),
+            (Value::Str(s), Value::Null) => s[..].partial_cmp(""),
+            (Value::Bool(_), _) | (_, Value::Bool(_)) | (Value::Null, _) | (_, Value::Null) => {
+                self.to_bool().partial_cmp(&other.to_bool())
+            },
+            (Value::Str(a), Value::Str(b)) => {
+                match (parse_numeric(a), parse_numeric(b)) {
+                    (Some(x), Some(y)) => x.compare(&y),
+                    _ => a.partial_cmp(b),
+                }
+            },
+            (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+            (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) | (Value::Float(_), Value::Float(_)) => {
+                self.to_float().partial_cmp(&other.to_float())
+            },
+            (Value::Str(s), Value::Int(_)) | (Value::Str(s), Value::Float(_)) => {
+                match parse_numeric(s) {
+                    Some(x) => x.compare(other),
+                    None => s[..].partial_cmp(&other.to_string()[..]),
+                }
+            },
+            (Value::Int(_), Value::Str(_)) | (Value::Float(_), Value::Str(_)) => {
+                other.compare(self).map(Ordering::reverse)
+            },
+            (Value::Array(a), Value::Array(b)) => {
+                if a.len() != b.len() {
+                    return a.len().partial_cmp(&b.len());
+                }
+
+                for (x, y) in a.iter().zip(b.iter()) {
+                    match x.compare(y) {
+                        Some(Ordering::Equal) => continue,
+                        ordering => return ordering,
+                    }
+                }
+
+                Some(Ordering::Equal)
+            },
+            (Value::Array(_), Value::Hash(_)) | (Value::Hash(_), Value::Array(_)) | (Value::Hash(_), Value::Hash(_)) => {
+                let (a, b) = (self.to_hash(), other.to_hash());
+
+                if a.len() != b.len() {
+                    return a.len().partial_cmp(&b.len());
+                }
+
+                for (key, x) in &a {
+                    match b.get(key).and_then(|y| x.compare(y)) {
+                        Some(Ordering::Equal) => continue,
+                        ordering => return ordering,
+                    }
+                }
+
+                Some(Ordering::Equal)
+            },
+            (Value::Array(_), _) | (Value::Hash(_), _) => Some(Ordering::Greater),
+            (_, Value::Array(_)) | (_, Value::Hash(_)) => Some(Ordering::Less),
+        }
+    }
+
+    /// Returns true if both values are equal, like PHP `==`.
+    pub fn loose_eq(&self, other: &Value) -> bool {
+        self.compare(other) == Some(Ordering::Equal)
+    }
+
+    /// Converts collections to hashes, with array indices as keys.
+    ///
+    /// Other values are wrapped into a hash with one element.
+    pub fn to_hash(&self) -> Hash {
+        match *self {
+            Value::Hash(ref h) => h.clone(),
+            Value::Array(ref a) => a.iter().enumerate().map(|(i, v)| (i.to_string(), v.clone())).collect(),
+            Value::Null => Hash::new(),
+            _ => vec![("0", self.clone())].into_iter().collect(),
+        }
+    }
+
+    /// Get an element of a collection by key or index.
+    pub fn attribute(&self, key: &Value) -> Option<&Value> {
+        match *self {
+            Value::Hash(ref h) => h.get(&key.to_string()),
+            Value::Array(ref a) => match key.to_number() {
+                Some(Value::Int(i)) if i >= 0 => a.get(i as usize),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Get the key-value pairs of a collection, e.g. for iteration in loops.
+    ///
+    /// Arrays are indexed by integers. Other values have no elements.
+    pub fn to_pairs(&self) -> Vec<(Value, Value)> {
+        match *self {
+            Value::Array(ref a) => a.iter().enumerate().map(|(i, v)| (Value::from(i), v.clone())).collect(),
+            Value::Hash(ref h) => h.iter().map(|(k, v)| (Value::from(&k[..]), v.clone())).collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Parses numeric strings like `"42"`, `" 1.5"` or `"1e3"`.
+fn parse_numeric(s: &str) -> Option<Value> {
+    let s = s.trim();
+    let digits = s.trim_start_matches(['+', '-']);
+
+    if digits.is_empty() || !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        || !digits.chars().all(|c| c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || c == '+' || c == '-')
+    {
+        return None;
+    }
+
+    if let Ok(i) = s.parse::<i64>() {
+        return Some(Value::Int(i));
+    }
+
+    s.parse::<f64>().ok().map(Value::Float)
+}
+
+impl fmt::Display for Value {
+    /// Converts the value to a string, like PHP does.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Null => Ok(()),
+            Value::Bool(b) => write!(f, "{}", if b { "1" } else { "" }),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(x) => format_float(x, f),
+            Value::Str(ref s) => write!(f, "{}", s),
+            Value::Markup(ref m) => write!(f, "{}", m.content()),
+            Value::Array(_) | Value::Hash(_) => write!(f, "Array"),
+        }
+    }
+}
+
+fn format_float(x: f64, f: &mut fmt::Formatter) -> fmt::Result {
+    if x.is_nan() {
+        write!(f, "NAN")
+    } else if x.is_infinite() {
+        write!(f, "{}INF", if x < 0.0 { "-" } else { "" })
+    } else if x.fract() == 0.0 && x.abs() < 1e15 {
+        write!(f, "{}", x as i64)
+    } else {
+        write!(f, "{}", x)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Value {
+        Value::Int(i)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(i: i32) -> Value {
+        Value::Int(i64::from(i))
+    }
+}
+
+impl From<usize> for Value {
+    fn from(i: usize) -> Value {
+        Value::Int(i as i64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Value {
+        Value::Float(x)
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(s: &'a str) -> Value {
+        Value::Str(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::Str(s)
+    }
+}
+
+impl From<Markup> for Value {
+    fn from(m: Markup) -> Value {
+        Value::Markup(m)
+    }
+}
+
+impl From<Hash> for Value {
+    fn from(h: Hash) -> Value {
+        Value::Hash(h)
+    }
+}
+
+impl<T> From<Vec<T>> for Value where
+    T: Into<Value>
+{
+    fn from(v: Vec<T>) -> Value {
+        Value::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T> From<Option<T>> for Value where
+    T: Into<Value>
+{
+    fn from(o: Option<T>) -> Value {
+        match o {
+            Some(x) => x.into(),
+            None => Value::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn to_bool() {
+        assert!(!Value::Null.to_bool());
+        assert!(!Value::from("0").to_bool());
+        assert!(!Value::from("").to_bool());
+        assert!(!Value::Array(vec![]).to_bool());
+        assert!(Value::from(" ").to_bool());
+        assert!(Value::from(0.1).to_bool());
+    }
+
+    #[test]
+    fn compare() {
+        assert!(Value::from("1e1").loose_eq(&Value::from(10)));
+        assert!(Value::from("abc").loose_eq(&Value::from("abc")));
+        assert!(!Value::from("abc").loose_eq(&Value::from(0)));
+        assert!(Value::Null.loose_eq(&Value::from(false)));
+        assert!(Value::Null.loose_eq(&Value::from("")));
+        assert!(Value::from(vec![1, 2]).loose_eq(&Value::from(vec!["1", "2"])));
+        assert_eq!(Value::from(2).compare(&Value::from(10.5)), Some(Ordering::Less));
+        assert_eq!(Value::from("b").compare(&Value::from("a")), Some(Ordering::Greater));
+        assert_eq!(Value::from(vec![1]).compare(&Value::from(100)), Some(Ordering::Greater));
+        assert!(Value::from(Markup::new("<b>", "html")).loose_eq(&Value::from("<b>")));
+        assert!(Value::from(10).loose_eq(&Value::from(Markup::new("10", "all"))));
+    }
+
+    #[test]
+    fn to_number() {
+        assert_eq!(Value::from(" 12 ").to_number(), Some(Value::from(12)));
+        assert_eq!(Value::from("1.5").to_number(), Some(Value::from(1.5)));
+        assert_eq!(Value::from("abc").to_number(), None);
+        assert_eq!(Value::from("inf").to_number(), None);
+        assert_eq!(Value::from(true).to_int(), 1);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Value::Null.to_string(), "");
+        assert_eq!(Value::from(true).to_string(), "1");
+        assert_eq!(Value::from(false).to_string(), "");
+        assert_eq!(Value::from(2.0).to_string(), "2");
+        assert_eq!(Value::from(2.5).to_string(), "2.5");
+        assert_eq!(Value::from(vec![1, 2]).to_string(), "Array");
+    }
+}
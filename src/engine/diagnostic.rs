@@ -0,0 +1,552 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Diagnostics - human readable errors with the offending template line, similar to rustc.
+//!
+//! # Examples
+//!
+//! ```
+//! use twig::{Setup, loader};
+//! use twig::api::value::Hash;
+//! use twig::engine::diagnostic::Style;
+//!
+//! let mut twig = Setup::default().init_engine().unwrap();
+//! let mut templates = loader::Array::new();
+//! templates.set_template("index", "Hello {{ [1]|upper }}!");
+//! twig.set_loader(Box::new(templates));
+//!
+//! let error = twig.render("index", Hash::new()).unwrap_err();
+//! let diagnostic = twig.diagnostic(&error);
+//!
+//! assert!(diagnostic.render(Style::Plain).contains("--> index:1:"));
+//! ```
+
+use std::fmt::{self, Display, Write};
+use api::token::stream::Position;
+use api::lexer::{LexerError, SyntaxError};
+use api::parser::ParserError;
+use api::runtime::RuntimeError;
+use api::error::{suggest, Location, Traced};
+use engine::TwigError;
+
+const RED: &str = "1;31";
+const BLUE: &str = "1;34";
+const CYAN: &str = "1;36";
+const BOLD: &str = "1";
+
+/// Output style of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    Plain,
+    /// Coloured with ANSI escape codes, e.g. for terminals.
+    Ansi,
+}
+
+impl Style {
+    fn paint(self, color: &str, text: &str) -> String {
+        match self {
+            Style::Plain => text.to_string(),
+            Style::Ansi => format!("\x1b[{}m{}\x1b[0m", color, text),
+        }
+    }
+}
+
+/// An error, with its location within a template and hints how to fix it.
+///
+/// Diagnostics are created from errors via `Engine::diagnostic()`, which also loads the source
+/// of the template, or via `Diagnostic::new()`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagnostic {
+    /// Stable code of the error, e.g. `T0611` - see `TwigError::code()`.
+    pub code: &'static str,
+    pub message: String,
+    /// Name of the template the error occurred in.
+    pub template: Option<String>,
+    /// Position within the template.
+    pub position: Option<Position>,
+    /// Number of characters to underline from `position` on - guessed from the source if zero.
+    pub length: usize,
+    /// Source of the template.
+    pub source: Option<String>,
+    pub hint: Option<String>,
+    /// Additional information, e.g. the templates including the template.
+    pub notes: Vec<String>,
+    /// Locations in the Rust source the error passed, the origin first.
+    pub backtrace: Vec<Location>,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic from an error.
+    ///
+    /// The source of the template is only known for lexer errors - see `Engine::diagnostic()`.
+    pub fn new(error: &Traced<TwigError>) -> Diagnostic {
+        let mut diagnostic = Diagnostic::from_error(error);
+
+        if diagnostic.code.is_empty() {
+            diagnostic.code = error.code();
+        }
+        diagnostic.backtrace.extend(error.backtrace().iter().cloned());
+
+        diagnostic
+    }
+
+    fn from_error(error: &TwigError) -> Diagnostic {
+        match *error {
+            TwigError::Loader(ref cause) => Diagnostic {
+                message: cause.to_string(),
+                hint: if cause.is_not_found() {
+                    Some("check the name of the template and the configuration of the loader".to_string())
+                } else {
+                    None
+                },
+                ..Diagnostic::default()
+            },
+            TwigError::Lexer(LexerError::Syntax(ref cause)) => lexer(cause),
+            TwigError::Lexer(ref cause) => Diagnostic {
+                message: cause.to_string(),
+                ..Diagnostic::default()
+            },
+            TwigError::Parser(ref cause, ref template) => parser(cause, template),
+            TwigError::Runtime(RuntimeError::LoadTemplate { ref cause, ref caller, ref position, .. }, ref trace)
+                if !cause.is_template_not_found() =>
+            {
+                let mut diagnostic = Diagnostic::new(cause);
+                diagnostic.notes.push(format!("loaded from {:?} at {}", caller, position));
+                diagnostic.notes.extend(trace.frames.iter().map(|f| f.to_string()));
+
+                diagnostic
+            },
+            TwigError::Runtime(ref cause, ref trace) => {
+                let mut diagnostic = runtime(cause);
+
+                if !trace.is_empty() {
+                    diagnostic.template = Some(trace.template.clone());
+                    diagnostic.position = trace.position.clone();
+                    diagnostic.notes = trace.frames.iter().map(|f| f.to_string()).collect();
+                }
+
+                diagnostic
+            },
+            TwigError::ExtensionRegistry(ref cause) => Diagnostic {
+                message: cause.to_string(),
+                ..Diagnostic::default()
+            },
+            _ => Diagnostic {
+                message: error.to_string(),
+                ..Diagnostic::default()
+            },
+        }
+    }
+
+    /// Renders the diagnostic, e.g.
+    ///
+    /// ```text
+    /// error[T0611]: Undefined variable. Variable "nmae" does not exist in template "index" at 1:10.
+    ///  --> index:1:10
+    ///   |
+    /// 1 | Hello {{ nmae }}!
+    ///   |          ^^^^
+    ///   |
+    ///   = hint: did you mean "name"?
+    /// ```
+    pub fn render(&self, style: Style) -> String {
+        let mut output = format!("{}{}\n", style.paint(RED, &format!("error[{}]", self.code)), style.paint(BOLD, &format!(": {}", self.message)));
+        let line_number = self.position.as_ref().map_or(0, |p| p.line);
+        let gutter = " ".repeat(line_number.to_string().len());
+
+        if let Some(ref template) = self.template {
+            output.push_str(&format!("{}{} {}", gutter, style.paint(BLUE, "-->"), template));
+            if let Some(ref position) = self.position {
+                output.push_str(&format!(":{}", position));
+            }
+            output.push('\n');
+        }
+
+        let line = self.source.as_ref().and_then(|s| s.split('\n').nth(line_number.wrapping_sub(1)));
+        if let (Some(line), Some(position)) = (line, self.position.as_ref()) {
+            // tabs are kept, so the marker lines up with the source
+            let indent: String = line.chars().take(position.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let length = self.span_length().unwrap_or(1);
+            let bar = style.paint(BLUE, "|");
+
+            output.push_str(&format!("{} {}\n", gutter, bar));
+            output.push_str(&format!("{} {} {}\n", style.paint(BLUE, &line_number.to_string()), bar, line));
+            output.push_str(&format!("{} {} {}{}\n", gutter, bar, indent, style.paint(RED, &"^".repeat(length))));
+        }
+
+        if self.hint.is_some() || !self.notes.is_empty() {
+            output.push_str(&format!("{} {}\n", gutter, style.paint(BLUE, "|")));
+        }
+
+        if let Some(ref hint) = self.hint {
+            output.push_str(&format!("{} {} {}\n", gutter, style.paint(BLUE, "="), style.paint(CYAN, &format!("hint: {}", hint))));
+        }
+
+        for note in &self.notes {
+            output.push_str(&format!("{} {} {}\n", gutter, style.paint(BLUE, "="), style.paint(BOLD, &format!("note: {}", note))));
+        }
+
+        output
+    }
+
+    /// Serializes the diagnostic to JSON, e.g.
+    ///
+    /// ```text
+    /// {
+    ///     "code": "T0611",
+    ///     "message": "Undefined variable. ..",
+    ///     "template": "index",
+    ///     "span": {"line": 1, "column": 10, "length": 4},
+    ///     "hint": "did you mean \"name\"?",
+    ///     "notes": ["included from \"page\" at 2:4"],
+    ///     "backtrace": [{"file": "src/api/node/expr/name.rs", "line": 60, "column": 20}]
+    /// }
+    /// ```
+    ///
+    /// All keys are always present - `template`, `span` and `hint` are `null` if unknown.
+    /// The output is compact, i.e. without the whitespace of the example.
+    pub fn to_json(&self) -> String {
+        let mut json = format!("{{\"code\":{},\"message\":{},\"template\":", json_string(self.code), json_string(&self.message));
+
+        match self.template {
+            Some(ref template) => json.push_str(&json_string(template)),
+            None => json.push_str("null"),
+        }
+
+        json.push_str(",\"span\":");
+        match self.position {
+            Some(ref position) => {
+                let _ = write!(json, "{{\"line\":{},\"column\":{},\"length\":{}}}",
+                    position.line, position.column, self.span_length().unwrap_or(self.length));
+            },
+            None => json.push_str("null"),
+        }
+
+        json.push_str(",\"hint\":");
+        match self.hint {
+            Some(ref hint) => json.push_str(&json_string(hint)),
+            None => json.push_str("null"),
+        }
+
+        let notes: Vec<String> = self.notes.iter().map(|n| json_string(n)).collect();
+        let _ = write!(json, ",\"notes\":[{}]", notes.join(","));
+
+        let backtrace: Vec<String> = self.backtrace.iter().map(|l| format!("{{\"file\":{},\"line\":{},\"column\":{}}}",
+            json_string(l.filename), l.line, l.column)).collect();
+        let _ = write!(json, ",\"backtrace\":[{}]}}", backtrace.join(","));
+
+        json
+    }
+
+    /// Get the number of characters to underline - guessed from the source if `length` is zero.
+    ///
+    /// Returns `None` if neither the length nor the offending line is known.
+    fn span_length(&self) -> Option<usize> {
+        if self.length > 0 {
+            return Some(self.length);
+        }
+
+        let position = self.position.as_ref()?;
+        let line = self.source.as_ref()?.split('\n').nth(position.line.wrapping_sub(1))?;
+
+        Some(span(line, position.column))
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(Style::Plain))
+    }
+}
+
+fn lexer(cause: &SyntaxError) -> Diagnostic {
+    let cursor = cause.cursor();
+    let code = &cursor.template().code;
+    let before = code.get(..cursor.pos()).unwrap_or(code);
+    let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+
+    Diagnostic {
+        message: cause.to_string(),
+        template: Some(cursor.template().name().to_string()),
+        position: Some(Position {
+            line: before.matches('\n').count() + 1,
+            column,
+        }),
+        length: 1,
+        source: Some(code.clone()),
+        hint: match *cause {
+            SyntaxError::UnclosedBlock { .. } => Some("close the tag with `%}`".to_string()),
+            SyntaxError::UnclosedVariable { .. } => Some("close the expression with `}}`".to_string()),
+            SyntaxError::UnclosedComment { .. } => Some("close the comment with `#}`".to_string()),
+            _ => None,
+        },
+        ..Diagnostic::default()
+    }
+}
+
+fn parser(cause: &ParserError, template: &str) -> Diagnostic {
+    let similar = cause.similar();
+
+    Diagnostic {
+        message: without_suggestions(cause.to_string(), similar),
+        template: if template.is_empty() { None } else { Some(template.to_string()) },
+        position: cause.position().cloned(),
+        hint: match *cause {
+            ParserError::UnexpectedEof { .. } => Some("check for tags which are not closed, e.g. `{% if %}` without `{% endif %}`".to_string()),
+            _ if !similar.is_empty() => Some(format!("did you mean {}?", suggest::one_of(similar))),
+            ParserError::NoTagHandler { .. } => Some("tags are provided by extensions - is the extension registered?".to_string()),
+            _ => None,
+        },
+        ..Diagnostic::default()
+    }
+}
+
+fn runtime(cause: &RuntimeError) -> Diagnostic {
+    let similar = cause.similar();
+    let (length, hint) = match *cause {
+        RuntimeError::UndefinedVariable { ref name, .. } => (
+            name.chars().count(),
+            if similar.is_empty() {
+                Some("pass the variable to the template, or check it with `is defined`".to_string())
+            } else {
+                Some(format!("did you mean {}?", suggest::one_of(similar)))
+            },
+        ),
+        RuntimeError::UndefinedAttribute { .. } if !similar.is_empty() => (
+            0, Some(format!("did you mean {}?", suggest::one_of(similar))),
+        ),
+        RuntimeError::DepthLimitExceeded { .. } => (
+            0, Some("check for templates including or extending themselves".to_string()),
+        ),
+        _ => (0, None),
+    };
+
+    Diagnostic {
+        message: without_suggestions(cause.to_string(), similar),
+        position: cause.position().cloned(),
+        length,
+        hint,
+        ..Diagnostic::default()
+    }
+}
+
+/// Quotes and escapes a string for JSON.
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            },
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+/// Removes the suggestions appended to the message of an error, which the hint names instead.
+fn without_suggestions(message: String, similar: &[String]) -> String {
+    match message.strip_suffix(&suggest::did_you_mean(similar)[..]) {
+        Some(message) => message.to_string(),
+        None => message,
+    }
+}
+
+/// Guesses the length of the token at `column`, e.g. of a name or string.
+fn span(line: &str, column: usize) -> usize {
+    let mut chars = line.chars().skip(column.saturating_sub(1)).peekable();
+
+    match chars.peek().cloned() {
+        Some(c) if c.is_alphanumeric() || c == '_' => chars.take_while(|&c| c.is_alphanumeric() || c == '_').count(),
+        Some(quote @ '\'') | Some(quote @ '"') => match chars.skip(1).position(|c| c == quote) {
+            Some(end) => end + 2,
+            None => 1,
+        },
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use setup::Setup;
+    use api::value::Hash;
+    use extension::core::test::engine_with;
+    use engine::Engine;
+
+    #[test]
+    fn runtime_errors() {
+        let mut twig = engine_with(Setup::default().set_strict_variables(true), &[
+            ("index", "<ul>\n{% include 'item' %}\n</ul>"),
+            ("item", "\t<li>{{ nmae }}</li>"),
+        ]);
+        let data: Hash = vec![("name", "Twig")].into_iter().collect();
+
+        let error = twig.render("index", data).unwrap_err();
+        assert_eq!(twig.diagnostic(&error).to_string(), [
+            "error[T0611]: Undefined variable. Variable \"nmae\" does not exist in template \"item\" at 1:9.",
+            " --> item:1:9",
+            "  |",
+            "1 | \t<li>{{ nmae }}</li>",
+            "  | \t       ^^^^",
+            "  |",
+            "  = hint: did you mean \"name\"?",
+            "  = note: included from \"index\" at 2:4",
+            "",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn compile_errors() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("lexer", "a\n{{ 'b' "),
+            ("parser", "{% if true %}\n  {{ 'x' 'y' }}\n{% endif %}"),
+            ("included", "{% include 'parser' %}"),
+        ]);
+
+        let error = twig.render("lexer", Hash::new()).unwrap_err();
+        let diagnostic = twig.diagnostic(&error);
+        assert_eq!(diagnostic.template.as_ref().map(|t| &t[..]), Some("lexer"));
+        assert_eq!(diagnostic.hint.as_ref().map(|h| &h[..]), Some("close the expression with `}}`"));
+        assert!(diagnostic.to_string().contains("2 | {{ 'b' \n"), "{}", diagnostic);
+
+        let error = twig.render("included", Hash::new()).unwrap_err();
+        let diagnostic = twig.diagnostic(&error);
+        assert_eq!(diagnostic.position, Some(Position { line: 2, column: 10 }));
+        assert!(diagnostic.to_string().contains(" --> parser:2:10\n  |\n2 |   {{ 'x' 'y' }}\n  |          ^^^\n"), "{}", diagnostic);
+        assert_eq!(diagnostic.notes, vec!["loaded from \"included\" at 1:4".to_string()]);
+    }
+
+    #[test]
+    fn suggestions() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("tag", "{% for i in 1..2 %}{{ i }}{% endfro %}"),
+            ("filter", "{{ [1]|lenght }}"),
+            ("function", "{{ rnage(1, 2)|join }}"),
+            ("test", "{{ x is defind ? 'y' }}"),
+            ("unknown", "{% frobnicate %}"),
+            ("operator", "{{ 'ab' startswith 'a' }}"),
+            ("two_words", "{% if 4 is divisble by 2 %}{% endif %}"),
+            ("is_test", "{{ x defnied }}"),
+        ]);
+
+        let hint = |twig: &mut Engine, name: &str| {
+            let error = twig.render(name, Hash::new()).unwrap_err();
+            twig.diagnostic(&error).hint
+        };
+
+        assert_eq!(hint(&mut twig, "tag"), Some("did you mean \"endfor\"?".to_string()));
+        assert_eq!(hint(&mut twig, "filter"), Some("did you mean \"length\"?".to_string()));
+        assert_eq!(hint(&mut twig, "function"), Some("did you mean \"range\"?".to_string()));
+        assert_eq!(hint(&mut twig, "test"), Some("did you mean \"defined\"?".to_string()));
+        assert_eq!(hint(&mut twig, "unknown"), Some("tags are provided by extensions - is the extension registered?".to_string()));
+        assert_eq!(hint(&mut twig, "operator"), Some("did you mean \"starts with\"?".to_string()));
+        assert_eq!(hint(&mut twig, "two_words"), Some("did you mean \"divisible by\"?".to_string()));
+        assert_eq!(hint(&mut twig, "is_test"), Some("did you mean \"is defined\"?".to_string()));
+
+        let error = twig.render("filter", Hash::new()).unwrap_err();
+        assert!(error.to_string().contains("The filter \"lenght\" at 1:8 is unknown to the engine. Did you mean \"length\"?"), "{}", error);
+
+        let diagnostic = twig.diagnostic(&error);
+        assert!(diagnostic.message.ends_with("The filter \"lenght\" at 1:8 is unknown to the engine."), "{}", diagnostic.message);
+        assert_eq!(diagnostic.to_string().matches("\"length\"").count(), 1, "{}", diagnostic);
+
+        let error = twig.render("operator", Hash::new()).unwrap_err();
+        assert_eq!(twig.diagnostic(&error).position, Some(Position { line: 1, column: 9 }));
+    }
+
+    #[test]
+    fn lint() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("index", "{{ 'a' 'b' }}\n{{ $ }}\n{% for i in 1..2 %}{{ i|lenght }}{% endfor %}\n{{ ok }}"),
+        ]);
+
+        let (root, diagnostics) = twig.lint("index");
+        assert!(root.is_some());
+
+        let summary: Vec<String> = diagnostics.iter()
+            .map(|d| format!("{} {}", d.code, d.position.as_ref().map(|p| p.to_string()).unwrap_or_default()))
+            .collect();
+        assert_eq!(summary, vec!["T0307 1:8", "T0201 2:4", "T0304 3:25"]);
+        assert!(diagnostics[2].to_string().contains("3 | {% for i in 1..2 %}{{ i|lenght }}{% endfor %}\n"), "{}", diagnostics[2]);
+
+        let mut twig = engine_with(Setup::default(), &[
+            ("index", "{% for 1 in items %}\n{{ i|lenght }}\n{% else %}{% endfor %}\n{% if x %}{% for a b %}{% endfor %}{% endif %}\n{% endfor %}"),
+        ]);
+
+        let (_, diagnostics) = twig.lint("index");
+        let summary: Vec<String> = diagnostics.iter()
+            .map(|d| format!("{} {}", d.code, d.position.as_ref().map(|p| p.to_string()).unwrap_or_default()))
+            .collect();
+        assert_eq!(summary, vec!["T0301 1:8", "T0304 2:6", "T0307 4:20", "T0302 5:4"]);
+
+        let (root, diagnostics) = twig.lint("missing");
+        assert!(root.is_none());
+        assert_eq!(diagnostics[0].code, "T0401");
+    }
+
+    #[test]
+    fn json() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("index", "{% include 'item' %}"),
+            ("item", "<li>{{ \"a\tb\"|upper(x => x) }}</li>"),
+            ("tag", "{% block a %}{% endblock %}\n{% block a %}{% endblock %}"),
+            ("eof", "{% if a %}\n{{ a }}"),
+        ]);
+
+        let error = twig.render("index", Hash::new()).unwrap_err();
+        let json = twig.diagnostic(&error).to_json();
+        assert!(json.starts_with(concat!(
+            "{\"code\":\"T0610\",\"message\":\"Invalid argument type. The filter \\\"upper\\\" expects argument 1 to be a value, ",
+            "got arrow function in template \\\"item\\\" at 1:14.\",\"template\":\"item\",",
+            "\"span\":{\"line\":1,\"column\":14,\"length\":5},\"hint\":null,",
+            "\"notes\":[\"included from \\\"index\\\" at 1:4\"],\"backtrace\":[{\"file\":\"src/",
+        )), "{}", json);
+        assert!(json.ends_with("}]}"), "{}", json);
+
+        let error = twig.render("tag", Hash::new()).unwrap_err();
+        let json = twig.diagnostic(&error).to_json();
+        assert!(json.starts_with(concat!(
+            "{\"code\":\"T0301\",\"message\":\"Token parser error. \\\"block\\\"-block at 2:4: The block \\\"a\\\" has already been defined at 1:4.\",",
+            "\"template\":\"tag\",\"span\":{\"line\":2,\"column\":4,\"length\":5},",
+        )), "{}", json);
+
+        let error = twig.render("eof", Hash::new()).unwrap_err();
+        let json = twig.diagnostic(&error).to_json();
+        assert!(json.contains("\"code\":\"T0308\""), "{}", json);
+        assert!(json.contains("\"template\":\"eof\",\"span\":{\"line\":2,\"column\":8,"), "{}", json);
+
+        let error = Setup::default().add_extension(::extension::Core::new()).init_engine().unwrap_err();
+        assert_eq!(Diagnostic::new(&error).to_json(), format!(
+            "{{\"code\":\"T0502\",\"message\":\"Duplicate extension. \\\"core\\\" has already been registered.\",\"template\":null,\"span\":null,\"hint\":null,\"notes\":[],\"backtrace\":[{}]}}",
+            error.backtrace().iter().map(|l| format!("{{\"file\":\"{}\",\"line\":{},\"column\":{}}}", l.filename, l.line, l.column)).collect::<Vec<_>>().join(","),
+        ));
+    }
+
+    #[test]
+    fn styles() {
+        let mut twig = engine_with(Setup::default(), &[]);
+
+        let error = twig.render("missing", Hash::new()).unwrap_err();
+        let diagnostic = twig.diagnostic(&error);
+        assert_eq!(diagnostic.to_string(), [
+            "error[T0401]: Template not found. Template \"missing\" is not present in array template loader.",
+            "  |",
+            "  = hint: check the name of the template and the configuration of the loader",
+            "",
+        ].join("\n"));
+        assert!(diagnostic.render(Style::Ansi).starts_with("\x1b[1;31merror[T0401]\x1b[0m\x1b[1m: Template not found."));
+    }
+}
@@ -11,6 +11,7 @@ use std::error::Error;
 use loader::LoaderError;
 use api::parser::ParserError;
 use api::lexer::LexerError;
+use api::runtime::{RuntimeError, TemplateTrace};
 use api::ext;
 use std::convert::From;
 
@@ -35,14 +36,24 @@ quick_error! {
         LexerNotInitialized {
             description("The template lexer must be initializied prior usage.")
         }
-        Parser(cause: ParserError) {
+        /// Parser error, with the name of the template.
+        Parser(cause: ParserError, template: String) {
             description("Twig parser error")
             display(me) -> ("{}: {}", me.description(), cause)
-            from()
-            cause(&*cause)
+            from(cause: ParserError) -> (cause, String::new())
+            cause(cause)
         }
-        Runtime {
-            description("Twig runtime error.")
+        EmbeddedTemplateNotFound { name: String, index: u32 } {
+            description("Embedded template not found.")
+            display(me) -> ("{} Template {:?} has no embedded template #{}.", me.description(), name, index)
+        }
+        /// Runtime error, with its location within the templates.
+        Runtime(cause: RuntimeError, trace: TemplateTrace) {
+            description("Twig runtime error")
+            display(me) -> ("{}: {}{}", me.description(), cause,
+                if cause.position().is_some() { trace.callers().to_string() } else { trace.to_string() })
+            from(cause: RuntimeError) -> (cause, TemplateTrace::default())
+            cause(cause)
         }
         ExtensionRegistry(cause: ExtensionRegistryError) {
             description("Twig extension registry error")
@@ -53,6 +64,31 @@ quick_error! {
     }
 }
 
+impl TwigError {
+    /// Get the stable code of the error, see `twig::api::error::index` - the code of the cause for wrapped
+    /// errors.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            TwigError::LoaderNotInitialized => "T0101",
+            TwigError::LexerNotInitialized => "T0102",
+            TwigError::EmbeddedTemplateNotFound{..} => "T0103",
+            TwigError::Loader(ref cause) => cause.code(),
+            TwigError::Lexer(ref cause) => cause.code(),
+            TwigError::Parser(ref cause, _) => cause.code(),
+            TwigError::Runtime(ref cause, _) => cause.code(),
+            TwigError::ExtensionRegistry(ref cause) => cause.code(),
+        }
+    }
+
+    /// Returns true if the error is raised because a template does not exist.
+    pub fn is_template_not_found(&self) -> bool {
+        match *self {
+            TwigError::Loader(ref error) => error.is_not_found(),
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ExtensionRegistryError {
     /// To be used by custom implementations of `twig::api::ext::Extension::init()`
@@ -62,6 +98,13 @@ pub enum ExtensionRegistryError {
     DuplicateExtension {
         name: String,
     },
+    MissingDependency {
+        name: String,
+        dependency: String,
+    },
+    DependencyCycle {
+        chain: Vec<String>,
+    },
     DuplicateFilter {
         prev: Box<ext::Filter>
     },
@@ -77,6 +120,9 @@ pub enum ExtensionRegistryError {
     DuplicateTest {
         prev: Box<ext::Test>
     },
+    DuplicateGlobal {
+        prev: Box<dyn ext::Global>
+    },
     DuplicateTagHandler {
         prev: Box<ext::TokenParser>
     },
@@ -85,16 +131,39 @@ pub enum ExtensionRegistryError {
     },
 }
 
+impl ExtensionRegistryError {
+    /// Get the stable code of the error, see `twig::api::error::index`.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            ExtensionRegistryError::ExtensionInitFailure{..} => "T0501",
+            ExtensionRegistryError::DuplicateExtension{..} => "T0502",
+            ExtensionRegistryError::MissingDependency{..} => "T0503",
+            ExtensionRegistryError::DependencyCycle{..} => "T0504",
+            ExtensionRegistryError::DuplicateFilter{..} => "T0505",
+            ExtensionRegistryError::DuplicateFunction{..} => "T0506",
+            ExtensionRegistryError::DuplicateOperatorUnary{..} => "T0507",
+            ExtensionRegistryError::DuplicateOperatorBinary{..} => "T0508",
+            ExtensionRegistryError::DuplicateTest{..} => "T0509",
+            ExtensionRegistryError::DuplicateGlobal{..} => "T0510",
+            ExtensionRegistryError::DuplicateTagHandler{..} => "T0511",
+            ExtensionRegistryError::DuplicateTokenParser{..} => "T0512",
+        }
+    }
+}
+
 impl Error for ExtensionRegistryError {
     fn description(&self) -> &str {
         match *self {
             ExtensionRegistryError::ExtensionInitFailure{..} => "Engine extension failed to initialize.",
             ExtensionRegistryError::DuplicateExtension{..} => "Duplicate extension.",
+            ExtensionRegistryError::MissingDependency{..} => "Missing extension dependency.",
+            ExtensionRegistryError::DependencyCycle{..} => "Cyclic extension dependencies.",
             ExtensionRegistryError::DuplicateFilter{..} => "Duplicate filter.",
             ExtensionRegistryError::DuplicateFunction{..} => "Duplicate function.",
             ExtensionRegistryError::DuplicateOperatorBinary{..} => "Duplicate binary operator.",
             ExtensionRegistryError::DuplicateOperatorUnary{..} => "Duplicate unary operator.",
             ExtensionRegistryError::DuplicateTest{..} => "Duplicate test.",
+            ExtensionRegistryError::DuplicateGlobal{..} => "Duplicate global.",
             ExtensionRegistryError::DuplicateTagHandler{..} => "Duplicate tag handler.",
             ExtensionRegistryError::DuplicateTokenParser{..} => "Duplicate token parser.",
         }
@@ -117,6 +186,18 @@ impl Display for ExtensionRegistryError {
                 write!(f, " {prev:?} has already been registered.",
                     prev = name)
             },
+            ExtensionRegistryError::MissingDependency {
+                ref name, ref dependency
+            } => {
+                write!(f, " {name:?} depends on {dependency:?} which has not been registered.",
+                    name = name, dependency = dependency)
+            },
+            ExtensionRegistryError::DependencyCycle {
+                ref chain
+            } => {
+                write!(f, " {chain}.",
+                    chain = chain.join(" -> "))
+            },
             ExtensionRegistryError::DuplicateFilter {
                 prev: ref p
             } => {
@@ -147,6 +228,12 @@ impl Display for ExtensionRegistryError {
                 write!(f, " {prev:?} has already been registered.",
                     prev = p)
             },
+            ExtensionRegistryError::DuplicateGlobal {
+                prev: ref p
+            } => {
+                write!(f, " {prev:?} has already been registered.",
+                    prev = p)
+            },
             ExtensionRegistryError::DuplicateTagHandler {
                 prev: ref p
             } => {
@@ -25,24 +25,32 @@ pub struct ExtensionRegistry {
     node_visitors: Vec<Box<ext::NodeVisitor>>,
     operators_unary: HashMap<String, ext::UnaryOperator>,
     operators_binary: HashMap<String, ext::BinaryOperator>,
-    _globals: Vec<Box<ext::Global>>,
+    globals: HashMap<String, Box<dyn ext::Global>>,
 }
 
 impl ExtensionRegistry {
     /// Initialize new extension registry instance.
+    ///
+    /// Extensions are initialized in topological order of their dependencies. Apart from that
+    /// the order of the given extensions is preserved.
     pub fn new<I>(iterable: I, options: &Options) -> Result<Self, Traced<ExtensionRegistryError>> where
-        I: IntoIterator<Item=Box<Extension>>
+        I: IntoIterator<Item=Box<dyn Extension>>
     {
         let mut builder = Builder::default();
+        let mut extensions = vec![];
 
-        for mut ext in iterable {
+        for ext in iterable {
             if !builder.staged.ext_names.insert(ext.name().to_string())  {
                 return traced_err!(ExtensionRegistryError::DuplicateExtension {
                     name: ext.name().to_string()
                 })
             };
 
-            try!(ext.init(&mut builder, options));
+            extensions.push(ext);
+        }
+
+        for mut ext in try_traced!(DependencyResolver::sort(extensions)) {
+            try_traced!(ext.init(&mut builder, options));
         }
 
         Ok(builder.into())
@@ -99,8 +107,8 @@ impl ExtensionRegistry {
     }
 
     /// Get the global variables defined by engine extensions.
-    pub fn _globals(&self) -> &Vec<Box<ext::Global>> {
-        &self._globals
+    pub fn globals(&self) -> &HashMap<String, Box<dyn ext::Global>> {
+        &self.globals
     }
 }
 
@@ -110,10 +118,9 @@ pub struct Builder {
 }
 
 impl Builder {
-    #[allow(dead_code)]
     /// Register token parser instances with the engine.
-    fn push_token_parsers<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
-        I: IntoIterator<Item=(String, Box<ext::TokenParser>)>
+    pub fn push_token_parsers<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
+        I: IntoIterator<Item=(String, Box<dyn ext::TokenParser>)>
     {
         for (k, v) in iterable {
             // #NOTE:60 can't have a reference to something owned within the same struct
@@ -136,10 +143,9 @@ impl Builder {
         Ok(())
     }
 
-    #[allow(dead_code)]
     /// Register node visitor instances with the engine.
-    fn push_node_visitors<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
-        I: IntoIterator<Item=Box<ext::NodeVisitor>>
+    pub fn push_node_visitors<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
+        I: IntoIterator<Item=Box<dyn ext::NodeVisitor>>
     {
         for v in iterable {
             self.staged.node_visitors.push(v)
@@ -148,10 +154,9 @@ impl Builder {
         Ok(())
     }
 
-    #[allow(dead_code)]
     /// Register filters with the engine.
-    fn push_filters<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
-        I: IntoIterator<Item=(String, Box<ext::Filter>)>
+    pub fn push_filters<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
+        I: IntoIterator<Item=(String, Box<dyn ext::Filter>)>
     {
         for (k, v) in iterable {
             if let Some(prev) = self.staged.filters.insert(k, v) {
@@ -164,10 +169,9 @@ impl Builder {
         Ok(())
     }
 
-    #[allow(dead_code)]
     /// Register tests with the engine.
-    fn push_tests<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
-        I: IntoIterator<Item=(String, Box<ext::Test>)>
+    pub fn push_tests<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
+        I: IntoIterator<Item=(String, Box<dyn ext::Test>)>
     {
         for (k, v) in iterable {
             if let Some(prev) = self.staged.tests.insert(k, v) {
@@ -180,10 +184,9 @@ impl Builder {
         Ok(())
     }
 
-    #[allow(dead_code)]
     /// Register functions with the engine.
-    fn push_functions<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
-        I: IntoIterator<Item=(String, Box<ext::Function>)>
+    pub fn push_functions<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
+        I: IntoIterator<Item=(String, Box<dyn ext::Function>)>
     {
         for (k, v) in iterable {
             if let Some(prev) = self.staged.functions.insert(k, v) {
@@ -196,9 +199,8 @@ impl Builder {
         Ok(())
     }
 
-    #[allow(dead_code)]
     /// Register unary operators with the engine.
-    fn push_operators_unary<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
+    pub fn push_operators_unary<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
         I: IntoIterator<Item=ext::UnaryOperator>
     {
         for v in iterable {
@@ -212,9 +214,8 @@ impl Builder {
         Ok(())
     }
 
-    #[allow(dead_code)]
     /// Register binary operators with the engine.
-    fn push_operators_binary<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
+    pub fn push_operators_binary<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
         I: IntoIterator<Item=ext::BinaryOperator>
     {
         for v in iterable {
@@ -228,12 +229,98 @@ impl Builder {
         Ok(())
     }
 
-    #[allow(dead_code)]
     /// Register global variables with the engine.
-    fn push_globals<I>(&mut self, _iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
-        I: IntoIterator<Item=Box<ext::Global>>
+    pub fn push_globals<I>(&mut self, iterable: I) -> Result<(), Traced<ExtensionRegistryError>> where
+        I: IntoIterator<Item=(String, Box<dyn ext::Global>)>
     {
-        unimplemented!()
+        for (k, v) in iterable {
+            if let Some(prev) = self.staged.globals.insert(k, v) {
+                return traced_err!(ExtensionRegistryError::DuplicateGlobal {
+                    prev
+                })
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mark {
+    Unvisited,
+    InProgress,
+    Done,
+}
+
+/// Orders extensions such that each extension is preceded by its dependencies.
+struct DependencyResolver<'a> {
+    extensions: &'a [Box<dyn Extension>],
+    index: HashMap<&'static str, usize>,
+    marks: Vec<Mark>,
+    chain: Vec<usize>,
+    order: Vec<usize>,
+}
+
+impl<'a> DependencyResolver<'a> {
+    /// Sort extensions by dependencies (depth-first, preserving the original order of independent extensions).
+    fn sort(extensions: Vec<Box<dyn Extension>>) -> Result<Vec<Box<dyn Extension>>, Traced<ExtensionRegistryError>> {
+        let order = {
+            let mut resolver = DependencyResolver {
+                extensions: &extensions,
+                index: extensions.iter().enumerate().map(|(i, ext)| (ext.name(), i)).collect(),
+                marks: vec![Mark::Unvisited; extensions.len()],
+                chain: vec![],
+                order: Vec::with_capacity(extensions.len()),
+            };
+
+            for i in 0..extensions.len() {
+                try_traced!(resolver.visit(i));
+            }
+
+            resolver.order
+        };
+
+        let mut slots: Vec<Option<Box<dyn Extension>>> = extensions.into_iter().map(Some).collect();
+
+        Ok(order.into_iter().filter_map(|i| slots[i].take()).collect())
+    }
+
+    fn visit(&mut self, i: usize) -> Result<(), Traced<ExtensionRegistryError>> {
+        match self.marks[i] {
+            Mark::Done => return Ok(()),
+            Mark::InProgress => {
+                let start = self.chain.iter().position(|&j| j == i).unwrap_or(0);
+                let mut chain: Vec<String> = self.chain[start..].iter()
+                    .map(|&j| self.extensions[j].name().to_string())
+                    .collect();
+                chain.push(self.extensions[i].name().to_string());
+
+                return traced_err!(ExtensionRegistryError::DependencyCycle {
+                    chain
+                })
+            },
+            Mark::Unvisited => {},
+        }
+
+        self.marks[i] = Mark::InProgress;
+        self.chain.push(i);
+
+        let ext = &self.extensions[i];
+        for dependency in ext.dependencies() {
+            match self.index.get(dependency) {
+                Some(&j) => try_traced!(self.visit(j)),
+                None => return traced_err!(ExtensionRegistryError::MissingDependency {
+                    name: ext.name().to_string(),
+                    dependency: dependency.to_string(),
+                }),
+            }
+        }
+
+        self.chain.pop();
+        self.marks[i] = Mark::Done;
+        self.order.push(i);
+
+        Ok(())
     }
 }
 
@@ -242,3 +329,100 @@ impl Into<ExtensionRegistry> for Builder {
         self.staged
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::*;
+    use api::error::Traced;
+    use engine::extension_registry::Builder;
+
+    #[derive(Debug)]
+    struct Dummy {
+        name: &'static str,
+        dependencies: &'static [&'static str],
+        log: Rc<RefCell<Vec<&'static str>>>,
+    }
+
+    impl Extension for Dummy {
+        fn name(&self) -> &'static str { self.name }
+
+        fn dependencies(&self) -> &'static [&'static str] { self.dependencies }
+
+        fn init(&mut self, _registry: &mut Builder, _options: &Options)
+            -> Result<(), Traced<ExtensionRegistryError>>
+        {
+            self.log.borrow_mut().push(self.name);
+
+            Ok(())
+        }
+    }
+
+    fn init(extensions: &[(&'static str, &'static [&'static str])])
+        -> (Result<ExtensionRegistry, Traced<ExtensionRegistryError>>, Vec<&'static str>)
+    {
+        let log = Rc::new(RefCell::new(vec![]));
+        let ext: Vec<Box<dyn Extension>> = extensions.iter().map(|&(name, dependencies)| {
+            Box::new(Dummy { name, dependencies, log: log.clone() }) as Box<dyn Extension>
+        }).collect();
+
+        let result = ExtensionRegistry::new(ext, &Options::default());
+        let log = log.borrow().clone();
+
+        (result, log)
+    }
+
+    #[test]
+    fn init_in_dependency_order() {
+        let (result, log) = init(&[
+            ("a", &["c"]),
+            ("b", &[]),
+            ("c", &["b"]),
+            ("d", &[]),
+        ]);
+
+        assert!(result.is_ok());
+        assert_eq!(log, vec!["b", "c", "a", "d"]);
+    }
+
+    #[test]
+    fn missing_dependency() {
+        let (result, log) = init(&[("a", &["missing"])]);
+
+        match *result.unwrap_err() {
+            ExtensionRegistryError::MissingDependency { ref name, ref dependency } => {
+                assert_eq!(name, "a");
+                assert_eq!(dependency, "missing");
+            },
+            ref err => panic!("unexpected error {:?}", err),
+        }
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn dependency_cycle() {
+        let (result, log) = init(&[
+            ("a", &["b"]),
+            ("b", &["c"]),
+            ("c", &["a"]),
+        ]);
+
+        match *result.unwrap_err() {
+            ExtensionRegistryError::DependencyCycle { ref chain } => {
+                assert_eq!(chain, &["a", "b", "c", "a"]);
+            },
+            ref err => panic!("unexpected error {:?}", err),
+        }
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn default_setup() {
+        let registry = ::Setup::default().init_engine().unwrap();
+
+        assert!(registry.extensions().has("core"));
+        assert!(registry.extensions().has("escaper"));
+        assert!(registry.extensions().has("optimizer"));
+    }
+}
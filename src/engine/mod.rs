@@ -8,15 +8,22 @@
 mod template_cache;
 use loader::Loader;
 use std::rc::Rc;
+use std::collections::HashMap;
 use template;
 use setup::Setup;
 use api::{parser, Parser, lexer, Lexer};
+use api::node;
+use api::Node;
+use api::runtime;
+use api::value::Hash;
 use api::error::Traced;
 
 pub mod error;
 pub mod options;
 pub mod extension_registry;
+pub mod diagnostic;
 pub use self::error::{TwigError, ExtensionRegistryError};
+pub use self::diagnostic::Diagnostic;
 pub use self::options::Options;
 pub use self::extension_registry::ExtensionRegistry;
 
@@ -27,6 +34,7 @@ pub struct Engine {
     loader: Option<Box<Loader>>,
     lexer: Option<Lexer>,
     parser: Option<Parser>,
+    templates: HashMap<String, Rc<template::Compiled>>,
 }
 
 
@@ -57,6 +65,7 @@ impl Engine {
             loader: None,
             lexer: None,
             parser: None,
+            templates: HashMap::new(),
         }
     }
 
@@ -66,8 +75,17 @@ impl Engine {
     /// * When the template cannot be found
     /// * When an error occurred during compilation
     /// * When an error occurred during rendering
-    pub fn render(&mut self, _path: &str, _data: ()) -> Result<String, Traced<TwigError>> {
-        unimplemented!()
+    pub fn render(&mut self, path: &str, data: Hash) -> Result<String, Traced<TwigError>> {
+        let template = try!(self.load_template(path, None));
+
+        let mut job = runtime::Job::new(self, data);
+        if let Err(error) = job.render_template(template) {
+            let trace = job.take_trace();
+
+            return Err(error.map(|cause| TwigError::Runtime(cause, trace)).trace(loc!()));
+        }
+
+        Ok(job.into_output())
     }
 
     /// Displays a template.
@@ -76,8 +94,62 @@ impl Engine {
     /// * When the template cannot be found
     /// * When an error occurred during compilation
     /// * When an error occurred during rendering
-    pub fn display(&mut self, _path: &str, _data: ()) -> Result<(), Traced<TwigError>> {
-       unimplemented!()
+    pub fn display(&mut self, path: &str, data: Hash) -> Result<(), Traced<TwigError>> {
+        print!("{}", try!(self.render(path, data)));
+
+        Ok(())
+    }
+
+    /// Creates a diagnostic for an error, with the source of the template it occurred in.
+    ///
+    /// The source is loaded via the loader, unless the error provides it.
+    pub fn diagnostic(&mut self, error: &Traced<TwigError>) -> Diagnostic {
+        let mut diagnostic = Diagnostic::new(error);
+
+        if diagnostic.source.is_none() {
+            if let (Some(name), Some(ref mut loader)) = (diagnostic.template.as_ref(), self.loader.as_mut()) {
+                diagnostic.source = loader.source(name).ok().map(|source| template::Raw::new(source, name).code);
+            }
+        }
+
+        diagnostic
+    }
+
+    /// Lexes and parses a template with error recovery and collects diagnostics of all errors,
+    /// instead of stopping at the first one - e.g. for linting.
+    ///
+    /// Returns the (partial) abstract syntax tree of the template, if it could be parsed at all,
+    /// and the diagnostics ordered by position - diagnostics without a position last. Node
+    /// visitors are not applied.
+    pub fn lint(&mut self, path: &str) -> (Option<Box<dyn Node>>, Vec<Diagnostic>) {
+        let template = match self.load_template_raw(path) {
+            Ok(template) => template,
+            Err(error) => return (None, vec![self.diagnostic(&error)]),
+        };
+
+        let (stream, lexer_errors) = match self.lexer() {
+            Ok(lexer) => lexer.tokenize_recovering(&template),
+            Err(error) => return (None, vec![self.diagnostic(&error)]),
+        };
+
+        let (root, parser_errors) = match self.parser() {
+            Ok(parser) => parser.parse_recovering(&stream),
+            Err(error) => return (None, vec![self.diagnostic(&error)]),
+        };
+
+        let errors = lexer_errors.into_iter().map(|error| error.map(TwigError::from))
+            .chain(parser_errors.into_iter().map(|error| error.map(|cause| TwigError::Parser(cause, path.to_string()))));
+
+        let mut diagnostics: Vec<Diagnostic> = errors.map(|error| {
+            let mut diagnostic = Diagnostic::new(&error);
+            diagnostic.source = diagnostic.source.or_else(|| Some(template.code.clone()));
+            diagnostic
+        }).collect();
+
+        // stable, i.e. diagnostics without a position keep their order after all others
+        diagnostics.sort_by_key(|d| d.position.as_ref().map_or((usize::MAX, usize::MAX), |p| (p.line, p.column)));
+
+        (root, diagnostics)
     }
 
     /// Loads and compiles a template.
@@ -85,14 +157,38 @@ impl Engine {
     /// # Failures
     /// * When the template cannot be found
     /// * When an error occurred during compilation
-    pub fn load_template(&mut self, path: &str, _index: Option<u32>) -> Result<template::Compiled, Traced<TwigError>> {
-        // TODO: Cache compiled templates
-        //  * cache lookup
-        //  * check if cache is fresh
-        //  * store in cache
+    pub fn load_template(&mut self, path: &str, index: Option<u32>) -> Result<Rc<template::Compiled>, Traced<TwigError>> {
+        // TODO: check if cache is fresh
+        let template = match self.templates.get(path) {
+            Some(template) => template.clone(),
+            None => {
+                let template_raw = try!(self.load_template_raw(path));
+                let template = Rc::new(try!(self.compile_template(&template_raw)));
+                self.templates.insert(path.to_string(), template.clone());
 
-        let template_raw = try!(self.load_template_raw(path));
-        return self.compile_template(&template_raw);
+                template
+            },
+        };
+
+        match index {
+            None => Ok(template),
+            Some(index) => match template.embedded_template(index as usize) {
+                Some(embedded) => Ok(embedded),
+                None => traced_err!(TwigError::EmbeddedTemplateNotFound {
+                    name: path.to_string(),
+                    index,
+                }),
+            },
+        }
+    }
+
+    /// Compiles a template from a string, without registering it - e.g. for
+    /// `template_from_string()`.
+    ///
+    /// # Failures
+    /// * When an error occurred during compilation
+    pub fn compile_string(&mut self, name: &str, source: &str) -> Result<template::Compiled, Traced<TwigError>> {
+        self.compile_template(&template::Raw::new(source, name))
     }
 
     /// Loads raw template.
@@ -115,12 +211,16 @@ impl Engine {
             try_traced!(lexer.tokenize(template))
         };
 
-        let compiled = {
+        let name = template.name();
+        let root = {
             let parser = try!(self.parser());
-            try_traced!(parser.parse(&tokenstream))
+            try_traced!(parser.parse(&tokenstream).map_err(|e| e.map(|cause| TwigError::Parser(cause, name.to_string()))))
         };
 
-        Ok(compiled)
+        let root = try_traced!(node::Traverser::new(self.ext.node_visitors()).traverse(root, &self.ext)
+            .map_err(|e| e.map(|cause| TwigError::Parser(cause, name.to_string()))));
+
+        Ok(template::Compiled::new(template.name(), root))
     }
 
     /// Get the engine extensions.
@@ -128,9 +228,22 @@ impl Engine {
         &self.ext
     }
 
+    /// Get a shared handle to the engine extensions.
+    pub fn extension_registry(&self) -> Rc<ExtensionRegistry> {
+        self.ext.clone()
+    }
+
+    /// Get the engine options.
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
     /// Sets the loader instance.
+    ///
+    /// Clears all compiled templates.
     pub fn set_loader(&mut self, loader: Box<Loader>) -> &mut Engine {
         self.loader = Some(loader); // TODO: switch to callback pattern to provide arguments
+        self.templates.clear();
 
         self
     }
@@ -150,9 +263,9 @@ impl Engine {
         match self.lexer {
             Some(ref lexer) => return Ok(lexer),
             None => {
-                let _options: lexer::Options = unimplemented!();
+                let options = lexer::Options::default();
 
-                self.lexer = Some(try_traced!(Lexer::new(_options)));
+                self.lexer = Some(try_traced!(Lexer::new(&self.ext, options)));
                 return self.lexer();
             }
         }
@@ -163,9 +276,9 @@ impl Engine {
         match self.parser {
             Some(ref parser) => return Ok(parser),
             None => {
-                let _options: parser::Options = unimplemented!();
+                let options = parser::Options::default();
 
-                self.parser = Some(try_traced!(Parser::new(_options)));
+                self.parser = Some(try_traced!(Parser::new(self.ext.clone(), options)));
                 return self.parser();
             }
         }
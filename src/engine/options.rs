@@ -5,7 +5,9 @@
 
 //! Twig configuration options.
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use api::Value;
 use extension::escaper;
 use extension::optimizer;
 
@@ -21,6 +23,7 @@ pub struct Options {
     cache: Option<PathBuf>,
     auto_reload: Option<bool>, // defaults to `self.debug` if unset
     optimizations: Optimizations,
+    constants: HashMap<String, Value>,
 }
 
 impl Default for Options {
@@ -32,6 +35,7 @@ impl Default for Options {
             cache: None,
             auto_reload: None,
             optimizations: optimizer::Mode::default(),
+            constants: HashMap::new(),
         }
     }
 }
@@ -53,8 +57,8 @@ impl Options {
         self.strict_variables = strict_variables;
     }
 
-    pub fn auto_escape(&self) -> AutoEscape {
-        self.auto_escape
+    pub fn auto_escape(&self) -> &AutoEscape {
+        &self.auto_escape
     }
 
     pub fn set_auto_escape(&mut  self, auto_escape: AutoEscape) {
@@ -89,4 +93,13 @@ impl Options {
     pub fn set_optimizations(&mut self, optimizations: Optimizations) {
         self.optimizations = optimizations;
     }
+
+    /// Get a constant, e.g. for `constant('NAME')`.
+    pub fn constant(&self, name: &str) -> Option<&Value> {
+        self.constants.get(name)
+    }
+
+    pub fn set_constant(&mut self, name: String, value: Value) {
+        self.constants.insert(name, value);
+    }
 }
@@ -0,0 +1,334 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Date parsing and formatting for the `date()` function and the `date` filter.
+//!
+//! Dates are represented as Unix timestamps. Supported formats are a subset of PHP's
+//! `strtotime()`:
+//!
+//! * timestamps, e.g. `1234567890`
+//! * ISO 8601 dates, e.g. `2015-06-01`, `2015-06-01 12:30` or `2015-06-01T12:30:00+02:00`
+//! * keywords `now`, `today`, `midnight`, `tomorrow` and `yesterday`
+//! * relative offsets, e.g. `+2 days`, `-1 week` or `1 year 3 months`
+//!
+//! Relative offsets are applied after absolute dates, e.g. `2015-06-01 +1 month`.
+//!
+//! Timezones are fixed UTC offsets. Named timezones like `Europe/Paris` are not supported,
+//! because their offsets depend on the timezone database.
+
+const DAY: i64 = 86_400;
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+
+const WEEKDAYS: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+/// Parses a date relative to `now`.
+///
+/// `offset` is the UTC offset of the timezone in seconds, applied to dates without an explicit
+/// offset. Returns `None` if the date can not be parsed.
+pub fn parse(date: &str, offset: i64, now: i64) -> Option<i64> {
+    let date = date.trim().to_lowercase();
+
+    if let Ok(timestamp) = date.parse::<i64>() {
+        return Some(timestamp);
+    }
+
+    match absolute(&date, offset) {
+        Some((time, rest)) => relative(rest, time, offset),
+        None => relative(&date, now, offset),
+    }
+}
+
+/// Parses a timezone, i.e. `UTC` or an offset like `+02:00`, into its UTC offset in seconds.
+///
+/// Returns the reason if the timezone is unknown or a named timezone like `Europe/Paris`.
+pub fn timezone(timezone: &str) -> Result<i64, String> {
+    match &timezone.trim().to_lowercase()[..] {
+        "utc" | "gmt" | "z" => Ok(0),
+        tz => match zone(tz) {
+            Some((offset, "")) => Ok(offset),
+            _ if tz.contains('/') => Err(format!(
+                "Named timezones like {:?} are not supported, use \"UTC\" or an offset like \"+02:00\".", timezone)),
+            _ => Err(format!("Unknown timezone {:?}, expected \"UTC\" or an offset like \"+02:00\".", timezone)),
+        },
+    }
+}
+
+/// Formats a timestamp like PHP's `date()`, e.g. `Y-m-d H:i` for `2015-06-01 12:30`.
+///
+/// `offset` is the UTC offset of the timezone in seconds. Supported are the day (`d`, `D`, `j`,
+/// `l`, `N`, `S`, `w`, `z`), month (`F`, `m`, `M`, `n`, `t`), year (`L`, `Y`, `y`), time (`a`,
+/// `A`, `g`, `G`, `h`, `H`, `i`, `s`), timezone (`e`, `T`, `O`, `P`, `Z`) and full date/time
+/// (`c`, `r`, `U`) characters. Other characters and characters escaped with `\` are copied.
+pub fn format(time: i64, pattern: &str, offset: i64) -> String {
+    let local = time.saturating_add(offset);
+    let days = local.div_euclid(DAY);
+    let seconds = local.rem_euclid(DAY);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    let weekday = (days + 4).rem_euclid(7) as usize;
+    let utc_offset = |separator| {
+        let sign = if offset < 0 { '-' } else { '+' };
+
+        format!("{}{:02}{}{:02}", sign, offset.abs() / 3600, separator, offset.abs() % 3600 / 60)
+    };
+
+    let mut output = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        output.push_str(&match c {
+            'd' => format!("{:02}", day),
+            'D' => WEEKDAYS[weekday][..3].to_string(),
+            'j' => day.to_string(),
+            'l' => WEEKDAYS[weekday].to_string(),
+            'N' => (if weekday == 0 { 7 } else { weekday }).to_string(),
+            'S' => match day {
+                1 | 21 | 31 => "st",
+                2 | 22 => "nd",
+                3 | 23 => "rd",
+                _ => "th",
+            }.to_string(),
+            'w' => weekday.to_string(),
+            'z' => (days - days_from_civil(year, 1, 1)).to_string(),
+            'F' => MONTHS[month as usize - 1].to_string(),
+            'm' => format!("{:02}", month),
+            'M' => MONTHS[month as usize - 1][..3].to_string(),
+            'n' => month.to_string(),
+            't' => (days_from_civil(year, month + 1, 1) - days_from_civil(year, month, 1)).to_string(),
+            'L' => (days_from_civil(year, 3, 1) - days_from_civil(year, 2, 1) - 28).to_string(),
+            'Y' => year.to_string(),
+            'y' => format!("{:02}", year.rem_euclid(100)),
+            'a' => (if hour < 12 { "am" } else { "pm" }).to_string(),
+            'A' => (if hour < 12 { "AM" } else { "PM" }).to_string(),
+            'g' => ((hour + 11) % 12 + 1).to_string(),
+            'G' => hour.to_string(),
+            'h' => format!("{:02}", (hour + 11) % 12 + 1),
+            'H' => format!("{:02}", hour),
+            'i' => format!("{:02}", minute),
+            's' => format!("{:02}", second),
+            'e' | 'T' if offset == 0 => "UTC".to_string(),
+            'e' | 'T' | 'P' => utc_offset(":"),
+            'O' => utc_offset(""),
+            'Z' => offset.to_string(),
+            'c' => format(time, "Y-m-d\\TH:i:sP", offset),
+            'r' => format(time, "D, d M Y H:i:s O", offset),
+            'U' => time.to_string(),
+            '\\' => chars.next().map(String::from).unwrap_or_default(),
+            c => c.to_string(),
+        });
+    }
+
+    output
+}
+
+/// Get the days since the Unix epoch of a date of the proleptic Gregorian calendar.
+///
+/// Days beyond the end of the month overflow into the next month (like PHP).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146_097 + doe - 719_468
+}
+
+/// Get the year, month and day of a number of days since the Unix epoch.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+
+    (if month <= 2 { yoe + era * 400 + 1 } else { yoe + era * 400 }, month, day)
+}
+
+/// Parses exactly `n` digits at the beginning of `s`.
+fn digits(s: &str, n: usize) -> Option<(i64, &str)> {
+    match s.get(..n) {
+        Some(d) if d.bytes().all(|b| b.is_ascii_digit()) => d.parse().ok().map(|d| (d, &s[n..])),
+        _ => None,
+    }
+}
+
+/// Parses a UTC offset like `+02:00`, `-0130` or `+02`.
+fn zone(s: &str) -> Option<(i64, &str)> {
+    let sign = match s.as_bytes().first() {
+        Some(&b'+') => 1,
+        Some(&b'-') => -1,
+        _ => return None,
+    };
+    let (hours, s) = digits(&s[1..], 2)?;
+    let s = s.strip_prefix(':').unwrap_or(s);
+    let (minutes, s) = digits(s, 2).unwrap_or((0, s));
+
+    Some((hours.checked_mul(3600)?.checked_add(minutes.checked_mul(60)?)?.checked_mul(sign)?, s))
+}
+
+/// Parses an ISO 8601 date at the beginning of `s`.
+fn absolute(s: &str, offset: i64) -> Option<(i64, &str)> {
+    let (year, s) = digits(s, 4)?;
+    let (month, s) = digits(s.strip_prefix('-')?, 2)?;
+    let (day, mut s) = digits(s.strip_prefix('-')?, 2)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let mut seconds = 0;
+    if let Some(time) = s.strip_prefix('t').or_else(|| s.strip_prefix(' ')) {
+        if let Some((hour, time)) = digits(time, 2) {
+            if let Some((minute, time)) = time.strip_prefix(':').and_then(|t| digits(t, 2)) {
+                let (second, time) = time.strip_prefix(':').and_then(|t| digits(t, 2)).unwrap_or((0, time));
+                if hour > 23 || minute > 59 || second > 59 {
+                    return None;
+                }
+
+                seconds = hour * 3600 + minute * 60 + second;
+                s = time;
+            }
+        }
+    }
+
+    let (offset, s) = match s.strip_prefix('z') {
+        Some(s) => (0, s),
+        None => zone(s).unwrap_or((offset, s)),
+    };
+
+    let time = days_from_civil(year, month, day).checked_mul(DAY)?.checked_add(seconds)?.checked_sub(offset)?;
+
+    Some((time, s))
+}
+
+/// Get the beginning of the day of a timestamp.
+fn midnight(time: i64, offset: i64) -> Option<i64> {
+    let local = time.checked_add(offset)?;
+
+    (local - local.rem_euclid(DAY)).checked_sub(offset)
+}
+
+/// Adds a number of months to a timestamp, keeping the day of the month and time of day.
+fn add_months(time: i64, months: i64, offset: i64) -> Option<i64> {
+    let local = time.checked_add(offset)?;
+    let (year, month, day) = civil_from_days(local.div_euclid(DAY));
+    let months = year.checked_mul(12)?.checked_add(month - 1)?.checked_add(months)?;
+    let year = months.div_euclid(12);
+
+    // the days of the year must fit into an `i64`, so `days_from_civil()` does not overflow
+    year.checked_mul(366)?;
+
+    days_from_civil(year, months.rem_euclid(12) + 1, day).checked_mul(DAY)?
+        .checked_add(local.rem_euclid(DAY))?
+        .checked_sub(offset)
+}
+
+/// Applies keywords and relative offsets, e.g. `tomorrow +2 hours`, to a timestamp.
+fn relative(s: &str, time: i64, offset: i64) -> Option<i64> {
+    let mut time = time;
+    let mut rest = s.trim_start();
+
+    while !rest.is_empty() {
+        let word_len = rest.find(|c: char| !c.is_alphabetic()).unwrap_or(rest.len());
+
+        match &rest[..word_len] {
+            "now" => {},
+            "today" | "midnight" => time = midnight(time, offset)?,
+            "tomorrow" => time = midnight(time, offset)?.checked_add(DAY)?,
+            "yesterday" => time = midnight(time, offset)?.checked_sub(DAY)?,
+            "" => {
+                let (sign, number) = match rest.as_bytes()[0] {
+                    b'+' => (1, &rest[1..]),
+                    b'-' => (-1, &rest[1..]),
+                    _ => (1, rest),
+                };
+                let len = number.find(|c: char| !c.is_ascii_digit()).unwrap_or(number.len());
+                let n = number[..len].parse::<i64>().ok()?.checked_mul(sign)?;
+
+                let unit = number[len..].trim_start();
+                let unit_len = unit.find(|c: char| !c.is_alphabetic()).unwrap_or(unit.len());
+
+                time = match &unit[..unit_len] {
+                    "sec" | "secs" | "second" | "seconds" => time.checked_add(n)?,
+                    "min" | "mins" | "minute" | "minutes" => time.checked_add(n.checked_mul(60)?)?,
+                    "hour" | "hours" => time.checked_add(n.checked_mul(3600)?)?,
+                    "day" | "days" => time.checked_add(n.checked_mul(DAY)?)?,
+                    "week" | "weeks" => time.checked_add(n.checked_mul(7 * DAY)?)?,
+                    "month" | "months" => add_months(time, n, offset)?,
+                    "year" | "years" => add_months(time, n.checked_mul(12)?, offset)?,
+                    _ => return None,
+                };
+
+                rest = unit[unit_len..].trim_start();
+                continue;
+            },
+            _ => return None,
+        }
+
+        rest = rest[word_len..].trim_start();
+    }
+
+    Some(time)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // 2015-06-01 12:30:00 UTC
+    const NOW: i64 = 1_433_161_800;
+
+    #[test]
+    fn absolute_dates() {
+        assert_eq!(parse("2015-06-01 12:30", 0, 0), Some(NOW));
+        assert_eq!(parse("2015-06-01T14:30:00+02:00", 0, 0), Some(NOW));
+        assert_eq!(parse("2015-06-01 14:30", 7200, 0), Some(NOW));
+        assert_eq!(parse("1433161800", 0, 0), Some(NOW));
+        assert_eq!(parse("1969-12-31", 0, 0), Some(-DAY));
+        assert_eq!(parse("2015-13-01", 0, 0), None);
+        assert_eq!(parse("next thursday", 0, 0), None);
+    }
+
+    #[test]
+    fn relative_dates() {
+        assert_eq!(parse("now", 0, NOW), Some(NOW));
+        assert_eq!(parse("-2 days", 0, NOW), Some(NOW - 2 * DAY));
+        assert_eq!(parse("+1 week 2hours", 0, NOW), Some(NOW + 7 * DAY + 7200));
+        assert_eq!(parse("tomorrow", 0, NOW), parse("2015-06-02", 0, 0));
+        assert_eq!(parse("today", 3600, NOW), parse("2015-06-01T00:00+01:00", 0, 0));
+        assert_eq!(parse("2015-01-31 +1 month", 0, 0), parse("2015-03-03", 0, 0));
+        assert_eq!(parse("2016-02-29 -1 year", 0, 0), parse("2015-03-01", 0, 0));
+        assert_eq!(parse("+9223372036854775807 days", 0, NOW), None);
+        assert_eq!(parse("-9223372036854775807 seconds -9223372036854775807 seconds", 0, NOW), None);
+        assert_eq!(parse("+700000000000000000 years", 0, NOW), None);
+        assert_eq!(parse("+9223372036854775807 months", 0, NOW), None);
+    }
+
+    #[test]
+    fn timezones() {
+        assert_eq!(timezone("UTC"), Ok(0));
+        assert_eq!(timezone("+02:00"), Ok(7200));
+        assert_eq!(timezone("-0130"), Ok(-5400));
+        assert!(timezone("Europe/Paris").unwrap_err().contains("Named timezones"));
+        assert!(timezone("+2 hours").unwrap_err().contains("Unknown timezone"));
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(format(NOW, "Y-m-d H:i:s", 0), "2015-06-01 12:30:00");
+        assert_eq!(format(NOW, "D, jS F y, g:i a", 0), "Mon, 1st June 15, 12:30 pm");
+        assert_eq!(format(NOW, "l N w z t L", 0), "Monday 1 1 151 30 0");
+        assert_eq!(format(NOW, "c|r|T|U", 0), "2015-06-01T12:30:00+00:00|Mon, 01 Jun 2015 12:30:00 +0000|UTC|1433161800");
+        assert_eq!(format(NOW, "H:i P O Z e", -5400), "11:00 -01:30 -0130 -5400 -01:30");
+        assert_eq!(format(NOW, "\\Y\\m Y", 0), "Ym 2015");
+        assert_eq!(format(-1, "Y-m-d H:i:s", 0), "1969-12-31 23:59:59");
+    }
+}
@@ -0,0 +1,1301 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Filters of the core extension.
+//!
+//! The filters follow the TwigPHP documentation. Arguments of the wrong type are reported as
+//! `RuntimeError::InvalidArgumentType`, with the template and position of the filter.
+
+use std::cmp::Ordering;
+use std::fmt::Write;
+use std::iter::Peekable;
+use std::str::Chars;
+use api::{Node, Value};
+use api::value::{Hash, Markup};
+use api::node::Arrow;
+use api::ext::Filter;
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use extension::escaper::strategy;
+use super::{date, function};
+
+/// Get all filters of the core extension, by name.
+pub fn all() -> Vec<(String, Box<dyn Filter>)> {
+    vec![
+        ("upper".to_string(), Box::new(Upper) as Box<dyn Filter>),
+        ("lower".to_string(), Box::new(Lower)),
+        ("title".to_string(), Box::new(Title)),
+        ("capitalize".to_string(), Box::new(Capitalize)),
+        ("trim".to_string(), Box::new(Trim)),
+        ("length".to_string(), Box::new(Length)),
+        ("join".to_string(), Box::new(Join)),
+        ("split".to_string(), Box::new(Split)),
+        ("slice".to_string(), Box::new(Slice)),
+        ("first".to_string(), Box::new(First)),
+        ("last".to_string(), Box::new(Last)),
+        ("reverse".to_string(), Box::new(Reverse)),
+        ("sort".to_string(), Box::new(Sort)),
+        ("keys".to_string(), Box::new(Keys)),
+        ("merge".to_string(), Box::new(Merge)),
+        ("default".to_string(), Box::new(Default)),
+        ("replace".to_string(), Box::new(Replace)),
+        ("format".to_string(), Box::new(Format)),
+        ("striptags".to_string(), Box::new(Striptags)),
+        ("nl2br".to_string(), Box::new(Nl2br)),
+        ("abs".to_string(), Box::new(Abs)),
+        ("round".to_string(), Box::new(Round)),
+        ("date".to_string(), Box::new(Date)),
+        ("batch".to_string(), Box::new(Batch)),
+        ("column".to_string(), Box::new(Column)),
+        ("filter".to_string(), Box::new(FilterFilter)),
+        ("map".to_string(), Box::new(Map)),
+        ("reduce".to_string(), Box::new(Reduce)),
+        ("url_encode".to_string(), Box::new(UrlEncode)),
+        ("json_encode".to_string(), Box::new(JsonEncode)),
+        ("spaceless".to_string(), Box::new(Spaceless)),
+    ]
+}
+
+/// Fails because an argument of a filter has the wrong type.
+pub fn type_error<T>(job: &Job, filter: &str, argument: &str, expected: &str, value: &Value) -> Result<T, Traced<RuntimeError>> {
+    traced_err!(RuntimeError::InvalidArgumentType {
+        callable: format!("filter {:?}", filter),
+        argument: argument.to_string(),
+        expected: expected.to_string(),
+        found: value.type_name(),
+        template: job.template_name().to_string(),
+        position: job.position(),
+    })
+}
+
+/// Converts a scalar argument to a string, or fails for collections.
+fn string(job: &Job, filter: &str, argument: &str, value: &Value) -> Result<String, Traced<RuntimeError>> {
+    match *value {
+        Value::Array(_) | Value::Hash(_) => type_error(job, filter, argument, "a string", value),
+        ref value => Ok(value.to_string()),
+    }
+}
+
+/// Converts a numeric argument to an integer, or fails for other values.
+fn int(job: &Job, filter: &str, argument: &str, value: &Value) -> Result<i64, Traced<RuntimeError>> {
+    match value.to_number() {
+        Some(_) if !value.is_null() => Ok(value.to_int()),
+        _ => type_error(job, filter, argument, "a number", value),
+    }
+}
+
+/// Get the key-value pairs of a collection argument (null is empty), or fails for other values.
+fn pairs(job: &Job, filter: &str, argument: &str, value: &Value) -> Result<Vec<(Value, Value)>, Traced<RuntimeError>> {
+    match *value {
+        Value::Array(_) | Value::Hash(_) => Ok(value.to_pairs()),
+        Value::Null => Ok(vec![]),
+        ref value => type_error(job, filter, argument, "an array or hash", value),
+    }
+}
+
+/// Builds a hash from key-value pairs - or an array of the values, unless `hash` is true.
+fn collect(pairs: Vec<(Value, Value)>, hash: bool) -> Value {
+    if hash {
+        Value::Hash(pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    } else {
+        Value::Array(pairs.into_iter().map(|(_, v)| v).collect())
+    }
+}
+
+/// Converts a hash key to an integer, if it is the canonical representation of one (like PHP).
+fn key(key: &str) -> Value {
+    match key.parse::<i64>() {
+        Ok(i) if i.to_string() == key => Value::from(i),
+        _ => Value::from(key),
+    }
+}
+
+/// Characters removed by PHP's `trim()`.
+const WHITESPACE: &[char] = &[' ', '\t', '\n', '\r', '\0', '\x0B'];
+
+/// `value|upper` converts a string to uppercase.
+#[derive(Debug, Default)]
+pub struct Upper;
+
+impl Filter for Upper {
+    fn apply(&self, job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        Ok(Value::from(try_traced!(string(job, "upper", "the value", &value)).to_uppercase()))
+    }
+}
+
+/// `value|lower` converts a string to lowercase.
+#[derive(Debug, Default)]
+pub struct Lower;
+
+impl Filter for Lower {
+    fn apply(&self, job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        Ok(Value::from(try_traced!(string(job, "lower", "the value", &value)).to_lowercase()))
+    }
+}
+
+/// `value|title` capitalizes all words, i.e. words start with uppercase and continue with
+/// lowercase characters.
+#[derive(Debug, Default)]
+pub struct Title;
+
+impl Filter for Title {
+    fn apply(&self, job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let s = try_traced!(string(job, "title", "the value", &value));
+        let mut title = String::with_capacity(s.len());
+        let mut in_word = false;
+
+        for c in s.chars() {
+            if in_word {
+                title.extend(c.to_lowercase());
+            } else {
+                title.extend(c.to_uppercase());
+            }
+            in_word = c.is_alphanumeric() || c == '\'';
+        }
+
+        Ok(Value::from(title))
+    }
+}
+
+/// `value|capitalize` converts the first character to uppercase and the others to lowercase.
+#[derive(Debug, Default)]
+pub struct Capitalize;
+
+impl Filter for Capitalize {
+    fn apply(&self, job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let s = try_traced!(string(job, "capitalize", "the value", &value));
+        let mut chars = s.chars();
+
+        Ok(Value::from(match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars.as_str().to_lowercase().chars()).collect(),
+            None => String::new(),
+        }))
+    }
+}
+
+/// `value|trim(characters = whitespace, side = 'both')` strips characters from the beginning
+/// and/or end of a string. `side` is one of `both`, `left` or `right`.
+#[derive(Debug, Default)]
+pub struct Trim;
+
+impl Filter for Trim {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let s = try_traced!(string(job, "trim", "the value", &value));
+        let characters: Vec<char> = match arguments.first() {
+            Some(&Value::Null) | None => WHITESPACE.to_vec(),
+            Some(characters) => try_traced!(string(job, "trim", "the characters", characters)).chars().collect(),
+        };
+        let side = match arguments.get(1) {
+            Some(side) => try_traced!(string(job, "trim", "the side", side)),
+            None => "both".to_string(),
+        };
+
+        let trimmed = match &side[..] {
+            "both" => s.trim_matches(&characters[..]),
+            "left" => s.trim_start_matches(&characters[..]),
+            "right" => s.trim_end_matches(&characters[..]),
+            _ => return traced_err!(RuntimeError::InvalidArgument {
+                reason: format!("Trimming side must be \"left\", \"right\" or \"both\", got {:?}.", side),
+            }),
+        };
+
+        Ok(Value::from(trimmed))
+    }
+}
+
+/// `value|length` returns the number of elements of a collection, or characters of a string.
+#[derive(Debug, Default)]
+pub struct Length;
+
+impl Filter for Length {
+    fn apply(&self, _job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        Ok(Value::from(match value {
+            Value::Null => 0,
+            Value::Array(ref a) => a.len(),
+            Value::Hash(ref h) => h.len(),
+            ref value => value.to_string().chars().count(),
+        }))
+    }
+}
+
+/// `value|join(glue = '', and = null)` concatenates the elements of a collection, with `and`
+/// between the last two elements.
+#[derive(Debug, Default)]
+pub struct Join;
+
+impl Filter for Join {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let values: Vec<String> = match value {
+            Value::Null => vec![],
+            Value::Array(_) | Value::Hash(_) => value.to_pairs().into_iter().map(|(_, v)| v.to_string()).collect(),
+            value => vec![value.to_string()],
+        };
+        let glue = match arguments.first() {
+            Some(glue) => try_traced!(string(job, "join", "the glue", glue)),
+            None => String::new(),
+        };
+
+        match arguments.get(1) {
+            Some(and) if !and.is_null() && values.len() > 1 => {
+                let and = try_traced!(string(job, "join", "the last glue", and));
+                let (last, init) = values.split_last().expect("at least two values");
+
+                Ok(Value::from(format!("{}{}{}", init.join(&glue), and, last)))
+            },
+            _ => Ok(Value::from(values.join(&glue))),
+        }
+    }
+}
+
+/// `value|split(delimiter, limit = null)` splits a string into an array.
+///
+/// With a positive `limit`, the last element contains the rest of the string. With a negative
+/// `limit`, the last `-limit` elements are omitted. An empty delimiter splits the string into
+/// chunks of `limit` characters.
+#[derive(Debug, Default)]
+pub struct Split;
+
+impl Filter for Split {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let s = try_traced!(string(job, "split", "the value", &value));
+        let delimiter = match arguments.first() {
+            Some(delimiter) => try_traced!(string(job, "split", "the delimiter", delimiter)),
+            None => return traced_err!(RuntimeError::InvalidArgument {
+                reason: "Filter \"split\" expects a delimiter.".to_string(),
+            }),
+        };
+        let limit = match arguments.get(1) {
+            Some(&Value::Null) | None => None,
+            Some(limit) => Some(try_traced!(int(job, "split", "the limit", limit))),
+        };
+
+        if delimiter.is_empty() {
+            let size = limit.map_or(1, |limit| limit.max(1) as usize);
+            let chars: Vec<char> = s.chars().collect();
+
+            return Ok(Value::from(chars.chunks(size).map(|c| c.iter().collect::<String>()).collect::<Vec<_>>()));
+        }
+
+        let parts: Vec<&str> = match limit {
+            Some(limit) if limit > 0 => s.splitn(limit as usize, &delimiter[..]).collect(),
+            Some(limit) if limit < 0 => {
+                let mut parts: Vec<&str> = s.split(&delimiter[..]).collect();
+                let len = parts.len().saturating_sub(limit.unsigned_abs() as usize);
+                parts.truncate(len);
+                parts
+            },
+            Some(_) => vec![&s[..]],
+            None => s.split(&delimiter[..]).collect(),
+        };
+
+        Ok(Value::from(parts))
+    }
+}
+
+/// Get the range of `slice(start, length)` for a sequence of `len` elements, like PHP's
+/// `array_slice()`.
+fn slice_range(len: usize, start: i64, length: Option<i64>) -> (usize, usize) {
+    let len = len as i64;
+    let start = if start < 0 { (len + start).max(0) } else { start.min(len) };
+    let end = match length {
+        Some(length) if length < 0 => (len + length).max(start),
+        Some(length) => start.saturating_add(length).min(len),
+        None => len,
+    };
+
+    (start as usize, end as usize)
+}
+
+/// `value|slice(start, length = null, preserve_keys = false)` extracts a slice of a collection
+/// or string. Negative values count from the end.
+#[derive(Debug, Default)]
+pub struct Slice;
+
+impl Filter for Slice {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let start = match arguments.first() {
+            Some(start) => try_traced!(int(job, "slice", "the start", start)),
+            None => 0,
+        };
+        let length = match arguments.get(1) {
+            Some(&Value::Null) | None => None,
+            Some(length) => Some(try_traced!(int(job, "slice", "the length", length))),
+        };
+        let preserve_keys = arguments.get(2).is_some_and(|p| p.to_bool());
+
+        match value {
+            Value::Array(_) | Value::Hash(_) => {
+                let is_hash = matches!(value, Value::Hash(_)) || preserve_keys;
+                let pairs = value.to_pairs();
+                let (start, end) = slice_range(pairs.len(), start, length);
+
+                Ok(collect(pairs[start..end].to_vec(), is_hash))
+            },
+            value => {
+                let chars: Vec<char> = value.to_string().chars().collect();
+                let (start, end) = slice_range(chars.len(), start, length);
+
+                Ok(Value::from(chars[start..end].iter().collect::<String>()))
+            },
+        }
+    }
+}
+
+/// `value|first` returns the first element of a collection, or the first character of a string.
+#[derive(Debug, Default)]
+pub struct First;
+
+impl Filter for First {
+    fn apply(&self, _job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        Ok(match value {
+            Value::Array(_) | Value::Hash(_) => value.to_pairs().into_iter().next().map(|(_, v)| v).unwrap_or_default(),
+            value => Value::from(value.to_string().chars().take(1).collect::<String>()),
+        })
+    }
+}
+
+/// `value|last` returns the last element of a collection, or the last character of a string.
+#[derive(Debug, Default)]
+pub struct Last;
+
+impl Filter for Last {
+    fn apply(&self, _job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        Ok(match value {
+            Value::Array(_) | Value::Hash(_) => value.to_pairs().pop().map(|(_, v)| v).unwrap_or_default(),
+            value => Value::from(value.to_string().chars().last().map(String::from).unwrap_or_default()),
+        })
+    }
+}
+
+/// `value|reverse(preserve_keys = false)` reverses a collection or string.
+#[derive(Debug, Default)]
+pub struct Reverse;
+
+impl Filter for Reverse {
+    fn apply(&self, _job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let preserve_keys = arguments.first().is_some_and(|p| p.to_bool());
+
+        Ok(match value {
+            Value::Array(_) | Value::Hash(_) => {
+                let is_hash = matches!(value, Value::Hash(_)) || preserve_keys;
+                let mut pairs = value.to_pairs();
+                pairs.reverse();
+
+                collect(pairs, is_hash)
+            },
+            value => Value::from(value.to_string().chars().rev().collect::<String>()),
+        })
+    }
+}
+
+/// `value|sort` sorts a collection by value, `value|sort((a, b) => ..)` with a comparison
+/// function returning a negative number, zero or a positive number.
+///
+/// Hashes keep their keys, arrays are re-indexed.
+#[derive(Debug, Default)]
+pub struct Sort;
+
+impl Sort {
+    fn sort<F>(job: &mut Job, value: Value, mut compare: F) -> Result<Value, Traced<RuntimeError>> where
+        F: FnMut(&mut Job, &Value, &Value) -> Result<Ordering, Traced<RuntimeError>>
+    {
+        let is_hash = matches!(value, Value::Hash(_));
+        let mut pairs = try_traced!(pairs(job, "sort", "the value", &value));
+        let mut error = None;
+
+        pairs.sort_by(|a, b| match error {
+            Some(_) => Ordering::Equal,
+            None => compare(job, &a.1, &b.1).unwrap_or_else(|e| {
+                error = Some(e);
+                Ordering::Equal
+            }),
+        });
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(collect(pairs, is_hash)),
+        }
+    }
+}
+
+impl Filter for Sort {
+    fn apply(&self, job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        Sort::sort(job, value, |_, a, b| Ok(a.compare(b).unwrap_or(Ordering::Equal)))
+    }
+
+    fn apply_arrow(&self, job: &mut Job, value: Value, arrow: &Arrow, _arguments: Vec<Value>)
+        -> Option<Result<Value, Traced<RuntimeError>>> {
+        Some(Sort::sort(job, value, |job, a, b| {
+            let result = try_traced!(arrow.call(job, vec![a.clone(), b.clone()]));
+
+            Ok(result.to_float().partial_cmp(&0.0).unwrap_or(Ordering::Equal))
+        }))
+    }
+}
+
+/// `value|keys` returns the keys of a collection.
+#[derive(Debug, Default)]
+pub struct Keys;
+
+impl Filter for Keys {
+    fn apply(&self, _job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let keys: Vec<Value> = match value {
+            Value::Hash(ref h) => h.keys().into_iter().map(key).collect(),
+            value => value.to_pairs().into_iter().map(|(k, _)| k).collect(),
+        };
+
+        Ok(Value::Array(keys))
+    }
+}
+
+/// `value|merge(other)` merges two collections, like PHP's `array_merge()`.
+///
+/// Integer keys are renumbered and elements with other keys are overridden by `other`. The
+/// result is an array if all keys are integers.
+#[derive(Debug, Default)]
+pub struct Merge;
+
+impl Filter for Merge {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let other = arguments.first().cloned().unwrap_or_default();
+        let mut left = try_traced!(pairs(job, "merge", "the value", &value));
+        let right = match other {
+            Value::Array(_) | Value::Hash(_) => other.to_pairs(),
+            ref other => return type_error(job, "merge", "the merged value", "an array or hash", other),
+        };
+
+        left.extend(right);
+
+        let mut merged = Hash::new();
+        let mut index = 0;
+        let mut is_array = true;
+
+        for (k, v) in left {
+            match key(&k.to_string()) {
+                Value::Int(_) => {
+                    merged.insert(index.to_string(), v);
+                    index += 1;
+                },
+                k => {
+                    is_array = false;
+                    merged.insert(k.to_string(), v);
+                },
+            }
+        }
+
+        match is_array {
+            true => Ok(Value::Array(merged.into_iter().map(|(_, v)| v).collect())),
+            false => Ok(Value::Hash(merged)),
+        }
+    }
+}
+
+/// `value|default(default = '')` returns `default` if the value is null, false, an empty
+/// string or an empty collection.
+#[derive(Debug, Default)]
+pub struct Default;
+
+impl Filter for Default {
+    fn accepts_undefined(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, _job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let is_empty = match value {
+            Value::Null | Value::Bool(false) => true,
+            Value::Str(ref s) => s.is_empty(),
+            Value::Markup(ref m) => m.content().is_empty(),
+            Value::Array(ref a) => a.is_empty(),
+            Value::Hash(ref h) => h.is_empty(),
+            _ => false,
+        };
+
+        match is_empty {
+            true => Ok(arguments.into_iter().next().unwrap_or_else(|| Value::from(""))),
+            false => Ok(value),
+        }
+    }
+}
+
+/// `value|replace(pairs)` replaces placeholders in a string, like PHP's `strtr()`.
+///
+/// Longer placeholders are replaced first and replacements are not replaced again.
+#[derive(Debug, Default)]
+pub struct Replace;
+
+impl Filter for Replace {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let s = try_traced!(string(job, "replace", "the value", &value));
+        let from = arguments.first().cloned().unwrap_or_default();
+        let mut replacements: Vec<(String, String)> = match from {
+            Value::Array(_) | Value::Hash(_) => from.to_pairs().into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .filter(|(k, _)| !k.is_empty())
+                .collect(),
+            ref from => return type_error(job, "replace", "the replacements", "a hash", from),
+        };
+        replacements.sort_by_key(|r| ::std::cmp::Reverse(r.0.len()));
+
+        let mut replaced = String::with_capacity(s.len());
+        let mut rest = &s[..];
+
+        while let Some(c) = rest.chars().next() {
+            match replacements.iter().find(|&(k, _)| rest.starts_with(&k[..])) {
+                Some((k, v)) => {
+                    replaced.push_str(v);
+                    rest = &rest[k.len()..];
+                },
+                None => {
+                    replaced.push(c);
+                    rest = &rest[c.len_utf8()..];
+                },
+            }
+        }
+
+        Ok(Value::from(replaced))
+    }
+}
+
+/// `format|format(arguments..)` formats a string like PHP's `sprintf()`.
+///
+/// Supported conversions are `b`, `c`, `d`, `e`, `E`, `f`, `F`, `o`, `s`, `u`, `x`, `X` and
+/// `%`, with argument numbers (`%1$s`), flags (`-`, `+`, `0`, space, `'x`), width and precision.
+#[derive(Debug, Default)]
+pub struct Format;
+
+impl Filter for Format {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let format = try_traced!(string(job, "format", "the format", &value));
+
+        sprintf(&format, &arguments).map(Value::from)
+    }
+}
+
+/// The maximum width and precision of `sprintf()` conversions.
+const FORMAT_LIMIT: usize = 1024;
+
+/// Parses the width or precision of a `sprintf()` conversion.
+fn format_number(chars: &mut Peekable<Chars>, name: &str) -> Result<usize, Traced<RuntimeError>> {
+    let mut n: usize = 0;
+    while let Some(d) = chars.peek().and_then(|d| d.to_digit(10)) {
+        n = n.saturating_mul(10).saturating_add(d as usize);
+        chars.next();
+    }
+
+    if n > FORMAT_LIMIT {
+        return traced_err!(RuntimeError::InvalidArgument {
+            reason: format!("The {} of a conversion in format must not exceed {}, got {}.", name, FORMAT_LIMIT, n),
+        });
+    }
+
+    Ok(n)
+}
+
+/// Formats a string like PHP's `sprintf()`.
+fn sprintf(format: &str, arguments: &[Value]) -> Result<String, Traced<RuntimeError>> {
+    let mut output = String::with_capacity(format.len());
+    let mut chars = format.chars().peekable();
+    let mut next_argument = 0;
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            output.push('%');
+            continue;
+        }
+
+        // argument number, e.g. `%1$s`
+        let mut digits = String::new();
+        let mut lookahead = chars.clone();
+        while let Some(&d) = lookahead.peek().filter(|d| d.is_ascii_digit()) {
+            digits.push(d);
+            lookahead.next();
+        }
+        let argnum = match lookahead.peek() {
+            Some(&'$') if !digits.is_empty() => {
+                lookahead.next();
+                chars = lookahead;
+                Some(digits.parse::<usize>().unwrap_or(0).saturating_sub(1))
+            },
+            _ => None,
+        };
+
+        // flags
+        let (mut left, mut plus, mut pad) = (false, false, ' ');
+        loop {
+            match chars.peek() {
+                Some(&'-') => left = true,
+                Some(&'+') => plus = true,
+                Some(&'0') => pad = '0',
+                Some(&' ') => pad = ' ',
+                Some(&'\'') => {
+                    chars.next();
+                    pad = chars.peek().cloned().unwrap_or(' ');
+                },
+                _ => break,
+            }
+            chars.next();
+        }
+
+        let width = try_traced!(format_number(&mut chars, "width"));
+
+        let mut precision = None;
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            precision = Some(try_traced!(format_number(&mut chars, "precision")));
+        }
+
+        let conversion = match chars.next() {
+            Some(conversion) => conversion,
+            None => return traced_err!(RuntimeError::InvalidArgument {
+                reason: "Missing conversion specifier at the end of the format.".to_string(),
+            }),
+        };
+
+        let index = argnum.unwrap_or_else(|| {
+            next_argument += 1;
+            next_argument - 1
+        });
+        let argument = match arguments.get(index) {
+            Some(argument) => argument,
+            None => return traced_err!(RuntimeError::InvalidArgument {
+                reason: format!("Filter \"format\" expects at least {} arguments, {} given.", index + 1, arguments.len()),
+            }),
+        };
+
+        let (sign, digits) = match conversion {
+            'd' => {
+                let i = argument.to_int();
+                (if i < 0 { "-" } else if plus { "+" } else { "" }, i.unsigned_abs().to_string())
+            },
+            'u' => ("", (argument.to_int() as u64).to_string()),
+            'e' | 'E' | 'f' | 'F' => {
+                let x = argument.to_float();
+                let precision = precision.unwrap_or(6);
+                let digits = match conversion {
+                    'e' | 'E' => {
+                        let formatted = format!("{:.*e}", precision, x.abs());
+                        let formatted = match formatted.find('e') {
+                            Some(e) if !formatted[e + 1..].starts_with('-') => format!("{}e+{}", &formatted[..e], &formatted[e + 1..]),
+                            _ => formatted,
+                        };
+                        if conversion == 'E' { formatted.to_uppercase() } else { formatted }
+                    },
+                    // round half away from zero, like PHP
+                    _ => format!("{:.*}", precision, round(x.abs(), precision as i64, f64::round)),
+                };
+                (if x < 0.0 { "-" } else if plus { "+" } else { "" }, digits)
+            },
+            'b' => ("", format!("{:b}", argument.to_int())),
+            'o' => ("", format!("{:o}", argument.to_int())),
+            'x' => ("", format!("{:x}", argument.to_int())),
+            'X' => ("", format!("{:X}", argument.to_int())),
+            'c' => {
+                output.extend(::std::char::from_u32(argument.to_int() as u32));
+                continue;
+            },
+            's' => {
+                let s = argument.to_string();
+                ("", match precision {
+                    Some(precision) => s.chars().take(precision).collect(),
+                    None => s,
+                })
+            },
+            _ => return traced_err!(RuntimeError::InvalidArgument {
+                reason: format!("Unknown conversion specifier {:?} in format.", conversion),
+            }),
+        };
+
+        let len = sign.chars().count() + digits.chars().count();
+        let padding: String = ::std::iter::repeat_n(pad, width.saturating_sub(len)).collect();
+
+        if left {
+            let padding = if pad == '0' { padding.replace('0', " ") } else { padding };
+            let _ = write!(output, "{}{}{}", sign, digits, padding);
+        } else if pad == '0' {
+            let _ = write!(output, "{}{}{}", sign, padding, digits);
+        } else {
+            let _ = write!(output, "{}{}{}", padding, sign, digits);
+        }
+    }
+
+    Ok(output)
+}
+
+/// `value|striptags(allowed_tags = '')` strips HTML tags and comments, except the allowed
+/// tags, e.g. `'<b><i>'`.
+#[derive(Debug, Default)]
+pub struct Striptags;
+
+impl Filter for Striptags {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let s = try_traced!(string(job, "striptags", "the value", &value));
+        let allowed = match arguments.first() {
+            Some(allowed) => try_traced!(string(job, "striptags", "the allowed tags", allowed)).to_lowercase(),
+            None => String::new(),
+        };
+
+        let mut stripped = String::with_capacity(s.len());
+        let mut rest = &s[..];
+
+        while let Some(start) = rest.find('<') {
+            stripped.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            if rest[1..].starts_with(char::is_whitespace) || rest.len() == 1 {
+                stripped.push('<');
+                rest = &rest[1..];
+                continue;
+            }
+
+            let (tag, end) = if rest.starts_with("<!--") {
+                ("", rest.find("-->").map_or(rest.len(), |end| end + 3))
+            } else {
+                (&rest[1..], rest.find('>').map_or(rest.len(), |end| end + 1))
+            };
+
+            let name: String = tag.trim_start_matches('/').chars()
+                .take_while(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase();
+            if !name.is_empty() && allowed.contains(&format!("<{}>", name)) {
+                stripped.push_str(&rest[..end]);
+            }
+
+            rest = &rest[end..];
+        }
+        stripped.push_str(rest);
+
+        Ok(Value::from(stripped))
+    }
+}
+
+/// `value|nl2br` inserts HTML line breaks before all newlines. The value is escaped for HTML
+/// first, unless it is markup which is safe for HTML.
+#[derive(Debug, Default)]
+pub struct Nl2br;
+
+impl Filter for Nl2br {
+    fn is_safe(&self, _arguments: &[Box<dyn Node>]) -> Vec<String> {
+        vec!["html".to_string()]
+    }
+
+    fn apply(&self, job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let s = match value {
+            Value::Markup(ref markup) if markup.is_safe_for("html") => markup.content().to_string(),
+            ref value => strategy::html(&try_traced!(string(job, "nl2br", "the value", value))),
+        };
+
+        let mut output = String::with_capacity(s.len());
+        let mut chars = s.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '\n' && c != '\r' {
+                output.push(c);
+                continue;
+            }
+
+            output.push_str("<br />");
+            output.push(c);
+
+            // `\r\n` and `\n\r` are one line break
+            if let Some(&next) = chars.peek() {
+                if (next == '\n' || next == '\r') && next != c {
+                    output.push(next);
+                    chars.next();
+                }
+            }
+        }
+
+        Ok(Value::from(Markup::new(output, "html")))
+    }
+}
+
+/// `number|abs` returns the absolute value of a number.
+#[derive(Debug, Default)]
+pub struct Abs;
+
+impl Filter for Abs {
+    fn apply(&self, job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        match value.to_number() {
+            Some(Value::Int(i)) => Ok(Value::from(i.checked_abs().map_or((i as f64).abs(), |i| i as f64))).map(|v| match v {
+                Value::Float(x) if x.fract() == 0.0 && x < i64::MAX as f64 => Value::from(x as i64),
+                v => v,
+            }),
+            Some(Value::Float(x)) => Ok(Value::from(x.abs())),
+            _ => type_error(job, "abs", "the value", "a number", &value),
+        }
+    }
+}
+
+/// Rounds `x` to `precision` decimal places with `method`, e.g. `f64::round`.
+///
+/// Returns a signed zero if `10^precision` is too small for `f64`, like PHP, and `x` unchanged
+/// if `10^precision` or the scaled value is too large.
+fn round(x: f64, precision: i64, method: fn(f64) -> f64) -> f64 {
+    let factor = 10f64.powi(precision.clamp(-400, 400) as i32);
+    let scaled = x * factor;
+
+    if !factor.is_finite() || !scaled.is_finite() {
+        return x;
+    }
+    if factor == 0.0 {
+        return 0f64.copysign(x);
+    }
+
+    method(scaled) / factor
+}
+
+/// `number|round(precision = 0, method = 'common')` rounds a number. `method` is one of
+/// `common` (half away from zero), `ceil` or `floor`.
+#[derive(Debug, Default)]
+pub struct Round;
+
+impl Filter for Round {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let x = match value.to_number() {
+            Some(_) => value.to_float(),
+            None => return type_error(job, "round", "the value", "a number", &value),
+        };
+        let precision = match arguments.first() {
+            Some(precision) => try_traced!(int(job, "round", "the precision", precision)),
+            None => 0,
+        };
+        let method = match arguments.get(1) {
+            Some(method) => try_traced!(string(job, "round", "the method", method)),
+            None => "common".to_string(),
+        };
+
+        let rounded = match &method[..] {
+            "common" => round(x, precision, f64::round),
+            "ceil" => round(x, precision, f64::ceil),
+            "floor" => round(x, precision, f64::floor),
+            _ => return traced_err!(RuntimeError::InvalidArgument {
+                reason: format!("The round filter only supports the \"common\", \"ceil\" and \"floor\" methods, got {:?}.", method),
+            }),
+        };
+
+        Ok(Value::from(rounded))
+    }
+}
+
+/// `date|date(format = 'F j, Y H:i', timezone = 'UTC')` formats a date like PHP's `date()`,
+/// e.g. `date()|date('Y-m-d')`.
+///
+/// The date is a timestamp or date string, like the argument of the `date()` function. See
+/// `twig::extension::core::date::format` for the supported format characters.
+#[derive(Debug, Default)]
+pub struct Date;
+
+impl Filter for Date {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let format = match arguments.first() {
+            Some(&Value::Null) | None => "F j, Y H:i".to_string(),
+            Some(format) => try_traced!(string(job, "date", "the format", format)),
+        };
+        let offset = try_traced!(function::timezone_offset(arguments.get(1)));
+        let timestamp = try_traced!(function::timestamp(Some(&value), offset));
+
+        Ok(Value::from(date::format(timestamp, &format, offset)))
+    }
+}
+
+/// `value|batch(size, fill = null, preserve_keys = true)` splits a collection into chunks of
+/// `size` elements. The last chunk is filled up with `fill`, if given.
+#[derive(Debug, Default)]
+pub struct Batch;
+
+impl Filter for Batch {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let pairs = try_traced!(pairs(job, "batch", "the value", &value));
+        let size = match arguments.first() {
+            Some(size) => try_traced!(int(job, "batch", "the size", size)),
+            None => 0,
+        };
+        if size < 1 {
+            return traced_err!(RuntimeError::InvalidArgument {
+                reason: format!("Filter \"batch\" expects a size of at least 1, got {}.", size),
+            });
+        }
+        let fill = arguments.get(1).filter(|f| !f.is_null());
+        let is_hash = matches!(value, Value::Hash(_)) && arguments.get(2).is_none_or(|p| p.to_bool());
+
+        let mut chunks: Vec<Value> = vec![];
+        for chunk in pairs.chunks(size as usize) {
+            let mut chunk = chunk.to_vec();
+
+            if let Some(fill) = fill {
+                for i in chunk.len()..size as usize {
+                    chunk.push((Value::from(i), fill.clone()));
+                }
+            }
+
+            chunks.push(collect(chunk, is_hash));
+        }
+
+        Ok(Value::Array(chunks))
+    }
+}
+
+/// `rows|column(name, index = null)` returns the values of a column of a list of rows, indexed
+/// by another column if `index` is given.
+#[derive(Debug, Default)]
+pub struct Column;
+
+impl Filter for Column {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let rows = try_traced!(pairs(job, "column", "the value", &value));
+        let name = arguments.first().cloned().unwrap_or_default();
+        let index = arguments.get(1).filter(|i| !i.is_null());
+
+        let mut column: Vec<(Value, Value)> = vec![];
+        for (_, row) in rows {
+            if let Some(cell) = row.attribute(&name) {
+                let key = index.and_then(|i| row.attribute(i)).cloned().unwrap_or_else(|| Value::from(column.len()));
+                column.push((key, cell.clone()));
+            }
+        }
+
+        Ok(collect(column, index.is_some()))
+    }
+}
+
+/// `value|filter((value, key) => ..)` keeps the elements of a collection for which the arrow
+/// function returns true.
+///
+/// Hashes keep their keys, arrays are re-indexed.
+#[derive(Debug, Default)]
+pub struct FilterFilter;
+
+impl Filter for FilterFilter {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        type_error(job, "filter", "its argument", "an arrow function", &arguments.first().cloned().unwrap_or(value))
+    }
+
+    fn apply_arrow(&self, job: &mut Job, value: Value, arrow: &Arrow, _arguments: Vec<Value>)
+        -> Option<Result<Value, Traced<RuntimeError>>> {
+        Some((|| {
+            let mut filtered = vec![];
+
+            for (k, v) in try_traced!(pairs(job, "filter", "the value", &value)) {
+                if try_traced!(arrow.call(job, vec![v.clone(), k.clone()])).to_bool() {
+                    filtered.push((k, v));
+                }
+            }
+
+            Ok(collect(filtered, matches!(value, Value::Hash(_))))
+        })())
+    }
+}
+
+/// `value|map((value, key) => ..)` applies an arrow function to all elements of a collection.
+#[derive(Debug, Default)]
+pub struct Map;
+
+impl Filter for Map {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        type_error(job, "map", "its argument", "an arrow function", &arguments.first().cloned().unwrap_or(value))
+    }
+
+    fn apply_arrow(&self, job: &mut Job, value: Value, arrow: &Arrow, _arguments: Vec<Value>)
+        -> Option<Result<Value, Traced<RuntimeError>>> {
+        Some((|| {
+            let mut mapped = vec![];
+
+            for (k, v) in try_traced!(pairs(job, "map", "the value", &value)) {
+                let v = try_traced!(arrow.call(job, vec![v, k.clone()]));
+                mapped.push((k, v));
+            }
+
+            Ok(collect(mapped, matches!(value, Value::Hash(_))))
+        })())
+    }
+}
+
+/// `value|reduce((carry, value, key) => .., initial = null)` reduces a collection to a single
+/// value.
+#[derive(Debug, Default)]
+pub struct Reduce;
+
+impl Filter for Reduce {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        type_error(job, "reduce", "its first argument", "an arrow function", &arguments.first().cloned().unwrap_or(value))
+    }
+
+    fn apply_arrow(&self, job: &mut Job, value: Value, arrow: &Arrow, arguments: Vec<Value>)
+        -> Option<Result<Value, Traced<RuntimeError>>> {
+        Some((|| {
+            let mut carry = arguments.into_iter().next().unwrap_or_default();
+
+            for (k, v) in try_traced!(pairs(job, "reduce", "the value", &value)) {
+                carry = try_traced!(arrow.call(job, vec![carry, v, k]));
+            }
+
+            Ok(carry)
+        })())
+    }
+}
+
+/// `value|url_encode` encodes a string as URI component, or a hash as query string.
+#[derive(Debug, Default)]
+pub struct UrlEncode;
+
+impl UrlEncode {
+    /// Appends the pairs of a hash to a query string, like PHP's `http_build_query()`.
+    fn query(query: &mut Vec<String>, prefix: Option<&str>, value: &Value) {
+        for (k, v) in value.to_pairs() {
+            let k = match prefix {
+                Some(prefix) => format!("{}[{}]", prefix, k),
+                None => k.to_string(),
+            };
+
+            match v {
+                Value::Null => {},
+                Value::Array(_) | Value::Hash(_) => UrlEncode::query(query, Some(&k), &v),
+                Value::Bool(b) => query.push(format!("{}={}", strategy::url(&k), b as i32)),
+                v => query.push(format!("{}={}", strategy::url(&k), strategy::url(&v.to_string()))),
+            }
+        }
+    }
+}
+
+impl Filter for UrlEncode {
+    fn apply(&self, _job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        Ok(Value::from(match value {
+            Value::Array(_) | Value::Hash(_) => {
+                let mut query = vec![];
+                UrlEncode::query(&mut query, None, &value);
+                query.join("&")
+            },
+            value => strategy::url(&value.to_string()),
+        }))
+    }
+}
+
+/// `value|json_encode(options = 0)` returns the JSON representation of a value, like PHP's
+/// `json_encode()`.
+///
+/// Supported options are `JSON_UNESCAPED_SLASHES` (64), `JSON_PRETTY_PRINT` (128) and
+/// `JSON_UNESCAPED_UNICODE` (256).
+#[derive(Debug, Default)]
+pub struct JsonEncode;
+
+const JSON_UNESCAPED_SLASHES: i64 = 64;
+const JSON_PRETTY_PRINT: i64 = 128;
+const JSON_UNESCAPED_UNICODE: i64 = 256;
+
+impl JsonEncode {
+    fn encode(output: &mut String, value: &Value, options: i64, depth: usize) -> Result<(), Traced<RuntimeError>> {
+        match *value {
+            Value::Null => output.push_str("null"),
+            Value::Bool(b) => output.push_str(if b { "true" } else { "false" }),
+            Value::Int(i) => output.push_str(&i.to_string()),
+            Value::Float(x) if !x.is_finite() => return traced_err!(RuntimeError::InvalidArgument {
+                reason: "Inf and NaN cannot be JSON encoded.".to_string(),
+            }),
+            Value::Float(x) if x.fract() == 0.0 && x.abs() < 1e15 => {
+                let _ = write!(output, "{:.1}", x);
+            },
+            Value::Float(x) => output.push_str(&x.to_string()),
+            Value::Str(ref s) => JsonEncode::string(output, s, options),
+            Value::Markup(ref m) => JsonEncode::string(output, m.content(), options),
+            Value::Array(_) | Value::Hash(_) => {
+                let pairs = value.to_pairs();
+                let is_list = pairs.iter().enumerate().all(|(i, (k, _))| k.to_string() == i.to_string());
+                let (open, close) = if is_list { ('[', ']') } else { ('{', '}') };
+
+                output.push(open);
+                for (i, (k, v)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        output.push(',');
+                    }
+                    JsonEncode::newline(output, options, depth + 1);
+                    if !is_list {
+                        JsonEncode::string(output, &k.to_string(), options);
+                        output.push_str(if options & JSON_PRETTY_PRINT != 0 { ": " } else { ":" });
+                    }
+                    try_traced!(JsonEncode::encode(output, v, options, depth + 1));
+                }
+                if !pairs.is_empty() {
+                    JsonEncode::newline(output, options, depth);
+                }
+                output.push(close);
+            },
+        }
+
+        Ok(())
+    }
+
+    fn newline(output: &mut String, options: i64, depth: usize) {
+        if options & JSON_PRETTY_PRINT != 0 {
+            output.push('\n');
+            output.push_str(&"    ".repeat(depth));
+        }
+    }
+
+    fn string(output: &mut String, s: &str, options: i64) {
+        output.push('"');
+
+        for c in s.chars() {
+            match c {
+                '"' => output.push_str("\\\""),
+                '\\' => output.push_str("\\\\"),
+                '/' if options & JSON_UNESCAPED_SLASHES == 0 => output.push_str("\\/"),
+                '\x08' => output.push_str("\\b"),
+                '\x0C' => output.push_str("\\f"),
+                '\n' => output.push_str("\\n"),
+                '\r' => output.push_str("\\r"),
+                '\t' => output.push_str("\\t"),
+                c if (c as u32) < 0x20 || ((c as u32) >= 0x80 && options & JSON_UNESCAPED_UNICODE == 0) => {
+                    let mut units = [0; 2];
+                    for unit in c.encode_utf16(&mut units) {
+                        let _ = write!(output, "\\u{:04x}", unit);
+                    }
+                },
+                c => output.push(c),
+            }
+        }
+
+        output.push('"');
+    }
+}
+
+impl Filter for JsonEncode {
+    fn apply(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let options = match arguments.first() {
+            Some(options) => try_traced!(int(job, "json_encode", "the options", options)),
+            None => 0,
+        };
+
+        let mut output = String::new();
+        try_traced!(JsonEncode::encode(&mut output, &value, options, 0));
+
+        Ok(Value::from(output))
+    }
+}
+
+/// `value|spaceless` removes whitespace between HTML tags.
+#[derive(Debug, Default)]
+pub struct Spaceless;
+
+impl Filter for Spaceless {
+    fn is_safe(&self, _arguments: &[Box<dyn Node>]) -> Vec<String> {
+        vec!["html".to_string()]
+    }
+
+    fn apply(&self, job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let s = try_traced!(string(job, "spaceless", "the value", &value));
+        let mut output = String::with_capacity(s.len());
+        let mut rest = s.trim();
+
+        while let Some(end) = rest.find('>') {
+            output.push_str(&rest[..end + 1]);
+            rest = &rest[end + 1..];
+
+            let trimmed = rest.trim_start();
+            if trimmed.starts_with('<') {
+                rest = trimmed;
+            }
+        }
+        output.push_str(rest);
+
+        Ok(Value::from(output))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use api::Value;
+    use api::value::Hash;
+    use extension::core::test::render;
+
+    fn data() -> Hash {
+        vec![
+            ("s", Value::from("hello wORLD")),
+            ("list", Value::from(vec![3, 1, 2])),
+            ("user", Value::Hash(vec![("name", Value::from("Tom")), ("age", Value::from(42))].into_iter().collect())),
+        ].into_iter().collect()
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(render("{{ s|upper }}|{{ s|lower }}|{{ s|title }}|{{ s|capitalize }}", data()), "HELLO WORLD|hello world|Hello World|Hello world");
+        assert_eq!(render("{{ '  a  '|trim }}|{{ '--a--'|trim('-', 'left') }}|{{ '--a--'|trim('-', 'right') }}", data()), "a|a--|--a");
+        assert_eq!(render("{{ s|length }}|{{ list|length }}|{{ user|length }}|{{ null|length }}", data()), "11|3|2|0");
+        assert_eq!(render("{{ 'a,b,c'|split(',')|join('|') }}|{{ 'a,b,c'|split(',', 2)|join('|') }}|{{ 'a,b,c'|split(',', -1)|join('|') }}|{{ 'abcde'|split('', 2)|join('|') }}", data()), "a|b|c|a|b,c|a|b|ab|cd|e");
+        assert_eq!(render("{{ list|join(', ', ' and ') }}|{{ s|slice(6) }}|{{ s|slice(-5, 2) }}|{{ s|first }}{{ s|last }}|{{ 'abc'|reverse }}", data()), "3, 1 and 2|wORLD|wO|hD|cba");
+        assert_eq!(render("{{ 'I like this and that.'|replace({'this': 'foo', 'that': 'bar', 'th': 'x'}) }}", data()), "I like foo and bar.");
+        assert_eq!(render("{{ '%s is %05.1f%% %-3d|%2$s'|format('x', 9.25, 7) }}", data()), "x is 009.3% 7  |9.25");
+        assert_eq!(render("{{ 'a%20b'|url_encode }}|{{ {'a': 1, 'b': 'x y', 'c': [true, null]}|url_encode|raw }}", data()), "a%2520b|a=1&b=x%20y&c%5B0%5D=1");
+    }
+
+    #[test]
+    fn collections() {
+        assert_eq!(render("{{ list|sort|join }}|{{ list|reverse|join }}|{{ list|slice(1, 1)|join }}|{{ list|first }}{{ list|last }}", data()), "123|213|1|32");
+        assert_eq!(render("{{ list|slice(1, 9223372036854775807)|join }}|{{ s|slice(-3, 9223372036854775807) }}", data()), "12|RLD");
+        assert_eq!(render("{{ user|keys|join(',') }}|{{ list|keys|join(',') }}", data()), "name,age|0,1,2");
+        assert_eq!(render("{{ list|merge([4])|join(',') }}|{% for k, v in user|merge({'age': 7, 'x': 1}) %}{{ k }}={{ v }};{% endfor %}", data()), "3,1,2,4|name=Tom;age=7;x=1;");
+        assert_eq!(render("{{ undefined|default('x') }}|{{ ''|default('y') }}|{{ 0|default('z') }}|{{ []|default }}", data()), "x|y|0|");
+        assert_eq!(render("{% for row in [1, 2, 3, 4, 5]|batch(2, 'x') %}{{ row|join }};{% endfor %}", data()), "12;34;5x;");
+        assert_eq!(render("{{ [user, {'name': 'Ann'}, {}]|column('name')|join(',') }}|{{ [user]|column('age', 'name')|keys|join }}", data()), "Tom,Ann|Tom");
+        assert_eq!(render("{{ (-5)|abs }}|{{ 2.5|round }}|{{ 1.2345|round(2) }}|{{ 1.21|round(1, 'ceil') }}|{{ (-1.5)|round(0, 'floor') }}", data()), "5|3|1.23|1.3|-2");
+        assert_eq!(render("{{ 1.5|round(-400) }}|{{ 5|round(-9223372036854775808) }}|{{ 1.5|round(-300) }}|{{ 1.5|round(400, 'ceil') }}|{{ 1234|round(-2) }}", data()), "0|0|0|1.5|1200");
+    }
+
+    #[test]
+    fn arrow_functions() {
+        assert_eq!(render("{{ list|map(x => x * 2)|join(',') }}|{{ list|filter(x => x > 1)|join(',') }}", data()), "6,2,4|3,2");
+        assert_eq!(render("{{ list|reduce((carry, x) => carry + x, 10) }}|{{ list|sort((a, b) => b - a)|join }}", data()), "16|321");
+        assert_eq!(render("{{ user|map((v, k) => k ~ ':' ~ v)|join(',') }}|{{ user|filter((v, k) => k == 'age')|keys|join }}", data()), "name:Tom,age:42|age");
+    }
+
+    #[test]
+    fn html() {
+        assert_eq!(render("{{ '<p>a <b>b</b><!-- c --> <br/>d</p>'|striptags('<b>')|raw }}", data()), "a <b>b</b> d");
+        assert_eq!(render("{{ '<a>\nb'|nl2br }}|{{ ' <ul>\n  <li> x </li>\n</ul> '|spaceless }}", data()), "&lt;a&gt;<br />\nb|<ul><li> x </li></ul>");
+        assert_eq!(render("{{ {'a': [1, 2.0, 'é/\"'], 'b': null}|json_encode|raw }}", data()), "{\"a\":[1,2.0,\"\\u00e9\\/\\\"\"],\"b\":null}");
+        assert_eq!(render("{{ [1, {'a': 'é/'}]|json_encode(128 b-or 64 b-or 256)|raw }}", data()),
+            "[\n    1,\n    {\n        \"a\": \"é/\"\n    }\n]");
+    }
+
+    #[test]
+    fn type_errors() {
+        use setup::Setup;
+        use extension::core::test::engine_with;
+
+        let mut twig = engine_with(Setup::default(), &[("index", "{% if true %}\n  {{ [1]|upper }}{% endif %}")]);
+        let error = twig.render("index", data()).unwrap_err().to_string();
+        assert!(error.contains("The filter \"upper\" expects the value to be a string, got array in template \"index\" at"), "{}", error);
+        assert!(error.contains(" at 2:"), "{}", error);
+
+        for source in &["{{ 'a'|merge([1]) }}", "{{ 'x'|abs }}", "{{ list|slice('a') }}", "{{ list|map('x') }}", "{{ list|upper(x => x) }}"] {
+            let mut twig = engine_with(Setup::default(), &[("index", source)]);
+            let error = twig.render("index", data()).unwrap_err().to_string();
+            assert!(error.contains("Invalid argument type."), "{}: {}", source, error);
+        }
+    }
+
+    #[test]
+    fn format_limits() {
+        use setup::Setup;
+        use extension::core::test::engine_with;
+
+        assert_eq!(render("{{ '%.1024f'|format(1)|length }}|{{ '%1024d'|format(1)|length }}", data()), "1026|1024");
+
+        for &(source, expected) in &[
+            ("{{ '%.999999999999f'|format(1) }}", "The precision of a conversion in format must not exceed 1024, got 999999999999."),
+            ("{{ '%999999999999d'|format(1) }}", "The width of a conversion in format must not exceed 1024, got 999999999999."),
+        ] {
+            let mut twig = engine_with(Setup::default(), &[("index", source)]);
+            let error = twig.render("index", data()).unwrap_err().to_string();
+            assert!(error.contains(expected), "{}: {}", source, error);
+        }
+    }
+}
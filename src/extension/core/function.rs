@@ -0,0 +1,494 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Functions of the core extension.
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash as StdHash, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+use api::{Node, Value};
+use api::value::Hash;
+use api::ext::Function;
+use api::runtime::{Job, RuntimeError, FrameKind};
+use api::error::Traced;
+use super::{date, operator};
+use super::random::Rng;
+
+/// Get all functions of the core extension, by name.
+///
+/// `rng` is the random number generator of the `random()` function.
+pub fn all(rng: Box<dyn Rng>) -> Vec<(String, Box<dyn Function>)> {
+    vec![
+        ("parent".to_string(), Box::new(Parent) as Box<dyn Function>),
+        ("block".to_string(), Box::new(Block)),
+        ("include".to_string(), Box::new(Include)),
+        ("source".to_string(), Box::new(Source)),
+        ("range".to_string(), Box::new(Range)),
+        ("cycle".to_string(), Box::new(Cycle)),
+        ("max".to_string(), Box::new(Max)),
+        ("min".to_string(), Box::new(Min)),
+        ("random".to_string(), Box::new(Random::new(rng))),
+        ("date".to_string(), Box::new(Date)),
+        ("attribute".to_string(), Box::new(Attribute)),
+        ("constant".to_string(), Box::new(Constant)),
+        ("template_from_string".to_string(), Box::new(TemplateFromString)),
+    ]
+}
+
+/// Gets the argument at `index`, or fails if it is missing.
+pub fn argument<'a>(function: &str, arguments: &'a [Value], index: usize) -> Result<&'a Value, Traced<RuntimeError>> {
+    match arguments.get(index) {
+        Some(argument) => Ok(argument),
+        None => traced_err!(RuntimeError::InvalidArgument {
+            reason: format!("Function {:?} expects at least {} arguments, {} given.", function, index + 1, arguments.len()),
+        }),
+    }
+}
+
+/// `parent()` renders the definition of the current block in the parent template.
+#[derive(Debug, Default)]
+pub struct Parent;
+
+impl Function for Parent {
+    fn is_safe(&self, _arguments: &[Box<dyn Node>]) -> Vec<String> {
+        vec!["all".to_string()]
+    }
+
+    fn call(&self, job: &mut Job, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let output = try_traced!(job.capture(|job| job.render_parent_block()));
+
+        Ok(Value::from(output))
+    }
+}
+
+/// `block('name')` renders a block of the current template, `block('name', 'template')` a
+/// block of another template.
+#[derive(Debug, Default)]
+pub struct Block;
+
+impl Function for Block {
+    fn is_safe(&self, _arguments: &[Box<dyn Node>]) -> Vec<String> {
+        vec!["all".to_string()]
+    }
+
+    fn call(&self, job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let name = try_traced!(argument("block", &arguments, 0)).to_string();
+
+        let output = match arguments.get(1) {
+            Some(template) => {
+                let position = job.position();
+                let template = try_traced!(job.load_template(&template.to_string(), &position));
+
+                try_traced!(job.capture(|job| job.render_block_of(&name, template)))
+            },
+            None => try_traced!(job.capture(|job| job.render_block(&name))),
+        };
+
+        Ok(Value::from(output))
+    }
+}
+
+/// `include(template, variables = {}, with_context = true, ignore_missing = false)` renders
+/// another template, like the `include` tag.
+#[derive(Debug, Default)]
+pub struct Include;
+
+impl Function for Include {
+    fn is_safe(&self, _arguments: &[Box<dyn Node>]) -> Vec<String> {
+        vec!["all".to_string()]
+    }
+
+    fn call(&self, job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let names = try_traced!(argument("include", &arguments, 0));
+        let variables = match arguments.get(1) {
+            Some(value @ &Value::Hash(_)) => value.to_hash(),
+            Some(&Value::Null) | None => Hash::new(),
+            Some(value) => return traced_err!(RuntimeError::InvalidArgument {
+                reason: format!("Variables passed to \"include\" must be a hash, got {}.", value.type_name()),
+            }),
+        };
+        let with_context = arguments.get(2).is_none_or(|v| v.to_bool());
+        let ignore_missing = arguments.get(3).is_some_and(|v| v.to_bool());
+
+        let position = job.position();
+        let template = match job.load_first_template(names, &position) {
+            Ok(template) => template,
+            Err(ref error) if ignore_missing && error.is_template_not_found() => return Ok(Value::from("")),
+            Err(error) => return Err(error),
+        };
+
+        let output = try_traced!(job.capture(|job| job.include(template, variables, with_context, FrameKind::Include, &position)));
+
+        Ok(Value::from(output))
+    }
+}
+
+/// `source(name, ignore_missing = false)` returns the content of a template without rendering it.
+#[derive(Debug, Default)]
+pub struct Source;
+
+impl Function for Source {
+    fn is_safe(&self, _arguments: &[Box<dyn Node>]) -> Vec<String> {
+        vec!["all".to_string()]
+    }
+
+    fn call(&self, job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let name = try_traced!(argument("source", &arguments, 0)).to_string();
+        let ignore_missing = arguments.get(1).is_some_and(|v| v.to_bool());
+
+        let position = job.position();
+        match job.template_source(&name, &position) {
+            Ok(source) => Ok(Value::from(source)),
+            Err(ref error) if ignore_missing && error.is_template_not_found() => Ok(Value::from("")),
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// `range(low, high, step = 1)` creates a list of numbers or characters, like the `..` operator.
+#[derive(Debug, Default)]
+pub struct Range;
+
+impl Function for Range {
+    fn call(&self, _job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let low = try_traced!(argument("range", &arguments, 0));
+        let high = try_traced!(argument("range", &arguments, 1));
+
+        operator::range_with_step(low, high, arguments.get(2).unwrap_or(&Value::Int(1)))
+    }
+}
+
+/// `cycle(values, position)` returns the element of a collection at `position`, starting over
+/// at the beginning after the last element.
+#[derive(Debug, Default)]
+pub struct Cycle;
+
+impl Function for Cycle {
+    fn call(&self, _job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let values = try_traced!(argument("cycle", &arguments, 0));
+        let position = try_traced!(argument("cycle", &arguments, 1)).to_int();
+
+        match *values {
+            Value::Array(_) | Value::Hash(_) => {
+                let mut values: Vec<Value> = values.to_pairs().into_iter().map(|(_, v)| v).collect();
+                if values.is_empty() {
+                    return traced_err!(RuntimeError::InvalidArgument {
+                        reason: "The \"cycle\" function does not work on empty collections.".to_string(),
+                    });
+                }
+
+                let index = position.rem_euclid(values.len() as i64) as usize;
+
+                Ok(values.swap_remove(index))
+            },
+            ref value => Ok(value.clone()),
+        }
+    }
+}
+
+/// Get the greatest or smallest of the arguments - or of the elements of a single collection.
+fn extremum(function: &str, arguments: Vec<Value>, ordering: Ordering) -> Result<Value, Traced<RuntimeError>> {
+    let values = match arguments.first() {
+        Some(collection @ &Value::Array(_)) | Some(collection @ &Value::Hash(_)) if arguments.len() == 1 => {
+            collection.to_pairs().into_iter().map(|(_, v)| v).collect()
+        },
+        _ => arguments,
+    };
+
+    let mut values = values.into_iter();
+    let mut extremum = match values.next() {
+        Some(value) => value,
+        None => return traced_err!(RuntimeError::InvalidArgument {
+            reason: format!("Function {:?} expects at least one value.", function),
+        }),
+    };
+
+    for value in values {
+        if value.compare(&extremum) == Some(ordering) {
+            extremum = value;
+        }
+    }
+
+    Ok(extremum)
+}
+
+/// `max(values..)` returns the greatest value, e.g. `max(1, 3, 2)` or `max([1, 3, 2])`.
+#[derive(Debug, Default)]
+pub struct Max;
+
+impl Function for Max {
+    fn call(&self, _job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        extremum("max", arguments, Ordering::Greater)
+    }
+}
+
+/// `min(values..)` returns the smallest value, e.g. `min(1, 3, 2)` or `min([1, 3, 2])`.
+#[derive(Debug, Default)]
+pub struct Min;
+
+impl Function for Min {
+    fn call(&self, _job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        extremum("min", arguments, Ordering::Less)
+    }
+}
+
+/// `random(values = null, max = null)` returns a random value:
+///
+/// * without arguments, a random integer between 0 and 2147483647
+/// * for a collection, a random element
+/// * for a string, a random character
+/// * for a number, an integer between 0 and the number - or between the number and `max`
+#[derive(Debug)]
+pub struct Random {
+    rng: RefCell<Box<dyn Rng>>,
+}
+
+impl Random {
+    pub fn new(rng: Box<dyn Rng>) -> Random {
+        Random {
+            rng: RefCell::new(rng),
+        }
+    }
+}
+
+impl Function for Random {
+    fn call(&self, _job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let mut rng = self.rng.borrow_mut();
+        let max = arguments.get(1).filter(|max| !max.is_null());
+
+        match arguments.first().unwrap_or(&Value::Null) {
+            &Value::Null if max.is_none() => Ok(Value::Int(rng.between(0, 2_147_483_647))),
+            values @ &Value::Array(_) | values @ &Value::Hash(_) => {
+                let mut values = values.to_pairs();
+                if values.is_empty() {
+                    return traced_err!(RuntimeError::InvalidArgument {
+                        reason: "The \"random\" function can not pick from an empty collection.".to_string(),
+                    });
+                }
+
+                let index = rng.between(0, values.len() as i64 - 1) as usize;
+
+                Ok(values.swap_remove(index).1)
+            },
+            value @ &Value::Str(_) | value @ &Value::Markup(_) => {
+                let chars: Vec<char> = value.to_string().chars().collect();
+                if chars.is_empty() {
+                    return Ok(Value::from(""));
+                }
+
+                Ok(Value::from(chars[rng.between(0, chars.len() as i64 - 1) as usize].to_string()))
+            },
+            value => Ok(Value::Int(match max {
+                Some(max) => rng.between(value.to_int(), max.to_int()),
+                None => rng.between(0, value.to_int()),
+            })),
+        }
+    }
+}
+
+/// Gets the UTC offset in seconds of a timezone argument, e.g. `'+02:00'`. Null is `UTC`.
+pub fn timezone_offset(timezone: Option<&Value>) -> Result<i64, Traced<RuntimeError>> {
+    match timezone {
+        Some(&Value::Null) | None => Ok(0),
+        Some(timezone) => match date::timezone(&timezone.to_string()) {
+            Ok(offset) => Ok(offset),
+            Err(reason) => traced_err!(RuntimeError::InvalidArgument { reason }),
+        },
+    }
+}
+
+/// Converts a date argument, i.e. a timestamp or a date string, to a Unix timestamp. Null is now.
+///
+/// `offset` is the UTC offset of dates without an explicit offset.
+pub fn timestamp(date: Option<&Value>, offset: i64) -> Result<i64, Traced<RuntimeError>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+    let timestamp = match date {
+        Some(&Value::Null) | None => Some(now),
+        Some(&Value::Int(timestamp)) => Some(timestamp),
+        Some(&Value::Float(timestamp)) => Some(timestamp as i64),
+        Some(value @ &Value::Str(_)) | Some(value @ &Value::Markup(_)) => date::parse(&value.to_string(), offset, now),
+        Some(_) => None,
+    };
+
+    match timestamp {
+        Some(timestamp) => Ok(timestamp),
+        None => traced_err!(RuntimeError::InvalidArgument {
+            reason: format!("Failed to parse date {}.", date.unwrap_or(&Value::Null)),
+        }),
+    }
+}
+
+/// `date(date = 'now', timezone = 'UTC')` converts a date to a Unix timestamp, e.g. to compare
+/// dates like `date(user.created_at) < date('-2 days')`. Use the `date` filter to format it.
+///
+/// See `twig::extension::core::date` for the supported formats. The timezone is `UTC` or an
+/// offset like `+02:00`, and applies to dates without an explicit offset.
+#[derive(Debug, Default)]
+pub struct Date;
+
+impl Function for Date {
+    fn call(&self, _job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let offset = try_traced!(timezone_offset(arguments.get(1)));
+
+        Ok(Value::Int(try_traced!(timestamp(arguments.first(), offset))))
+    }
+}
+
+/// `attribute(value, attribute)` gets an attribute of a value, e.g. for dynamic attribute
+/// names like `attribute(user, field)`.
+#[derive(Debug, Default)]
+pub struct Attribute;
+
+impl Function for Attribute {
+    fn call(&self, _job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let value = try_traced!(argument("attribute", &arguments, 0));
+        let attribute = try_traced!(argument("attribute", &arguments, 1));
+
+        Ok(value.attribute(attribute).cloned().unwrap_or_default())
+    }
+}
+
+/// `constant('NAME')` returns a constant defined via `Setup::add_constant()`.
+#[derive(Debug, Default)]
+pub struct Constant;
+
+impl Function for Constant {
+    fn call(&self, job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let name = try_traced!(argument("constant", &arguments, 0)).to_string();
+
+        match job.options().constant(&name) {
+            Some(constant) => Ok(constant.clone()),
+            None => traced_err!(RuntimeError::UnknownConstant {
+                name,
+                template: job.template_name().to_string(),
+                position: job.position(),
+            }),
+        }
+    }
+}
+
+/// `template_from_string(source, name = null)` compiles a template from a string and returns
+/// its name, e.g. for `include(template_from_string('Hello {{ name }}'))`.
+///
+/// The template is named after a hash of its source, so it does not shadow templates of the
+/// loader. The name is only used in error messages, like TwigPHP.
+#[derive(Debug, Default)]
+pub struct TemplateFromString;
+
+impl Function for TemplateFromString {
+    fn call(&self, job: &mut Job, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let source = try_traced!(argument("template_from_string", &arguments, 0)).to_string();
+
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        let name = match arguments.get(1) {
+            Some(&Value::Null) | None => format!("__string_template__{:016x}", hasher.finish()),
+            Some(name) => format!("{} (string template {:016x})", name, hasher.finish()),
+        };
+
+        let position = job.position();
+        try_traced!(job.add_template(&name, &source, &position));
+
+        Ok(Value::from(name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use setup::Setup;
+    use api::Value;
+    use api::value::Hash;
+    use extension::core::random::XorShift;
+    use extension::core::test::{engine_with, render};
+
+    fn data() -> Hash {
+        vec![
+            ("user", Value::Hash(vec![("name", "Tom"), ("role", "admin")].into_iter().collect())),
+            ("field", Value::from("role")),
+        ].into_iter().collect()
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(render("{{ range(1, 3)|join(',') }}|{{ range(0, 10, 5)|join(',') }}|{{ range('a', 'c')|join }}|{{ (1..3)|join }}", data()), "1,2,3|0,5,10|abc|123");
+
+        let mut twig = engine_with(Setup::default(), &[("index", "{{ range(1, 3, 0)|join }}")]);
+        assert!(twig.render("index", Hash::new()).unwrap_err().to_string().contains("must not be zero"));
+    }
+
+    #[test]
+    fn collections() {
+        assert_eq!(render("{% for i in 0..4 %}{{ cycle(['odd', 'even'], i) }},{% endfor %}{{ cycle('x', 3) }}", data()), "odd,even,odd,even,odd,x");
+        assert_eq!(render("{{ max(1, 3, 2) }}{{ max([1, 5]) }}{{ max({'a': 7, 'b': 4}) }}|{{ min(4, 2, 3) }}{{ min(['b', 'a']) }}", data()), "357|2a");
+        assert_eq!(render("{{ attribute(user, field) }}|{{ attribute(user, 'missing') }}|{{ attribute([1, 2], 1) }}", data()), "admin||2");
+    }
+
+    #[test]
+    fn random() {
+        let template = "{{ random() }}|{{ random(10) }}|{{ random(5, 7) }}|{{ random(['a', 'b', 'c']) }}|{{ random('xyz') }}";
+        let render_seeded = |seed| {
+            let setup = Setup::default().set_random_generator(Box::new(XorShift::new(seed)));
+
+            engine_with(setup, &[("index", template)]).render("index", Hash::new()).unwrap()
+        };
+
+        assert_eq!(render_seeded(42), render_seeded(42));
+
+        let output = render_seeded(7);
+        let parts: Vec<&str> = output.split('|').collect();
+        assert!(parts[0].parse::<i64>().is_ok_and(|n| (0..=2_147_483_647).contains(&n)), "{}", output);
+        assert!(parts[1].parse::<i64>().is_ok_and(|n| (0..=10).contains(&n)), "{}", output);
+        assert!(parts[2].parse::<i64>().is_ok_and(|n| (5..=7).contains(&n)), "{}", output);
+        assert!(["a", "b", "c"].contains(&parts[3]) && ["x", "y", "z"].contains(&parts[4]), "{}", output);
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(render("{{ date('2015-06-01 12:30') }}|{{ date('2015-06-01 14:30', '+02:00') }}|{{ date(1433161800) }}", data()), "1433161800|1433161800|1433161800");
+        assert_eq!(render("{{ date('-1 day') < date() }}{{ date('2015-06-01') < date('2015-06-01 +1 hour') }}", data()), "11");
+
+        assert_eq!(render("{{ date(1433161800)|date('Y-m-d H:i') }}|{{ '2015-06-01 12:30'|date('H:i', '+02:00') }}|{{ 1433161800|date }}", data()),
+            "2015-06-01 12:30|12:30|June 1, 2015 12:30");
+        assert_eq!(render("{{ 1433161800|date('H:i T', '-01:30') }}|{{ date()|date('Y') >= 2015 }}", data()), "11:00 -01:30|1");
+
+        let mut twig = engine_with(Setup::default(), &[
+            ("index", "{{ date('someday') }}"),
+            ("named", "{{ date('now', 'Europe/Paris') }}"),
+            ("filter", "{{ 'someday'|date('Y') }}"),
+            ("overflow", "{{ date('+9223372036854775807 days') }}"),
+        ]);
+        assert!(twig.render("index", Hash::new()).is_err());
+        assert!(twig.render("named", Hash::new()).unwrap_err().to_string().contains("Named timezones like \"Europe/Paris\" are not supported"));
+        assert!(twig.render("filter", Hash::new()).is_err());
+        assert!(twig.render("overflow", Hash::new()).unwrap_err().to_string().contains("Failed to parse date +9223372036854775807 days."));
+    }
+
+    #[test]
+    fn constants() {
+        let setup = Setup::default().add_constant("VERSION", "1.0");
+        let mut twig = engine_with(setup, &[("index", "{{ constant('VERSION') }}"), ("missing", "{{ constant('NOPE') }}")]);
+
+        assert_eq!(twig.render("index", Hash::new()).unwrap(), "1.0");
+        assert!(twig.render("missing", Hash::new()).unwrap_err().to_string().contains("The constant \"NOPE\" is not defined"));
+    }
+
+    #[test]
+    fn template_from_string() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("index", "{{ include(template_from_string('Hello {{ user.name }}')) }}|{% include template_from_string('{{ 1 + 1 }}', 'two') %}|{% include 'two' %}"),
+            ("two", "two"),
+            ("embed", "{% set t = template_from_string('{% embed \"two\" %}{% endembed %}!', 'e') %}{{ include(t) }}{{ include(t) }}"),
+            ("invalid", "{{ include(template_from_string('{% if %}', 'broken')) }}"),
+        ]);
+
+        assert_eq!(twig.render("index", data()).unwrap(), "Hello Tom|2|two");
+        assert_eq!(twig.render("embed", data()).unwrap(), "two!two!");
+        assert_eq!(twig.render("two", data()).unwrap(), "two");
+        let error = twig.render("invalid", Hash::new()).unwrap_err().to_string();
+        assert!(error.contains("broken (string template "), "{}", error);
+    }
+}
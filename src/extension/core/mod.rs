@@ -8,17 +8,34 @@
 use api::Extension;
 use api::error::Traced;
 use engine;
+use self::random::{Rng, XorShift};
 
-#[allow(dead_code)] // dummy
-#[derive(Default, Debug, PartialEq)]
-pub struct Core;
+pub mod date;
+pub mod filter;
+pub mod function;
+pub mod operator;
+pub mod random;
+pub mod tests;
+pub mod token_parser;
+
+#[derive(Default, Debug)]
+pub struct Core {
+    random_generator: Option<Box<dyn Rng>>,
+}
 
 impl Extension for Core {
     fn name(&self) -> &'static str { "core" }
 
-    fn init(&mut self, _registry: &mut engine::extension_registry::Builder, _options: &engine::Options)
+    fn init(&mut self, registry: &mut engine::extension_registry::Builder, _options: &engine::Options)
         -> Result<(), Traced<engine::ExtensionRegistryError>> {
-        // unimplemented!()
+        try_traced!(registry.push_token_parsers(token_parser::all()));
+        try_traced!(registry.push_filters(filter::all()));
+        let rng = self.random_generator.take().unwrap_or_else(|| Box::new(XorShift::from_time()));
+        try_traced!(registry.push_functions(function::all(rng)));
+        try_traced!(registry.push_tests(tests::all()));
+        try_traced!(registry.push_operators_unary(operator::unary()));
+        try_traced!(registry.push_operators_binary(operator::binary()));
+
         Ok(())
     }
 }
@@ -27,4 +44,118 @@ impl Core {
     pub fn new() -> Box<Core> {
         Box::new(Core::default())
     }
+
+    /// Sets the random number generator of the `random()` function.
+    pub fn set_random_generator(&mut self, rng: Box<dyn Rng>) {
+        self.random_generator = Some(rng);
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use setup::Setup;
+    use engine::{self, Engine};
+    use engine::extension_registry::Builder;
+    use loader;
+    use api::{Extension, Value};
+    use api::ext::{BinaryOperator, op};
+    use api::value::Hash;
+    use api::runtime::RuntimeError;
+    use api::error::Traced;
+
+    pub fn engine_with(setup: Setup, templates: &[(&str, &str)]) -> Engine {
+        let mut loader = loader::Array::new();
+        for &(name, source) in templates {
+            loader.set_template(name, source);
+        }
+
+        let mut twig = setup.init_engine().unwrap();
+        twig.set_loader(Box::new(loader));
+
+        twig
+    }
+
+    pub fn render(source: &str, data: Hash) -> String {
+        engine_with(Setup::default(), &[("index", source)]).render("index", data).unwrap()
+    }
+
+    #[test]
+    fn expressions() {
+        let data: Hash = vec![("name", Value::from("Twig")), ("list", Value::from(vec![1, 2]))].into_iter().collect();
+
+        assert_eq!(render("{{ 1 + 2 * 3 }}|{{ (1 + 2) * 3 }}|{{ 2 ** 3 ** 2 }}|{{ -2 ** 2 }}", Hash::new()), "7|9|512|4");
+        assert_eq!(render("{{ 'a' ~ \"b#{1 + 1}\" ~ name }}", data.clone()), "ab2Twig");
+        assert_eq!(render("{{ 3 not in list ? 'yes' }}|{{ name starts  with 'Tw' }}|{{ name ends with 'x' }}", data.clone()), "yes|1|");
+        assert_eq!(render("{{ false and undefined.x }}{{ true or undefined.x }}", Hash::new()), "1");
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let mut twig = engine_with(Setup::default(), &[("index", "{{ 1 / 0 }}")]);
+
+        assert!(twig.render("index", Hash::new()).is_err());
+    }
+
+    #[derive(Debug)]
+    struct Spaceship;
+
+    fn spaceship(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+        Ok(Value::from(left.compare(right).map_or(0, |o| o as i64)))
+    }
+
+    impl Extension for Spaceship {
+        fn name(&self) -> &'static str { "spaceship" }
+
+        fn init(&mut self, registry: &mut Builder, _options: &engine::Options)
+            -> Result<(), Traced<engine::ExtensionRegistryError>> {
+            registry.push_operators_binary(vec![BinaryOperator {
+                repr: "<=>".to_string(),
+                ext: op::Extension("spaceship".to_string()),
+                prec: op::Precedence(20),
+                op: op::Operation::Callable(op::Function::binary("spaceship", spaceship)),
+                assoc: op::Assoc::Left,
+            }])
+        }
+    }
+
+    #[test]
+    fn custom_operator() {
+        let setup = Setup::default().add_extension(Box::new(Spaceship));
+        let mut twig = engine_with(setup, &[("index", "{{ 1 <=> 2 }}{{ 2 <=> 2 }}{{ 3<=>2 }}")]);
+
+        assert_eq!(twig.render("index", Hash::new()).unwrap(), "-101");
+    }
+
+    /// Operator with configuration - `a ~= b` if both differ by at most `tolerance`.
+    #[derive(Debug)]
+    struct Approx {
+        tolerance: f64,
+    }
+
+    impl Extension for Approx {
+        fn name(&self) -> &'static str { "approx" }
+
+        fn init(&mut self, registry: &mut Builder, _options: &engine::Options)
+            -> Result<(), Traced<engine::ExtensionRegistryError>> {
+            let tolerance = self.tolerance;
+
+            registry.push_operators_binary(vec![BinaryOperator {
+                repr: "~=".to_string(),
+                ext: op::Extension("approx".to_string()),
+                prec: op::Precedence(20),
+                op: op::Operation::Callable(op::Function::binary("approx", move |left: &Value, right: &Value| {
+                    Ok(Value::from((left.to_float() - right.to_float()).abs() <= tolerance))
+                })),
+                assoc: op::Assoc::Left,
+            }])
+        }
+    }
+
+    #[test]
+    fn custom_operator_with_state() {
+        let setup = Setup::default().add_extension(Box::new(Approx { tolerance: 0.5 }));
+        let mut twig = engine_with(setup, &[("index", "{{ 1 ~= 1.4 ? 'y' : 'n' }}{{ 1 ~= 1.6 ? 'y' : 'n' }}")]);
+
+        assert_eq!(twig.render("index", Hash::new()).unwrap(), "yn");
+    }
 }
@@ -0,0 +1,545 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Operators of the core extension.
+
+use regex::Regex;
+use api::{Node, Value};
+use api::node::{self, NodeError, Test, Unary};
+use api::ext::{UnaryOperator, BinaryOperator};
+use api::ext::op::{self, Operation, Assoc, Precedence, Factory, Class, Function};
+use api::parser::{Job, ParserError};
+use api::runtime::{self, RuntimeError};
+use api::token::{Token, Type, Punctuation, BracketType};
+use api::token::stream::Position;
+use api::error::{suggest, Traced};
+
+/// Get the unary operators of the core extension.
+pub fn unary() -> Vec<UnaryOperator> {
+    vec![
+        unary_operator("not", 50, Function::unary("not", not)),
+        unary_operator("-", 500, Function::unary("neg", neg)),
+        unary_operator("+", 500, Function::unary("pos", pos)),
+    ]
+}
+
+/// Get the binary operators of the core extension.
+pub fn binary() -> Vec<BinaryOperator> {
+    vec![
+        binary_operator("or", 10, Operation::Class(Class::new("or", Factory::Binary(Or::factory)))),
+        binary_operator("and", 15, Operation::Class(Class::new("and", Factory::Binary(And::factory)))),
+        binary_operator("b-or", 16, Operation::Callable(Function::binary("bitwise_or", bitwise_or))),
+        binary_operator("b-xor", 17, Operation::Callable(Function::binary("bitwise_xor", bitwise_xor))),
+        binary_operator("b-and", 18, Operation::Callable(Function::binary("bitwise_and", bitwise_and))),
+        binary_operator("==", 20, Operation::Callable(Function::binary("equal", equal))),
+        binary_operator("!=", 20, Operation::Callable(Function::binary("not_equal", not_equal))),
+        binary_operator("<", 20, Operation::Callable(Function::binary("less", less))),
+        binary_operator(">", 20, Operation::Callable(Function::binary("greater", greater))),
+        binary_operator(">=", 20, Operation::Callable(Function::binary("greater_equal", greater_equal))),
+        binary_operator("<=", 20, Operation::Callable(Function::binary("less_equal", less_equal))),
+        binary_operator("not in", 20, Operation::Callable(Function::binary("not_in", not_in))),
+        binary_operator("in", 20, Operation::Callable(Function::binary("in", in_))),
+        binary_operator("matches", 20, Operation::Callable(Function::binary("matches", matches))),
+        binary_operator("starts with", 20, Operation::Callable(Function::binary("starts_with", starts_with))),
+        binary_operator("ends with", 20, Operation::Callable(Function::binary("ends_with", ends_with))),
+        binary_operator("..", 25, Operation::Callable(Function::binary("range", range))),
+        binary_operator("+", 30, Operation::Callable(Function::binary("add", add))),
+        binary_operator("-", 30, Operation::Callable(Function::binary("sub", sub))),
+        binary_operator("~", 40, Operation::Callable(Function::binary("concat", concat))),
+        binary_operator("*", 60, Operation::Callable(Function::binary("mul", mul))),
+        binary_operator("/", 60, Operation::Callable(Function::binary("div", div))),
+        binary_operator("//", 60, Operation::Callable(Function::binary("floor_div", floor_div))),
+        binary_operator("%", 60, Operation::Callable(Function::binary("rem", rem))),
+        binary_operator("is", 100, Operation::Class(Class::new("is", Factory::Parser(parse_test)))),
+        binary_operator("is not", 100, Operation::Class(Class::new("is not", Factory::Parser(parse_not_test)))),
+        BinaryOperator {
+            assoc: Assoc::Right,
+            ..binary_operator("**", 200, Operation::Callable(Function::binary("pow", pow)))
+        },
+    ]
+}
+
+fn unary_operator(repr: &str, prec: usize, function: Function) -> UnaryOperator {
+    UnaryOperator {
+        repr: repr.to_string(),
+        ext: op::Extension("core".to_string()),
+        prec: Precedence(prec),
+        op: Operation::Callable(function),
+    }
+}
+
+fn binary_operator(repr: &str, prec: usize, op: Operation) -> BinaryOperator {
+    BinaryOperator {
+        repr: repr.to_string(),
+        ext: op::Extension("core".to_string()),
+        prec: Precedence(prec),
+        op,
+        assoc: Assoc::Left,
+    }
+}
+
+/// Short-circuiting `and` operator.
+#[derive(Debug)]
+pub struct And {
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl And {
+    pub fn factory(left: Box<dyn Node>, right: Box<dyn Node>, position: Position) -> Box<dyn Node> {
+        Box::new(And {
+            position,
+            children: vec![left, right],
+        })
+    }
+}
+
+impl Node for And {
+    fn tag(&self) -> &str { "and" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "operator" => Ok(Value::from("and")),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn evaluate(&self, job: &mut runtime::Job) -> Result<Value, Traced<RuntimeError>> {
+        Ok(Value::from(try_traced!(self.children[0].evaluate(job)).to_bool()
+            && try_traced!(self.children[1].evaluate(job)).to_bool()))
+    }
+}
+
+/// Short-circuiting `or` operator.
+#[derive(Debug)]
+pub struct Or {
+    position: Position,
+    children: Vec<Box<dyn Node>>,
+}
+
+impl Or {
+    pub fn factory(left: Box<dyn Node>, right: Box<dyn Node>, position: Position) -> Box<dyn Node> {
+        Box::new(Or {
+            position,
+            children: vec![left, right],
+        })
+    }
+}
+
+impl Node for Or {
+    fn tag(&self) -> &str { "or" }
+    fn position(&self) -> &Position { &self.position }
+    fn children(&self) -> &Vec<Box<dyn Node>> { &self.children }
+    fn children_mut(&mut self) -> &mut Vec<Box<dyn Node>> { &mut self.children }
+
+    fn attribute(&self, key: &str) -> Result<Value, Traced<NodeError>> {
+        match key {
+            "operator" => Ok(Value::from("or")),
+            _ => node::attribute_not_found(self.tag(), key),
+        }
+    }
+
+    fn evaluate(&self, job: &mut runtime::Job) -> Result<Value, Traced<RuntimeError>> {
+        Ok(Value::from(try_traced!(self.children[0].evaluate(job)).to_bool()
+            || try_traced!(self.children[1].evaluate(job)).to_bool()))
+    }
+}
+
+/// Parses `value is test` and `value is test(arguments)`.
+pub fn parse_test(job: &mut Job, node: Box<dyn Node>, position: Position) -> Result<Box<dyn Node>, Traced<ParserError>> {
+    let item = try_traced!(job.cursor().expect(Type::Name, Some("A test name was expected.")));
+    let name = item.token().value_as_str().unwrap_or_default().to_string();
+    let ext = job.ext().clone();
+
+    // tests made of two words, e.g. `divisible by`
+    let name = match *job.cursor().current().token() {
+        Token::Name(ref second) if ext.tests().contains_key(&format!("{} {}", name, second)) => {
+            job.cursor().next();
+            format!("{} {}", name, second)
+        },
+        _ => name,
+    };
+
+    if !ext.tests().contains_key(&name) {
+        return traced_err!(ParserError::UnknownTest {
+            similar: suggest::similar_words(&name, ext.tests().keys().map(|k| &k[..])),
+            name,
+            position: item.position().clone(),
+        });
+    }
+
+    let arguments = if job.cursor().test(Token::Punctuation(Punctuation::OpeningBracket(BracketType::Round))) {
+        try_traced!(job.parse_arguments())
+    } else {
+        vec![]
+    };
+
+    Ok(Test::new(name, node, arguments, position))
+}
+
+/// Parses `value is not test`.
+pub fn parse_not_test(job: &mut Job, node: Box<dyn Node>, position: Position) -> Result<Box<dyn Node>, Traced<ParserError>> {
+    let test = try_traced!(parse_test(job, node, position.clone()));
+
+    Ok(Unary::new("not", test, position))
+}
+
+fn not(value: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::from(!value.to_bool()))
+}
+
+fn neg(value: &Value) -> Result<Value, Traced<RuntimeError>> {
+    match number("-", value) {
+        Ok(Value::Int(i)) => Ok(i.checked_neg().map_or(Value::Float(-(i as f64)), Value::Int)),
+        Ok(Value::Float(x)) => Ok(Value::Float(-x)),
+        result => result,
+    }
+}
+
+fn pos(value: &Value) -> Result<Value, Traced<RuntimeError>> {
+    number("+", value)
+}
+
+/// Converts an operand to a number, or fails with unsupported operand types.
+fn number(operator: &str, value: &Value) -> Result<Value, Traced<RuntimeError>> {
+    match value.to_number() {
+        Some(number) => Ok(number),
+        None => traced_err!(RuntimeError::UnsupportedOperandTypes {
+            operator: operator.to_string(),
+            types: vec![value.type_name()],
+        }),
+    }
+}
+
+/// Applies an arithmetic operation to integers (falling back to floats on overflow) or floats.
+fn arithmetic<I, F>(operator: &str, left: &Value, right: &Value, int: I, float: F) -> Result<Value, Traced<RuntimeError>> where
+    I: Fn(i64, i64) -> Option<i64>,
+    F: Fn(f64, f64) -> f64
+{
+    let (a, b) = match (left.to_number(), right.to_number()) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return traced_err!(RuntimeError::UnsupportedOperandTypes {
+            operator: operator.to_string(),
+            types: vec![left.type_name(), right.type_name()],
+        }),
+    };
+
+    Ok(match (a, b) {
+        (Value::Int(a), Value::Int(b)) => match int(a, b) {
+            Some(i) => Value::Int(i),
+            None => Value::Float(float(a as f64, b as f64)),
+        },
+        (a, b) => Value::Float(float(a.to_float(), b.to_float())),
+    })
+}
+
+fn is_zero(value: &Value) -> bool {
+    match value.to_number() {
+        Some(Value::Int(0)) => true,
+        Some(Value::Float(x)) => x == 0.0,
+        _ => false,
+    }
+}
+
+fn division_by_zero(operator: &str) -> Result<Value, Traced<RuntimeError>> {
+    traced_err!(RuntimeError::DivisionByZero {
+        operator: operator.to_string(),
+    })
+}
+
+fn add(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    arithmetic("+", left, right, i64::checked_add, |a, b| a + b)
+}
+
+fn sub(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    arithmetic("-", left, right, i64::checked_sub, |a, b| a - b)
+}
+
+fn mul(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    arithmetic("*", left, right, i64::checked_mul, |a, b| a * b)
+}
+
+fn div(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    if is_zero(right) {
+        return division_by_zero("/");
+    }
+
+    // like PHP: integer result only for exact divisions
+    arithmetic("/", left, right, |a, b| if a % b == 0 { a.checked_div(b) } else { None }, |a, b| a / b)
+}
+
+fn floor_div(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    if is_zero(right) {
+        return division_by_zero("//");
+    }
+
+    match try_traced!(arithmetic("//", left, right, |a, b| a.checked_div_euclid(b).map(|q| if b < 0 && a % b != 0 { q + 1 } else { q }), |a, b| (a / b).floor())) {
+        Value::Float(x) if x.abs() < 9.2e18 => Ok(Value::Int(x as i64)),
+        value => Ok(value),
+    }
+}
+
+fn rem(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    let (a, b) = (left.to_int(), right.to_int());
+
+    if b == 0 {
+        return division_by_zero("%");
+    }
+
+    // like PHP: operands are converted to integers, the sign follows the dividend
+    Ok(Value::Int(a.checked_rem(b).unwrap_or(0)))
+}
+
+fn pow(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    arithmetic("**", left, right,
+        |a, b| if b >= 0 && b <= u32::MAX as i64 { a.checked_pow(b as u32) } else { None },
+        f64::powf)
+}
+
+fn bitwise_or(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::Int(left.to_int() | right.to_int()))
+}
+
+fn bitwise_xor(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::Int(left.to_int() ^ right.to_int()))
+}
+
+fn bitwise_and(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::Int(left.to_int() & right.to_int()))
+}
+
+fn equal(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::from(left.loose_eq(right)))
+}
+
+fn not_equal(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::from(!left.loose_eq(right)))
+}
+
+fn less(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::from(left.compare(right).is_some_and(|o| o.is_lt())))
+}
+
+fn greater(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::from(left.compare(right).is_some_and(|o| o.is_gt())))
+}
+
+fn less_equal(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::from(left.compare(right).is_some_and(|o| o.is_le())))
+}
+
+fn greater_equal(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::from(left.compare(right).is_some_and(|o| o.is_ge())))
+}
+
+/// Returns true if `needle` is contained in a collection or a string.
+pub fn contains(haystack: &Value, needle: &Value) -> bool {
+    match *haystack {
+        Value::Array(ref a) => a.iter().any(|v| v.loose_eq(needle)),
+        Value::Hash(ref h) => h.values().into_iter().any(|v| v.loose_eq(needle)),
+        Value::Str(ref s) => match *needle {
+            Value::Array(_) | Value::Hash(_) => false,
+            _ => s.contains(&needle.to_string()[..]),
+        },
+        _ => false,
+    }
+}
+
+fn in_(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::from(contains(right, left)))
+}
+
+fn not_in(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::from(!contains(right, left)))
+}
+
+fn starts_with(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::from(left.to_string().starts_with(&right.to_string()[..])))
+}
+
+fn ends_with(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::from(left.to_string().ends_with(&right.to_string()[..])))
+}
+
+/// Matches a PHP-like regular expression with delimiters, e.g. `'/^[a-z]+$/i'`.
+fn matches(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    let pattern = right.to_string();
+    let regex = try_traced!(regex(&pattern));
+
+    Ok(Value::from(regex.is_match(&left.to_string())))
+}
+
+/// Converts a PHP-like regular expression with delimiters and flags.
+pub fn regex(pattern: &str) -> Result<Regex, Traced<RuntimeError>> {
+    let invalid = |reason: &str| traced_err!(RuntimeError::InvalidRegex {
+        pattern: pattern.to_string(),
+        reason: reason.to_string(),
+    });
+
+    let delimiter = match pattern.chars().next() {
+        Some(c) if !c.is_alphanumeric() && c != '\\' && !c.is_whitespace() => c,
+        _ => return invalid("Delimiter must not be alphanumeric, backslash or whitespace"),
+    };
+    let closing = match delimiter {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        '<' => '>',
+        c => c,
+    };
+
+    let end = match pattern.rfind(closing) {
+        Some(end) if end > 0 => end,
+        _ => return invalid("No ending delimiter"),
+    };
+
+    let mut flags = String::new();
+    for flag in pattern[end + closing.len_utf8()..].chars() {
+        match flag {
+            'i' | 'm' | 's' | 'x' | 'U' => flags.push(flag),
+            'u' => {},
+            _ => return invalid(&format!("Unknown modifier {:?}", flag)),
+        }
+    }
+
+    let expression = &pattern[delimiter.len_utf8()..end];
+    let expression = if flags.is_empty() {
+        expression.to_string()
+    } else {
+        format!("(?{}){}", flags, expression)
+    };
+
+    match Regex::new(&expression) {
+        Ok(regex) => Ok(regex),
+        Err(error) => invalid(&error.to_string()),
+    }
+}
+
+fn concat(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    Ok(Value::from(format!("{}{}", left, right)))
+}
+
+/// Creates a list of integers or characters, e.g. `1..3` or `'a'..'c'`.
+pub fn range(left: &Value, right: &Value) -> Result<Value, Traced<RuntimeError>> {
+    range_with_step(left, right, &Value::Int(1))
+}
+
+/// Creates a list of numbers or characters, like PHP `range()`.
+pub fn range_with_step(low: &Value, high: &Value, step: &Value) -> Result<Value, Traced<RuntimeError>> {
+    const MAX_LENGTH: usize = 1_000_000;
+
+    let too_long = || traced_err!(RuntimeError::InvalidArgument {
+        reason: format!("Range from {} to {} exceeds the maximum length of {} elements.", low, high, MAX_LENGTH),
+    });
+    let zero_step = || traced_err!(RuntimeError::InvalidArgument {
+        reason: format!("The step of the range from {} to {} must not be zero.", low, high),
+    });
+
+    // character ranges
+    if let (Some(a), Some(b)) = (single_char(low), single_char(high)) {
+        let step = match step.to_int().unsigned_abs() {
+            0 => return zero_step(),
+            step => step as usize,
+        };
+        let (from, to) = (a as u32, b as u32);
+        let chars: Vec<Value> = if from <= to {
+            (from..to + 1).step_by(step).filter_map(::std::char::from_u32).map(|c| Value::from(c.to_string())).collect()
+        } else {
+            (to..from + 1).rev().step_by(step).filter_map(::std::char::from_u32).map(|c| Value::from(c.to_string())).collect()
+        };
+
+        return Ok(Value::Array(chars));
+    }
+
+    let a = try_traced!(number("..", low));
+    let b = try_traced!(number("..", high));
+    let s = try_traced!(number("..", step));
+
+    match (a, b, s) {
+        (Value::Int(a), Value::Int(b), Value::Int(s)) => {
+            let step = match s.unsigned_abs() {
+                0 => return zero_step(),
+                step => step,
+            };
+            if (a.abs_diff(b) / step) as usize >= MAX_LENGTH {
+                return too_long();
+            }
+
+            let values: Vec<Value> = if a <= b {
+                (a..=b).step_by(step as usize).map(Value::Int).collect()
+            } else {
+                (b..=a).rev().step_by(step as usize).map(Value::Int).collect()
+            };
+
+            Ok(Value::Array(values))
+        },
+        (a, b, s) => {
+            let (a, b) = (a.to_float(), b.to_float());
+            let step = s.to_float().abs();
+            if step == 0.0 {
+                return zero_step();
+            }
+            let count = ((a - b).abs() / step).floor();
+
+            if !count.is_finite() || count as usize >= MAX_LENGTH {
+                return too_long();
+            }
+
+            let direction = if a <= b { 1.0 } else { -1.0 };
+
+            Ok(Value::Array((0..count as usize + 1).map(|i| Value::Float(a + direction * step * i as f64)).collect()))
+        },
+    }
+}
+
+/// Returns the character of non-numeric strings with exactly one character.
+fn single_char(value: &Value) -> Option<char> {
+    match *value {
+        Value::Str(ref s) if s.chars().count() == 1 && value.to_number().is_none() => s.chars().next(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(add(&Value::from(1), &Value::from("2")).unwrap(), Value::from(3));
+        assert_eq!(add(&Value::from(i64::MAX), &Value::from(1)).unwrap(), Value::from(i64::MAX as f64 + 1.0));
+        assert_eq!(div(&Value::from(6), &Value::from(3)).unwrap(), Value::from(2));
+        assert_eq!(div(&Value::from(7), &Value::from(2)).unwrap(), Value::from(3.5));
+        assert_eq!(floor_div(&Value::from(-7), &Value::from(2)).unwrap(), Value::from(-4));
+        assert_eq!(rem(&Value::from(-7), &Value::from(2)).unwrap(), Value::from(-1));
+        assert_eq!(pow(&Value::from(2), &Value::from(-1)).unwrap(), Value::from(0.5));
+        assert!(div(&Value::from(1), &Value::from(0)).is_err());
+
+        match *add(&Value::from("a"), &Value::from(1)).unwrap_err() {
+            RuntimeError::UnsupportedOperandTypes { ref types, .. } => assert_eq!(*types, vec!["string", "integer"]),
+            ref error => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(range(&Value::from(3), &Value::from(1)).unwrap(), Value::from(vec![3, 2, 1]));
+        assert_eq!(range(&Value::from("a"), &Value::from("c")).unwrap(), Value::from(vec!["a", "b", "c"]));
+        assert!(range(&Value::from(0), &Value::from(i64::MAX)).is_err());
+        assert!(range_with_step(&Value::from(1), &Value::from(3), &Value::from(0)).is_err());
+        assert!(range_with_step(&Value::from(1.5), &Value::from(3), &Value::from(0.0)).is_err());
+        assert!(range_with_step(&Value::from("a"), &Value::from("c"), &Value::from(0)).is_err());
+    }
+
+    #[test]
+    fn regular_expressions() {
+        assert_eq!(matches(&Value::from("Twig"), &Value::from("/^t/i")).unwrap(), Value::from(true));
+        assert_eq!(matches(&Value::from("Twig"), &Value::from("{^t}")).unwrap(), Value::from(false));
+        assert!(matches(&Value::from("Twig"), &Value::from("^t")).is_err());
+    }
+}
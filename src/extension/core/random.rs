@@ -0,0 +1,78 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Random number generators for the `random()` function.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Source of random numbers for the `random()` function.
+///
+/// A custom generator can be registered via `Setup::set_random_generator()`, e.g. a seeded
+/// `XorShift` to get reproducible output in tests.
+pub trait Rng : fmt::Debug {
+    /// Get the next random number.
+    fn next_u64(&mut self) -> u64;
+
+    /// Get a random number between `min` and `max` (both inclusive).
+    fn between(&mut self, min: i64, max: i64) -> i64 {
+        let (min, max) = if min <= max { (min, max) } else { (max, min) };
+        let range = (max as i128 - min as i128 + 1) as u128;
+
+        (min as i128 + (self.next_u64() as u128 % range) as i128) as i64
+    }
+}
+
+/// Xorshift* generator - fast, seedable and good enough for templates, but not
+/// cryptographically secure.
+///
+/// # Examples
+///
+/// ```
+/// use twig::extension::core::random::{Rng, XorShift};
+///
+/// let (mut a, mut b) = (XorShift::new(42), XorShift::new(42));
+///
+/// assert_eq!(a.next_u64(), b.next_u64());
+/// assert!((1..=6).contains(&a.between(1, 6)));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    /// Creates a generator from a seed. Generators with the same seed yield the same numbers.
+    pub fn new(seed: u64) -> XorShift {
+        XorShift {
+            // the state must not be zero
+            state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed },
+        }
+    }
+
+    /// Creates a generator seeded from the system time.
+    pub fn from_time() -> XorShift {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() ^ (d.subsec_nanos() as u64) << 32);
+
+        XorShift::new(seed)
+    }
+}
+
+impl Default for XorShift {
+    fn default() -> XorShift {
+        XorShift::from_time()
+    }
+}
+
+impl Rng for XorShift {
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
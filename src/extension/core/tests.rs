@@ -0,0 +1,230 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Tests of the core extension, e.g. `value is defined`.
+
+use api::{Node, Value};
+use api::ext::Test;
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+
+/// Get all tests of the core extension, by name.
+pub fn all() -> Vec<(String, Box<dyn Test>)> {
+    vec![
+        ("defined".to_string(), Box::new(Defined) as Box<dyn Test>),
+        ("null".to_string(), Box::new(Null)),
+        ("none".to_string(), Box::new(Null)),
+        ("empty".to_string(), Box::new(Empty)),
+        ("even".to_string(), Box::new(Even)),
+        ("odd".to_string(), Box::new(Odd)),
+        ("iterable".to_string(), Box::new(Iterable)),
+        ("divisible by".to_string(), Box::new(DivisibleBy)),
+        ("same as".to_string(), Box::new(SameAs)),
+        ("constant".to_string(), Box::new(Constant)),
+    ]
+}
+
+/// Fails because the tested value or an argument of a test has the wrong type.
+fn type_error<T>(job: &Job, test: &str, argument: &str, expected: &str, value: &Value) -> Result<T, Traced<RuntimeError>> {
+    traced_err!(RuntimeError::InvalidArgumentType {
+        callable: format!("test {:?}", test),
+        argument: argument.to_string(),
+        expected: expected.to_string(),
+        found: value.type_name(),
+        template: job.template_name().to_string(),
+        position: job.position(),
+    })
+}
+
+/// Converts a value to an integer, or fails if it is not numeric.
+fn int(job: &Job, test: &str, argument: &str, value: &Value) -> Result<i64, Traced<RuntimeError>> {
+    match value.to_number() {
+        Some(number) if !value.is_null() => Ok(number.to_int()),
+        _ => type_error(job, test, argument, "a number", value),
+    }
+}
+
+/// Gets the argument at `index`, or fails if it is missing.
+fn argument<'a>(test: &str, arguments: &'a [Value], index: usize) -> Result<&'a Value, Traced<RuntimeError>> {
+    match arguments.get(index) {
+        Some(argument) => Ok(argument),
+        None => traced_err!(RuntimeError::InvalidArgument {
+            reason: format!("Test {:?} expects at least {} arguments, {} given.", test, index + 1, arguments.len()),
+        }),
+    }
+}
+
+/// `foo is defined` checks if a variable - or an attribute, e.g. `foo.bar is defined` - exists.
+///
+/// Undefined variables do not raise errors, even with strict variables.
+#[derive(Debug, Default)]
+pub struct Defined;
+
+impl Test for Defined {
+    fn test(&self, _job: &mut Job, _value: Value, _arguments: Vec<Value>) -> Result<bool, Traced<RuntimeError>> {
+        Ok(true)
+    }
+
+    fn test_node(&self, job: &mut Job, node: &dyn Node, _arguments: &[Value])
+        -> Option<Result<bool, Traced<RuntimeError>>> {
+        Some(node.is_defined(job))
+    }
+}
+
+/// `value is null` (or `value is none`) checks if a value is null.
+#[derive(Debug, Default)]
+pub struct Null;
+
+impl Test for Null {
+    fn test(&self, _job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<bool, Traced<RuntimeError>> {
+        Ok(value.is_null())
+    }
+}
+
+/// `value is empty` checks if a value is null, false, an empty string or an empty collection.
+#[derive(Debug, Default)]
+pub struct Empty;
+
+impl Test for Empty {
+    fn test(&self, _job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<bool, Traced<RuntimeError>> {
+        Ok(match value {
+            Value::Null | Value::Bool(false) => true,
+            Value::Str(ref s) => s.is_empty(),
+            Value::Markup(ref m) => m.content().is_empty(),
+            Value::Array(ref a) => a.is_empty(),
+            Value::Hash(ref h) => h.is_empty(),
+            _ => false,
+        })
+    }
+}
+
+/// `number is even` checks if a number is even.
+#[derive(Debug, Default)]
+pub struct Even;
+
+impl Test for Even {
+    fn test(&self, job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<bool, Traced<RuntimeError>> {
+        Ok(try_traced!(int(job, "even", "the value", &value)) % 2 == 0)
+    }
+}
+
+/// `number is odd` checks if a number is odd.
+#[derive(Debug, Default)]
+pub struct Odd;
+
+impl Test for Odd {
+    fn test(&self, job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<bool, Traced<RuntimeError>> {
+        Ok(try_traced!(int(job, "odd", "the value", &value)) % 2 != 0)
+    }
+}
+
+/// `value is iterable` checks if a value is an array or hash.
+#[derive(Debug, Default)]
+pub struct Iterable;
+
+impl Test for Iterable {
+    fn test(&self, _job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<bool, Traced<RuntimeError>> {
+        Ok(matches!(value, Value::Array(_) | Value::Hash(_)))
+    }
+}
+
+/// `number is divisible by(divisor)` checks if a number is divisible by another number.
+#[derive(Debug, Default)]
+pub struct DivisibleBy;
+
+impl Test for DivisibleBy {
+    fn test(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<bool, Traced<RuntimeError>> {
+        let value = try_traced!(int(job, "divisible by", "the value", &value));
+        let divisor = try_traced!(argument("divisible by", &arguments, 0));
+
+        match try_traced!(int(job, "divisible by", "the divisor", divisor)) {
+            0 => traced_err!(RuntimeError::DivisionByZero {
+                operator: "divisible by".to_string(),
+            }),
+            divisor => Ok(value.wrapping_rem(divisor) == 0),
+        }
+    }
+}
+
+/// `value is same as(other)` checks if two values are identical, i.e. of the same type and
+/// equal (like `===` in PHP).
+#[derive(Debug, Default)]
+pub struct SameAs;
+
+impl Test for SameAs {
+    fn test(&self, _job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<bool, Traced<RuntimeError>> {
+        Ok(value == *try_traced!(argument("same as", &arguments, 0)))
+    }
+}
+
+/// `value is constant('NAME')` checks if a value is identical to a constant defined via
+/// `Setup::add_constant()`.
+#[derive(Debug, Default)]
+pub struct Constant;
+
+impl Test for Constant {
+    fn test(&self, job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<bool, Traced<RuntimeError>> {
+        let name = try_traced!(argument("constant", &arguments, 0)).to_string();
+
+        match job.options().constant(&name) {
+            Some(constant) => Ok(value == *constant),
+            None => traced_err!(RuntimeError::UnknownConstant {
+                name,
+                template: job.template_name().to_string(),
+                position: job.position(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use setup::Setup;
+    use api::Value;
+    use api::value::Hash;
+    use extension::core::test::{engine_with, render};
+
+    fn data() -> Hash {
+        vec![
+            ("n", Value::from(6)),
+            ("nothing", Value::Null),
+            ("user", Value::Hash(vec![("name", Value::from("Tom")), ("tags", Value::from(Vec::<Value>::new()))].into_iter().collect())),
+        ].into_iter().collect()
+    }
+
+    #[test]
+    fn defined() {
+        assert_eq!(render("{{ n is defined }}|{{ nothing is defined }}|{{ missing is defined }}|{{ missing is not defined }}", data()), "1|1||1");
+        assert_eq!(render("{{ user.name is defined }}|{{ user.age is defined }}|{{ missing.name is defined }}|{{ user['tags'] is defined }}", data()), "1|||1");
+        assert_eq!(render("{% for i in [1] %}{{ loop is defined }}{% endfor %}{{ _self is defined }}", data()), "11");
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(render("{{ nothing is null }}{{ missing is none }}{{ n is null }}|{{ 0 is empty }}{{ '' is empty }}{{ user.tags is empty }}{{ user is empty }}", data()), "11|11");
+        assert_eq!(render("{{ n is even }}{{ n is odd }}{{ 7 is odd }}|{{ n is divisible by(3) }}{{ n is divisible by(4) }}", data()), "11|1");
+        assert_eq!(render("{{ user is iterable }}{{ user.tags is iterable }}{{ n is iterable }}|{{ n is same as(6) }}{{ n is same as(6.0) }}{{ n is not same as('6') }}", data()), "11|11");
+    }
+
+    #[test]
+    fn constant() {
+        let setup = Setup::default().add_constant("ANSWER", 42);
+        let mut twig = engine_with(setup, &[("index", "{{ 42 is constant('ANSWER') }}|{{ '42' is constant('ANSWER') }}"), ("missing", "{{ 1 is constant('FOO') }}")]);
+
+        assert_eq!(twig.render("index", Hash::new()).unwrap(), "1|");
+
+        let error = twig.render("missing", Hash::new()).unwrap_err();
+        assert!(error.to_string().contains("The constant \"FOO\" is not defined in template \"missing\" at 1:6."), "{}", error);
+    }
+
+    #[test]
+    fn errors() {
+        for source in &["{{ 'a' is even }}", "{{ [] is odd }}", "{{ 4 is divisible by('x') }}", "{{ 4 is divisible by(0) }}"] {
+            let mut twig = engine_with(Setup::default(), &[("index", source)]);
+
+            assert!(twig.render("index", Hash::new()).is_err(), "{}", source);
+        }
+    }
+}
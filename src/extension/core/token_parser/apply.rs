@@ -0,0 +1,35 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `apply` tag.
+
+use api::Node;
+use api::node;
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::stream::Item;
+use api::error::Traced;
+use super::{expect_block_end, parse_body};
+
+/// Name of the temporary variable which holds the rendered body.
+const VARIABLE: &str = "_apply";
+
+/// Parses `{% apply upper|trim %}..{% endapply %}`.
+#[derive(Debug, Default)]
+pub struct Apply;
+
+impl TokenParser for Apply {
+    fn tag(&self) -> &'static str { "apply" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let position = item.position().clone();
+        let filter = try_traced!(job.parse_filter_expression_raw(node::Name::new(VARIABLE, position.clone())));
+        try_traced!(expect_block_end(job));
+
+        let body = try_traced!(parse_body(job, "endapply"));
+
+        Ok(node::Apply::new(VARIABLE, body, filter, position))
+    }
+}
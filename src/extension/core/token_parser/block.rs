@@ -0,0 +1,68 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `block` tag.
+
+use api::Node;
+use api::node::{self, BlockReference};
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::{Token, Type};
+use api::token::stream::Item;
+use api::error::Traced;
+use super::{drop_at, expect_block_end};
+
+/// Parses `{% block name %}..{% endblock %}` and the short form `{% block name expression %}`.
+///
+/// The block definition is added to the template, the returned node only refers to it.
+#[derive(Debug, Default)]
+pub struct Block;
+
+impl TokenParser for Block {
+    fn tag(&self) -> &'static str { "block" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let name_item = try_traced!(job.cursor().expect(Type::Name, Some("A block must have a name.")));
+        let name = name_item.token().value_as_str().unwrap_or_default().to_string();
+
+        if let Some(block) = job.block(&name) {
+            return traced_err!(ParserError::TokenParserError {
+                tag: self.tag(),
+                error: format!("The block {:?} has already been defined at {}", name, block.position()),
+                position: item.position().clone(),
+            });
+        }
+
+        job.push_block_stack(&name[..]);
+
+        let body = if job.cursor().next_if(Type::BlockEnd).is_some() {
+            let body = try_traced!(job.subparse(Some(&drop_at(&["endblock"]))));
+
+            if let Token::Name(ref end_name) = *job.cursor().current().token() {
+                if *end_name != name {
+                    return traced_err!(ParserError::TokenParserError {
+                        tag: self.tag(),
+                        error: format!("Expected endblock for block {:?} (but {:?} given)", name, end_name),
+                        position: job.cursor().current().position().clone(),
+                    });
+                }
+
+                job.cursor().next();
+            }
+
+            body
+        } else {
+            let expr = try_traced!(job.parse_expression());
+
+            node::Print::new(expr, item.position().clone())
+        };
+        try_traced!(expect_block_end(job));
+
+        job.pop_block_stack();
+        job.push_block(node::Block::new(&name[..], body, item.position().clone()));
+
+        Ok(BlockReference::new(name, item.position().clone()))
+    }
+}
@@ -0,0 +1,29 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `do` tag.
+
+use api::Node;
+use api::node;
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::stream::Item;
+use api::error::Traced;
+use super::expect_block_end;
+
+/// Parses `{% do expression %}`.
+#[derive(Debug, Default)]
+pub struct Do;
+
+impl TokenParser for Do {
+    fn tag(&self) -> &'static str { "do" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let expr = try_traced!(job.parse_expression());
+        try_traced!(expect_block_end(job));
+
+        Ok(node::Do::new(expr, item.position().clone()))
+    }
+}
@@ -0,0 +1,42 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `embed` tag.
+
+use api::Node;
+use api::node;
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::stream::Item;
+use api::error::Traced;
+use super::{drop_at, expect_block_end};
+use super::include::parse_arguments;
+
+/// Parses `{% embed 'template.twig' with {..} only %}{% block a %}..{% endblock %}{% endembed %}`.
+///
+/// The body is compiled as a separate template, which extends the embedded template and may
+/// override its blocks.
+#[derive(Debug, Default)]
+pub struct Embed;
+
+impl TokenParser for Embed {
+    fn tag(&self) -> &'static str { "embed" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        // the template expression is needed twice: as parent of the embedded template and
+        // to check whether it exists in case of `ignore missing`
+        let start = job.cursor().position();
+        let parent = try_traced!(job.parse_expression());
+        job.cursor().seek(start);
+        let expr = try_traced!(job.parse_expression());
+
+        let (variables, only, ignore_missing) = try_traced!(parse_arguments(job));
+
+        let index = try_traced!(job.embed_template(parent, &drop_at(&["endembed"])));
+        try_traced!(expect_block_end(job));
+
+        Ok(node::Embed::new(index, expr, variables, only, ignore_missing, item.position().clone()))
+    }
+}
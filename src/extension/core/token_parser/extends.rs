@@ -0,0 +1,50 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `extends` tag.
+
+use api::Node;
+use api::node::Body;
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::stream::Item;
+use api::error::Traced;
+use super::expect_block_end;
+
+/// Parses `{% extends 'parent.twig' %}`.
+///
+/// The parent may be any expression, e.g. `{% extends standalone ? 'minimal.twig' : 'base.twig' %}`
+/// or a list of candidates `{% extends ['custom.twig', 'base.twig'] %}`, where the first
+/// existing template is used.
+#[derive(Debug, Default)]
+pub struct Extends;
+
+impl TokenParser for Extends {
+    fn tag(&self) -> &'static str { "extends" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let error = if !job.block_stack().is_empty() {
+            Some("Cannot use \"extends\" in a block")
+        } else if job.parent().is_some() {
+            Some("Multiple extends tags are forbidden")
+        } else {
+            None
+        };
+
+        if let Some(error) = error {
+            return traced_err!(ParserError::TokenParserError {
+                tag: self.tag(),
+                error: error.to_string(),
+                position: item.position().clone(),
+            });
+        }
+
+        let parent = try_traced!(job.parse_expression());
+        try_traced!(expect_block_end(job));
+        job.set_parent(parent);
+
+        Ok(Body::new(vec![], item.position().clone()))
+    }
+}
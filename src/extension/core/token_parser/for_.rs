@@ -0,0 +1,54 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `for` tag.
+
+use api::Node;
+use api::node;
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::Token;
+use api::token::stream::Item;
+use api::error::Traced;
+use super::{keep_at, drop_at, expect_block_end, parse_targets};
+
+/// Parses `{% for key, value in items %}..{% else %}..{% endfor %}`.
+#[derive(Debug, Default)]
+pub struct For;
+
+impl TokenParser for For {
+    fn tag(&self) -> &'static str { "for" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let mut targets = try_traced!(parse_targets(job, self.tag()));
+
+        if targets.len() > 2 {
+            return traced_err!(ParserError::TokenParserError {
+                tag: self.tag(),
+                error: format!("Only a key and a value can be assigned to, found {} variables", targets.len()),
+                position: item.position().clone(),
+            });
+        }
+
+        try_traced!(job.cursor().expect(Token::Operator("in".to_string()), Some("The loop sequence must follow the keyword \"in\".")));
+        let seq = try_traced!(job.parse_expression());
+        try_traced!(expect_block_end(job));
+
+        let body = try_traced!(job.subparse(Some(&keep_at(&["else", "endfor"]))));
+        let else_body = match *job.cursor().next().token() {
+            Token::Name(ref name) if name == "else" => {
+                try_traced!(expect_block_end(job));
+                Some(try_traced!(job.subparse(Some(&drop_at(&["endfor"])))))
+            },
+            _ => None,
+        };
+        try_traced!(expect_block_end(job));
+
+        let value_target = targets.pop().unwrap_or_default();
+        let key_target = targets.pop();
+
+        Ok(node::For::new(key_target, value_target, seq, body, else_body, item.position().clone()))
+    }
+}
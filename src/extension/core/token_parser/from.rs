@@ -0,0 +1,55 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `from` tag.
+
+use api::Node;
+use api::node;
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::{Token, Type, Punctuation};
+use api::token::stream::Item;
+use api::error::Traced;
+use super::expect_block_end;
+
+/// Parses `{% from 'forms.twig' import input as field, textarea %}`.
+///
+/// The template is imported under an internal name, the macros are called like functions.
+#[derive(Debug, Default)]
+pub struct From;
+
+impl TokenParser for From {
+    fn tag(&self) -> &'static str { "from" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let expr = try_traced!(job.parse_expression());
+        try_traced!(job.cursor().expect(Token::Name("import".to_string()), None));
+
+        let template_alias = job.internal_name();
+
+        loop {
+            let name_item = try_traced!(job.cursor().expect(Type::Name, Some("A macro name was expected.")));
+            let name = name_item.token().value_as_str().unwrap_or_default().to_string();
+
+            let alias = if job.cursor().next_if(Token::Name("as".to_string())).is_some() {
+                let alias_item = try_traced!(job.cursor().expect(Type::Name, Some("An alias was expected.")));
+                alias_item.token().value_as_str().unwrap_or_default().to_string()
+            } else {
+                name.clone()
+            };
+
+            job.import_macro(alias, &template_alias[..], name);
+
+            if job.cursor().next_if(Token::Punctuation(Punctuation::Comma)).is_none() {
+                break;
+            }
+        }
+        try_traced!(expect_block_end(job));
+
+        job.import_template(&template_alias[..]);
+
+        Ok(node::Import::new(expr, template_alias, item.position().clone()))
+    }
+}
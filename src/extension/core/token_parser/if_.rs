@@ -0,0 +1,51 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `if` tag.
+
+use api::Node;
+use api::node;
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::Token;
+use api::token::stream::Item;
+use api::error::Traced;
+use super::{keep_at, drop_at, expect_block_end};
+
+/// Parses `{% if a %}..{% elseif b %}..{% else %}..{% endif %}`.
+#[derive(Debug, Default)]
+pub struct If;
+
+impl TokenParser for If {
+    fn tag(&self) -> &'static str { "if" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let mut tests = vec![];
+        let mut else_body = None;
+        let mut test = try_traced!(job.parse_expression());
+
+        loop {
+            try_traced!(expect_block_end(job));
+            let body = try_traced!(job.subparse(Some(&keep_at(&["elseif", "else", "endif"]))));
+            tests.push((test, body));
+
+            match *job.cursor().next().token() {
+                Token::Name(ref name) if name == "elseif" => {
+                    test = try_traced!(job.parse_expression());
+                },
+                Token::Name(ref name) if name == "else" => {
+                    try_traced!(expect_block_end(job));
+                    else_body = Some(try_traced!(job.subparse(Some(&drop_at(&["endif"])))));
+                    break;
+                },
+                _ => break, // endif
+            }
+        }
+
+        try_traced!(expect_block_end(job));
+
+        Ok(node::If::new(tests, else_body, item.position().clone()))
+    }
+}
@@ -0,0 +1,36 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `import` tag.
+
+use api::Node;
+use api::node;
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::{Token, Type};
+use api::token::stream::Item;
+use api::error::Traced;
+use super::expect_block_end;
+
+/// Parses `{% import 'forms.twig' as forms %}` - or `{% import _self as forms %}` to use the
+/// macros of the current template.
+#[derive(Debug, Default)]
+pub struct Import;
+
+impl TokenParser for Import {
+    fn tag(&self) -> &'static str { "import" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let expr = try_traced!(job.parse_expression());
+        try_traced!(job.cursor().expect(Token::Name("as".to_string()), None));
+        let alias_item = try_traced!(job.cursor().expect(Type::Name, Some("An import must have an alias.")));
+        let alias = alias_item.token().value_as_str().unwrap_or_default().to_string();
+        try_traced!(expect_block_end(job));
+
+        job.import_template(&alias[..]);
+
+        Ok(node::Import::new(expr, alias, item.position().clone()))
+    }
+}
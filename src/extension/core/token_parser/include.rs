@@ -0,0 +1,58 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `include` tag.
+
+use api::Node;
+use api::node;
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::Token;
+use api::token::stream::Item;
+use api::error::Traced;
+use super::expect_block_end;
+
+/// Parses `{% include 'template.twig' ignore missing with {'foo': 'bar'} only %}`.
+///
+/// The template may be any expression, or a list of candidates where the first existing
+/// template is included.
+#[derive(Debug, Default)]
+pub struct Include;
+
+impl TokenParser for Include {
+    fn tag(&self) -> &'static str { "include" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let expr = try_traced!(job.parse_expression());
+        let (variables, only, ignore_missing) = try_traced!(parse_arguments(job));
+
+        Ok(node::Include::new(expr, variables, only, ignore_missing, item.position().clone()))
+    }
+}
+
+/// Options of `include` and `embed` tags: the expression of the variables (if any) and the
+/// `only` and `ignore missing` flags.
+pub type Arguments = (Option<Box<dyn Node>>, bool, bool);
+
+/// Parses the options of `include` and `embed` tags up to the end of the block.
+pub fn parse_arguments(job: &mut Job) -> Result<Arguments, Traced<ParserError>> {
+    let ignore_missing = match job.cursor().next_if(Token::Name("ignore".to_string())) {
+        Some(_) => {
+            try_traced!(job.cursor().expect(Token::Name("missing".to_string()), None));
+            true
+        },
+        None => false,
+    };
+
+    let variables = match job.cursor().next_if(Token::Name("with".to_string())) {
+        Some(_) => Some(try_traced!(job.parse_expression())),
+        None => None,
+    };
+
+    let only = job.cursor().next_if(Token::Name("only".to_string())).is_some();
+    try_traced!(expect_block_end(job));
+
+    Ok((variables, only, ignore_missing))
+}
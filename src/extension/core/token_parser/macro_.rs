@@ -0,0 +1,126 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `macro` tag.
+
+use api::{Node, Value};
+use api::node::{self, Array, Body, Constant, Hash, Unary};
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::{Token, Type, Punctuation, BracketType};
+use api::token::stream::Item;
+use api::error::Traced;
+use super::{drop_at, expect_block_end};
+
+/// Argument names with their default values.
+type Arguments = Vec<(String, Box<dyn Node>)>;
+
+/// Parses `{% macro input(name, value = '', type = 'text') %}..{% endmacro %}`.
+///
+/// Arguments without default value default to `null`. Default values must be constant, like
+/// in TwigPHP, i.e. booleans, strings, numbers and arrays or hashes of them. Additional arguments
+/// of a call are available as `varargs`.
+#[derive(Debug, Default)]
+pub struct Macro;
+
+impl TokenParser for Macro {
+    fn tag(&self) -> &'static str { "macro" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let name_item = try_traced!(job.cursor().expect(Type::Name, Some("A macro must have a name.")));
+        let name = name_item.token().value_as_str().unwrap_or_default().to_string();
+
+        if let Some(definition) = job.macro_(&name) {
+            return traced_err!(ParserError::TokenParserError {
+                tag: self.tag(),
+                error: format!("The macro {:?} has already been defined at {}", name, definition.position()),
+                position: item.position().clone(),
+            });
+        }
+
+        let arguments = try_traced!(self.parse_arguments(job));
+        try_traced!(expect_block_end(job));
+
+        let body = try_traced!(job.subparse(Some(&drop_at(&["endmacro"]))));
+
+        if let Token::Name(ref end_name) = *job.cursor().current().token() {
+            if *end_name != name {
+                return traced_err!(ParserError::TokenParserError {
+                    tag: self.tag(),
+                    error: format!("Expected endmacro for macro {:?} (but {:?} given)", name, end_name),
+                    position: job.cursor().current().position().clone(),
+                });
+            }
+
+            job.cursor().next();
+        }
+        try_traced!(expect_block_end(job));
+
+        job.push_macro(node::Macro::new(name, arguments, body, item.position().clone()));
+
+        Ok(Body::new(vec![], item.position().clone()))
+    }
+}
+
+impl Macro {
+    /// Parses the argument list with optional default values, e.g. `(name, value = '')`.
+    fn parse_arguments(&self, job: &mut Job) -> Result<Arguments, Traced<ParserError>> {
+        try_traced!(job.cursor().expect(Token::Punctuation(Punctuation::OpeningBracket(BracketType::Round)),
+            Some("A list of arguments must begin with an opening parenthesis.")));
+        let mut arguments: Arguments = vec![];
+
+        while job.cursor().next_if(Token::Punctuation(Punctuation::ClosingBracket(BracketType::Round))).is_none() {
+            if !arguments.is_empty() {
+                try_traced!(job.cursor().expect(Token::Punctuation(Punctuation::Comma),
+                    Some("Arguments must be separated by a comma.")));
+            }
+
+            let item = try_traced!(job.cursor().expect(Type::Name, Some("An argument must be a name.")));
+            let name = item.token().value_as_str().unwrap_or_default().to_string();
+
+            if arguments.iter().any(|a| a.0 == name) {
+                return traced_err!(ParserError::TokenParserError {
+                    tag: self.tag(),
+                    error: format!("The argument {:?} is defined twice", name),
+                    position: item.position().clone(),
+                });
+            }
+
+            let default = if job.cursor().next_if(Token::Operator("=".to_string())).is_some() {
+                let default = try_traced!(job.parse_expression());
+
+                if !is_constant(&*default) {
+                    return traced_err!(ParserError::TokenParserError {
+                        tag: self.tag(),
+                        error: format!("The default value of the argument {:?} must be a constant \
+                            (a boolean, a string, a number, an array or a hash)", name),
+                        position: default.position().clone(),
+                    });
+                }
+
+                default
+            } else {
+                Constant::new(Value::Null, item.position().clone())
+            };
+
+            arguments.push((name, default));
+        }
+
+        Ok(arguments)
+    }
+}
+
+/// Whether an expression is a constant, e.g. `'a'`, `-1` or `[1, {'b': true}]`.
+fn is_constant(node: &dyn Node) -> bool {
+    if node.is::<Constant>() {
+        return true;
+    }
+
+    if let Some(unary) = node.downcast_ref::<Unary>() {
+        return (unary.operator() == "-" || unary.operator() == "+") && unary.node().is::<Constant>();
+    }
+
+    (node.is::<Array>() || node.is::<Hash>()) && node.children().iter().all(|child| is_constant(&**child))
+}
@@ -0,0 +1,415 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Token parsers of the core extension.
+
+use api::Node;
+use api::ext::TokenParser;
+use api::ext::token_parser::TestResult;
+use api::parser::{Job, ParserError};
+use api::token::{Token, Type, Punctuation};
+use api::token::stream::Item;
+use api::error::Traced;
+
+pub mod apply;
+pub mod block;
+pub mod do_;
+pub mod embed;
+pub mod extends;
+pub mod for_;
+pub mod from;
+pub mod if_;
+pub mod import;
+pub mod include;
+pub mod macro_;
+pub mod set;
+pub mod use_;
+pub mod with;
+pub use self::apply::Apply;
+pub use self::block::Block;
+pub use self::do_::Do;
+pub use self::embed::Embed;
+pub use self::extends::Extends;
+pub use self::for_::For;
+pub use self::from::From;
+pub use self::if_::If;
+pub use self::import::Import;
+pub use self::include::Include;
+pub use self::macro_::Macro;
+pub use self::set::Set;
+pub use self::use_::Use;
+pub use self::with::With;
+
+/// Get all token parsers of the core extension, by tag.
+pub fn all() -> Vec<(String, Box<dyn TokenParser>)> {
+    let token_parsers: Vec<Box<dyn TokenParser>> = vec![
+        Box::new(If),
+        Box::new(For),
+        Box::new(Set),
+        Box::new(With),
+        Box::new(Do),
+        Box::new(Apply),
+        Box::new(Extends),
+        Box::new(Block),
+        Box::new(Include),
+        Box::new(Embed),
+        Box::new(Macro),
+        Box::new(Import),
+        Box::new(From),
+        Box::new(Use),
+    ];
+
+    token_parsers.into_iter().map(|t| (t.tag().to_string(), t)).collect()
+}
+
+/// Returns true if the item is one of the given tag names.
+pub fn is_tag(item: &Item, tags: &[&str]) -> bool {
+    match *item.token() {
+        Token::Name(ref name) => tags.contains(&&name[..]),
+        _ => false,
+    }
+}
+
+/// Subparse test which stops at one of the given tags, leaving the tag name to the caller.
+pub fn keep_at<'a>(tags: &'a [&'a str]) -> impl Fn(&Item) -> TestResult + 'a {
+    move |item| if is_tag(item, tags) { TestResult::KeepToken } else { TestResult::Continue }
+}
+
+/// Subparse test which stops at one of the given tags and drops the tag name.
+pub fn drop_at<'a>(tags: &'a [&'a str]) -> impl Fn(&Item) -> TestResult + 'a {
+    move |item| if is_tag(item, tags) { TestResult::DropToken } else { TestResult::Continue }
+}
+
+/// Expects the end of a block, i.e. `%}`.
+pub fn expect_block_end(job: &mut Job) -> Result<(), Traced<ParserError>> {
+    job.expect_end(Type::BlockEnd)
+}
+
+/// Parses the body of a block up to its end tag, e.g. `..{% endset %}`.
+pub fn parse_body(job: &mut Job, end_tag: &'static str) -> Result<Box<dyn Node>, Traced<ParserError>> {
+    let test = move |item: &Item| if is_tag(item, &[end_tag]) { TestResult::DropToken } else { TestResult::Continue };
+    let body = try_traced!(job.subparse(Some(&test)));
+    try_traced!(expect_block_end(job));
+
+    Ok(body)
+}
+
+/// Parses a list of variable names, e.g. `key, value` in `{% for key, value in items %}`.
+pub fn parse_targets(job: &mut Job, tag: &'static str) -> Result<Vec<String>, Traced<ParserError>> {
+    let mut targets = vec![];
+
+    loop {
+        let item = job.cursor().current();
+
+        match *item.token() {
+            Token::Name(ref name) => {
+                job.cursor().next();
+                targets.push(name.clone());
+            },
+            _ => return traced_err!(ParserError::TokenParserError {
+                tag,
+                error: format!("Only variables can be assigned to, found {}", item.token().describe()),
+                position: item.position().clone(),
+            }),
+        }
+
+        if job.cursor().next_if(Token::Punctuation(Punctuation::Comma)).is_none() {
+            return Ok(targets);
+        }
+    }
+}
+
+/// Parses a list of comma separated expressions, e.g. `1, a + b`.
+pub fn parse_expressions(job: &mut Job) -> Result<Vec<Box<dyn Node>>, Traced<ParserError>> {
+    let mut expressions = vec![try_traced!(job.parse_expression())];
+
+    while job.cursor().next_if(Token::Punctuation(Punctuation::Comma)).is_some() {
+        expressions.push(try_traced!(job.parse_expression()));
+    }
+
+    Ok(expressions)
+}
+
+#[cfg(test)]
+mod test {
+    use setup::Setup;
+    use engine::{self, Engine};
+    use api::Value;
+    use api::value::Hash;
+    use api::runtime::RuntimeError;
+    use extension::core::test::{engine_with, render};
+
+    fn data() -> Hash {
+        vec![
+            ("items", Value::from(vec!["a", "b", "c"])),
+            ("user", Value::Hash(vec![("name", "Alice"), ("role", "admin")].into_iter().collect())),
+            ("n", Value::from(2)),
+        ].into_iter().collect()
+    }
+
+    #[test]
+    fn if_tag() {
+        let template = "{% for i in [1, 2, 3] %}{% if i == 1 %}one{% elseif i == n %}two{% else %}many{% endif %},{% endfor %}";
+
+        assert_eq!(render(template, data()), "one,two,many,");
+        assert_eq!(render("{% if false %}no{% endif %}", Hash::new()), "");
+    }
+
+    #[test]
+    fn for_tag() {
+        assert_eq!(render("{% for item in items %}{{ loop.index }}{{ item }}{{ loop.last ? '' : ',' }}{% endfor %}", data()), "1a,2b,3c");
+        assert_eq!(render("{% for key, value in user %}{{ key }}={{ value }};{% endfor %}", data()), "name=Alice;role=admin;");
+        assert_eq!(render("{% for item in [] %}x{% else %}empty{% endfor %}", Hash::new()), "empty");
+        assert_eq!(render("{% for i in [1, 2] %}{{ loop.first }}{{ loop.revindex0 }}{{ loop.length }}{{ loop.parent.n }} {% endfor %}", data()), "1122 022 ");
+        assert_eq!(render("{% for i in items %}{% for j in [1] %}{{ loop.parent.i }}{% endfor %}{% endfor %}", data()), "abc");
+    }
+
+    #[test]
+    fn for_tag_scope() {
+        let template = "{% for item in items %}{% set n = n + 1 %}{% set tmp = 1 %}{% endfor %}{{ n }}{{ item }}{{ tmp }}";
+
+        assert_eq!(render(template, data()), "5");
+    }
+
+    #[test]
+    fn set_tag() {
+        assert_eq!(render("{% set a, b = 1, 2 %}{% set a, b = b, a %}{{ a }}{{ b }}", Hash::new()), "21");
+        assert_eq!(render("{% set greeting %}Hello {{ user.name }}{% endset %}[{{ greeting }}]", data()), "[Hello Alice]");
+    }
+
+    #[test]
+    fn with_tag() {
+        assert_eq!(render("{% with { a: 1 } %}{{ a }}{{ n }}{% set n = 3 %}{% endwith %}{{ n }}", data()), "122");
+        assert_eq!(render("{% with { a: 1 } only %}{{ a }}{{ n }}{% endwith %}", data()), "1");
+    }
+
+    #[test]
+    fn do_tag() {
+        assert_eq!(render("{% do 1 + 2 %}", Hash::new()), "");
+    }
+
+    #[test]
+    fn apply_tag() {
+        let mut twig: Engine = engine_with(Setup::default(), &[("index", "{% apply upper %}Hello {{ user.name }}{% endapply %}")]);
+
+        assert_eq!(twig.render("index", data()).unwrap(), "HELLO ALICE");
+    }
+
+    #[test]
+    fn inheritance() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("base", "<{% block title %}Base{% endblock %}|{% block content %}{% block inner %}I{% endblock %}{% endblock %}>"),
+            ("child", "{% extends 'base' %}\n{% set who = 'Child' %}\n{% block title %}{{ who }} {{ parent() }}{% endblock title %}"),
+            ("grandchild", "{% extends 'child' %}{% block title %}G/{{ parent() }}{% endblock %}{% block inner %}J{% endblock %}"),
+            ("dynamic", "{% extends standalone ? 'base' : 'child' %}{% block content %}{{ block('title') }}{% endblock %}"),
+            ("candidates", "{% extends ['missing', 'base'] %}{% block content %}X{% endblock %}"),
+            ("foreign", "{{ block('title', 'grandchild') }}"),
+        ]);
+
+        assert_eq!(twig.render("child", Hash::new()).unwrap(), "<Child Base|I>");
+        assert_eq!(twig.render("grandchild", Hash::new()).unwrap(), "<G/Child Base|J>");
+        assert_eq!(twig.render("dynamic", vec![("standalone", true)].into_iter().collect()).unwrap(), "<Base|Base>");
+        assert_eq!(twig.render("dynamic", vec![("standalone", false)].into_iter().collect()).unwrap(), "<Child Base|Child Base>");
+        assert_eq!(twig.render("candidates", Hash::new()).unwrap(), "<Base|X>");
+        assert_eq!(twig.render("foreign", Hash::new()).unwrap(), "G/ Base");
+    }
+
+    #[test]
+    fn inheritance_errors() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("a", "{% extends 'b' %}"),
+            ("b", "{% extends 'c' %}"),
+            ("c", "{% extends 'a' %}"),
+            ("content", "{% extends 'base' %}text"),
+            ("parent", "{% block a %}{{ parent() }}{% endblock %}"),
+            ("duplicate", "{% block a %}{% endblock %}{% block a %}{% endblock %}"),
+        ]);
+
+        let error = twig.render("a", Hash::new()).unwrap_err();
+        assert!(error.to_string().contains("a -> b -> c -> a"), "{}", error);
+
+        for template in &["content", "parent", "duplicate"] {
+            assert!(twig.render(template, Hash::new()).is_err(), "{:?} should fail", template);
+        }
+    }
+
+    #[test]
+    fn include_tag() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("index", "{% set a = 1 %}{% include 'partial' %}|{% include 'partial' with {'b': 2} %}|{% include 'partial' with {'b': 3} only %}|{% include ['missing', 'partial'] %}|{% include 'missing' ignore missing %}."),
+            ("partial", "({{ a }}{{ b }}){% set a = 'x' %}"),
+            ("functions", "{{ include('partial', {'b': 2}) }}|{{ include('partial', {}, false) }}|{{ include('missing', {}, true, true) }}|{{ source('partial') }}|{{ source('missing', true) }}."),
+        ]);
+
+        assert_eq!(twig.render("index", Hash::new()).unwrap(), "(1)|(12)|(3)|(1)|.");
+        assert_eq!(twig.render("functions", vec![("a", 1)].into_iter().collect()).unwrap(),
+            "(12)|()||({{ a }}{{ b }}){% set a = 'x' %}|.");
+    }
+
+    #[test]
+    fn embed_tag() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("index", "{% embed 'box' with {'title': 'T'} %}{% block content %}<{{ title }}{{ parent() }}>{% endblock %}{% endembed %}|{% embed 'box' %}{% endembed %}|{% embed 'missing' ignore missing %}{% endembed %}."),
+            ("box", "[{{ title }}:{% block content %}default{% endblock %}]"),
+            ("nested", "{% embed 'box' %}{% block content %}{% embed 'box' %}{% block content %}inner{% endblock %}{% endembed %}{% endblock %}{% endembed %}"),
+            ("child", "{% extends 'box' %}{% block content %}{% embed 'box' %}{% block content %}embedded{% endblock %}{% endembed %}{% endblock %}"),
+        ]);
+
+        assert_eq!(twig.render("index", Hash::new()).unwrap(), "[T:<Tdefault>]|[:default]|.");
+        assert_eq!(twig.render("nested", Hash::new()).unwrap(), "[:[:inner]]");
+        assert_eq!(twig.render("child", Hash::new()).unwrap(), "[:[:embedded]]");
+    }
+
+    #[test]
+    fn include_errors() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("missing", "line 1\n  {% include 'nowhere' %}"),
+            ("recursive", "{% include 'recursive' %}"),
+            ("variables", "{% include 'recursive' with 1 %}"),
+        ]);
+
+        let error = twig.render("missing", Hash::new()).unwrap_err();
+        match *error {
+            engine::TwigError::Runtime(ref error @ RuntimeError::LoadTemplate { .. }, _) => {
+                assert!(error.is_template_not_found());
+
+                if let RuntimeError::LoadTemplate { ref name, ref caller, ref position, .. } = *error {
+                    assert_eq!((&name[..], &caller[..], position.line), ("nowhere", "missing", 2));
+                }
+            },
+            ref error => panic!("unexpected error {}", error),
+        }
+
+        let error = twig.render("recursive", Hash::new()).unwrap_err();
+        assert!(error.to_string().contains("infinite recursion"), "{}", error);
+
+        assert!(twig.render("variables", Hash::new()).is_err());
+    }
+
+    #[test]
+    fn macros() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("forms", "{% macro input(name, value = 'v', type = 'text') %}<{{ type }} {{ name }}={{ value }}{{ secret }}>{% endmacro %}\
+                {% macro list() %}{% for v in varargs %}{{ v }};{% endfor %}{% endmacro list %}"),
+            ("import", "{% set secret = 's' %}{% import 'forms' as forms %}{{ forms.input('a') }}{{ forms.input('b', 1, 'hidden') }}{{ forms.list(1, 2) }}"),
+            ("from", "{% from 'forms' import input as field, list %}{{ field('c') }}{{ list() }}."),
+            ("self", "{% macro twice(x) %}{{ x }}{{ x }}{% endmacro %}{% import _self as m %}{{ m.twice(_self) }}"),
+            ("recursive", "{% macro count(n) %}{% import _self as m %}{{ n }}{% if n > 0 %}{{ m.count(n - 1) }}{% endif %}{% endmacro %}{% import _self as m %}{{ m.count(3) }}"),
+        ]);
+
+        assert_eq!(twig.render("import", Hash::new()).unwrap(), "<text a=v><hidden b=1>1;2;");
+        assert_eq!(twig.render("from", Hash::new()).unwrap(), "<text c=v>.");
+        assert_eq!(twig.render("self", Hash::new()).unwrap(), "selfself");
+        assert_eq!(twig.render("recursive", Hash::new()).unwrap(), "3210");
+    }
+
+    #[test]
+    fn macro_errors() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("forms", "{% macro input() %}{% endmacro %}"),
+            ("undefined", "{% import 'forms' as forms %}\n{{ forms.textarea() }}"),
+            ("not_imported", "{% from _self import a, b %}{% macro a() %}{{ b() }}{% endmacro %}{% macro b() %}{% endmacro %}{{ a() }}"),
+            ("duplicate", "{% macro a() %}{% endmacro %}{% macro a() %}{% endmacro %}"),
+            ("arguments", "{% macro a(x, x) %}{% endmacro %}"),
+            ("endmacro", "{% macro a() %}{% endmacro b %}"),
+            ("default", "{% macro m(x, y = x ~ \"!\") %}{{ y }}{% endmacro %}"),
+            ("constant_defaults", "{% macro m(a = -1, b = [1, {'c': true}], d = null) %}{{ a }}{{ b|length }}{{ d is null }}{% endmacro %}{% import _self as f %}{{ f.m() }}"),
+        ]);
+
+        match *twig.render("undefined", Hash::new()).unwrap_err() {
+            engine::TwigError::Runtime(RuntimeError::UnknownMacro { ref name, ref template, ref caller, ref position }, _) => {
+                assert_eq!((&name[..], template.as_deref(), &caller[..], position.line), ("textarea", Some("forms"), "undefined", 2));
+            },
+            ref error => panic!("unexpected error {}", error),
+        }
+
+        let error = twig.render("not_imported", Hash::new()).unwrap_err();
+        assert!(error.to_string().contains("has not been imported"), "{}", error);
+
+        for template in &["duplicate", "arguments", "endmacro"] {
+            assert!(twig.render(template, Hash::new()).is_err(), "{:?} should fail", template);
+        }
+
+        let error = twig.render("default", Hash::new()).unwrap_err().to_string();
+        assert!(error.contains("The default value of the argument \"y\" must be a constant"), "{}", error);
+        assert!(error.contains("\"macro\"-block at 1:21"), "{}", error);
+        assert_eq!(twig.render("constant_defaults", Hash::new()).unwrap(), "-121");
+    }
+
+    #[test]
+    fn use_tag() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("base", "<{% block header %}base header{% endblock %}|{% block sidebar %}base sidebar{% endblock %}>"),
+            ("blocks", "{% block sidebar %}used sidebar{% endblock %}{% block footer %}used footer{% endblock %}"),
+            ("more", "{% use 'blocks' with footer as blocks_footer %}{% block header %}more header{% endblock %}"),
+            ("child", "{% extends 'base' %}{% use 'blocks' %}"),
+            ("override", "{% extends 'base' %}{% use 'blocks' %}{% block sidebar %}[{{ parent() }}]{% endblock %}"),
+            ("renamed", "{% extends 'base' %}{% use 'blocks' with sidebar as used_sidebar %}{% block sidebar %}{{ block('used_sidebar') }}!{% endblock %}"),
+            ("nested", "{% use 'more' %}{{ block('header') }}|{{ block('blocks_footer') }}|{{ block('sidebar') }}"),
+        ]);
+
+        assert_eq!(twig.render("child", Hash::new()).unwrap(), "<base header|used sidebar>");
+        assert_eq!(twig.render("override", Hash::new()).unwrap(), "<base header|[used sidebar]>");
+        assert_eq!(twig.render("renamed", Hash::new()).unwrap(), "<base header|used sidebar!>");
+        assert_eq!(twig.render("nested", Hash::new()).unwrap(), "more header|used footer|used sidebar");
+    }
+
+    #[test]
+    fn use_errors() {
+        let mut twig = engine_with(Setup::default(), &[
+            ("a", "{% block sidebar %}a{% endblock %}"),
+            ("b", "{% block sidebar %}b{% endblock %}"),
+            ("conflict", "{% use 'a' %}{% use 'b' %}{{ block('sidebar') }}"),
+            ("resolved", "{% use 'a' %}{% use 'b' with sidebar as b_sidebar %}{{ block('sidebar') }}{{ block('b_sidebar') }}"),
+            ("missing", "{% use 'a' with footer as a_footer %}"),
+            ("content", "{% use 'conflict' %}"),
+            ("cycle_a", "{% use 'cycle_b' %}"),
+            ("cycle_b", "{% use 'cycle_a' %}"),
+            ("dynamic", "{% use name %}"),
+        ]);
+
+        match *twig.render("conflict", Hash::new()).unwrap_err() {
+            engine::TwigError::Runtime(RuntimeError::TraitConflict { ref name, ref template, ref traits, ref position }, ref trace) => {
+                assert_eq!((&name[..], &template[..], &traits[..]), ("sidebar", "conflict", &["a".to_string(), "b".to_string()][..]));
+                assert_eq!((position.line, position.column, &trace.template[..]), (1, 17, "conflict"));
+            },
+            ref error => panic!("unexpected error {}", error),
+        }
+
+        assert_eq!(twig.render("resolved", Hash::new()).unwrap(), "ab");
+
+        let error = twig.render("cycle_a", Hash::new()).unwrap_err().to_string();
+        assert!(error.contains("cycle_a -> cycle_b -> cycle_a, referred to by \"cycle_b\" at 1:4."), "{}", error);
+
+        for template in &["missing", "content", "cycle_a", "dynamic"] {
+            assert!(twig.render(template, Hash::new()).is_err(), "{:?} should fail", template);
+        }
+    }
+
+    #[test]
+    fn syntax_errors() {
+        for template in &["{% if a %}", "{% for a in b %}{% endif %}", "{% set a, b = 1 %}", "{% set a, b %}{% endset %}", "{% for a b in c %}{% endfor %}",
+            "{% include 'a' ignore %}", "{% embed 'a' %}", "{% embed 'a' %}text{% endembed %}"] {
+            let mut twig = engine_with(Setup::default(), &[("index", template)]);
+
+            assert!(twig.render("index", Hash::new()).is_err(), "{:?} should fail", template);
+        }
+
+        for &(template, message, line, column) in &[
+            ("{% for 1 in b %}{% endfor %}", "\"for\"-block at 1:8: Only variables can be assigned to, found number \"1\".", 1, 8),
+            ("{% if a %}\n{{ a }}", "Missing end tag. The template ends at 2:8.", 2, 8),
+        ] {
+            let mut twig = engine_with(Setup::default(), &[("index", template)]);
+
+            match *twig.render("index", Hash::new()).unwrap_err() {
+                engine::TwigError::Parser(ref cause, _) => {
+                    assert!(cause.to_string().ends_with(message), "{}", cause);
+                    assert_eq!(cause.position().map(|p| (p.line, p.column)), Some((line, column)));
+                },
+                ref error => panic!("unexpected error {}", error),
+            }
+        }
+    }
+}
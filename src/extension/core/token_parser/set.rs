@@ -0,0 +1,55 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `set` tag.
+
+use api::Node;
+use api::node;
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::Token;
+use api::token::stream::Item;
+use api::error::Traced;
+use super::{expect_block_end, parse_body, parse_targets, parse_expressions};
+
+/// Parses `{% set a, b = 1, 2 %}` and `{% set a %}..{% endset %}`.
+#[derive(Debug, Default)]
+pub struct Set;
+
+impl TokenParser for Set {
+    fn tag(&self) -> &'static str { "set" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let mut names = try_traced!(parse_targets(job, self.tag()));
+
+        if job.cursor().next_if(Token::Operator("=".to_string())).is_some() {
+            let values = try_traced!(parse_expressions(job));
+            try_traced!(expect_block_end(job));
+
+            if names.len() != values.len() {
+                return traced_err!(ParserError::TokenParserError {
+                    tag: self.tag(),
+                    error: "When using set, you must have the same number of variables and assignments".to_string(),
+                    position: item.position().clone(),
+                });
+            }
+
+            return Ok(node::Set::new(names, values, item.position().clone()));
+        }
+
+        if names.len() > 1 {
+            return traced_err!(ParserError::TokenParserError {
+                tag: self.tag(),
+                error: "When using set with a block, you cannot have a multi-target".to_string(),
+                position: item.position().clone(),
+            });
+        }
+
+        try_traced!(expect_block_end(job));
+        let body = try_traced!(parse_body(job, "endset"));
+
+        Ok(node::Set::new_capture(names.remove(0), body, item.position().clone()))
+    }
+}
@@ -0,0 +1,69 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `use` tag.
+
+use api::{Node, Value};
+use api::node::{self, Body, Constant};
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::{Token, Type, Punctuation};
+use api::token::stream::Item;
+use api::error::Traced;
+use super::expect_block_end;
+
+/// Parses `{% use 'blocks.twig' %}` and `{% use 'blocks.twig' with sidebar as base_sidebar %}`.
+///
+/// Imports the blocks of another template without extending it. Blocks of the current template
+/// override imported blocks, which are still accessible via `parent()` - or under their new name.
+#[derive(Debug, Default)]
+pub struct Use;
+
+impl TokenParser for Use {
+    fn tag(&self) -> &'static str { "use" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let template = try_traced!(job.parse_expression());
+
+        if !template.downcast_ref::<Constant>().is_some_and(|c| matches!(*c.value(), Value::Str(_))) {
+            return traced_err!(ParserError::TokenParserError {
+                tag: self.tag(),
+                error: "The template references in a \"use\" statement must be a string".to_string(),
+                position: template.position().clone(),
+            });
+        }
+
+        let mut renames: Vec<(String, String)> = vec![];
+
+        if job.cursor().next_if(Token::Name("with".to_string())).is_some() {
+            loop {
+                let name_item = try_traced!(job.cursor().expect(Type::Name, Some("A block name was expected.")));
+                let name = name_item.token().value_as_str().unwrap_or_default().to_string();
+                try_traced!(job.cursor().expect(Token::Name("as".to_string()), None));
+                let alias_item = try_traced!(job.cursor().expect(Type::Name, Some("An alias was expected.")));
+                let alias = alias_item.token().value_as_str().unwrap_or_default().to_string();
+
+                if renames.iter().any(|r| r.1 == alias) {
+                    return traced_err!(ParserError::TokenParserError {
+                        tag: self.tag(),
+                        error: format!("The alias {:?} is used twice", alias),
+                        position: alias_item.position().clone(),
+                    });
+                }
+
+                renames.push((name, alias));
+
+                if job.cursor().next_if(Token::Punctuation(Punctuation::Comma)).is_none() {
+                    break;
+                }
+            }
+        }
+        try_traced!(expect_block_end(job));
+
+        job.push_trait(node::Use::new(template, renames, item.position().clone()));
+
+        Ok(Body::new(vec![], item.position().clone()))
+    }
+}
@@ -0,0 +1,37 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! The `with` tag.
+
+use api::Node;
+use api::node;
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::{Token, Type};
+use api::token::stream::Item;
+use api::error::Traced;
+use super::{expect_block_end, parse_body};
+
+/// Parses `{% with { a: 1 } only %}..{% endwith %}`.
+#[derive(Debug, Default)]
+pub struct With;
+
+impl TokenParser for With {
+    fn tag(&self) -> &'static str { "with" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let vars = if job.cursor().test(Type::BlockEnd) || job.cursor().test(Token::Name("only".to_string())) {
+            None
+        } else {
+            Some(try_traced!(job.parse_expression()))
+        };
+        let only = job.cursor().next_if(Token::Name("only".to_string())).is_some();
+        try_traced!(expect_block_end(job));
+
+        let body = try_traced!(parse_body(job, "endwith"));
+
+        Ok(node::With::new(body, vars, only, item.position().clone()))
+    }
+}
@@ -16,6 +16,8 @@ pub struct Debug;
 impl Extension for Debug {
     fn name(&self) -> &'static str { "debug" }
 
+    fn dependencies(&self) -> &'static [&'static str] { &["core"] }
+
     fn init(&mut self, _registry: &mut engine::extension_registry::Builder, _options: &engine::Options)
         -> Result<(), Traced<engine::ExtensionRegistryError>> {
         // unimplemented!()
@@ -0,0 +1,87 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Filters of the escaper extension.
+
+use std::rc::Rc;
+use api::{Node, Value};
+use api::value::Markup;
+use api::node::Constant;
+use api::ext::Filter;
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
+use super::strategy::Strategies;
+
+/// Get all filters of the escaper extension, by name.
+pub fn all(strategies: Rc<Strategies>) -> Vec<(String, Box<dyn Filter>)> {
+    vec![
+        ("escape".to_string(), Box::new(Escape::new(strategies.clone())) as Box<dyn Filter>),
+        ("e".to_string(), Box::new(Escape::new(strategies)) as Box<dyn Filter>),
+        ("raw".to_string(), Box::new(Raw) as Box<dyn Filter>),
+    ]
+}
+
+/// `value|escape(strategy = 'html')` (or `value|e`) escapes a string for the given context.
+///
+/// The result is markup which is safe for the strategy. Markup which is already safe for the
+/// strategy and other values than strings are returned unchanged.
+#[derive(Debug, Default)]
+pub struct Escape {
+    strategies: Rc<Strategies>,
+}
+
+impl Escape {
+    pub fn new(strategies: Rc<Strategies>) -> Escape {
+        Escape {
+            strategies,
+        }
+    }
+}
+
+impl Filter for Escape {
+    fn apply(&self, _job: &mut Job, value: Value, arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        let strategy = arguments.first().map_or("html".to_string(), |s| s.to_string());
+
+        let s = match value {
+            Value::Str(s) => s,
+            Value::Markup(ref markup) if markup.is_safe_for(&strategy) => return Ok(value),
+            Value::Markup(markup) => markup.content().to_string(),
+            value => return Ok(value),
+        };
+
+        match self.strategies.escape(&strategy, &s) {
+            Some(escaped) => Ok(Value::from(Markup::new(escaped, strategy))),
+            None => traced_err!(RuntimeError::InvalidArgument {
+                reason: format!("Invalid escaping strategy {:?} (valid ones: {}).", strategy, self.strategies.names().join(", ")),
+            }),
+        }
+    }
+
+    fn is_safe(&self, arguments: &[Box<dyn Node>]) -> Vec<String> {
+        match arguments.first() {
+            None => vec!["html".to_string()],
+            Some(strategy) => match strategy.downcast_ref::<Constant>() {
+                // output escaped for html attributes is safe within html, too
+                Some(constant) if constant.value().to_string() == "html_attr" => vec!["html_attr".to_string(), "html".to_string()],
+                Some(constant) => vec![constant.value().to_string()],
+                None => vec![],
+            },
+        }
+    }
+}
+
+/// `value|raw` marks a value as safe, i.e. it is not auto-escaped when printed.
+#[derive(Debug, Default)]
+pub struct Raw;
+
+impl Filter for Raw {
+    fn apply(&self, _job: &mut Job, value: Value, _arguments: Vec<Value>) -> Result<Value, Traced<RuntimeError>> {
+        Ok(value)
+    }
+
+    fn is_safe(&self, _arguments: &[Box<dyn Node>]) -> Vec<String> {
+        vec!["all".to_string()]
+    }
+}
@@ -4,23 +4,54 @@
 // file that was distributed with this source code.
 
 //! Escaper Extension
+//!
+//! Escapes the output of `{{ }}` statements automatically, with the strategy chosen by
+//! `Options::auto_escape` or the `autoescape` tag. The `escape` filter escapes values
+//! explicitly and the `raw` filter marks them as safe.
+//!
+//! Custom strategies can be registered by name, see `Setup::add_escaping_strategy`.
 
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+use std::collections::HashMap;
 use api::Extension;
+use api::ext::NodeVisitor;
 use api::error::Traced;
 use engine;
 
-#[derive(Default, Debug, PartialEq)]
+pub mod filter;
+pub mod node_visitor;
+pub mod strategy;
+pub mod token_parser;
+
+#[derive(Default, Debug)]
 pub struct Escaper {
     mode: Mode,
+    strategies: strategy::Strategies,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Auto-escaping mode.
+#[derive(Clone, Debug, PartialEq)]
 pub enum Mode {
+    /// Escape for the HTML body context (default).
     Html,
-    _Enabled,
-    _Disabled,
-    _Filename,
-    _Callback,
+    /// Escape for JavaScript string literals.
+    Js,
+    /// Escape for CSS.
+    Css,
+    /// Escape for URI components.
+    Url,
+    /// Escape for HTML attribute values.
+    HtmlAttr,
+    /// Equivalent to `Html`.
+    Enabled,
+    /// No auto-escaping.
+    Disabled,
+    /// Choose the strategy by the extension of the template name, e.g. `js` for `app.js.twig`.
+    Filename(Filename),
+    /// Choose the strategy by a callback, which is called with the template name.
+    Callback(Callback),
 }
 
 impl Default for Mode {
@@ -29,12 +60,144 @@ impl Default for Mode {
     }
 }
 
+impl Mode {
+    /// Get the name of the escaping strategy for a template, if auto-escaping is enabled.
+    pub fn strategy(&self, template: &str) -> Option<String> {
+        match *self {
+            Mode::Html | Mode::Enabled => Some("html".to_string()),
+            Mode::Js => Some("js".to_string()),
+            Mode::Css => Some("css".to_string()),
+            Mode::Url => Some("url".to_string()),
+            Mode::HtmlAttr => Some("html_attr".to_string()),
+            Mode::Disabled => None,
+            Mode::Filename(ref filename) => filename.strategy(template).map(|s| s.to_string()),
+            Mode::Callback(ref callback) => callback.call(template),
+        }
+    }
+}
+
+/// Callback to choose the escaping strategy of a template by its name, at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use twig::extension::escaper::{Callback, Mode};
+///
+/// let mode = Mode::Callback(Callback::new(|name| match name.starts_with("mail/") {
+///     true => None,
+///     false => Some("html".to_string()),
+/// }));
+///
+/// assert_eq!(mode.strategy("mail/welcome.twig"), None);
+/// assert_eq!(mode.strategy("index.twig"), Some("html".to_string()));
+/// ```
+#[derive(Clone)]
+pub struct Callback(Rc<CallbackFn>);
+
+type CallbackFn = dyn Fn(&str) -> Option<String>;
+
+impl Callback {
+    pub fn new<F>(callback: F) -> Callback where
+        F: Fn(&str) -> Option<String> + 'static
+    {
+        Callback(Rc::new(callback))
+    }
+
+    /// Get the strategy for a template name, or `None` to disable auto-escaping.
+    pub fn call(&self, template: &str) -> Option<String> {
+        (self.0)(template)
+    }
+}
+
+// NOTE: closures can not be compared - callbacks are only equal to their own clones.
+impl PartialEq for Callback {
+    fn eq(&self, other: &Callback) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for Callback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Callback")
+    }
+}
+
+/// Mapping from file extensions of template names to escaping strategies.
+///
+/// A trailing `.twig` is ignored, i.e. `page.html.twig` has the extension `html`. Templates
+/// with unknown extensions are not escaped.
+///
+/// # Examples
+///
+/// ```
+/// use twig::extension::escaper::{Filename, Mode};
+///
+/// let mode = Mode::Filename(Filename::default().set("jsx", Some("js")).set("md", None));
+///
+/// assert_eq!(mode.strategy("app.jsx.twig"), Some("js".to_string()));
+/// assert_eq!(mode.strategy("page.html.twig"), Some("html".to_string()));
+/// assert_eq!(mode.strategy("README.md"), None);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filename {
+    extensions: HashMap<String, Option<String>>,
+}
+
+impl Default for Filename {
+    /// Maps `html` and `htm` to `html`, `js` to `js`, `css` to `css` and `txt` to plain output.
+    fn default() -> Filename {
+        Filename::new()
+            .set("html", Some("html"))
+            .set("htm", Some("html"))
+            .set("js", Some("js"))
+            .set("css", Some("css"))
+            .set("txt", None)
+    }
+}
+
+impl Filename {
+    /// Create an empty mapping, i.e. no template is escaped.
+    pub fn new() -> Filename {
+        Filename {
+            extensions: HashMap::new(),
+        }
+    }
+
+    /// Sets the strategy for an extension (without leading dot), or `None` for plain output.
+    pub fn set<E>(mut self, extension: E, strategy: Option<&str>) -> Filename where
+        E: Into<String>
+    {
+        self.extensions.insert(extension.into(), strategy.map(|s| s.to_string()));
+
+        self
+    }
+
+    /// Get the strategy for a template name, if it is escaped.
+    pub fn strategy(&self, template: &str) -> Option<&str> {
+        let name = template.rsplit(['/', '\\']).next().unwrap_or(template);
+        let name = name.strip_suffix(".twig").unwrap_or(name);
+
+        match name.rfind('.') {
+            Some(dot) => self.extensions.get(&name[dot + 1..]).and_then(|s| s.as_deref()),
+            None => None,
+        }
+    }
+}
+
 impl Extension for Escaper {
     fn name(&self) -> &'static str { "escaper" }
 
-    fn init(&mut self, _registry: &mut engine::extension_registry::Builder, _options: &engine::Options)
+    fn dependencies(&self) -> &'static [&'static str] { &["core"] }
+
+    fn init(&mut self, registry: &mut engine::extension_registry::Builder, _options: &engine::Options)
         -> Result<(), Traced<engine::ExtensionRegistryError>> {
-        // unimplemented!()
+        let strategies = Rc::new(mem::take(&mut self.strategies));
+
+        try_traced!(registry.push_filters(filter::all(strategies.clone())));
+        try_traced!(registry.push_token_parsers(token_parser::all(strategies)));
+        try_traced!(registry.push_node_visitors(vec![
+            Box::new(node_visitor::Escaper::new(self.mode.clone())) as Box<dyn NodeVisitor>,
+        ]));
 
         Ok(())
     }
@@ -43,7 +206,148 @@ impl Extension for Escaper {
 impl Escaper {
     pub fn new(mode: Mode) -> Box<Escaper> {
         Box::new(Escaper {
-            mode: mode
+            mode,
+            strategies: strategy::Strategies::new(),
         })
     }
+
+    /// Registers a custom escaping strategy.
+    pub fn add_strategy<N>(&mut self, name: N, strategy: Box<dyn strategy::Strategy>) where
+        N: Into<String>
+    {
+        self.strategies.insert(name, strategy);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use setup::Setup;
+    use api::Value;
+    use api::value::Hash;
+    use extension::core::test::engine_with;
+    use super::{Callback, Filename, Mode};
+    use super::strategy::Strategy;
+
+    fn render(mode: Mode, source: &str) -> String {
+        let data: Hash = vec![("x", Value::from("<a href='#'>")), ("n", Value::from(1))].into_iter().collect();
+
+        engine_with(Setup::default().set_auto_escape(mode), &[("index", source)]).render("index", data).unwrap()
+    }
+
+    #[test]
+    fn auto_escape() {
+        assert_eq!(render(Mode::default(), "<p>{{ x }}|{{ n }}|{{ '<b>' }}</p>"), "<p>&lt;a href=&#039;#&#039;&gt;|1|<b></p>");
+        assert_eq!(render(Mode::Disabled, "{{ x }}"), "<a href='#'>");
+        assert_eq!(render(Mode::Js, "{{ x }}"), "\\x3Ca\\x20href\\x3D\\x27\\x23\\x27\\x3E");
+        assert_eq!(render(Mode::Url, "{{ x }}"), "%3Ca%20href%3D%27%23%27%3E");
+    }
+
+    #[test]
+    fn safe_values() {
+        // escaped only once
+        assert_eq!(render(Mode::Html, "{{ x|escape }}|{{ x|escape('html') }}"), "&lt;a href=&#039;#&#039;&gt;|&lt;a href=&#039;#&#039;&gt;");
+        // escaped for a different context first
+        assert_eq!(render(Mode::Html, "{{ '<'|escape('url') }}{{ '\\''|escape('js') }}"), "%3C\\x27");
+        assert_eq!(render(Mode::Html, "{{ n ? '<b>' : x|escape }}|{{ n ? '<b>' : x }}"), "<b>|&lt;b&gt;");
+        // html attribute escaping is safe for html
+        assert_eq!(render(Mode::Html, "{{ '<b>'|e('html_attr') }}|{% set y = '<b>'|e('html_attr') %}{{ y }}|{{ y|e }}"), "&lt;b&gt;|&lt;b&gt;|&lt;b&gt;");
+    }
+
+    #[test]
+    fn before_optimizer() {
+        let twig = engine_with(Setup::default(), &[]);
+        let priority = |name: &str| twig.extensions().node_visitors().iter()
+            .find(|v| format!("{:?}", v).starts_with(name))
+            .map(|v| v.priority());
+        assert!(priority("Escaper") < priority("Optimizer"));
+        assert!(priority("Escaper").is_some());
+
+        // folded to a constant by the optimizer, but escaped as a concatenation
+        assert_eq!(render(Mode::Html, "{{ '<' ~ 'b>' }}"), "&lt;b&gt;");
+    }
+
+    #[test]
+    fn filename() {
+        let templates = [
+            ("page.html.twig", "{{ x|escape('url') }}{{ x }}{% include 'app.js.twig' %}"),
+            ("app.js.twig", "|{{ x }}"),
+            ("mail/plain.txt.twig", "{{ x }}"),
+            ("style.scss", "{{ x }}"),
+        ];
+        let render = |filename: Filename, name: &str| {
+            let data: Hash = vec![("x", Value::from("<"))].into_iter().collect();
+
+            engine_with(Setup::default().set_auto_escape(Mode::Filename(filename)), &templates).render(name, data).unwrap()
+        };
+
+        assert_eq!(render(Filename::default(), "page.html.twig"), "%3C&lt;|\\x3C");
+        assert_eq!(render(Filename::default(), "mail/plain.txt.twig"), "<");
+        assert_eq!(render(Filename::default(), "style.scss"), "<");
+        assert_eq!(render(Filename::default().set("scss", Some("css")), "style.scss"), "\\3C ");
+    }
+
+    #[derive(Debug)]
+    struct Latex;
+
+    impl Strategy for Latex {
+        fn escape(&self, s: &str) -> String {
+            s.chars().fold(String::new(), |mut escaped, c| {
+                if "&%$#_{}".contains(c) {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+                escaped
+            })
+        }
+    }
+
+    #[test]
+    fn custom_strategies() {
+        let templates = [
+            ("index.tex", "{{ x }}|{{ x|escape('latex') }}|{{ x|escape }}"),
+            ("index.html", "{{ x }}|{{ x|escape('latex') }}"),
+            ("index.csv", "{{ x }}"),
+        ];
+        let mode = Mode::Callback(Callback::new(|name| match name.rsplit('.').next() {
+            Some("tex") => Some("latex".to_string()),
+            Some("csv") => Some("csv".to_string()),
+            _ => None,
+        }));
+        let mut twig = engine_with(Setup::default().set_auto_escape(mode).add_escaping_strategy("latex", Box::new(Latex)), &templates);
+        let data = || -> Hash { vec![("x", Value::from("50% & <b>"))].into_iter().collect() };
+
+        assert_eq!(twig.render("index.tex", data()).unwrap(), "50\\% \\& <b>|50\\% \\& <b>|50\\% \\&amp; \\&lt;b\\&gt;");
+        assert_eq!(twig.render("index.html", data()).unwrap(), "50% & <b>|50\\% \\& <b>");
+        assert!(twig.render("index.csv", data()).unwrap_err().to_string()
+            .contains("Invalid escaping strategy \"csv\" (valid ones: html, js, css, url, html_attr, latex)"));
+    }
+
+    #[test]
+    fn autoescape_tag() {
+        assert_eq!(render(Mode::Html, "{% autoescape 'js' %}{{ x }}{% endautoescape %}|{{ x }}"),
+            "\\x3Ca\\x20href\\x3D\\x27\\x23\\x27\\x3E|&lt;a href=&#039;#&#039;&gt;");
+        assert_eq!(render(Mode::Html, "{% autoescape false %}{{ x }}{% autoescape %}|{{ x }}{% endautoescape %}{% endautoescape %}"),
+            "<a href='#'>|&lt;a href=&#039;#&#039;&gt;");
+        assert_eq!(render(Mode::Disabled, "{% autoescape true %}{{ x }}{% endautoescape %}"), "&lt;a href=&#039;#&#039;&gt;");
+        assert_eq!(render(Mode::Html, "{% autoescape 'url' %}{{ x|raw }}{% endautoescape %}"), "<a href='#'>");
+
+        let error = engine_with(Setup::default(), &[("index", "{% autoescape 'latex' %}{% endautoescape %}")])
+            .render("index", Hash::new()).unwrap_err();
+        assert!(error.to_string().contains("Invalid escaping strategy \"latex\""));
+        let error = engine_with(Setup::default(), &[("index", "{% autoescape x %}{% endautoescape %}")])
+            .render("index", Hash::new()).unwrap_err();
+        assert!(error.to_string().contains("An escaping strategy must be a string or a bool"));
+    }
+
+    #[test]
+    fn markup() {
+        // escaped markup is only safe for its own strategy
+        assert_eq!(render(Mode::Html, "{% set y = x|e %}{{ y }}{% set y = x|e('url') %}|{{ y }}"),
+            "&lt;a href=&#039;#&#039;&gt;|%3Ca%20href%3D%27%23%27%3E");
+        assert_eq!(render(Mode::Html, "{% set y = '&'|e('url') %}{{ y }}{% autoescape 'url' %}|{{ y }}{% endautoescape %}"), "%26|%26");
+        assert_eq!(render(Mode::Html, "{% set y = x|raw %}{{ y }}"), "&lt;a href=&#039;#&#039;&gt;");
+        // captures and macros are trusted
+        assert_eq!(render(Mode::Html, "{% set y %}<b>{{ x }}</b>{% endset %}{{ y }}"), "<b>&lt;a href=&#039;#&#039;&gt;</b>");
+        assert_eq!(render(Mode::Html, "{% macro m() %}<b>{% endmacro %}{% import _self as macros %}{% set y = macros.m() %}{{ y }}{{ y ~ '' }}"), "<b>&lt;b&gt;");
+    }
 }
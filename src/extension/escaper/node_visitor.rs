@@ -0,0 +1,106 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Node visitor of the escaper extension.
+
+use std::cell::RefCell;
+use api::{Node, Value};
+use api::node::{AutoEscape, Constant, Conditional, Filter, Function, MacroCall, Module, Print};
+use api::ext::NodeVisitor;
+use api::parser::ParserError;
+use api::error::Traced;
+use engine::ExtensionRegistry;
+use super::Mode;
+
+/// Escapes the output of print statements, i.e. `{{ expr }}` is compiled like `{{ expr|escape(strategy) }}`.
+///
+/// The strategy is chosen per template and can be changed by the `autoescape` tag. Expressions which are already safe for the strategy
+/// (e.g. escaped with the same strategy) are not escaped twice.
+#[derive(Debug)]
+pub struct Escaper {
+    mode: Mode,
+    /// Strategies of the templates and `autoescape` tags currently visited, the innermost last.
+    strategies: RefCell<Vec<Option<String>>>,
+}
+
+impl Escaper {
+    pub fn new(mode: Mode) -> Escaper {
+        Escaper {
+            mode,
+            strategies: RefCell::new(vec![]),
+        }
+    }
+
+    /// Get the strategy for the current node, if auto-escaping is enabled.
+    fn strategy(&self) -> Option<String> {
+        self.strategies.borrow().last().cloned().unwrap_or(None)
+    }
+}
+
+impl NodeVisitor for Escaper {
+    fn enter_node(&self, node: Box<dyn Node>, _ext: &ExtensionRegistry) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        if let Some(module) = node.downcast_ref::<Module>() {
+            self.strategies.borrow_mut().push(self.mode.strategy(module.name()));
+        } else if let Some(auto_escape) = node.downcast_ref::<AutoEscape>() {
+            self.strategies.borrow_mut().push(auto_escape.strategy().map(|s| s.to_string()));
+        }
+
+        Ok(node)
+    }
+
+    fn leave_node(&self, mut node: Box<dyn Node>, ext: &ExtensionRegistry) -> Result<Option<Box<dyn Node>>, Traced<ParserError>> {
+        if node.is::<Module>() || node.is::<AutoEscape>() {
+            self.strategies.borrow_mut().pop();
+        } else if node.is::<Print>() {
+            if let Some(strategy) = self.strategy() {
+                if !is_safe(node.children()[0].as_ref(), &strategy, ext) {
+                    let expr = node.children_mut().remove(0);
+                    let position = expr.position().clone();
+                    let strategy = Constant::new(Value::from(strategy), position.clone());
+
+                    node.children_mut().insert(0, Filter::new("escape", expr, vec![strategy], position));
+                }
+            }
+        }
+
+        Ok(Some(node))
+    }
+
+    /// Escaping is applied before the optimizer (priority 10), as in TwigPHP, so it decides on
+    /// the expressions as written - e.g. `'<' ~ 'b>'` is escaped, although it is folded to a
+    /// constant later.
+    fn priority(&self) -> i32 {
+        -10
+    }
+}
+
+/// Returns true if the output of an expression is safe for the given strategy.
+pub fn is_safe(expr: &dyn Node, strategy: &str, ext: &ExtensionRegistry) -> bool {
+    safe_strategies(expr, ext).iter().any(|s| s == "all" || s == strategy)
+}
+
+/// Get the strategies the output of an expression is safe for.
+fn safe_strategies(expr: &dyn Node, ext: &ExtensionRegistry) -> Vec<String> {
+    if expr.is::<Constant>() || expr.is::<MacroCall>() {
+        vec!["all".to_string()]
+    } else if let Some(conditional) = expr.downcast_ref::<Conditional>() {
+        let then = safe_strategies(conditional.then(), ext);
+        let otherwise = safe_strategies(conditional.otherwise(), ext);
+
+        if then.iter().any(|s| s == "all") {
+            otherwise
+        } else if otherwise.iter().any(|s| s == "all") {
+            then
+        } else {
+            then.into_iter().filter(|s| otherwise.contains(s)).collect()
+        }
+    } else if let Some(filter) = expr.downcast_ref::<Filter>() {
+        ext.filters().get(filter.name()).map_or(vec![], |f| f.is_safe(filter.arguments()))
+    } else if let Some(function) = expr.downcast_ref::<Function>() {
+        ext.functions().get(function.name()).map_or(vec![], |f| f.is_safe(function.arguments()))
+    } else {
+        vec![]
+    }
+}
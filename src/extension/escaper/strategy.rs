@@ -0,0 +1,231 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Escaping strategies.
+//!
+//! The strategies follow TwigPHP (and thus the OWASP recommendations for the respective context).
+
+use std::fmt::{self, Write};
+use std::collections::HashMap;
+
+/// Names of all built-in strategies.
+pub const ALL: &[&str] = &["html", "js", "css", "url", "html_attr"];
+
+/// Custom escaping strategy, e.g. for LaTeX or CSV.
+pub trait Strategy : fmt::Debug {
+    /// Escapes a string.
+    fn escape(&self, s: &str) -> String;
+}
+
+/// Built-in and custom escaping strategies by name.
+///
+/// Custom strategies take precedence over built-in strategies with the same name.
+#[derive(Debug, Default)]
+pub struct Strategies {
+    custom: HashMap<String, Box<dyn Strategy>>,
+}
+
+impl Strategies {
+    pub fn new() -> Strategies {
+        Strategies::default()
+    }
+
+    /// Registers a custom strategy.
+    pub fn insert<N>(&mut self, name: N, strategy: Box<dyn Strategy>) where
+        N: Into<String>
+    {
+        self.custom.insert(name.into(), strategy);
+    }
+
+    /// Returns true if a strategy with this name exists.
+    pub fn contains(&self, name: &str) -> bool {
+        self.custom.contains_key(name) || ALL.contains(&name)
+    }
+
+    /// Get the names of all strategies, the built-in ones first.
+    pub fn names(&self) -> Vec<&str> {
+        let mut custom: Vec<&str> = self.custom.keys()
+            .map(|name| name.as_str())
+            .filter(|name| !ALL.contains(name))
+            .collect();
+        custom.sort();
+
+        ALL.iter().cloned().chain(custom).collect()
+    }
+
+    /// Escapes a string with the given strategy.
+    ///
+    /// Returns `None` if the strategy is unknown.
+    pub fn escape(&self, name: &str, s: &str) -> Option<String> {
+        match self.custom.get(name) {
+            Some(strategy) => Some(strategy.escape(s)),
+            None => escape(name, s),
+        }
+    }
+}
+
+/// Escapes a string with the given strategy.
+///
+/// Returns `None` if the strategy is unknown.
+pub fn escape(strategy: &str, s: &str) -> Option<String> {
+    match strategy {
+        "html" => Some(html(s)),
+        "js" => Some(js(s)),
+        "css" => Some(css(s)),
+        "url" => Some(url(s)),
+        "html_attr" => Some(html_attr(s)),
+        _ => None,
+    }
+}
+
+/// Escapes a string for the HTML body context, i.e. `&`, `<`, `>`, `"` and `'`.
+pub fn html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#039;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Escapes a string for JavaScript string literals.
+///
+/// All characters except `a-zA-Z0-9,._` are escaped as `\xHH` or `\uHHHH` (using surrogate
+/// pairs outside of the basic multilingual plane).
+pub fn js(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | ',' | '.' | '_' => escaped.push(c),
+            '\\' => escaped.push_str("\\\\"),
+            '/' => escaped.push_str("\\/"),
+            '\x08' => escaped.push_str("\\b"),
+            '\x0C' => escaped.push_str("\\f"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x80 => {
+                let _ = write!(escaped, "\\x{:02X}", c as u32);
+            },
+            c => {
+                let mut units = [0; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    let _ = write!(escaped, "\\u{:04X}", unit);
+                }
+            },
+        }
+    }
+
+    escaped
+}
+
+/// Escapes a string for CSS, i.e. all characters except `a-zA-Z0-9` as `\HH `.
+pub fn css(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => escaped.push(c),
+            c => {
+                let _ = write!(escaped, "\\{:X} ", c as u32);
+            },
+        }
+    }
+
+    escaped
+}
+
+/// Escapes a string for URI components, like PHP's `rawurlencode()`.
+pub fn url(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for byte in s.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => escaped.push(byte as char),
+            byte => {
+                let _ = write!(escaped, "%{:02X}", byte);
+            },
+        }
+    }
+
+    escaped
+}
+
+/// Escapes a string for HTML attribute values, including unquoted ones.
+///
+/// All characters except `a-zA-Z0-9,.-_` are replaced by entities. Characters which are
+/// undefined in HTML are replaced by the unicode replacement character.
+pub fn html_attr(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | ',' | '.' | '-' | '_' => escaped.push(c),
+            '\t' | '\n' | '\r' => {
+                let _ = write!(escaped, "&#x{:02X};", c as u32);
+            },
+            '\x00'..='\x1F' | '\x7F'..='\u{9F}' => escaped.push_str("&#xFFFD;"),
+            '"' => escaped.push_str("&quot;"),
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c if (c as u32) < 0x80 => {
+                let _ = write!(escaped, "&#x{:02X};", c as u32);
+            },
+            c => {
+                let _ = write!(escaped, "&#x{:04X};", c as u32);
+            },
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strategies() {
+        assert_eq!(html("<a href=\"x\">'Tom' & Jerry</a>"), "&lt;a href=&quot;x&quot;&gt;&#039;Tom&#039; &amp; Jerry&lt;/a&gt;");
+        assert_eq!(js("a.b,c_d 'e'</script>\n\u{e9}\u{1F600}"), "a.b,c_d\\x20\\x27e\\x27\\x3C\\/script\\x3E\\n\\u00E9\\uD83D\\uDE00");
+        assert_eq!(css("a1 {color: red}"), "a1\\20 \\7B color\\3A \\20 red\\7D ");
+        assert_eq!(url("a b/c?d=\u{e9}~"), "a%20b%2Fc%3Fd%3D%C3%A9~");
+        assert_eq!(html_attr("a-b.c d\"<\u{e9}\x01\t"), "a-b.c&#x20;d&quot;&lt;&#x00E9;&#xFFFD;&#x09;");
+        assert_eq!(escape("latex", "x"), None);
+    }
+
+    #[derive(Debug)]
+    struct Upper;
+
+    impl Strategy for Upper {
+        fn escape(&self, s: &str) -> String {
+            s.to_uppercase()
+        }
+    }
+
+    #[test]
+    fn custom_strategies() {
+        let mut strategies = Strategies::new();
+        strategies.insert("upper", Box::new(Upper));
+        strategies.insert("html", Box::new(Upper));
+
+        assert_eq!(strategies.escape("upper", "<a>"), Some("<A>".to_string()));
+        assert_eq!(strategies.escape("html", "<a>"), Some("<A>".to_string()));
+        assert_eq!(strategies.escape("js", "<"), Some("\\x3C".to_string()));
+        assert_eq!(strategies.escape("latex", "x"), None);
+        assert!(strategies.contains("upper") && !strategies.contains("latex"));
+        assert_eq!(strategies.names(), vec!["html", "js", "css", "url", "html_attr", "upper"]);
+    }
+}
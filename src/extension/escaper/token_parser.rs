@@ -0,0 +1,75 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Token parsers of the escaper extension.
+
+use std::rc::Rc;
+use api::{Node, Value};
+use api::node::{self, Constant};
+use api::ext::TokenParser;
+use api::parser::{Job, ParserError};
+use api::token::Type;
+use api::token::stream::Item;
+use api::error::Traced;
+use extension::core::token_parser::{expect_block_end, parse_body};
+use super::strategy::Strategies;
+
+/// Get all token parsers of the escaper extension, by tag.
+pub fn all(strategies: Rc<Strategies>) -> Vec<(String, Box<dyn TokenParser>)> {
+    vec![
+        ("autoescape".to_string(), Box::new(AutoEscape::new(strategies)) as Box<dyn TokenParser>),
+    ]
+}
+
+/// Parses `{% autoescape 'js' %}..{% endautoescape %}`.
+///
+/// Without a strategy or with `true`, the body is escaped for `html`. With `false`,
+/// auto-escaping is disabled.
+#[derive(Debug, Default)]
+pub struct AutoEscape {
+    strategies: Rc<Strategies>,
+}
+
+impl AutoEscape {
+    pub fn new(strategies: Rc<Strategies>) -> AutoEscape {
+        AutoEscape {
+            strategies,
+        }
+    }
+}
+
+impl TokenParser for AutoEscape {
+    fn tag(&self) -> &'static str { "autoescape" }
+
+    fn parse(&self, job: &mut Job, item: &Item) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        let strategy = if job.cursor().test(Type::BlockEnd) {
+            Some("html".to_string())
+        } else {
+            let expr = try_traced!(job.parse_expression());
+
+            match expr.downcast_ref::<Constant>().map(|c| c.value()) {
+                Some(Value::Bool(true)) => Some("html".to_string()),
+                Some(Value::Bool(false)) => None,
+                Some(Value::Str(strategy)) if self.strategies.contains(strategy) => Some(strategy.clone()),
+                Some(Value::Str(strategy)) => return traced_err!(ParserError::TokenParserError {
+                    tag: self.tag(),
+                    error: format!("Invalid escaping strategy {:?} (valid ones: {})",
+                        strategy, self.strategies.names().join(", ")),
+                    position: item.position().clone(),
+                }),
+                _ => return traced_err!(ParserError::TokenParserError {
+                    tag: self.tag(),
+                    error: "An escaping strategy must be a string or a bool".to_string(),
+                    position: item.position().clone(),
+                }),
+            }
+        };
+        try_traced!(expect_block_end(job));
+
+        let body = try_traced!(parse_body(job, "endautoescape"));
+
+        Ok(node::AutoEscape::new(strategy, body, item.position().clone()))
+    }
+}
@@ -4,20 +4,29 @@
 // file that was distributed with this source code.
 
 //! Optimizer Extension
+//!
+//! Simplifies templates at compile time, e.g. by evaluating constant expressions and removing
+//! dead branches. Enabled by default, see `Options::optimizations`.
 
 use api::Extension;
+use api::ext::NodeVisitor;
 use api::error::Traced;
 use engine;
 
+pub mod node_visitor;
+
 #[derive(Default, Debug, PartialEq)]
 pub struct Optimizer {
     mode: Mode
 }
 
+/// Optimization mode.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Mode {
+    /// Apply all optimizations (default).
     Enabled,
-    _Disabled,
+    /// Compile templates as they are parsed.
+    Disabled,
 }
 
 impl Default for Mode {
@@ -29,9 +38,15 @@ impl Default for Mode {
 impl Extension for Optimizer {
     fn name(&self) -> &'static str { "optimizer" }
 
-    fn init(&mut self, _registry: &mut engine::extension_registry::Builder, _options: &engine::Options)
+    fn dependencies(&self) -> &'static [&'static str] { &["core"] }
+
+    fn init(&mut self, registry: &mut engine::extension_registry::Builder, _options: &engine::Options)
         -> Result<(), Traced<engine::ExtensionRegistryError>> {
-        // unimplemented!()
+        if self.mode == Mode::Enabled {
+            try_traced!(registry.push_node_visitors(vec![
+                Box::new(node_visitor::Optimizer::new()) as Box<dyn NodeVisitor>,
+            ]));
+        }
 
         Ok(())
     }
@@ -40,7 +55,118 @@ impl Extension for Optimizer {
 impl Optimizer {
     pub fn new(mode: Mode) -> Box<Optimizer> {
         Box::new(Optimizer {
-            mode: mode
+            mode,
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use setup::Setup;
+    use engine;
+    use engine::extension_registry::Builder;
+    use api::{Extension, Node, Value};
+    use api::ext::{UnaryOperator, op};
+    use api::error::Traced;
+    use api::node::{Binary, Constant, For, Name, Print, Text};
+    use api::value::Hash;
+    use extension::core::test::engine_with;
+    use super::Mode;
+
+    /// Compiles a template and applies `check` to the nodes of its body.
+    fn compile<F>(mode: Mode, source: &str, check: F) where
+        F: FnOnce(&[Box<dyn Node>])
+    {
+        let mut twig = engine_with(Setup::default().set_optimizations(mode), &[("index", source)]);
+        let template = twig.load_template("index", None).unwrap();
+
+        check(template.module().unwrap().body().children());
+    }
+
+    fn printed(node: &dyn Node) -> &dyn Node {
+        &*node.downcast_ref::<Print>().unwrap().children()[0]
+    }
+
+    #[test]
+    fn constant_folding() {
+        compile(Mode::Enabled, "{{ 1 + 2 * 3 }}{{ not false ? 'a' ~ 'b' : x }}", |body| {
+            assert_eq!(printed(&*body[0]).downcast_ref::<Constant>().unwrap().value(), &Value::from(7));
+            // strings are still escaped
+            let escaped = printed(&*body[1]).children()[0].downcast_ref::<Constant>().unwrap();
+            assert_eq!(escaped.value(), &Value::from("ab"));
+        });
+        compile(Mode::Disabled, "{{ 1 + 2 }}", |body| {
+            assert!(printed(&*body[0]).children()[0].is::<Binary>());
+        });
+    }
+
+    #[test]
+    fn dead_branches() {
+        compile(Mode::Enabled, "a{% if false %}x{% elseif true %}b{% else %}c{% endif %}{% if 0 %}d{% endif %}e", |body| {
+            assert_eq!(body.len(), 1);
+            assert_eq!(body[0].downcast_ref::<Text>().unwrap().data(), "abe");
+        });
+        compile(Mode::Enabled, "{{ x|raw }}", |body| {
+            assert!(printed(&*body[0]).is::<Name>());
+        });
+    }
+
+    #[test]
+    fn loop_variable() {
+        let with_loop = |source: &str| {
+            let mut with_loop = None;
+            compile(Mode::Enabled, source, |body| with_loop = Some(body[0].downcast_ref::<For>().unwrap().with_loop()));
+            with_loop.unwrap()
+        };
+
+        assert!(!with_loop("{% for i in [1, 2] %}{{ i }}{% endfor %}"));
+        assert!(with_loop("{% for i in [1, 2] %}{% if true %}{{ loop.index }}{% endif %}{% endfor %}"));
+        assert!(with_loop("{% for i in [1, 2] %}{% include 'x' %}{% endfor %}"));
+        assert!(!with_loop("{% for i in [1, 2] %}{% include 'x' only %}{% endfor %}"));
+    }
+
+    #[test]
+    fn same_output() {
+        let source = "{% for i in [1, 2] %}{{ loop.index * 10 }}{{ i ~ '<' }}{% endfor %}{% if false %}{{ 1 / 0 }}{% endif %}{{ 2 ** 3 }}";
+
+        for &mode in &[Mode::Enabled, Mode::Disabled] {
+            let mut twig = engine_with(Setup::default().set_optimizations(mode), &[("index", source)]);
+            assert_eq!(twig.render("index", Hash::new()).unwrap(), "101&lt;202&lt;8");
+        }
+
+        let mut twig = engine_with(Setup::default(), &[("index", "{{ 1 / 0 }}")]);
+        assert!(twig.render("index", Hash::new()).is_err());
+    }
+
+    /// Impure operator - `next x` counts its calls, starting at `x`.
+    #[derive(Debug, Default)]
+    struct Counter;
+
+    impl Extension for Counter {
+        fn name(&self) -> &'static str { "counter" }
+
+        fn init(&mut self, registry: &mut Builder, _options: &engine::Options)
+            -> Result<(), Traced<engine::ExtensionRegistryError>> {
+            let calls = Cell::new(0);
+
+            registry.push_operators_unary(vec![UnaryOperator {
+                repr: "next".to_string(),
+                ext: op::Extension("counter".to_string()),
+                prec: op::Precedence(500),
+                op: op::Operation::Callable(op::Function::unary("next", move |start: &Value| {
+                    calls.set(calls.get() + 1);
+                    Ok(Value::from(start.to_int() + calls.get()))
+                })),
+            }])
+        }
+    }
+
+    #[test]
+    fn impure_operators() {
+        let setup = Setup::default().add_extension(Box::new(Counter));
+        let mut twig = engine_with(setup, &[("index", "{% for i in 1..3 %}{{ next 0 }}{% endfor %}")]);
+
+        assert_eq!(twig.render("index", Hash::new()).unwrap(), "123");
+    }
+}
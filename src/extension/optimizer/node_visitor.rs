@@ -0,0 +1,212 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Node visitor of the optimizer extension.
+
+use std::mem;
+use api::{Node, Value};
+use api::node::{Binary, BlockReference, Body, Conditional, Constant, Embed, Filter, For, Function, If,
+    Include, Name, Text, Unary};
+use api::ext::{NodeVisitor, UnaryOperator, BinaryOperator};
+use api::ext::op::{self, Operation, Callable};
+use api::parser::ParserError;
+use api::error::Traced;
+use engine::ExtensionRegistry;
+
+/// Simplifies the abstract syntax tree of a template, without changing its output.
+///
+/// * Operations of the core extension on constants are evaluated at compile time, e.g. `1 + 2`
+///   becomes `3`.
+/// * Branches of `if` tags and conditional expressions with constant tests are resolved.
+/// * Nested bodies are flattened and adjacent texts merged.
+/// * The `loop` variable of `for` tags is only maintained if the loop body may refer to it.
+/// * `raw` filters are removed, as well as `escape` filters on constants other than strings.
+#[derive(Debug, Default)]
+pub struct Optimizer;
+
+impl Optimizer {
+    pub fn new() -> Optimizer {
+        Optimizer
+    }
+}
+
+impl NodeVisitor for Optimizer {
+    fn enter_node(&self, node: Box<dyn Node>, _ext: &ExtensionRegistry) -> Result<Box<dyn Node>, Traced<ParserError>> {
+        Ok(node)
+    }
+
+    fn leave_node(&self, mut node: Box<dyn Node>, ext: &ExtensionRegistry) -> Result<Option<Box<dyn Node>>, Traced<ParserError>> {
+        if node.is::<Binary>() || node.is::<Unary>() {
+            node = fold_operation(node, ext);
+        } else if node.is::<Conditional>() {
+            node = optimize_conditional(node);
+        } else if node.is::<If>() {
+            node = optimize_if(node);
+        } else if node.is::<Body>() {
+            optimize_body(&mut *node);
+        } else if node.is::<Filter>() {
+            node = optimize_filter(node);
+        } else if let Some(for_) = node.downcast_mut::<For>() {
+            let with_loop = uses_loop(for_.body());
+            for_.set_with_loop(with_loop);
+        }
+
+        Ok(Some(node))
+    }
+
+    /// Optimizations are applied after the core visitors, i.e. after auto-escaping (priority -10).
+    fn priority(&self) -> i32 {
+        10
+    }
+}
+
+/// Replaces an operation on constants by its result.
+///
+/// Only operators of the core extension are folded, because operators of other extensions may
+/// be impure, e.g. return random numbers. Operations which fail (e.g. division by zero) are
+/// kept, so the error is raised at runtime.
+fn fold_operation(node: Box<dyn Node>, ext: &ExtensionRegistry) -> Box<dyn Node> {
+    let operands: Option<Vec<&Value>> = node.children().iter()
+        .map(|child| child.downcast_ref::<Constant>().map(|c| c.value()))
+        .collect();
+    let core = op::Extension("core".to_string());
+
+    let result = match (operands.as_deref(), node.downcast_ref::<Binary>(), node.downcast_ref::<Unary>()) {
+        (Some(&[left, right]), Some(binary), _) => match ext.operators_binary().get(binary.operator()) {
+            Some(&BinaryOperator { op: Operation::Callable(ref function), ext: ref operator_ext, .. }) if *operator_ext == core => {
+                match *function.callable() {
+                    Callable::Binary(ref callable) => callable(left, right).ok(),
+                    _ => None,
+                }
+            },
+            _ => None,
+        },
+        (Some(&[operand]), _, Some(unary)) => match ext.operators_unary().get(unary.operator()) {
+            Some(&UnaryOperator { op: Operation::Callable(ref function), ext: ref operator_ext, .. }) if *operator_ext == core => {
+                match *function.callable() {
+                    Callable::Unary(ref callable) => callable(operand).ok(),
+                    _ => None,
+                }
+            },
+            _ => None,
+        },
+        _ => None,
+    };
+
+    match result {
+        Some(value) => Constant::new(value, node.position().clone()),
+        None => node,
+    }
+}
+
+/// Replaces a conditional expression with a constant test by the chosen branch.
+fn optimize_conditional(mut node: Box<dyn Node>) -> Box<dyn Node> {
+    let test = match node.children()[0].downcast_ref::<Constant>() {
+        Some(test) => test.value().to_bool(),
+        None => return node,
+    };
+
+    node.children_mut().swap_remove(if test { 1 } else { 2 })
+}
+
+/// Removes branches of an `if` tag with constant tests which are false, and all branches after
+/// a constant test which is true.
+fn optimize_if(mut node: Box<dyn Node>) -> Box<dyn Node> {
+    let position = node.position().clone();
+    let mut children = mem::take(node.children_mut()).into_iter();
+    let mut tests = vec![];
+    let mut else_body = None;
+
+    while let Some(child) = children.next() {
+        let body = match children.next() {
+            Some(body) => body,
+            None => {
+                else_body = Some(child);
+                break;
+            },
+        };
+
+        match child.downcast_ref::<Constant>().map(|c| c.value().to_bool()) {
+            Some(false) => continue,
+            Some(true) => {
+                else_body = Some(body);
+                break;
+            },
+            None => tests.push((child, body)),
+        }
+    }
+
+    if tests.is_empty() {
+        return else_body.unwrap_or_else(|| Body::new(vec![], position));
+    }
+
+    If::new(tests, else_body, position)
+}
+
+/// Flattens nested bodies and merges adjacent texts.
+fn optimize_body(node: &mut dyn Node) {
+    let children = mem::take(node.children_mut());
+    let mut optimized: Vec<Box<dyn Node>> = Vec::with_capacity(children.len());
+
+    for child in children {
+        let nodes = if child.is::<Body>() {
+            let mut child = child;
+            mem::take(child.children_mut())
+        } else {
+            vec![child]
+        };
+
+        for child in nodes {
+            if let (Some(text), Some(last)) = (child.downcast_ref::<Text>(), optimized.last_mut()) {
+                if let Some(last) = last.downcast_mut::<Text>() {
+                    last.data_mut().push_str(text.data());
+                    continue;
+                }
+            }
+
+            optimized.push(child);
+        }
+    }
+
+    *node.children_mut() = optimized;
+}
+
+/// Removes filters which do not change the output, i.e. `raw` and `escape` on constants which
+/// are not strings.
+fn optimize_filter(mut node: Box<dyn Node>) -> Box<dyn Node> {
+    let is_noop = match node.downcast_ref::<Filter>() {
+        Some(filter) => match filter.name() {
+            "raw" => true,
+            "escape" | "e" => filter.node().downcast_ref::<Constant>()
+                .is_some_and(|c| !matches!(*c.value(), Value::Str(_) | Value::Markup(_))),
+            _ => false,
+        },
+        None => false,
+    };
+
+    if is_noop {
+        node.children_mut().swap_remove(0)
+    } else {
+        node
+    }
+}
+
+/// Returns true if a loop body may refer to the `loop` variable - directly, or via the context
+/// passed to included templates and blocks.
+fn uses_loop(node: &dyn Node) -> bool {
+    let refers_to_loop = if let Some(name) = node.downcast_ref::<Name>() {
+        name.name() == "loop"
+    } else if let Some(include) = node.downcast_ref::<Include>() {
+        !include.only()
+    } else if let Some(embed) = node.downcast_ref::<Embed>() {
+        !embed.only()
+    } else if let Some(function) = node.downcast_ref::<Function>() {
+        ["include", "block", "parent"].contains(&function.name())
+    } else {
+        node.is::<BlockReference>()
+    };
+
+    refers_to_loop || node.children().iter().any(|child| uses_loop(&**child))
+}
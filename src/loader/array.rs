@@ -0,0 +1,70 @@
+// This file is part of rust-web/twig
+//
+// For the copyright and license information, please view the LICENSE
+// file that was distributed with this source code.
+
+//! Loads templates from memory.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use loader::{Loader, LoaderError};
+use api::error::Traced;
+
+/// Loads templates from a map of template names and source code.
+///
+/// Useful for unit tests and templates stored in a database.
+///
+/// # Examples
+///
+/// ```
+/// use twig::loader::{Loader, Array};
+///
+/// let mut loader = Array::new();
+/// loader.set_template("index.twig", "Hello {{ name }}!");
+///
+/// assert_eq!(loader.source("index.twig").unwrap(), "Hello {{ name }}!");
+/// ```
+#[derive(Debug, Default)]
+pub struct Array {
+    templates: HashMap<String, String>,
+}
+
+impl Array {
+    pub fn new() -> Array {
+        Array::default()
+    }
+
+    /// Adds or overrides a template.
+    pub fn set_template<N, S>(&mut self, name: N, source: S) -> &mut Array where
+        N: Into<String>,
+        S: Into<String>
+    {
+        self.templates.insert(name.into(), source.into());
+
+        self
+    }
+}
+
+impl Loader for Array {
+    fn source(&mut self, name: &str) -> Result<Cow<'_, str>, Traced<LoaderError>> {
+        match self.templates.get(name) {
+            Some(source) => Ok(Cow::Borrowed(source)),
+            None => traced_err!(LoaderError::ArrayTemplateNotFound {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    fn cache_key<'a>(&'a mut self, name: &str) -> Result<Cow<'a, str>, LoaderError> {
+        match self.templates.get(name) {
+            Some(source) => Ok(Cow::Borrowed(source)),
+            None => Err(LoaderError::ArrayTemplateNotFound {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    fn is_fresh(&mut self, _name: &str, _time: i64) -> bool {
+        true
+    }
+}
@@ -34,6 +34,25 @@ pub enum LoaderError {
     }
 }
 
+impl LoaderError {
+    /// Get the stable code of the error, see `twig::api::error::index`.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            LoaderError::ArrayTemplateNotFound{..} => "T0401",
+            LoaderError::FileSystemTemplateNotFound{..} => "T0402",
+            LoaderError::FileSystemNamespaceNotInitialized{..} => "T0403",
+            LoaderError::FileSystemMalformedNamespacedPath{..} => "T0404",
+            LoaderError::FileSystemInvalidPath{..} => "T0405",
+            LoaderError::FileSystemTemplateNotReadable{..} => "T0406",
+        }
+    }
+
+    /// Returns true if the template does not exist (as opposed to e.g. not being readable).
+    pub fn is_not_found(&self) -> bool {
+        matches!(*self, LoaderError::ArrayTemplateNotFound{..} | LoaderError::FileSystemTemplateNotFound{..})
+    }
+}
+
 impl Error for LoaderError {
     fn description(&self) -> &str {
         match *self {
@@ -9,7 +9,9 @@ use api::error::Traced;
 use std::fmt::Debug;
 use std::borrow::Cow;
 
+pub mod array;
 pub mod error;
+pub use self::array::Array;
 pub use self::error::LoaderError;
 
 
@@ -7,9 +7,12 @@
 
 use std::path::Path;
 use extension;
-use api::Extension;
+use api::{Extension, Value};
+use api::ext::Global;
+use extension::escaper::strategy::Strategy;
+use extension::core::random::Rng;
 use api::error::Traced;
-use engine::{Engine, options, Options, ExtensionRegistry};
+use engine::{self, Engine, options, Options, ExtensionRegistry};
 use engine::error::{TwigError};
 
 #[allow(dead_code)]
@@ -19,17 +22,19 @@ pub const VERSION : &'static str = "1.18.1";
 pub struct Setup {
     opt: Options,
     ext: Vec<Box<Extension>>,
+    globals: Vec<(String, Box<dyn Global>)>,
+    escaping_strategies: Vec<(String, Box<dyn Strategy>)>,
+    random_generator: Option<Box<dyn Rng>>,
 }
 
 impl Default for Setup {
     fn default() -> Setup {
-        // prepend default extensions
-        let mut ext: Vec<Box<Extension>> = vec![];
-        ext.push(extension::Core::new());
-
         Setup {
             opt: Options::default(),
-            ext: ext,
+            ext: vec![],
+            globals: vec![],
+            escaping_strategies: vec![],
+            random_generator: None,
         }
     }
 }
@@ -41,6 +46,10 @@ impl Default for Setup {
 /// * escaper
 /// * optimizer
 ///
+/// Extensions are initialized in the order of their dependencies (see
+/// `twig::api::ext::Extension::dependencies()`), i.e. a custom extension may depend on
+/// the escaper, even though the escaper is appended after all custom extensions.
+///
 /// # Examples
 ///
 /// ```
@@ -65,12 +74,26 @@ impl Setup {
     /// let twig = Setup::default().init_engine().unwrap();
     /// ```
     pub fn init_engine(self) -> Result<Engine, Traced<TwigError>> {
-        let Setup { opt, mut ext } = self;
+        let Setup { opt, mut ext, globals, escaping_strategies, random_generator } = self;
 
-        // append default extensions
-        ext.push(extension::Escaper::new(opt.auto_escape()));
+        // prepend and append default extensions
+        let mut core = extension::Core::new();
+        if let Some(rng) = random_generator {
+            core.set_random_generator(rng);
+        }
+        ext.insert(0, core);
+
+        let mut escaper = extension::Escaper::new(opt.auto_escape().clone());
+        for (name, strategy) in escaping_strategies {
+            escaper.add_strategy(name, strategy);
+        }
+        ext.push(escaper);
         ext.push(extension::Optimizer::new(opt.optimizations()));
 
+        if !globals.is_empty() {
+            ext.push(Box::new(SetupGlobals { globals }));
+        }
+
         // init extensions
         let extension_registry = try_traced!(ExtensionRegistry::new(ext, &opt));
 
@@ -84,6 +107,50 @@ impl Setup {
         self
     }
 
+    /// Registers a global variable, which is visible in all templates.
+    ///
+    /// Variables passed to `Engine::render()` shadow globals with the same name.
+    pub fn add_global<N>(mut self, name: N, global: Box<dyn Global>) -> Self where
+        N: Into<String>
+    {
+        self.globals.push((name.into(), global));
+
+        self
+    }
+
+    /// Registers a custom escaping strategy, e.g. `latex` for `{{ value|escape('latex') }}`.
+    ///
+    /// The strategy can also be chosen for auto-escaping, e.g. via `AutoEscape::Callback`.
+    /// Custom strategies replace built-in strategies with the same name.
+    pub fn add_escaping_strategy<N>(mut self, name: N, strategy: Box<dyn Strategy>) -> Self where
+        N: Into<String>
+    {
+        self.escaping_strategies.push((name.into(), strategy));
+
+        self
+    }
+
+    /// Defines a constant, which templates can refer to via `constant('NAME')` and
+    /// `value is constant('NAME')`.
+    pub fn add_constant<N, V>(mut self, name: N, value: V) -> Self where
+        N: Into<String>,
+        V: Into<Value>
+    {
+        self.opt.set_constant(name.into(), value.into());
+
+        self
+    }
+
+    /// Sets the random number generator of the `random()` function.
+    ///
+    /// Defaults to a generator seeded from the system time. A seeded generator makes the output
+    /// reproducible, e.g. `Box::new(XorShift::new(42))`.
+    pub fn set_random_generator(mut self, rng: Box<dyn Rng>) -> Self {
+        self.random_generator = Some(rng);
+
+        self
+    }
+
     /// When set to true, it automatically set "auto_reload" to true as well
     ///     (default to false)
     pub fn set_debug(mut self, debug: bool) -> Self {
@@ -92,8 +159,8 @@ impl Setup {
         self
     }
 
-    /// Whether to ignore invalid variables in templates
-    ///     (default to false).
+    /// Whether undefined variables, attributes and keys raise errors (default to false).
+    ///     Otherwise they evaluate to null.
     pub fn set_strict_variables(mut self, strict_variables: bool) -> Self {
         self.opt.set_strict_variables(strict_variables);
 
@@ -101,11 +168,11 @@ impl Setup {
     }
 
     /// Whether to enable auto-escaping (default to html):
-    ///     * false: disable auto-escaping
-    ///     * true: equivalent to html
-    ///     * html, js: set the autoescaping to one of the supported strategies
-    ///     * filename: set the autoescaping strategy based on the template filename extension
-    ///     * PHP callback: a PHP callback that returns an escaping strategy based on the template "filename"
+    ///     * Disabled: disable auto-escaping
+    ///     * Enabled: equivalent to Html
+    ///     * Html, Js, Css, Url, HtmlAttr: set the autoescaping to one of the supported strategies
+    ///     * Filename: set the autoescaping strategy based on the template filename extension
+    ///     * Callback: a callback that returns an escaping strategy based on the template "filename"
     pub fn set_auto_escape(mut self, auto_escape: options::AutoEscape) -> Self {
         self.opt.set_auto_escape(auto_escape);
 
@@ -140,8 +207,23 @@ impl Setup {
         &self.opt
     }
 
-    /// Get all extensions
+    /// Get all custom extensions, i.e. without the default extensions.
     pub fn extensions(&self) -> &Vec<Box<Extension>> {
         &self.ext
     }
 }
+
+/// Registers the globals of the setup.
+#[derive(Debug)]
+struct SetupGlobals {
+    globals: Vec<(String, Box<dyn Global>)>,
+}
+
+impl Extension for SetupGlobals {
+    fn name(&self) -> &'static str { "setup_globals" }
+
+    fn init(&mut self, registry: &mut engine::extension_registry::Builder, _options: &Options)
+        -> Result<(), Traced<engine::ExtensionRegistryError>> {
+        registry.push_globals(self.globals.drain(..))
+    }
+}
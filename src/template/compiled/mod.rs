@@ -5,5 +5,62 @@
 
 //! Default base class for compiled templates.
 
+use std::rc::Rc;
+use api::Node;
+use api::node::Module;
+use api::runtime::{Job, RuntimeError};
+use api::error::Traced;
 
-pub struct Compiled;
+#[derive(Debug)]
+pub struct Compiled {
+    name: String, // twig template name, e.g. "@namespace/path/to/template"
+    root: Box<dyn Node>,
+    embedded: Vec<Rc<Compiled>>,
+}
+
+impl Compiled {
+    /// Create a compiled template from the (visited) abstract syntax tree.
+    ///
+    /// Embedded templates of the module are taken out of the tree and compiled separately.
+    pub fn new<N>(name: N, mut root: Box<dyn Node>) -> Compiled where
+        N: ToString
+    {
+        let name = name.to_string();
+        let embedded = match root.downcast_mut::<Module>() {
+            Some(module) => module.take_embedded_templates().into_iter()
+                .map(|e| Rc::new(Compiled::new(name.clone(), e)))
+                .collect(),
+            None => vec![],
+        };
+
+        Compiled {
+            name,
+            root,
+            embedded,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the root node of the abstract syntax tree.
+    pub fn root(&self) -> &dyn Node {
+        &*self.root
+    }
+
+    /// Get the module node, i.e. the root node - unless a node visitor replaced it.
+    pub fn module(&self) -> Option<&Module> {
+        self.root.downcast_ref::<Module>()
+    }
+
+    /// Get an embedded template by its index, i.e. the template of an `{% embed %}` tag.
+    pub fn embedded_template(&self, index: usize) -> Option<Rc<Compiled>> {
+        self.embedded.get(index).cloned()
+    }
+
+    /// Renders the template to the output of the runtime job.
+    pub fn render(&self, job: &mut Job) -> Result<(), Traced<RuntimeError>> {
+        self.root.render(job)
+    }
+}